@@ -0,0 +1,339 @@
+use std::any::TypeId;
+use std::fs::{read, write};
+use std::io::{BufRead, Write, stdin, stdout};
+use std::process::exit;
+use std::ptr::NonNull;
+
+use xjbutil::unchecked::UnsafeFrom;
+
+use pr47::builtins::vec::{VMGenericVec, create_vm_vec_vt};
+use pr47::data::Value;
+use pr47::data::generic::GenericTypeVT;
+use pr47::data::tyck::{TyckInfo, TyckInfoPool};
+use pr47::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use pr47::diag::Diagnostic;
+use pr47::diag::diag_data::{diag_message, is_error};
+use pr47::diag::location::SourceCoord;
+use pr47::diag::source::SourceManager;
+use pr47::driver::Compiler;
+use pr47::repl::{Repl, ReplError};
+use pr47::vm::al31f::alloc::Alloc;
+use pr47::vm::al31f::alloc::default_alloc::DefaultAlloc;
+use pr47::vm::al31f::asm::{assemble, disassemble};
+use pr47::vm::al31f::bytecode::{BYTECODE_MAGIC, deserialize_program, serialize_program};
+use pr47::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
+use pr47::vm::al31f::exception::Exception;
+use pr47::vm::al31f::ffi_registry::FFIRegistry;
+use pr47::vm::al31f::verifier::verify_program;
+
+#[cfg(feature = "async")] use xjbutil::async_utils::block_on_future;
+#[cfg(feature = "async")] use xjbutil::unchecked::UncheckedSendSync;
+#[cfg(feature = "async")] use pr47::vm::al31f::executor::{
+    VMThread,
+    create_vm_main_thread,
+    vm_thread_run_function
+};
+#[cfg(not(feature = "async"))] use pr47::vm::al31f::executor::vm_run_function_sync;
+#[cfg(feature = "std47")] use pr47::std47::io::PrintBind;
+
+const ENTRY_FUNC_NAME: &str = "application_start";
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect::<_>();
+    if args.len() < 2 {
+        run_repl();
+        return;
+    }
+
+    // `pr47 --compile main.p47 main.p47b` saves bytecode instead of running the script
+    if args[1] == "--compile" {
+        if args.len() != 4 {
+            eprintln!("usage: {} --compile <source file> <output file>", args[0]);
+            exit(1);
+        }
+        let (program, _): (CompiledProgram<DefaultAlloc>, SourceManager) =
+            load_program(&args[2]);
+        let bytes: Vec<u8> = serialize_program(&program).unwrap_or_else(|e| {
+            eprintln!("error: cannot serialize program: {:?}", e);
+            exit(1);
+        });
+        write(&args[3], bytes).expect("cannot write appointed file");
+        return;
+    }
+
+    // `pr47 --disassemble main.p47` prints the program in the form accepted by the assembler
+    if args[1] == "--disassemble" {
+        if args.len() != 3 {
+            eprintln!("usage: {} --disassemble <source or bytecode file>", args[0]);
+            exit(1);
+        }
+        let (program, _): (CompiledProgram<DefaultAlloc>, SourceManager) =
+            load_program(&args[2]);
+        print!("{}", unsafe { disassemble(&program) });
+        return;
+    }
+
+    let (mut program, source_manager): (CompiledProgram<DefaultAlloc>, SourceManager) =
+        load_program(&args[1]);
+    let func_id: usize = if let Some(func_id) = program.func_ids.get(ENTRY_FUNC_NAME) {
+        *func_id
+    } else {
+        eprintln!("error: no `{}` function found in {}", ENTRY_FUNC_NAME, args[1]);
+        exit(1);
+    };
+
+    // `application_start` may either take no arguments, or take command line arguments as a
+    // `vector<string>`. Types are interned, so the vector type made from the type pool of the
+    // program can be compared with the parameter type directly.
+    let tyck_info_pool: &mut TyckInfoPool = &mut program.tyck_info_pool;
+    let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+    let string_vec_type: NonNull<TyckInfo> =
+        tyck_info_pool.create_container_type(TypeId::of::<VMGenericVec>(), &[string_type]);
+    let string_vec_vt: Box<GenericTypeVT> =
+        Box::new(create_vm_vec_vt(tyck_info_pool, string_type));
+    let mut alloc: DefaultAlloc = DefaultAlloc::new();
+    let compiled_function: &CompiledFunction = &program.functions[func_id];
+    let param_type: Option<NonNull<TyckInfo>> =
+        compiled_function.param_tyck_info.first().copied().flatten();
+    let script_args: Vec<Value> = match compiled_function.arg_count {
+        0 => vec![],
+        1 if param_type.is_none_or(|ty: NonNull<TyckInfo>| ty == string_vec_type) =>
+            vec![unsafe { create_args_vec(&mut alloc, &string_vec_vt, &args[2..]) }],
+        _ => {
+            eprintln!(
+                "error: `{}` should take either no parameters or one `vector<string>` parameter",
+                ENTRY_FUNC_NAME
+            );
+            exit(1);
+        }
+    };
+
+    let result: Result<Vec<Value>, Exception> = run_program(alloc, &program, func_id, script_args);
+    let exit_code: i32 = match result {
+        Ok(rets) => rets.first().map_or(0, |ret| unsafe { value_to_exit_code(*ret) }),
+        Err(exception) => {
+            report_exception(&program, &source_manager, &exception);
+            1
+        }
+    };
+
+    drop(program);
+    drop(string_vec_vt);
+    exit(exit_code);
+}
+
+/// Loads a program from a source file, an assembly file or a bytecode file. Source files loaded
+/// are kept in the returned source manager.
+fn load_program(file_name: &str) -> (CompiledProgram<DefaultAlloc>, SourceManager) {
+    let bytes: Vec<u8> = read(file_name).expect("cannot read appointed file");
+    if bytes.starts_with(&BYTECODE_MAGIC) {
+        let program: CompiledProgram<DefaultAlloc> =
+            deserialize_program(&bytes, &create_ffi_registry()).unwrap_or_else(|e| {
+                eprintln!("error: cannot load bytecode file {}: {:?}", file_name, e);
+                exit(1);
+            });
+        return (verified(file_name, program), SourceManager::new());
+    }
+
+    let source: String = String::from_utf8(bytes).unwrap_or_else(|_| {
+        eprintln!("error: {} is not a valid UTF-8 file", file_name);
+        exit(1);
+    });
+    if file_name.ends_with(".p47asm") {
+        let program: CompiledProgram<DefaultAlloc> =
+            assemble(&source, &create_ffi_registry()).unwrap_or_else(|e| {
+                eprintln!("{}:{}: error: {}", file_name, e.line, e.message);
+                exit(1);
+            });
+        return (verified(file_name, program), SourceManager::new());
+    }
+
+    let mut compiler: Compiler<DefaultAlloc> =
+        Compiler::new().with_ffi_registry(create_ffi_registry());
+    compiler.add_file(file_name, &source);
+    match compiler.compile() {
+        Ok(program) => (program, compiler.into_source_manager()),
+        Err(diags) => {
+            report_diags(compiler.source_manager(), diags);
+            exit(1);
+        }
+    }
+}
+
+/// Bytecode and assembly files are not produced by the compiler, so they are verified before run
+fn verified(
+    file_name: &str,
+    program: CompiledProgram<DefaultAlloc>
+) -> CompiledProgram<DefaultAlloc> {
+    if let Err(e) = verify_program(&program) {
+        eprintln!("error: {} is malformed: {:?}", file_name, e);
+        exit(1);
+    }
+    program
+}
+
+fn create_ffi_registry() -> FFIRegistry<DefaultAlloc> {
+    #[allow(unused_mut)]
+    let mut ffi_registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+    #[cfg(feature = "std47")]
+    ffi_registry.register_func("print", &PrintBind());
+    ffi_registry
+}
+
+fn run_repl() {
+    eprintln!("Pr47 REPL. Enter declarations, statements or expressions, press Ctrl-D to exit.");
+    let mut repl: Repl<DefaultAlloc> = Repl::new(DefaultAlloc::new(), create_ffi_registry());
+    let mut chunk: String = String::new();
+    let mut lines = stdin().lock().lines();
+    loop {
+        print!("{}", if chunk.is_empty() { ">> " } else { ".. " });
+        let _ = stdout().flush();
+
+        let line: String = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break
+        };
+        chunk.push_str(&line);
+        chunk.push('\n');
+        if chunk.trim().is_empty() {
+            chunk.clear();
+            continue;
+        }
+        if !is_chunk_complete(&chunk) {
+            continue;
+        }
+
+        match repl.eval(&chunk) {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => {},
+            Err(ReplError::Diagnostics(diags)) => report_diags(repl.source_manager(), diags),
+            Err(ReplError::Exception(exception)) => report_exception(
+                repl.last_program().unwrap(),
+                repl.source_manager(),
+                &exception
+            )
+        }
+        chunk.clear();
+    }
+}
+
+/// A chunk is complete when all its brackets are closed
+fn is_chunk_complete(chunk: &str) -> bool {
+    let mut depth: i32 = 0;
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    for ch /*: char*/ in chunk.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0 && !in_string
+}
+
+unsafe fn create_args_vec(
+    alloc: &mut DefaultAlloc,
+    vt: &GenericTypeVT,
+    args: &[String]
+) -> Value {
+    let vec: Value = Value::new_container(pr47::builtins::vec::vec_ctor(), vt as *const _);
+    let vec_ref: &mut VMGenericVec = &mut *vec.get_as_mut_ptr::<VMGenericVec>();
+    for arg /*: &String*/ in args {
+        let arg: Value = Value::new_owned(arg.clone());
+        alloc.add_managed(arg);
+        vec_ref.inner.push(arg);
+    }
+    alloc.add_managed(vec);
+    vec
+}
+
+#[cfg(feature = "async")]
+fn run_program(
+    alloc: DefaultAlloc,
+    program: &CompiledProgram<DefaultAlloc>,
+    func_id: usize,
+    args: Vec<Value>
+) -> Result<Vec<Value>, Exception> {
+    // `block_on_future` requires a `'static` future, while the program always outlives the
+    // future since we block on it
+    let program: UncheckedSendSync<&'static CompiledProgram<DefaultAlloc>> =
+        unsafe { UncheckedSendSync::new(&*(program as *const _)) };
+    block_on_future(async move {
+        let program: &CompiledProgram<DefaultAlloc> = program.into_inner();
+        let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+            create_vm_main_thread(alloc, program).await?;
+        unsafe {
+            vm_thread_run_function::<_, false>(
+                UncheckedSendSync::new((&mut vm_thread, func_id, &args))
+            )?.await.into_inner()
+        }
+    })
+}
+
+#[cfg(not(feature = "async"))]
+fn run_program(
+    alloc: DefaultAlloc,
+    program: &CompiledProgram<DefaultAlloc>,
+    func_id: usize,
+    args: Vec<Value>
+) -> Result<Vec<Value>, Exception> {
+    unsafe { vm_run_function_sync(alloc, program, func_id, &args) }
+}
+
+unsafe fn value_to_exit_code(value: Value) -> i32 {
+    if value.is_value() {
+        match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+            ValueTypeTag::Int => value.vt_data.inner.int_value as i32,
+            ValueTypeTag::Bool => if value.vt_data.inner.bool_value { 0 } else { 1 },
+            ValueTypeTag::Float => 0
+        }
+    } else {
+        0
+    }
+}
+
+fn report_diags(source_mgr: &SourceManager, diags: Vec<Diagnostic>) {
+    for diag /*: Diagnostic*/ in diags {
+        let file_name: &str = source_mgr.file_name(diag.location.file_id);
+        let (source_line, coord): (&str, SourceCoord) = diag.location.compute_coord(source_mgr);
+
+        let mut message: String = diag_message(diag.diag_id).to_string();
+        for (i, arg) /*: (usize, &String)*/ in diag.args.iter().enumerate() {
+            message = message.replace(&format!("?{}", i), arg);
+        }
+
+        eprintln!(
+            "{}:{}:{}: {}: {}",
+            file_name,
+            coord.line + 1,
+            coord.col + 1,
+            if is_error(diag.diag_id) { "error" } else { "note" },
+            message
+        );
+        eprintln!("  |> {}", source_line.trim_end());
+    }
+}
+
+fn report_exception(
+    program: &CompiledProgram<DefaultAlloc>,
+    source_manager: &SourceManager,
+    exception: &Exception
+) {
+    eprintln!("uncaught exception: {}", exception);
+    for line /*: &str*/ in exception.render_backtrace(program, source_manager).lines() {
+        eprintln!("  {}", line);
+    }
+}
@@ -1,4 +1,5 @@
 use std::any::TypeId;
+use std::mem::size_of;
 use std::ptr::NonNull;
 
 use smallvec::SmallVec;
@@ -31,8 +32,16 @@ impl StaticBase<Closure> for Void {
         let vself: &Closure = unsafe { &*vself };
         Some(Box::new(vself.captures.iter().copied()))
     }
+
+    #[inline] fn heap_size(vself: *const Closure) -> usize {
+        let vself: &Closure = unsafe { &*vself };
+        if vself.captures.spilled() { vself.captures.capacity() * size_of::<Value>() } else { 0 }
+    }
 }
 
+/// Creates the vtable of closure types. By convention the only type argument of a closure type
+/// is the function type of the closure, like `closure<func(int) -> (int)>`, which `check_type`
+/// compares against when checking closures.
 pub fn create_closure_vt(
     tyck_info_pool: &mut TyckInfoPool,
     arg_types: &[NonNull<TyckInfo>]
@@ -49,6 +58,7 @@ pub fn create_closure_vt(
         #[cfg(not(debug_assertions))]
         move_out_fn: gen_impls::generic_move_out::<Closure>,
         children_fn: gen_impls::generic_children::<Closure>,
-        drop_fn: gen_impls::generic_drop::<Closure>
+        drop_fn: gen_impls::generic_drop::<Closure>,
+        size_fn: gen_impls::generic_size::<Closure>
     }
 }
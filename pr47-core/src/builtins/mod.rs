@@ -2,5 +2,6 @@ pub mod closure;
 pub mod object;
 pub mod set;
 pub mod vec;
+pub mod vm_error;
 
 #[cfg(test)] pub mod test_container;
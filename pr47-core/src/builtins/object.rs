@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::marker::PhantomPinned;
+use std::mem::size_of;
 
 use xjbutil::void::Void;
 
@@ -30,6 +31,12 @@ impl StaticBase<Object> for Void {
             Some(iter)
         }
     }
+
+    #[inline] fn heap_size(vself: *const Object) -> usize {
+        let fields: &HashMap<String, Value> = unsafe { &(*vself).fields };
+        fields.capacity() * size_of::<(String, Value)>()
+            + fields.keys().map(String::capacity).sum::<usize>()
+    }
 }
 
 #[cfg(all(feature = "al31fm2", not(feature = "al31fm2-builtin-ops")))]
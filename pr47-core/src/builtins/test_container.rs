@@ -118,6 +118,7 @@ pub fn create_test_container_vt<T: 'static>(tyck_info_pool: &mut TyckInfoPool) -
         #[cfg(not(debug_assertions))]
         move_out_fn: gen_impls::generic_move_out::<GenericTestContainer>,
         children_fn: gen_impls::generic_children::<GenericTestContainer>,
-        drop_fn: gen_impls::generic_drop::<GenericTestContainer>
+        drop_fn: gen_impls::generic_drop::<GenericTestContainer>,
+        size_fn: gen_impls::generic_size::<GenericTestContainer>
     }
 }
@@ -1,5 +1,6 @@
 use std::any::TypeId;
 use std::marker::{PhantomData, PhantomPinned};
+use std::mem::size_of;
 use std::ptr::NonNull;
 
 use xjbutil::mem::move_to_heap;
@@ -54,6 +55,10 @@ impl StaticBase<VMGenericVec> for Void {
             Some(iter)
         }
     }
+
+    fn heap_size(vself: *const VMGenericVec) -> usize {
+        unsafe { (*vself).inner.capacity() * size_of::<Value>() }
+    }
 }
 
 #[repr(transparent)]
@@ -94,6 +99,10 @@ impl<T> StaticBase<VMVec<T>> for Void
             Some(iter)
         }
     }
+
+    fn heap_size(vself: *const VMVec<T>) -> usize {
+        <Void as StaticBase<VMGenericVec>>::heap_size(vself as *const VMGenericVec)
+    }
 }
 
 pub fn create_vm_vec_vt(
@@ -112,7 +121,8 @@ pub fn create_vm_vec_vt(
         #[cfg(not(debug_assertions))]
         move_out_fn: gen_impls::generic_move_out::<VMGenericVec>,
         children_fn: gen_impls::generic_children::<VMGenericVec>,
-        drop_fn: gen_impls::generic_drop::<VMGenericVec>
+        drop_fn: gen_impls::generic_drop::<VMGenericVec>,
+        size_fn: gen_impls::generic_size::<VMGenericVec>
     }
 }
 
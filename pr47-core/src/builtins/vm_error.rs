@@ -0,0 +1,138 @@
+//! Script-visible forms of `UncheckedException`s
+//!
+//! Each catchable `UncheckedException` variant has a type of its own here, named after the
+//! variant, so that `catch (e DivideByZero)` in scripts only catches that kind of error. Objects
+//! of these types are only created when some exception handler actually catches the exception.
+//!
+//! All these types belong to the `VMError` family, so `catch (e VMError)` catches any of them.
+
+use std::any::TypeId;
+
+use xjbutil::void::Void;
+
+use crate::data::Value;
+use crate::data::exception::UncheckedException;
+use crate::data::traits::StaticBase;
+
+macro_rules! vm_error_types {
+    ($($name:ident),* $(,)?) => {
+        $(
+            pub struct $name {
+                pub message: String
+            }
+
+            impl StaticBase<$name> for Void {
+                fn type_name() -> String { stringify!($name).into() }
+            }
+        )*
+
+        /// Names and type ids of all error types, including `VMError`
+        const VM_ERROR_TYPES: [(&str, fn() -> TypeId); [$(stringify!($name)),*].len()] = [
+            $((stringify!($name), TypeId::of::<$name>)),*
+        ];
+    }
+}
+
+vm_error_types![
+    VMError,
+    AlreadyAwaited,
+    ArgCountMismatch,
+    RetCountMismatch,
+    NotCallable,
+    DivideByZero,
+    InvalidBinaryOp,
+    InvalidCastOp,
+    InvalidUnaryOp,
+    TypeCheckFailure,
+    OverloadCallFailure,
+    UnexpectedNull,
+    IndexOutOfBounds,
+    StackOverflow
+];
+
+pub fn vm_error_type_name(type_id: TypeId) -> Option<&'static str> {
+    VM_ERROR_TYPES.iter()
+        .find(|(_, error_type_id)| error_type_id() == type_id)
+        .map(|(type_name, _)| *type_name)
+}
+
+pub fn vm_error_type_id(type_name: &str) -> Option<TypeId> {
+    VM_ERROR_TYPES.iter()
+        .find(|(error_type_name, _)| *error_type_name == type_name)
+        .map(|(_, type_id)| type_id())
+}
+
+/// Returns `VMError` for all the other error types here
+pub fn vm_error_parent(type_id: TypeId) -> Option<TypeId> {
+    if type_id != TypeId::of::<VMError>() && vm_error_type_name(type_id).is_some() {
+        Some(TypeId::of::<VMError>())
+    } else {
+        None
+    }
+}
+
+/// Returns the type id scripts catch `exception` by, or `None` if `exception` is fatal
+///
+/// Ownership check failures indicate broken invariants of the VM itself, and join errors mean
+/// some coroutine panicked. Neither can be recovered from by scripts, so they are never caught.
+/// Running out of fuel or memory, or being interrupted, means the host wants the script stopped,
+/// so it is never caught either.
+pub fn catchable_type_id(exception: &UncheckedException) -> Option<TypeId> {
+    Some(match exception {
+        UncheckedException::AlreadyAwaited { .. } => TypeId::of::<AlreadyAwaited>(),
+        UncheckedException::ArgCountMismatch { .. } => TypeId::of::<ArgCountMismatch>(),
+        UncheckedException::RetCountMismatch { .. } => TypeId::of::<RetCountMismatch>(),
+        UncheckedException::NotCallable { .. } => TypeId::of::<NotCallable>(),
+        UncheckedException::DivideByZero => TypeId::of::<DivideByZero>(),
+        UncheckedException::InvalidBinaryOp { .. } => TypeId::of::<InvalidBinaryOp>(),
+        UncheckedException::InvalidCastOp { .. } => TypeId::of::<InvalidCastOp>(),
+        UncheckedException::InvalidUnaryOp { .. } => TypeId::of::<InvalidUnaryOp>(),
+        UncheckedException::TypeCheckFailure { .. } => TypeId::of::<TypeCheckFailure>(),
+        UncheckedException::OverloadCallFailure { .. } => TypeId::of::<OverloadCallFailure>(),
+        UncheckedException::UnexpectedNull { .. } => TypeId::of::<UnexpectedNull>(),
+        UncheckedException::IndexOutOfBounds { .. } => TypeId::of::<IndexOutOfBounds>(),
+        UncheckedException::StackOverflow { .. } => TypeId::of::<StackOverflow>(),
+        UncheckedException::OwnershipCheckFailure { .. }
+        | UncheckedException::OutOfFuel { .. }
+        | UncheckedException::OutOfMemory { .. }
+        | UncheckedException::Interrupted => return None,
+        #[cfg(feature = "async")]
+        UncheckedException::JoinError { .. } => return None
+    })
+}
+
+/// Creates the script-visible object for `exception`, carrying its message
+///
+/// The returned object is not managed by any allocator yet. `exception` should be catchable.
+pub fn reify(exception: &UncheckedException) -> Value {
+    let message: String = exception.to_string();
+    match exception {
+        UncheckedException::AlreadyAwaited { .. } =>
+            Value::new_owned(AlreadyAwaited { message }),
+        UncheckedException::ArgCountMismatch { .. } =>
+            Value::new_owned(ArgCountMismatch { message }),
+        UncheckedException::RetCountMismatch { .. } =>
+            Value::new_owned(RetCountMismatch { message }),
+        UncheckedException::NotCallable { .. } => Value::new_owned(NotCallable { message }),
+        UncheckedException::DivideByZero => Value::new_owned(DivideByZero { message }),
+        UncheckedException::InvalidBinaryOp { .. } =>
+            Value::new_owned(InvalidBinaryOp { message }),
+        UncheckedException::InvalidCastOp { .. } => Value::new_owned(InvalidCastOp { message }),
+        UncheckedException::InvalidUnaryOp { .. } =>
+            Value::new_owned(InvalidUnaryOp { message }),
+        UncheckedException::TypeCheckFailure { .. } =>
+            Value::new_owned(TypeCheckFailure { message }),
+        UncheckedException::OverloadCallFailure { .. } =>
+            Value::new_owned(OverloadCallFailure { message }),
+        UncheckedException::UnexpectedNull { .. } => Value::new_owned(UnexpectedNull { message }),
+        UncheckedException::IndexOutOfBounds { .. } =>
+            Value::new_owned(IndexOutOfBounds { message }),
+        UncheckedException::StackOverflow { .. } => Value::new_owned(StackOverflow { message }),
+        UncheckedException::OwnershipCheckFailure { .. }
+        | UncheckedException::OutOfFuel { .. }
+        | UncheckedException::OutOfMemory { .. }
+        | UncheckedException::Interrupted => unreachable!("fatal exception"),
+        #[cfg(feature = "async")]
+        UncheckedException::JoinError { .. } => unreachable!("fatal exception")
+    }
+}
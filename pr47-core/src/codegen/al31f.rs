@@ -0,0 +1,1080 @@
+//! # Code generator for the `al31f` VM
+//!
+//! Registers are allocated in a stack-like manner: function parameters take the first registers,
+//! each local variable holds one register until the end of its enclosing block, and temporaries
+//! are released as soon as the statement using them is done. Functions with exception handlers
+//! get one extra register at the end of their frames, where the VM puts caught exceptions.
+//!
+//! Script functions are numbered in their declaration order, so the `n`-th function with a body
+//! in `DeclContext::func_decls` gets function id `n`. Global variables are numbered in their
+//! declaration order likewise, and live in the VM instead of registers. A function initializing
+//! them is appended as `init_proc`.
+//!
+//! Every expression and statement records the range of instructions generated for it, and the
+//! line table of the program is built from these records at last. An instruction belongs to the
+//! innermost construct it was generated for.
+
+mod expr;
+mod stmt;
+
+use std::collections::HashMap;
+use std::mem::take;
+use std::ptr::NonNull;
+
+use xjbutil::slice_arena::SliceArena;
+
+use crate::data::Value;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
+use crate::data::wrapper::OwnershipInfo;
+use crate::diag::{diag_data, DiagContext};
+use crate::diag::location::{SourceLoc, SourceRange};
+use crate::ffi::sync_fn::Function as FFIFunction;
+use crate::sema::arena::Arena;
+use crate::sema::decl::{FuncDecl, ObjectDecl};
+use crate::sema::decl_context::DeclContext;
+use crate::sema::expr::Expr;
+use crate::sema::stmt::CompoundStmt;
+use crate::vm::al31f::Combustor;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    DebugInfo,
+    ExceptionHandlingBlock,
+    LineTableEntry,
+    OverloadTable
+};
+use crate::vm::al31f::ffi_registry::FFIRegistry;
+use crate::vm::al31f::insc::Insc;
+
+#[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
+#[cfg(feature = "async")] use crate::vm::al31f::{AL31F, AsyncCombustor};
+
+/// How a function declaration is called
+#[derive(Clone, Copy)]
+enum FuncRef {
+    Script(usize),
+    Host(usize),
+    #[cfg(feature = "async")]
+    AsyncHost(usize)
+}
+
+pub struct CodegenAL31F<'s, 'd, A: Alloc> {
+    arena: &'d Arena<'s>,
+    tyck_info_pool: TyckInfoPool,
+    ffi_registry: &'d FFIRegistry<A>,
+    diag: &'d mut DiagContext,
+    #[cfg(feature = "optimized-rtlc")]
+    rtlc: bool,
+    #[cfg(feature = "async")]
+    allow_async: bool,
+    initialized_globals: usize,
+
+    slice_arena: SliceArena<8192, 8>,
+    code: Vec<Insc>,
+    const_pool: Vec<Value>,
+    string_consts: HashMap<String, usize>,
+    functions: Vec<CompiledFunction>,
+    overload_tables: Vec<OverloadTable>,
+    /// Instruction ranges `[start, end)` and the source ranges they come from, recorded after
+    /// the instructions get generated, so inner constructs come before outer ones
+    source_spans: Vec<(usize, usize, SourceRange)>,
+    ffi_funcs: Vec<&'static dyn FFIFunction<Combustor<A>>>,
+    ffi_func_names: Vec<String>,
+    #[cfg(feature = "async")]
+    async_ffi_funcs: Vec<&'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>>,
+    #[cfg(feature = "async")]
+    async_ffi_func_names: Vec<String>,
+    func_refs: HashMap<*const FuncDecl<'s>, FuncRef>,
+    global_ids: HashMap<*const ObjectDecl<'s>, usize>,
+
+    // following fields are states of the function being generated
+    ret_types: Vec<NonNull<TyckInfo>>,
+    local_regs: HashMap<*const ObjectDecl<'s>, usize>,
+    next_reg: usize,
+    max_reg: usize,
+    exc_handlers: Vec<ExceptionHandlingBlock>,
+    /// Addresses of the `Move` instructions loading caught exceptions, patched after the frame
+    /// size is known
+    catch_moves: Vec<usize>,
+    /// `try` statements enclosing the statement being generated, innermost last
+    try_stack: Vec<TryContext<'s>>,
+    /// Registers holding exceptions caught by enclosing `catch` clauses, for `rethrow`
+    caught_regs: Vec<usize>
+}
+
+/// A `try` statement being generated
+///
+/// Returning from inside a `try` statement runs its `finally` block before the `return`, so
+/// `finally` blocks get copied to every `return`. Exceptions thrown by these copies should not
+/// be handled by the `try` statements they were copied out of, so handlers of these statements
+/// skip the copied instructions, recorded as holes.
+struct TryContext<'s> {
+    finally_body: Option<*const CompoundStmt<'s>>,
+    /// Length of `caught_regs` when the statement started
+    caught_depth: usize,
+    /// Instruction ranges `[start, end)` handlers of the statement should skip, in order
+    holes: Vec<(usize, usize)>
+}
+
+impl<'s, 'd, A: Alloc> CodegenAL31F<'s, 'd, A> {
+    /// Creates a code generator. `tyck_info_pool` should be the one used by sema, and it will be
+    /// moved into the generated program since instructions refer to type information in it.
+    pub fn new(
+        arena: &'d Arena<'s>,
+        tyck_info_pool: TyckInfoPool,
+        ffi_registry: &'d FFIRegistry<A>,
+        diag: &'d mut DiagContext
+    ) -> Self {
+        Self {
+            arena,
+            tyck_info_pool,
+            ffi_registry,
+            diag,
+            #[cfg(feature = "optimized-rtlc")]
+            rtlc: true,
+            #[cfg(feature = "async")]
+            allow_async: true,
+            initialized_globals: 0,
+
+            slice_arena: SliceArena::new(),
+            code: Vec::new(),
+            const_pool: Vec::new(),
+            string_consts: HashMap::new(),
+            functions: Vec::new(),
+            overload_tables: Vec::new(),
+            source_spans: Vec::new(),
+            ffi_funcs: Vec::new(),
+            ffi_func_names: Vec::new(),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: Vec::new(),
+            #[cfg(feature = "async")]
+            async_ffi_func_names: Vec::new(),
+            func_refs: HashMap::new(),
+            global_ids: HashMap::new(),
+
+            ret_types: Vec::new(),
+            local_regs: HashMap::new(),
+            next_reg: 0,
+            max_reg: 0,
+            exc_handlers: Vec::new(),
+            catch_moves: Vec::new(),
+            try_stack: Vec::new(),
+            caught_regs: Vec::new()
+        }
+    }
+
+    /// Sets whether host functions are called with run time lifetime checking (RTLC). Enabled by
+    /// default.
+    #[cfg(feature = "optimized-rtlc")]
+    #[must_use] pub fn with_rtlc(mut self, rtlc: bool) -> Self {
+        self.rtlc = rtlc;
+        self
+    }
+
+    /// Sets whether `await`, `spawn` and async host functions are allowed. Enabled by default.
+    #[cfg(feature = "async")]
+    #[must_use] pub fn with_async(mut self, allow_async: bool) -> Self {
+        self.allow_async = allow_async;
+        self
+    }
+
+    /// Sets how many leading global variables have been initialized by an earlier program running
+    /// on the same VM, so that `init_proc` leaves them untouched. Zero by default.
+    #[must_use] pub fn with_initialized_globals(mut self, count: usize) -> Self {
+        self.initialized_globals = count;
+        self
+    }
+
+    /// Generates a program from declarations checked by `SemaPhase2::check_program`. Returns
+    /// `None` if there were errors during semantic analysis, or errors during code generation.
+    pub fn generate(self, decl_context: &DeclContext<'s>) -> Option<CompiledProgram<A>> {
+        self.try_generate(decl_context).ok()
+    }
+
+    /// Like `generate`, but gives the type pool back on failure, so that it can be used for
+    /// further compilation
+    pub fn try_generate(
+        mut self,
+        decl_context: &DeclContext<'s>
+    ) -> Result<CompiledProgram<A>, TyckInfoPool> {
+        let mut script_funcs: Vec<&'d FuncDecl<'s>> = Vec::new();
+        let mut bound_ffi_funcs: HashMap<&'s str, FuncRef> = HashMap::new();
+        for func_decl /*: &ArenaPtr<FuncDecl>*/ in decl_context.func_decls.iter() {
+            let func_decl: &'d FuncDecl<'s> = func_decl.get_tricky(self.arena);
+            let func_ref: FuncRef = if func_decl.is_ffi() {
+                if let Some(func_ref) = bound_ffi_funcs.get(func_decl.name) {
+                    *func_ref
+                } else if let Some(func_ref) = self.bind_ffi_func(func_decl) {
+                    bound_ffi_funcs.insert(func_decl.name, func_ref);
+                    func_ref
+                } else {
+                    continue;
+                }
+            } else {
+                script_funcs.push(func_decl);
+                FuncRef::Script(script_funcs.len() - 1)
+            };
+            self.func_refs.insert(func_decl as *const FuncDecl<'s>, func_ref);
+        }
+
+        if self.diag.has_error() {
+            return Err(self.tyck_info_pool);
+        }
+
+        let mut globals: Vec<&'d ObjectDecl<'s>> = Vec::new();
+        for object_decl /*: &ArenaPtr<ObjectDecl>*/ in decl_context.object_decls.iter() {
+            let object_decl: &'d ObjectDecl<'s> = object_decl.get_tricky(self.arena);
+            if !object_decl.is_const {
+                self.global_ids.insert(object_decl as *const ObjectDecl<'s>, globals.len());
+                globals.push(object_decl);
+            }
+        }
+
+        let mut func_ids: HashMap<String, usize> = HashMap::new();
+        let mut func_names: Vec<String> = Vec::with_capacity(script_funcs.len());
+        for (func_id, func_decl) /*: (usize, &&FuncDecl)*/ in script_funcs.iter().enumerate() {
+            self.gen_func(func_decl);
+            func_ids.insert(func_decl.name.to_string(), func_id);
+            func_names.push(func_decl.name.to_string());
+        }
+        if self.diag.has_error() {
+            return Err(self.tyck_info_pool);
+        }
+
+        let init_proc: usize = self.functions.len();
+        self.gen_init_proc(&globals);
+        if self.diag.has_error() {
+            return Err(self.tyck_info_pool);
+        }
+
+        let debug_info: DebugInfo = DebugInfo {
+            line_table: self.build_line_table(),
+            func_names: func_names.into_boxed_slice()
+        };
+        Ok(CompiledProgram {
+            slice_arena: self.slice_arena,
+            code: self.code.into_boxed_slice(),
+            const_pool: self.const_pool.into_boxed_slice(),
+            tyck_info_pool: self.tyck_info_pool,
+            global_names: globals.iter()
+                .map(|object_decl: &&ObjectDecl<'s>| object_decl.name.to_string())
+                .collect(),
+            init_proc,
+            functions: self.functions.into_boxed_slice(),
+            func_ids,
+            generic_vts: Box::new([]),
+            overload_tables: self.overload_tables.into_boxed_slice(),
+            exception_parents: self.ffi_registry.exception_parents().clone(),
+            exception_type_names: self.ffi_registry.exception_type_names().clone(),
+            debug_info,
+            ffi_funcs: self.ffi_funcs.into_boxed_slice(),
+            ffi_func_names: self.ffi_func_names.into_boxed_slice(),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: self.async_ffi_funcs.into_boxed_slice(),
+            #[cfg(feature = "async")]
+            async_ffi_func_names: self.async_ffi_func_names.into_boxed_slice()
+        })
+    }
+
+    fn bind_ffi_func(&mut self, func_decl: &FuncDecl<'s>) -> Option<FuncRef> {
+        if let Some(ffi_func) = self.ffi_registry.get_func(func_decl.name) {
+            self.ffi_funcs.push(ffi_func);
+            self.ffi_func_names.push(func_decl.name.to_string());
+            return Some(FuncRef::Host(self.ffi_funcs.len() - 1));
+        }
+
+        let range: SourceRange = func_decl.concrete.func_name.source_range();
+
+        #[cfg(feature = "async")]
+        if let Some(async_ffi_func) = self.ffi_registry.get_async_func(func_decl.name) {
+            if !self.allow_async {
+                self.diag_async_disabled(range);
+                return None;
+            }
+
+            // async host functions return promises, which can only be typed `any` for now
+            if func_decl.ret_types.len() != 1
+                || !unsafe { func_decl.ret_types[0].as_ref() }.is_any()
+            {
+                self.diag.diag(range.left(), diag_data::err_async_ffi_ret_type_0)
+                    .add_arg(func_decl.name)
+                    .add_mark(range.into())
+                    .emit();
+                return None;
+            }
+
+            self.async_ffi_funcs.push(async_ffi_func);
+            self.async_ffi_func_names.push(func_decl.name.to_string());
+            return Some(FuncRef::AsyncHost(self.async_ffi_funcs.len() - 1));
+        }
+
+        self.diag.diag(range.left(), diag_data::err_unresolved_ffi_func_0)
+            .add_arg(func_decl.name)
+            .add_mark(range.into())
+            .emit();
+        None
+    }
+
+    fn gen_func(&mut self, func_decl: &FuncDecl<'s>) {
+        self.ret_types = func_decl.ret_types.clone();
+        self.local_regs.clear();
+        self.exc_handlers.clear();
+        self.catch_moves.clear();
+
+        let params: &[_] = &func_decl.param_decl_context.object_decls;
+        let mut param_tyck_info: Vec<Option<NonNull<TyckInfo>>> = Vec::with_capacity(params.len());
+        for (i, param) /*: (usize, &ArenaPtr<ObjectDecl>)*/ in params.iter().enumerate() {
+            let param: &ObjectDecl<'s> = param.get_tricky(self.arena);
+            self.local_regs.insert(param as *const ObjectDecl<'s>, i);
+            param_tyck_info.push(if unsafe { param.ty.as_ref() }.is_any() {
+                None
+            } else {
+                Some(self.runtime_type(param.ty))
+            });
+        }
+        self.next_reg = params.len();
+        self.max_reg = params.len();
+
+        let start_addr: usize = self.code.len();
+        let body = func_decl.func_body.as_ref()
+            .expect("function bodies should have been checked");
+        self.gen_compound_stmt(body);
+        if !body.always_exits() {
+            self.code.push(Insc::ReturnNothing);
+        }
+        self.mark_source(start_addr, func_decl.concrete.func_kwd_range);
+
+        let compiled_function: CompiledFunction = if self.exc_handlers.is_empty() {
+            CompiledFunction::new(
+                start_addr,
+                params.len(),
+                func_decl.ret_types.len(),
+                self.max_reg,
+                param_tyck_info.into_boxed_slice()
+            )
+        } else {
+            let stack_size: usize = self.max_reg + 1;
+            for catch_move /*: &usize*/ in self.catch_moves.iter() {
+                if let Insc::Move(src, _) = &mut self.code[*catch_move] {
+                    *src = stack_size - 1;
+                }
+            }
+
+            CompiledFunction::new_with_exc(
+                start_addr,
+                params.len(),
+                func_decl.ret_types.len(),
+                stack_size,
+                param_tyck_info.into_boxed_slice(),
+                take(&mut self.exc_handlers).into_boxed_slice()
+            )
+        };
+        self.functions.push(compiled_function);
+    }
+
+    /// Generates `init_proc`, which evaluates initializers of global variables in order, skipping
+    /// the ones already initialized
+    fn gen_init_proc(&mut self, globals: &[&'d ObjectDecl<'s>]) {
+        self.ret_types.clear();
+        self.local_regs.clear();
+        self.exc_handlers.clear();
+        self.catch_moves.clear();
+        self.next_reg = 0;
+        self.max_reg = 0;
+
+        let start_addr: usize = self.code.len();
+        let initialized_globals: usize = self.initialized_globals;
+        for (global_id, global) /*: (usize, &&ObjectDecl)*/ in
+            globals.iter().enumerate().skip(initialized_globals)
+        {
+            let decl_addr: usize = self.code.len();
+            let init: Expr<'s> = global.init.expect("global variables should have initializers");
+            let reg: usize = self.gen_operand(&init);
+            if needs_tyck(Some(global.ty), init.get_type(self.arena)) {
+                self.emit_tyck(reg, global.ty);
+            }
+            self.code.push(Insc::StoreGlobal(reg, global_id));
+            self.next_reg = 0;
+            if let Some(concrete) = global.concrete {
+                self.mark_source(decl_addr, concrete.kwd_range);
+            }
+        }
+        self.code.push(Insc::ReturnNothing);
+
+        self.functions.push(
+            CompiledFunction::new(start_addr, 0, 0, self.max_reg, Box::new([]))
+        );
+    }
+
+    fn async_enabled(&self) -> bool {
+        #[cfg(feature = "async")]
+        return self.allow_async;
+        #[cfg(not(feature = "async"))]
+        return false;
+    }
+
+    fn diag_async_disabled(&mut self, range: SourceRange) {
+        self.diag.diag(range.left(), diag_data::err_async_disabled)
+            .add_mark(range.into())
+            .emit();
+    }
+
+    fn alloc_reg(&mut self) -> usize {
+        let reg: usize = self.next_reg;
+        self.next_reg += 1;
+        if self.next_reg > self.max_reg {
+            self.max_reg = self.next_reg;
+        }
+        reg
+    }
+
+    /// Records that instructions generated since `start_addr` come from `range`
+    fn mark_source(&mut self, start_addr: usize, range: SourceRange) {
+        if self.code.len() > start_addr {
+            self.source_spans.push((start_addr, self.code.len(), range));
+        }
+    }
+
+    fn build_line_table(&self) -> Box<[LineTableEntry]> {
+        let mut ranges: Vec<SourceRange> = vec![SourceRange::unknown(); self.code.len()];
+        // outer constructs are recorded after inner ones, so walk backwards to let inner ones win
+        for (start_addr, end_addr, range) /*: &(usize, usize, SourceRange)*/ in
+            self.source_spans.iter().rev()
+        {
+            ranges[*start_addr..*end_addr].fill(*range);
+        }
+
+        let mut line_table: Vec<LineTableEntry> = Vec::new();
+        for (insc_ptr, range) /*: (usize, SourceRange)*/ in ranges.into_iter().enumerate() {
+            let (begin, end): (SourceLoc, SourceLoc) = (range.left(), range.right());
+            if !matches!(
+                line_table.last(),
+                Some(last) if last.file_id == begin.file_id
+                    && last.offset_begin == begin.offset
+                    && last.offset_end == end.offset
+            ) {
+                line_table.push(LineTableEntry::new(
+                    insc_ptr,
+                    begin.file_id,
+                    begin.offset,
+                    end.offset
+                ));
+            }
+        }
+        line_table.into_boxed_slice()
+    }
+
+    fn make_slice(&self, regs: &[usize]) -> &'static [usize] {
+        unsafe { self.slice_arena.unsafe_make(regs) }
+    }
+
+    fn make_str(&self, s: &str) -> NonNull<str> {
+        unsafe {
+            let bytes: &'static [u8] = self.slice_arena.unsafe_make(s.as_bytes());
+            NonNull::from(std::str::from_utf8_unchecked(bytes))
+        }
+    }
+
+    /// Adds a string to the constant pool, strings with same content share one entry
+    fn string_const(&mut self, s: &str) -> usize {
+        if let Some(const_id) = self.string_consts.get(s) {
+            return *const_id;
+        }
+
+        let value: Value = Value::new_owned(s.to_string());
+        unsafe { value.set_ownership_info(OwnershipInfo::GlobalConst); }
+        self.const_pool.push(value);
+        self.string_consts.insert(s.to_string(), self.const_pool.len() - 1);
+        self.const_pool.len() - 1
+    }
+
+    /// `char`s are represented as integers at run time
+    fn runtime_type(&mut self, ty: NonNull<TyckInfo>) -> NonNull<TyckInfo> {
+        let char_type: NonNull<TyckInfo> = self.tyck_info_pool.get_char_type();
+        if ty == char_type {
+            self.tyck_info_pool.get_int_type()
+        } else if let TyckInfo::Nullable(inner) = unsafe { ty.as_ref() } {
+            if *inner == char_type {
+                let int_type: NonNull<TyckInfo> = self.tyck_info_pool.get_int_type();
+                self.tyck_info_pool.create_nullable_type(int_type)
+            } else {
+                ty
+            }
+        } else {
+            ty
+        }
+    }
+
+    /// Checks the value in register `reg` against `ty` at run time
+    fn emit_tyck(&mut self, reg: usize, ty: NonNull<TyckInfo>) {
+        match unsafe { ty.as_ref() } {
+            TyckInfo::AnyType => {},
+            TyckInfo::Nullable(_) => {
+                let ty: NonNull<TyckInfo> = self.runtime_type(ty);
+                self.code.push(Insc::TypeCheck(reg, ty));
+            },
+            _ => {
+                // `check_type` does not accept `null` values for non-nullable types
+                let ty: NonNull<TyckInfo> = self.runtime_type(ty);
+                self.code.push(Insc::NullCheck(reg));
+                self.code.push(Insc::TypeCheck(reg, ty));
+            }
+        }
+    }
+
+    /// Emits a jump instruction whose destination is to be patched by `patch_jump`, returns its
+    /// address
+    fn emit_jump(&mut self, insc: Insc) -> usize {
+        self.code.push(insc);
+        self.code.len() - 1
+    }
+
+    /// Patches the jump instruction at `addr` to jump to the next instruction to be emitted
+    fn patch_jump(&mut self, addr: usize) {
+        let dest: usize = self.code.len();
+        match &mut self.code[addr] {
+            Insc::Jump(jump_dest)
+            | Insc::JumpIfTrue(_, jump_dest)
+            | Insc::JumpIfFalse(_, jump_dest) => *jump_dest = dest,
+            _ => unreachable!("not a jump instruction")
+        }
+    }
+}
+
+/// Checks if a value statically typed `src` requires checking before being used as `dest`,
+/// assuming that sema has confirmed the types are compatible.
+fn needs_tyck(dest: Option<NonNull<TyckInfo>>, src: Option<NonNull<TyckInfo>>) -> bool {
+    match dest {
+        Some(dest) if !unsafe { dest.as_ref() }.is_any() => match src {
+            Some(src) => unsafe { src.as_ref() }.is_any(),
+            None => true
+        },
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use crate::builtins::object::Object;
+    use crate::data::Value;
+    use crate::data::exception::{ExceptionInner, UncheckedException};
+    use crate::data::tyck::TyckInfoPool;
+    use crate::diag::DiagContext;
+    use crate::parse::parser::Parser;
+    use crate::sema::arena::Arena;
+    use crate::sema::decl_context::DeclContext;
+    use crate::sema::phase2::SemaPhase2;
+    use crate::sema::scope::{Scope, ScopeKind};
+    use crate::syntax::ConcreteProgram;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::exception::Exception;
+    use crate::vm::al31f::executor::vm_run_function_sync;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+    use crate::vm::al31f::host::ScriptFunction;
+
+    use super::CodegenAL31F;
+
+    fn compile(source: &'static str) -> CompiledProgram<DefaultAlloc> {
+        // concrete syntax trees are leaked so that they outlive the arena
+        let parse_diag: &'static RefCell<DiagContext> =
+            Box::leak(Box::new(RefCell::new(DiagContext::new())));
+        let mut parser: Parser = Parser::new(0, source, parse_diag);
+        let program: &'static ConcreteProgram = Box::leak(Box::new(parser.parse()));
+
+        let mut arena: Arena = Arena::new();
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let mut diag: DiagContext = DiagContext::new();
+        let mut sema: SemaPhase2 = SemaPhase2::new(
+            Scope::new(ScopeKind::Global),
+            &mut arena,
+            &mut tyck_info_pool,
+            &mut diag
+        );
+        let decl_context: DeclContext = sema.check_program(program);
+
+        let ffi_registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+        let codegen: CodegenAL31F<DefaultAlloc> =
+            CodegenAL31F::new(&arena, tyck_info_pool, &ffi_registry, &mut diag);
+        codegen.generate(&decl_context).unwrap()
+    }
+
+    fn run_int(program: &CompiledProgram<DefaultAlloc>, func_id: usize, args: &[Value]) -> i64 {
+        let result: Vec<Value> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), program, func_id, args)
+        }.unwrap_or_else(|_| panic!("should not throw"));
+        assert_eq!(result.len(), 1);
+        assert!(result[0].is_value());
+        unsafe { result[0].vt_data.inner.int_value }
+    }
+
+    #[test]
+    fn test_codegen_fibonacci() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func fib(n int): int {
+                if n <= 1 {
+                    return n;
+                }
+                return fib(n - 1) + fib(n - 2);
+            }
+        "#);
+        assert_eq!(program.func_ids["fib"], 0);
+        assert_eq!(run_int(&program, 0, &[Value::new_int(20)]), 6765);
+    }
+
+    #[test]
+    fn test_codegen_loops() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            const STEP = 1;
+
+            func sum_to(n int): int {
+                var s = 0;
+                var i = 0;
+                while i < n {
+                    i += STEP;
+                    if i % 2 == 0 {
+                        s += i;
+                    } else {
+                        s -= 1;
+                    }
+                }
+                do {
+                    s *= 2;
+                } while s < 0;
+                return s;
+            }
+        "#);
+        // (2 + 4 + 6 + 8 + 10 - 5) * 2
+        assert_eq!(run_int(&program, 0, &[Value::new_int(10)]), 50);
+    }
+
+    #[test]
+    fn test_codegen_try_catch() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func check(x int): int {
+                if x > 100 {
+                    throw "too large";
+                }
+                return x;
+            }
+
+            func guarded(x int): int {
+                try {
+                    return check(x) * 2;
+                } catch (e string) {
+                    if e == "too large" {
+                        return -1;
+                    }
+                    return -2;
+                }
+            }
+        "#);
+        assert_eq!(run_int(&program, 1, &[Value::new_int(21)]), 42);
+        assert_eq!(run_int(&program, 1, &[Value::new_int(514)]), -1);
+    }
+
+    #[test]
+    fn test_codegen_catch_unchecked() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func divide(a int, b int): int {
+                return a / b;
+            }
+
+            func safe_divide(a int, b int): int {
+                try {
+                    return divide(a, b);
+                } catch (e DivideByZero) {
+                    return -1;
+                }
+            }
+
+            func wrong_handler(a int, b int): int {
+                try {
+                    return divide(a, b);
+                } catch (e IndexOutOfBounds) {
+                    return -1;
+                }
+            }
+
+            func to_int(x any): int {
+                try {
+                    return x as int;
+                } catch (e InvalidCastOp) {
+                    return -2;
+                }
+            }
+        "#);
+        assert_eq!(run_int(&program, 1, &[Value::new_int(84), Value::new_int(2)]), 42);
+        assert_eq!(run_int(&program, 1, &[Value::new_int(84), Value::new_int(0)]), -1);
+        assert_eq!(run_int(&program, 3, &[Value::new_float(4.0)]), 4);
+        assert_eq!(run_int(&program, 3, &[Value::new_null()]), -2);
+
+        let result: Result<Vec<Value>, Exception> = unsafe {
+            vm_run_function_sync(
+                DefaultAlloc::new(),
+                &program,
+                2,
+                &[Value::new_int(1), Value::new_int(0)]
+            )
+        };
+        match result.map_err(|e: Exception| e.inner) {
+            Err(ExceptionInner::Unchecked(UncheckedException::DivideByZero)) => {},
+            _ => panic!("should not be caught")
+        }
+    }
+
+    #[test]
+    fn test_codegen_finally() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func finally_normal(x int): int {
+                var r int = 0;
+                try {
+                    r = x;
+                } finally {
+                    r = r + 100;
+                }
+                return r;
+            }
+
+            func finally_keeps_return(x int): int {
+                var r int = x;
+                try {
+                    return r;
+                } finally {
+                    r = 0;
+                }
+            }
+
+            func finally_overrides_return(): int {
+                try {
+                    return 1;
+                } finally {
+                    return 2;
+                }
+            }
+
+            func finally_on_throw(x int): int {
+                var r int = 0;
+                try {
+                    try {
+                        r = 10 / x;
+                    } finally {
+                        r = r + 1;
+                    }
+                } catch (e DivideByZero) {
+                    return r * 100;
+                }
+                return r;
+            }
+
+            func return_from_catch(x int): int {
+                var r int = 0;
+                try {
+                    r = 10 / x;
+                } catch (e any) {
+                    r = 5;
+                    return r;
+                } finally {
+                    r = 9;
+                }
+                return r;
+            }
+
+            func throw_from_finally(x int): int {
+                try {
+                    try {
+                        return 1;
+                    } catch (e any) {
+                        return -1;
+                    } finally {
+                        var y int = 10 / x;
+                    }
+                } catch (e DivideByZero) {
+                    return -2;
+                }
+            }
+        "#);
+        assert_eq!(run_int(&program, 0, &[Value::new_int(7)]), 107);
+        assert_eq!(run_int(&program, 1, &[Value::new_int(7)]), 7);
+        assert_eq!(run_int(&program, 2, &[]), 2);
+        assert_eq!(run_int(&program, 3, &[Value::new_int(5)]), 3);
+        assert_eq!(run_int(&program, 3, &[Value::new_int(0)]), 100);
+        assert_eq!(run_int(&program, 4, &[Value::new_int(2)]), 9);
+        assert_eq!(run_int(&program, 4, &[Value::new_int(0)]), 5);
+        assert_eq!(run_int(&program, 5, &[Value::new_int(1)]), 1);
+        assert_eq!(run_int(&program, 5, &[Value::new_int(0)]), -2);
+    }
+
+    #[test]
+    fn test_codegen_catch_all_and_rethrow() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func catch_any(x int): int {
+                try {
+                    return 10 / x;
+                } catch (e any) {
+                    return -1;
+                }
+            }
+
+            func catch_family(x any): int {
+                try {
+                    return x as int;
+                } catch (e VMError) {
+                    return -3;
+                }
+            }
+
+            func rethrow_caught(x int): int {
+                var r int = 0;
+                try {
+                    try {
+                        r = 10 / x;
+                    } catch (e DivideByZero) {
+                        r = 7;
+                        rethrow;
+                    }
+                } catch (e DivideByZero) {
+                    return r;
+                }
+                return r;
+            }
+
+            func rethrow_uncaught(x int): int {
+                try {
+                    return 10 / x;
+                } catch (e any) {
+                    e = 0;
+                    rethrow;
+                }
+            }
+        "#);
+        assert_eq!(run_int(&program, 0, &[Value::new_int(5)]), 2);
+        assert_eq!(run_int(&program, 0, &[Value::new_int(0)]), -1);
+        assert_eq!(run_int(&program, 1, &[Value::new_int(3)]), 3);
+        assert_eq!(run_int(&program, 1, &[Value::new_null()]), -3);
+        assert_eq!(run_int(&program, 2, &[Value::new_int(5)]), 2);
+        assert_eq!(run_int(&program, 2, &[Value::new_int(0)]), 7);
+
+        let result: Result<Vec<Value>, Exception> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), &program, 3, &[Value::new_int(0)])
+        };
+        match result.map_err(|e: Exception| e.inner) {
+            Err(ExceptionInner::Checked(_)) => {},
+            _ => panic!("should be thrown again")
+        }
+    }
+
+    #[test]
+    fn test_codegen_string_literals() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func plain(): string {
+                return "ab";
+            }
+
+            func escaped(): string {
+                return "a\tb\u{5B57}\"\\\n";
+            }
+
+            func raw(): string {
+                return `a\tb`;
+            }
+
+            func concat(s string): string {
+                return s + "c";
+            }
+        "#);
+        let get = |name: &str| -> String {
+            let func: ScriptFunction<DefaultAlloc, (), (String,)> =
+                program.get_function(name).unwrap();
+            func.call(DefaultAlloc::new(), ()).unwrap().0
+        };
+        let plain: String = get("plain");
+        assert_eq!(plain, "ab");
+        assert_eq!(plain.len(), 2);
+        assert_eq!(get("escaped"), "a\tb\u{5B57}\"\\\n");
+        assert_eq!(get("raw"), "a\\tb");
+
+        let concat: ScriptFunction<DefaultAlloc, (String,), (String,)> =
+            program.get_function("concat").unwrap();
+        assert_eq!(
+            concat.call(DefaultAlloc::new(), ("ab".to_string(),)).unwrap(),
+            ("abc".to_string(),)
+        );
+    }
+
+    #[test]
+    fn test_codegen_dynamic_values() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func identity(x any): any {
+                return x;
+            }
+
+            func concat_len(a string, b string): int {
+                var c any = a + b;
+                var d string = identity(c);
+                if (d != a + b) || !(d == c as string) {
+                    return 0;
+                }
+                return (identity(7) as int) + (identity(true) as int);
+            }
+        "#);
+        let a: Value = Value::new_owned(String::from("abc"));
+        let b: Value = Value::new_owned(String::from("def"));
+        assert_eq!(run_int(&program, 1, &[a, b]), 8);
+        unsafe {
+            let _ = Box::from_raw(a.get_as_dyn_base());
+            let _ = Box::from_raw(b.get_as_dyn_base());
+        }
+    }
+
+    #[test]
+    fn test_codegen_call_any() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func add(a int, b int): int {
+                return a + b;
+            }
+
+            func call_any(f any, n int): int {
+                try {
+                    if n == 2 {
+                        return f(n, 40);
+                    }
+                    return f(n);
+                } catch (e NotCallable) {
+                    return -1;
+                } catch (e ArgCountMismatch) {
+                    return -2;
+                }
+            }
+
+            func test(): int {
+                var f any = add;
+                return call_any(f, 2) * 100 + call_any(f, 1) * 10 + call_any(233, 2);
+            }
+        "#);
+        // 42 * 100 + (-2) * 10 + (-1)
+        assert_eq!(run_int(&program, 2, &[]), 4179);
+        assert_eq!(run_int(&program, 1, &[Value::new_null(), Value::new_int(2)]), -1);
+    }
+
+    #[test]
+    fn test_codegen_compound_assign_once() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            var calls = 0;
+
+            func touch(o object): object {
+                calls += 1;
+                return o;
+            }
+
+            func key(): string {
+                calls += 10;
+                return "n";
+            }
+
+            func bump(o object): int {
+                touch(o).n += 1;
+                touch(o)[key()] *= 3;
+                return calls;
+            }
+        "#);
+        let mut object: Object = Object::new();
+        object.fields.insert("n".to_string(), Value::new_int(4));
+        let object: Value = Value::new_owned(object);
+        assert_eq!(run_int(&program, 2, &[object]), 12);
+        unsafe {
+            let fields: &Object = &*(object.get_as_mut_ptr::<Object>() as *const _);
+            assert_eq!(fields.fields["n"].vt_data.inner.int_value, 15);
+            let _ = Box::from_raw(object.get_as_dyn_base());
+        }
+    }
+
+    #[test]
+    fn test_codegen_chars() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func letter_index(c char): int {
+                if c < 'a' || c > 'z' {
+                    return -1;
+                }
+                return (c as int) - ('a' as int);
+            }
+
+            func test(): int {
+                return letter_index('q') * 100 + letter_index('A');
+            }
+        "#);
+        assert_eq!(run_int(&program, 1, &[]), 1599);
+    }
+
+    #[test]
+    fn test_codegen_overload_dispatch() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            func describe(x int): int {
+                return 1;
+            }
+
+            func describe(x string): int {
+                return 2;
+            }
+
+            func describe(x float): int {
+                return 3;
+            }
+
+            func describe_static(): int {
+                return describe(0) * 100 + describe("s") * 10 + describe(0.0e0);
+            }
+
+            func describe_dynamic(x any): int {
+                return describe(x);
+            }
+        "#);
+        // only the call site with an `any` argument is resolved at run time
+        assert_eq!(program.overload_tables.len(), 1);
+        assert_eq!(&program.overload_tables[0].candidates[..], &[0, 1, 2]);
+        assert_eq!(run_int(&program, 3, &[]), 123);
+
+        let s: Value = Value::new_owned(String::from("s"));
+        assert_eq!(run_int(&program, 4, &[Value::new_int(42)]), 1);
+        assert_eq!(run_int(&program, 4, &[s]), 2);
+        assert_eq!(run_int(&program, 4, &[Value::new_float(1.5)]), 3);
+        // cached for the last argument type, which should not be reused for other types
+        assert_eq!(run_int(&program, 4, &[Value::new_float(2.5)]), 3);
+        assert_eq!(run_int(&program, 4, &[Value::new_int(7)]), 1);
+        unsafe {
+            assert!(vm_run_function_sync(
+                DefaultAlloc::new(),
+                &program,
+                4,
+                &[Value::new_bool(true)]
+            ).is_err());
+            let _ = Box::from_raw(s.get_as_dyn_base());
+        }
+    }
+
+    #[test]
+    fn test_codegen_globals() {
+        let program: CompiledProgram<DefaultAlloc> = compile(r#"
+            const BASE = 10;
+            var counter int = twice(BASE);
+            var next = counter + 1;
+
+            func twice(x int): int {
+                return x * 2;
+            }
+
+            func bump(): int {
+                counter += 1;
+                next = counter * 100;
+                return counter + next;
+            }
+        "#);
+        assert_eq!(&program.global_names[..], &["counter", "next"]);
+        // each run initializes globals again on a new VM
+        assert_eq!(run_int(&program, 1, &[]), 21 + 2100);
+        assert_eq!(run_int(&program, 1, &[]), 21 + 2100);
+    }
+}
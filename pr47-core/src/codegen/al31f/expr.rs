@@ -0,0 +1,732 @@
+use super::{CodegenAL31F, FuncRef, needs_tyck};
+
+use std::any::TypeId;
+use std::ptr::NonNull;
+
+use xjbutil::either::Either;
+use xjbutil::value::Value;
+
+use crate::builtins::vec::VMGenericVec;
+use crate::data::tyck::TyckInfo;
+use crate::sema::arena::{Arena, ArenaPtr};
+use crate::sema::decl::{FuncDecl, ObjectDecl};
+use crate::sema::expr::{
+    AsExpr,
+    AssignExpr,
+    AwaitExpr,
+    BinaryExpr,
+    Expr,
+    FieldRefExpr,
+    FuncCallExpr,
+    IdRefExpr,
+    ResolvedBinaryOp,
+    ResolvedUnaryOp,
+    SubscriptExpr,
+    SubscriptMode,
+    UnaryExpr
+};
+use crate::syntax::expr::LiteralExprContent;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::OverloadTable;
+use crate::vm::al31f::insc::Insc;
+
+impl<'s, 'd, A: Alloc> CodegenAL31F<'s, 'd, A> {
+    /// Gets a register holding the value of `expr`. Local variables are used in place, other
+    /// expressions are generated into a new temporary register.
+    pub(super) fn gen_operand(&mut self, expr: &Expr<'s>) -> usize {
+        if let Some(reg) = self.local_reg(expr) {
+            return reg;
+        }
+
+        let dst: usize = self.alloc_reg();
+        self.gen_expr(expr, dst);
+        dst
+    }
+
+    /// Generates `expr`, putting its value into register `dst`
+    pub(super) fn gen_expr(&mut self, expr: &Expr<'s>, dst: usize) {
+        let start_addr: usize = self.code.len();
+        self.gen_expr_inner(expr, dst);
+        self.mark_source(start_addr, expr.source_range(self.arena));
+    }
+
+    fn gen_expr_inner(&mut self, expr: &Expr<'s>, dst: usize) {
+        let arena: &'d Arena<'s> = self.arena;
+        if let Some(value) = expr.get_const_fold_value(arena) {
+            if self.gen_const(value, expr.get_type(arena), dst) {
+                return;
+            }
+        }
+
+        match expr {
+            Expr::LiteralExpr(literal_expr) => {
+                let insc: Insc = match &literal_expr.get_tricky(arena).content {
+                    LiteralExprContent::Int(i) => Insc::MakeIntConst(*i as i64, dst),
+                    LiteralExprContent::Float(f) => Insc::MakeFloatConst(*f, dst),
+                    LiteralExprContent::Char(ch) => Insc::MakeIntConst(*ch as i64, dst),
+                    LiteralExprContent::String(s) => Insc::LoadConst(self.string_const(s), dst),
+                    LiteralExprContent::Boolean(b) => Insc::MakeBoolConst(*b, dst)
+                };
+                self.code.push(insc);
+            },
+            Expr::IdRefExpr(id_ref_expr) =>
+                self.gen_id_ref_expr(id_ref_expr.get_tricky(arena), dst),
+            Expr::UnaryExpr(unary_expr) => self.gen_unary_expr(unary_expr.get_tricky(arena), dst),
+            Expr::BinaryExpr(binary_expr) =>
+                self.gen_binary_expr(binary_expr.get_tricky(arena), dst),
+            Expr::AssignExpr(assign_expr) =>
+                self.gen_assign(assign_expr.get_tricky(arena), Some(dst)),
+            Expr::FuncCallExpr(func_call_expr) =>
+                self.gen_func_call(func_call_expr.get_tricky(arena), &[dst]),
+            Expr::SubscriptExpr(subscript_expr) =>
+                self.gen_subscript(subscript_expr.get_tricky(arena), dst, false),
+            Expr::FieldRefExpr(field_ref_expr) =>
+                self.gen_field_ref(field_ref_expr.get_tricky(arena), dst, false),
+            Expr::AwaitExpr(await_expr) => {
+                let await_expr: &'d AwaitExpr<'s> = await_expr.get_tricky(arena);
+                if !self.async_enabled() {
+                    self.diag_async_disabled(await_expr.concrete.await_range);
+                    return;
+                }
+
+                let promise: usize = self.gen_operand(&await_expr.expr);
+                #[cfg(feature = "async")]
+                {
+                    let dests: &'static [usize] = self.make_slice(&[dst]);
+                    self.code.push(Insc::Await(promise, dests));
+                }
+                #[cfg(not(feature = "async"))]
+                let _ = (promise, dst);
+            },
+            Expr::AsExpr(as_expr) => self.gen_as_expr(as_expr.get_tricky(arena), dst)
+        }
+    }
+
+    /// Generates an expression whose value is not used
+    pub(super) fn gen_expr_stmt(&mut self, expr: &Expr<'s>) {
+        let arena: &'d Arena<'s> = self.arena;
+        match expr {
+            Expr::FuncCallExpr(func_call_expr) => {
+                let func_call_expr: &'d FuncCallExpr<'s> = func_call_expr.get_tricky(arena);
+                let ret_count: usize = func_call_expr.ret_types.as_ref()
+                    .map_or(1, |ret_types: &Vec<NonNull<TyckInfo>>| ret_types.len());
+                let rets: Vec<usize> = (0..ret_count).map(|_| self.alloc_reg()).collect();
+                self.gen_func_call(func_call_expr, &rets);
+            },
+            Expr::AssignExpr(assign_expr) => self.gen_assign(assign_expr.get_tricky(arena), None),
+            Expr::LiteralExpr(_) | Expr::IdRefExpr(_) => {},
+            _ => {
+                if expr.get_const_fold_value(arena).is_none() {
+                    let dst: usize = self.alloc_reg();
+                    self.gen_expr(expr, dst);
+                }
+            }
+        }
+    }
+
+    /// Generates a function call, putting returned values into `rets`
+    pub(super) fn gen_func_call(&mut self, func_call_expr: &FuncCallExpr<'s>, rets: &[usize]) {
+        let arena: &'d Arena<'s> = self.arena;
+        if !func_call_expr.overloads.is_empty() {
+            self.gen_overload_call(func_call_expr, rets);
+            return;
+        }
+
+        let param_types: Vec<NonNull<TyckInfo>> = self.param_types(func_call_expr);
+        let callee: Either<FuncRef, usize> = match &func_call_expr.func {
+            Either::Left(func_decl) => {
+                let func_decl: *const FuncDecl<'s> = func_decl.get_tricky(arena);
+                Either::Left(self.func_refs[&func_decl])
+            },
+            Either::Right(callee) => Either::Right(self.gen_operand(callee))
+        };
+
+        let args: Vec<usize> =
+            self.gen_args(&func_call_expr.args, &param_types, func_call_expr.tyck_args);
+        let args: &'static [usize] = self.make_slice(&args);
+        let insc: Insc = match callee {
+            Either::Left(FuncRef::Script(func_id)) =>
+                Insc::Call(func_id, args, self.make_slice(rets)),
+            #[cfg(feature = "optimized-rtlc")]
+            Either::Left(FuncRef::Host(ffi_func_id)) if !self.rtlc =>
+                Insc::FFICall(ffi_func_id, args, self.make_slice(rets)),
+            Either::Left(FuncRef::Host(ffi_func_id)) =>
+                Insc::FFICallRtlc(ffi_func_id, args, self.make_slice(rets)),
+            #[cfg(feature = "async")]
+            Either::Left(FuncRef::AsyncHost(async_ffi_func_id)) =>
+                Insc::FFICallAsync(async_ffi_func_id, args, rets[0]),
+            Either::Right(func) => {
+                if !self.callee_typed(func_call_expr) {
+                    // nothing is known about callees of `any` type until run time
+                    self.code.push(Insc::CallableCheck(func, args.len(), rets.len()));
+                }
+                Insc::CallPtr(func, args, self.make_slice(rets))
+            }
+        };
+        self.code.push(insc);
+    }
+
+    /// Checks whether the callee is statically known to be a function of matching signature
+    fn callee_typed(&self, func_call_expr: &FuncCallExpr<'s>) -> bool {
+        let arena: &'d Arena<'s> = self.arena;
+        match &func_call_expr.func {
+            Either::Left(_) => true,
+            Either::Right(callee) => matches!(
+                callee.get_type(arena).map(|ty: NonNull<TyckInfo>| unsafe { ty.as_ref() }),
+                Some(TyckInfo::Function(_))
+            )
+        }
+    }
+
+    /// Generates a call selecting from overloaded functions at run time. Every call site gets an
+    /// overload table of its own.
+    fn gen_overload_call(&mut self, func_call_expr: &FuncCallExpr<'s>, rets: &[usize]) {
+        let arena: &'d Arena<'s> = self.arena;
+        let candidates: Box<[usize]> = func_call_expr.overloads.iter()
+            .map(|func_decl: &ArenaPtr<'s, FuncDecl<'s>>| {
+                let func_decl: *const FuncDecl<'s> = func_decl.get_tricky(arena);
+                if let FuncRef::Script(func_id) = self.func_refs[&func_decl] {
+                    func_id
+                } else {
+                    unreachable!("overloads selected at run time should be script functions")
+                }
+            })
+            .collect::<_>();
+        let overload_table: usize = self.overload_tables.len();
+        self.overload_tables.push(OverloadTable::new(candidates));
+
+        // arguments get checked against parameters when selecting the candidate
+        let args: Vec<usize> = self.gen_args(&func_call_expr.args, &[], false);
+        let args: &'static [usize] = self.make_slice(&args);
+        let rets: &'static [usize] = self.make_slice(rets);
+        self.code.push(Insc::CallOverload(overload_table, args, rets));
+    }
+
+    /// Gets parameter types of the called function, empty if the callee is of `any` type
+    pub(super) fn param_types(&self, func_call_expr: &FuncCallExpr<'s>) -> Vec<NonNull<TyckInfo>> {
+        let arena: &'d Arena<'s> = self.arena;
+        match &func_call_expr.func {
+            Either::Left(func_decl) => func_decl.get_tricky(arena).param_decl_context
+                .object_decls
+                .iter()
+                .map(|param: &ArenaPtr<'s, ObjectDecl<'s>>| param.get_tricky(arena).ty)
+                .collect(),
+            Either::Right(callee) => match callee.get_type(arena)
+                .map(|ty: NonNull<TyckInfo>| unsafe { ty.as_ref() })
+            {
+                Some(TyckInfo::Function(function_tyck_info)) =>
+                    unsafe { function_tyck_info.params.as_ref() }.to_vec(),
+                _ => Vec::new()
+            }
+        }
+    }
+
+    /// Generates arguments of a function call, checking their types at run time if required
+    pub(super) fn gen_args(
+        &mut self,
+        args: &[Expr<'s>],
+        param_types: &[NonNull<TyckInfo>],
+        tyck_args: bool
+    ) -> Vec<usize> {
+        let arena: &'d Arena<'s> = self.arena;
+        let mut arg_regs: Vec<usize> = Vec::with_capacity(args.len());
+        for (i, arg) /*: (usize, &Expr)*/ in args.iter().enumerate() {
+            let reg: usize = self.gen_operand(arg);
+            if tyck_args {
+                let param_type: Option<NonNull<TyckInfo>> = param_types.get(i).copied();
+                if needs_tyck(param_type, arg.get_type(arena)) {
+                    self.emit_tyck(reg, param_type.unwrap());
+                }
+            }
+            arg_regs.push(reg);
+        }
+        arg_regs
+    }
+
+    /// Generates an assignment, also putting the assigned value into `dst` if there is one
+    pub(super) fn gen_assign(&mut self, assign_expr: &AssignExpr<'s>, dst: Option<usize>) {
+        let arena: &'d Arena<'s> = self.arena;
+        let value: usize = match &assign_expr.lhs {
+            Expr::IdRefExpr(_) => {
+                let local: Option<usize> = self.local_reg(&assign_expr.lhs);
+                let old_value: Option<usize> = if assign_expr.compound_op.is_some() {
+                    Some(self.gen_operand(&assign_expr.lhs))
+                } else {
+                    None
+                };
+                let value: usize = self.gen_assigned_value(assign_expr, old_value);
+                if let Some(local) = local {
+                    self.code.push(Insc::Move(value, local));
+                    local
+                } else {
+                    let global_id: usize = self.global_id(&assign_expr.lhs)
+                        .expect("assigned variables should be either local or global");
+                    self.code.push(Insc::StoreGlobal(value, global_id));
+                    value
+                }
+            },
+            Expr::SubscriptExpr(subscript_expr) => {
+                let subscript_expr: &'d SubscriptExpr<'s> = subscript_expr.get_tricky(arena);
+                if assign_expr.compound_op.is_some() {
+                    // the base and the index are evaluated once, for both loading and storing
+                    let (base, index): (usize, usize) = self.gen_subscript_operands(subscript_expr);
+                    let old_value: usize = self.alloc_reg();
+                    self.gen_subscript_access(subscript_expr, base, index, old_value, false);
+                    let value: usize = self.gen_assigned_value(assign_expr, Some(old_value));
+                    self.gen_subscript_access(subscript_expr, base, index, value, true);
+                    value
+                } else {
+                    let value: usize = self.gen_assigned_value(assign_expr, None);
+                    self.gen_subscript(subscript_expr, value, true);
+                    value
+                }
+            },
+            Expr::FieldRefExpr(field_ref_expr) => {
+                let field_ref_expr: &'d FieldRefExpr<'s> = field_ref_expr.get_tricky(arena);
+                if assign_expr.compound_op.is_some() {
+                    // the base is evaluated once, for both loading and storing
+                    let base: usize = self.gen_field_base(field_ref_expr);
+                    let old_value: usize = self.alloc_reg();
+                    self.gen_field_access(field_ref_expr, base, old_value, false);
+                    let value: usize = self.gen_assigned_value(assign_expr, Some(old_value));
+                    self.gen_field_access(field_ref_expr, base, value, true);
+                    value
+                } else {
+                    let value: usize = self.gen_assigned_value(assign_expr, None);
+                    self.gen_field_ref(field_ref_expr, value, true);
+                    value
+                }
+            },
+            _ => unreachable!("not assignable")
+        };
+
+        if let Some(dst) = dst {
+            if dst != value {
+                self.code.push(Insc::Move(value, dst));
+            }
+        }
+    }
+
+    /// Generates the value to be assigned, checking its type against the assigned location.
+    /// Compound assignments combine the right hand side with `old_value` loaded by the caller.
+    fn gen_assigned_value(
+        &mut self,
+        assign_expr: &AssignExpr<'s>,
+        old_value: Option<usize>
+    ) -> usize {
+        let (value, value_type): (usize, Option<NonNull<TyckInfo>>) =
+            if let Some((op, op_type)) = assign_expr.compound_op {
+                let old_value: usize =
+                    old_value.expect("compound assignments should load the old value");
+                let rhs: usize = self.gen_operand(&assign_expr.rhs);
+                let value: usize = self.alloc_reg();
+                self.gen_binary_op(op, old_value, rhs, value);
+                (value, op_type)
+            } else {
+                (self.gen_operand(&assign_expr.rhs), assign_expr.rhs.get_type(self.arena))
+            };
+        if needs_tyck(assign_expr.ty, value_type) {
+            self.emit_tyck(value, assign_expr.ty.unwrap());
+        }
+        value
+    }
+
+    fn local_reg(&self, expr: &Expr<'s>) -> Option<usize> {
+        if let Expr::IdRefExpr(id_ref_expr) = expr {
+            if let Either::Left(object_decl) = &id_ref_expr.get_tricky(self.arena).decl {
+                let object_decl: &ObjectDecl<'s> = object_decl.get_tricky(self.arena);
+                return self.local_regs.get(&(object_decl as *const ObjectDecl<'s>)).copied();
+            }
+        }
+        None
+    }
+
+    fn global_id(&self, expr: &Expr<'s>) -> Option<usize> {
+        if let Expr::IdRefExpr(id_ref_expr) = expr {
+            if let Either::Left(object_decl) = &id_ref_expr.get_tricky(self.arena).decl {
+                let object_decl: &ObjectDecl<'s> = object_decl.get_tricky(self.arena);
+                return self.global_ids.get(&(object_decl as *const ObjectDecl<'s>)).copied();
+            }
+        }
+        None
+    }
+
+    /// Generates a constant folded value, returns `false` if it cannot be represented directly
+    fn gen_const(&mut self, value: &Value, ty: Option<NonNull<TyckInfo>>, dst: usize) -> bool {
+        let insc: Insc = match value {
+            Value::Int(i) => Insc::MakeIntConst(*i, dst),
+            Value::Float(f) => Insc::MakeFloatConst(*f, dst),
+            Value::Bool(b) => Insc::MakeBoolConst(*b, dst),
+            // `char`s are also folded as strings
+            Value::String(s) if ty == Some(self.tyck_info_pool.get_char_type()) =>
+                Insc::MakeIntConst(s.chars().next().unwrap() as i64, dst),
+            Value::String(s) if ty == Some(self.tyck_info_pool.get_string_type()) =>
+                Insc::LoadConst(self.string_const(s), dst),
+            _ => return false
+        };
+        self.code.push(insc);
+        true
+    }
+
+    fn gen_id_ref_expr(&mut self, id_ref_expr: &IdRefExpr<'s>, dst: usize) {
+        match &id_ref_expr.decl {
+            Either::Left(object_decl) => {
+                let object_decl: &'d ObjectDecl<'s> = object_decl.get_tricky(self.arena);
+                if let Some(reg) = self.local_regs.get(&(object_decl as *const ObjectDecl<'s>)) {
+                    let reg: usize = *reg;
+                    self.code.push(Insc::Move(reg, dst));
+                } else if let Some(global_id) =
+                    self.global_ids.get(&(object_decl as *const ObjectDecl<'s>))
+                {
+                    let global_id: usize = *global_id;
+                    self.code.push(Insc::LoadGlobal(global_id, dst));
+                } else {
+                    // constants without registers
+                    let init: Expr<'s> =
+                        object_decl.init.expect("constants should have initializers");
+                    self.gen_expr(&init, dst);
+                }
+            },
+            Either::Right(func_decl) => {
+                let func_decl: *const FuncDecl<'s> = func_decl.get_tricky(self.arena);
+                if let FuncRef::Script(func_id) = self.func_refs[&func_decl] {
+                    self.code.push(Insc::MakeIntConst(func_id as i64, dst));
+                } else {
+                    unreachable!("host functions cannot be used as values")
+                }
+            }
+        }
+    }
+
+    fn gen_unary_expr(&mut self, unary_expr: &UnaryExpr<'s>, dst: usize) {
+        if let ResolvedUnaryOp::Positive = unary_expr.op {
+            self.gen_expr(&unary_expr.operand, dst);
+            return;
+        }
+
+        let src: usize = self.gen_operand(&unary_expr.operand);
+        self.code.push(match unary_expr.op {
+            ResolvedUnaryOp::Positive => unreachable!(),
+            ResolvedUnaryOp::Negation => Insc::NegAny(src, dst),
+            ResolvedUnaryOp::IntNegation => Insc::NegInt(src, dst),
+            ResolvedUnaryOp::FloatNegation => Insc::NegFloat(src, dst),
+            ResolvedUnaryOp::LogicalNot => Insc::NotBool(src, dst),
+            ResolvedUnaryOp::LogicalNotRTTI => Insc::NotAny(src, dst),
+            ResolvedUnaryOp::BitwiseReverse => Insc::BNotInt(src, dst),
+            ResolvedUnaryOp::BitwiseReverseRTTI => Insc::BNotAny(src, dst)
+        });
+    }
+
+    fn gen_binary_expr(&mut self, binary_expr: &BinaryExpr<'s>, dst: usize) {
+        use ResolvedBinaryOp::*;
+
+        let (lhs, rhs): (&Expr<'s>, &Expr<'s>) = (&binary_expr.lhs, &binary_expr.rhs);
+        match binary_expr.op {
+            Equal | NotEqual =>
+                self.gen_equality(lhs, rhs, matches!(binary_expr.op, NotEqual), dst),
+            LogicalAnd | LogicalAndRTTI | LogicalOr | LogicalOrRTTI =>
+                self.gen_short_circuit(binary_expr, dst),
+            LogicalXor | LogicalXorRTTI => {
+                let lhs_reg: usize = self.gen_operand(lhs);
+                let rhs_reg: usize = self.gen_operand(rhs);
+                if let LogicalXorRTTI = binary_expr.op {
+                    self.tyck_bool_operand(lhs, lhs_reg);
+                    self.tyck_bool_operand(rhs, rhs_reg);
+                }
+                self.code.push(Insc::NeValue(lhs_reg, rhs_reg, dst));
+            },
+            op => {
+                let lhs: usize = self.gen_operand(lhs);
+                let rhs: usize = self.gen_operand(rhs);
+                self.gen_binary_op(op, lhs, rhs, dst);
+            }
+        }
+    }
+
+    /// Generates an arithmetic, comparison or bitwise operator on operands already in registers
+    fn gen_binary_op(&mut self, op: ResolvedBinaryOp, lhs: usize, rhs: usize, dst: usize) {
+        use ResolvedBinaryOp::*;
+
+        let (make_insc, swap): (fn(usize, usize, usize) -> Insc, bool) = match op {
+            Mul => (Insc::MulAny, false),
+            IntMul => (Insc::MulInt, false),
+            FloatMul => (Insc::MulFloat, false),
+            Div => (Insc::DivAny, false),
+            IntDiv => (Insc::DivInt, false),
+            FloatDiv => (Insc::DivFloat, false),
+            IntMod => (Insc::ModInt, false),
+            IntModRTTI => (Insc::ModAny, false),
+            Add => (Insc::AddAny, false),
+            IntAdd => (Insc::AddInt, false),
+            FloatAdd => (Insc::AddFloat, false),
+            Minus => (Insc::SubAny, false),
+            IntMinus => (Insc::SubInt, false),
+            FloatMinus => (Insc::SubFloat, false),
+            // there are no "greater than" instructions, so swap operands of "less than" ones
+            GreaterThan => (Insc::LtAny, true),
+            IntGreaterThan => (Insc::LtInt, true),
+            FloatGreaterThan => (Insc::LtFloat, true),
+            LessThan => (Insc::LtAny, false),
+            IntLessThan => (Insc::LtInt, false),
+            FloatLessThan => (Insc::LtFloat, false),
+            GreaterThanOrEqual => (Insc::LeAny, true),
+            IntGreaterThanOrEqual => (Insc::LeInt, true),
+            FloatGreaterThanOrEqual => (Insc::LeFloat, true),
+            LessThanOrEqual => (Insc::LeAny, false),
+            IntLessThanOrEqual => (Insc::LeInt, false),
+            FloatLessThanOrEqual => (Insc::LeFloat, false),
+            BitwiseAnd => (Insc::BAndInt, false),
+            BitwiseOr => (Insc::BOrInt, false),
+            BitwiseXor => (Insc::BXorInt, false),
+            BitwiseAndRTTI => (Insc::BAndAny, false),
+            BitwiseOrRTTI => (Insc::BOrAny, false),
+            BitwiseXorRTTI => (Insc::BXorAny, false),
+            ShiftLeft => (Insc::ShlInt, false),
+            ShiftRight => (Insc::ShrInt, false),
+            ShiftLeftRTTI => (Insc::ShlAny, false),
+            ShiftRightRTTI => (Insc::ShrAny, false),
+            StringAdd => {
+                let sources: &'static [usize] = self.make_slice(&[lhs, rhs]);
+                self.code.push(Insc::StrConcat(sources, dst));
+                return;
+            },
+            Equal | NotEqual | LogicalAnd | LogicalAndRTTI | LogicalOr | LogicalOrRTTI
+            | LogicalXor | LogicalXorRTTI => unreachable!("generated by gen_binary_expr")
+        };
+
+        self.code.push(if swap { make_insc(rhs, lhs, dst) } else { make_insc(lhs, rhs, dst) });
+    }
+
+    fn gen_equality(&mut self, lhs: &Expr<'s>, rhs: &Expr<'s>, negate: bool, dst: usize) {
+        let arena: &'d Arena<'s> = self.arena;
+        let lhs_type: Option<NonNull<TyckInfo>> = lhs.get_type(arena);
+        let rhs_type: Option<NonNull<TyckInfo>> = rhs.get_type(arena);
+        let lhs: usize = self.gen_operand(lhs);
+        let rhs: usize = self.gen_operand(rhs);
+
+        let value_types: [NonNull<TyckInfo>; 4] = [
+            self.tyck_info_pool.get_int_type(),
+            self.tyck_info_pool.get_float_type(),
+            self.tyck_info_pool.get_bool_type(),
+            self.tyck_info_pool.get_char_type()
+        ];
+        let string_type: NonNull<TyckInfo> = self.tyck_info_pool.get_string_type();
+        match (lhs_type, rhs_type) {
+            (Some(lhs_type), Some(rhs_type))
+                if lhs_type == rhs_type && value_types.contains(&lhs_type) =>
+                self.code.push(if negate {
+                    Insc::NeValue(lhs, rhs, dst)
+                } else {
+                    Insc::EqValue(lhs, rhs, dst)
+                }),
+            (Some(lhs_type), Some(rhs_type))
+                if lhs_type == string_type && rhs_type == string_type =>
+            {
+                self.code.push(Insc::StrEquals(lhs, rhs, dst));
+                if negate {
+                    self.code.push(Insc::NotBool(dst, dst));
+                }
+            },
+            _ => self.code.push(if negate {
+                Insc::NeAny(lhs, rhs, dst)
+            } else {
+                Insc::EqAny(lhs, rhs, dst)
+            })
+        }
+    }
+
+    fn gen_short_circuit(&mut self, binary_expr: &BinaryExpr<'s>, dst: usize) {
+        use ResolvedBinaryOp::*;
+
+        let rtti: bool = matches!(binary_expr.op, LogicalAndRTTI | LogicalOrRTTI);
+        self.gen_expr(&binary_expr.lhs, dst);
+        if rtti {
+            self.tyck_bool_operand(&binary_expr.lhs, dst);
+        }
+        let jump: usize = if let LogicalAnd | LogicalAndRTTI = binary_expr.op {
+            self.emit_jump(Insc::JumpIfFalse(dst, 0))
+        } else {
+            self.emit_jump(Insc::JumpIfTrue(dst, 0))
+        };
+        self.gen_expr(&binary_expr.rhs, dst);
+        if rtti {
+            self.tyck_bool_operand(&binary_expr.rhs, dst);
+        }
+        self.patch_jump(jump);
+    }
+
+    fn tyck_bool_operand(&mut self, operand: &Expr<'s>, reg: usize) {
+        let bool_type: NonNull<TyckInfo> = self.tyck_info_pool.get_bool_type();
+        if needs_tyck(Some(bool_type), operand.get_type(self.arena)) {
+            self.emit_tyck(reg, bool_type);
+        }
+    }
+
+    /// Generates a subscript expression. If `put` is `true`, stores the value in register
+    /// `value` into the subscript, otherwise loads the subscript into it.
+    fn gen_subscript(&mut self, subscript_expr: &SubscriptExpr<'s>, value: usize, put: bool) {
+        let (base, index): (usize, usize) = self.gen_subscript_operands(subscript_expr);
+        self.gen_subscript_access(subscript_expr, base, index, value, put);
+    }
+
+    /// Gets registers holding the base and the index of a subscript expression
+    fn gen_subscript_operands(&mut self, subscript_expr: &SubscriptExpr<'s>) -> (usize, usize) {
+        let base: usize = self.gen_operand(&subscript_expr.base);
+        let index: usize = self.gen_operand(&subscript_expr.index);
+        (base, index)
+    }
+
+    /// Generates the access part of a subscript expression, with its base and index already in
+    /// registers `base` and `index`
+    fn gen_subscript_access(
+        &mut self,
+        subscript_expr: &SubscriptExpr<'s>,
+        base: usize,
+        index: usize,
+        value: usize,
+        put: bool
+    ) {
+        let arena: &'d Arena<'s> = self.arena;
+        let int_type: NonNull<TyckInfo> = self.tyck_info_pool.get_int_type();
+        let string_type: NonNull<TyckInfo> = self.tyck_info_pool.get_string_type();
+        let object_type: NonNull<TyckInfo> = self.tyck_info_pool.get_object_type();
+
+        let index_type: Option<NonNull<TyckInfo>> = subscript_expr.index.get_type(arena);
+        let vec_insc: Insc = if put {
+            Insc::VecIndexPut(base, index, value)
+        } else {
+            Insc::VecIndex(base, index, value)
+        };
+        let object_insc: Insc = if put {
+            Insc::ObjectPutDyn(base, index, value)
+        } else {
+            Insc::ObjectGetDyn(base, index, value)
+        };
+
+        match subscript_expr.mode {
+            SubscriptMode::ArrayIndex => {
+                if subscript_expr.tyck_index {
+                    self.emit_tyck(index, int_type);
+                }
+                self.code.push(vec_insc);
+            },
+            SubscriptMode::ObjectIndex => {
+                if subscript_expr.tyck_index {
+                    self.emit_tyck(index, string_type);
+                }
+                self.code.push(object_insc);
+            },
+            SubscriptMode::Undetermined => {
+                // decide between vector indexing and object field access at run time
+                let any_type: NonNull<TyckInfo> = self.tyck_info_pool.get_any_type();
+                let vec_type: NonNull<TyckInfo> = self.tyck_info_pool.create_container_type(
+                    TypeId::of::<VMGenericVec>(),
+                    &[any_type]
+                );
+                let is_vec: usize = self.alloc_reg();
+                self.code.push(Insc::NullCheck(base));
+                self.code.push(Insc::IsType(base, vec_type, is_vec));
+                let to_object: usize = self.emit_jump(Insc::JumpIfFalse(is_vec, 0));
+                if index_type != Some(int_type) {
+                    self.emit_tyck(index, int_type);
+                }
+                self.code.push(vec_insc);
+                let to_end: usize = self.emit_jump(Insc::Jump(0));
+
+                self.patch_jump(to_object);
+                self.emit_tyck(base, object_type);
+                if index_type != Some(string_type) {
+                    self.emit_tyck(index, string_type);
+                }
+                self.code.push(object_insc);
+                self.patch_jump(to_end);
+            }
+        }
+    }
+
+    /// Generates a field reference. If `put` is `true`, stores the value in register `value`
+    /// into the field, otherwise loads the field into it.
+    fn gen_field_ref(&mut self, field_ref_expr: &FieldRefExpr<'s>, value: usize, put: bool) {
+        let base: usize = self.gen_field_base(field_ref_expr);
+        self.gen_field_access(field_ref_expr, base, value, put);
+    }
+
+    /// Gets a register holding the base of a field reference, checked to be an object
+    fn gen_field_base(&mut self, field_ref_expr: &FieldRefExpr<'s>) -> usize {
+        let base: usize = self.gen_operand(&field_ref_expr.base);
+        if field_ref_expr.tyck_base {
+            let object_type: NonNull<TyckInfo> = self.tyck_info_pool.get_object_type();
+            self.emit_tyck(base, object_type);
+        }
+        base
+    }
+
+    /// Generates the access part of a field reference, with its base already in register `base`
+    fn gen_field_access(
+        &mut self,
+        field_ref_expr: &FieldRefExpr<'s>,
+        base: usize,
+        value: usize,
+        put: bool
+    ) {
+        let field: NonNull<str> = self.make_str(field_ref_expr.field);
+        self.code.push(if put {
+            Insc::ObjectPut(base, field, value)
+        } else {
+            Insc::ObjectGet(base, field, value)
+        });
+    }
+
+    fn gen_as_expr(&mut self, as_expr: &AsExpr<'s>, dst: usize) {
+        let src_type: Option<NonNull<TyckInfo>> = as_expr.expr.get_type(self.arena);
+        let is_dynamic: bool = match src_type {
+            Some(src_type) => unsafe { src_type.as_ref() }.is_any(),
+            None => true
+        };
+        let int_type: NonNull<TyckInfo> = self.tyck_info_pool.get_int_type();
+        let float_type: NonNull<TyckInfo> = self.tyck_info_pool.get_float_type();
+        let bool_type: NonNull<TyckInfo> = self.tyck_info_pool.get_bool_type();
+
+        let make_insc: Option<fn(usize, usize) -> Insc> = if as_expr.as_type == int_type {
+            if src_type == Some(float_type) {
+                Some(Insc::CastFloatInt)
+            } else if src_type == Some(bool_type) {
+                Some(Insc::CastBoolInt)
+            } else if is_dynamic {
+                Some(Insc::CastAnyInt)
+            } else {
+                None
+            }
+        } else if as_expr.as_type == float_type {
+            if src_type == Some(int_type) {
+                Some(Insc::CastIntFloat)
+            } else if is_dynamic {
+                Some(Insc::CastAnyFloat)
+            } else {
+                None
+            }
+        } else if as_expr.as_type == bool_type {
+            if src_type == Some(int_type) {
+                Some(Insc::CastIntBool)
+            } else if is_dynamic {
+                Some(Insc::CastAnyBool)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        if let Some(make_insc) = make_insc {
+            let src: usize = self.gen_operand(&as_expr.expr);
+            self.code.push(make_insc(src, dst));
+        } else {
+            // `char`s are already their code points at run time, so casting them to `int` only
+            // moves the value
+            self.gen_expr(&as_expr.expr, dst);
+            if needs_tyck(Some(as_expr.as_type), src_type) {
+                self.emit_tyck(dst, as_expr.as_type);
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use super::{CodegenAL31F, TryContext, needs_tyck};
+
+use std::ptr::NonNull;
+
+use crate::data::tyck::TyckInfo;
+use crate::diag::location::SourceRange;
+use crate::sema::arena::{Arena, ArenaPtr};
+use crate::sema::decl::ObjectDecl;
+use crate::sema::expr::Expr;
+use crate::sema::stmt::{
+    CatchClause,
+    CompoundStmt,
+    DoWhileStmt,
+    IfStmt,
+    ReturnStmt,
+    SpawnStmt,
+    Stmt,
+    TryStmt,
+    WhileStmt
+};
+use crate::syntax::decl::ConcreteObjectDecl;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{ExceptionHandlingBlock, HandlerKind};
+use crate::vm::al31f::insc::Insc;
+
+#[cfg(feature = "async")] use xjbutil::either::Either;
+
+#[cfg(feature = "async")] use super::FuncRef;
+#[cfg(feature = "async")] use crate::sema::decl::FuncDecl;
+#[cfg(feature = "async")] use crate::sema::expr::FuncCallExpr;
+
+impl<'s, 'd, A: Alloc> CodegenAL31F<'s, 'd, A> {
+    pub(super) fn gen_compound_stmt(&mut self, compound_stmt: &CompoundStmt<'s>) {
+        let mark: usize = self.next_reg;
+        for stmt /*: &Stmt*/ in compound_stmt.stmts.iter() {
+            let stmt_mark: usize = self.next_reg;
+            self.gen_stmt(stmt);
+            if !matches!(stmt, Stmt::DeclStmt(_)) {
+                self.next_reg = stmt_mark;
+            }
+            if stmt.always_exits() {
+                // anything after is unreachable
+                break;
+            }
+        }
+        self.next_reg = mark;
+    }
+
+    fn gen_stmt(&mut self, stmt: &Stmt<'s>) {
+        let start_addr: usize = self.code.len();
+        match stmt {
+            Stmt::CompoundStmt(compound_stmt) => self.gen_compound_stmt(compound_stmt),
+            Stmt::ExprStmt(expr) => self.gen_expr_stmt(expr),
+            Stmt::DeclStmt(object_decl) => self.gen_decl_stmt(*object_decl),
+            Stmt::IfStmt(if_stmt) => self.gen_if_stmt(if_stmt),
+            Stmt::WhileStmt(while_stmt) => self.gen_while_stmt(while_stmt),
+            Stmt::DoWhileStmt(do_while_stmt) => self.gen_do_while_stmt(do_while_stmt),
+            Stmt::ReturnStmt(return_stmt) => self.gen_return_stmt(return_stmt),
+            Stmt::ThrowStmt(throw_stmt) => {
+                let thrown: usize = self.gen_operand(&throw_stmt.thrown);
+                self.code.push(Insc::Raise(thrown));
+            },
+            Stmt::RethrowStmt(_) => {
+                let caught: usize = *self.caught_regs.last()
+                    .expect("`rethrow` should have been checked to be inside `catch`");
+                self.code.push(Insc::Rethrow(caught));
+            },
+            Stmt::TryStmt(try_stmt) => self.gen_try_stmt(try_stmt),
+            Stmt::SpawnStmt(spawn_stmt) => self.gen_spawn_stmt(spawn_stmt)
+        }
+        if let Some(range) = self.stmt_source_range(stmt) {
+            self.mark_source(start_addr, range);
+        }
+    }
+
+    /// Range marking instructions of `stmt` not belonging to any expression, mostly jumps
+    fn stmt_source_range(&self, stmt: &Stmt<'s>) -> Option<SourceRange> {
+        match stmt {
+            Stmt::CompoundStmt(_) => None,
+            Stmt::ExprStmt(expr) => Some(expr.source_range(self.arena)),
+            Stmt::DeclStmt(object_decl) => object_decl.get_tricky(self.arena).concrete
+                .map(|concrete: &ConcreteObjectDecl<'s>| concrete.kwd_range),
+            Stmt::IfStmt(if_stmt) => Some(if_stmt.concrete.if_kwd_range),
+            Stmt::WhileStmt(while_stmt) => Some(while_stmt.concrete.while_kwd_range),
+            Stmt::DoWhileStmt(do_while_stmt) => Some(do_while_stmt.concrete.while_kwd_range),
+            Stmt::ReturnStmt(return_stmt) => Some(return_stmt.concrete.return_kwd_range),
+            Stmt::ThrowStmt(throw_stmt) => Some(throw_stmt.concrete.throw_kwd_range),
+            Stmt::RethrowStmt(rethrow_stmt) => Some(rethrow_stmt.concrete.rethrow_kwd_range),
+            Stmt::TryStmt(try_stmt) => Some(try_stmt.concrete.try_kwd_range),
+            Stmt::SpawnStmt(spawn_stmt) => Some(spawn_stmt.concrete.spawn_kwd_range)
+        }
+    }
+
+    fn gen_decl_stmt(&mut self, object_decl: ArenaPtr<'s, ObjectDecl<'s>>) {
+        let arena: &'d Arena<'s> = self.arena;
+        let object_decl: &'d ObjectDecl<'s> = object_decl.get_tricky(arena);
+        let init: Expr<'s> = object_decl.init.expect("variables should have initializers");
+        if object_decl.is_const
+            && (matches!(init, Expr::LiteralExpr(_)) || init.get_const_fold_value(arena).is_some())
+        {
+            // references to constants are folded, so there is no need for a register
+            return;
+        }
+
+        let reg: usize = self.alloc_reg();
+        self.gen_expr(&init, reg);
+        if needs_tyck(Some(object_decl.ty), init.get_type(arena)) {
+            self.emit_tyck(reg, object_decl.ty);
+        }
+        self.local_regs.insert(object_decl as *const ObjectDecl<'s>, reg);
+        self.next_reg = reg + 1;
+    }
+
+    /// Generates a condition, returns the register holding it
+    fn gen_cond(&mut self, cond: &Expr<'s>, tyck_cond: bool) -> usize {
+        let reg: usize = self.gen_operand(cond);
+        if tyck_cond {
+            let bool_type: NonNull<TyckInfo> = self.tyck_info_pool.get_bool_type();
+            self.emit_tyck(reg, bool_type);
+        }
+        reg
+    }
+
+    fn gen_if_stmt(&mut self, if_stmt: &IfStmt<'s>) {
+        let mark: usize = self.next_reg;
+        let cond: usize = self.gen_cond(&if_stmt.cond, if_stmt.tyck_cond);
+        self.next_reg = mark;
+
+        let to_else: usize = self.emit_jump(Insc::JumpIfFalse(cond, 0));
+        self.gen_compound_stmt(&if_stmt.then_clause);
+        if let Some(else_clause) = &if_stmt.else_clause {
+            if if_stmt.then_clause.always_exits() {
+                self.patch_jump(to_else);
+                self.gen_stmt(else_clause);
+            } else {
+                let to_end: usize = self.emit_jump(Insc::Jump(0));
+                self.patch_jump(to_else);
+                self.gen_stmt(else_clause);
+                self.patch_jump(to_end);
+            }
+        } else {
+            self.patch_jump(to_else);
+        }
+    }
+
+    fn gen_while_stmt(&mut self, while_stmt: &WhileStmt<'s>) {
+        let mark: usize = self.next_reg;
+        let loop_start: usize = self.code.len();
+        let cond: usize = self.gen_cond(&while_stmt.cond, while_stmt.tyck_cond);
+        self.next_reg = mark;
+
+        let to_end: usize = self.emit_jump(Insc::JumpIfFalse(cond, 0));
+        self.gen_compound_stmt(&while_stmt.body);
+        self.code.push(Insc::Jump(loop_start));
+        self.patch_jump(to_end);
+    }
+
+    fn gen_do_while_stmt(&mut self, do_while_stmt: &DoWhileStmt<'s>) {
+        let loop_start: usize = self.code.len();
+        self.gen_compound_stmt(&do_while_stmt.body);
+        let cond: usize = self.gen_cond(&do_while_stmt.cond, do_while_stmt.tyck_cond);
+        self.code.push(Insc::JumpIfTrue(cond, loop_start));
+    }
+
+    fn gen_return_stmt(&mut self, return_stmt: &ReturnStmt<'s>) {
+        let arena: &'d Arena<'s> = self.arena;
+        let mut regs: Vec<usize> = Vec::with_capacity(return_stmt.values.len());
+        for (i, value) /*: (usize, &Expr)*/ in return_stmt.values.iter().enumerate() {
+            let reg: usize = self.gen_operand(value);
+            let ret_type: NonNull<TyckInfo> = self.ret_types[i];
+            if return_stmt.tyck_values && needs_tyck(Some(ret_type), value.get_type(arena)) {
+                self.emit_tyck(reg, ret_type);
+            }
+            regs.push(reg);
+        }
+
+        let has_finally: bool = self.try_stack.iter()
+            .any(|try_context: &TryContext<'s>| try_context.finally_body.is_some());
+        if has_finally {
+            // `finally` blocks may modify the returned variables, so keep the values first
+            for reg /*: &mut usize*/ in regs.iter_mut() {
+                let kept: usize = self.alloc_reg();
+                self.code.push(Insc::Move(*reg, kept));
+                *reg = kept;
+            }
+            self.gen_pending_finally();
+        }
+
+        let insc: Insc = match regs.len() {
+            0 => Insc::ReturnNothing,
+            1 => Insc::ReturnOne(regs[0]),
+            _ => Insc::Return(self.make_slice(&regs))
+        };
+        self.code.push(insc);
+    }
+
+    /// Generates `finally` blocks of all enclosing `try` statements, innermost first, before
+    /// leaving them with `return`
+    fn gen_pending_finally(&mut self) {
+        let mut left: Vec<TryContext<'s>> = Vec::with_capacity(self.try_stack.len());
+        while let Some(try_context) = self.try_stack.pop() {
+            left.push(try_context);
+            let try_context: &TryContext<'s> = left.last().unwrap();
+            let finally_body: &CompoundStmt<'s> = match try_context.finally_body {
+                Some(finally_body) => unsafe { &*finally_body },
+                None => continue
+            };
+
+            let caught_regs: Vec<usize> = self.caught_regs.split_off(try_context.caught_depth);
+            let start_addr: usize = self.code.len();
+            self.gen_compound_stmt(finally_body);
+            let end_addr: usize = self.code.len();
+            self.caught_regs.extend(caught_regs);
+
+            if end_addr != start_addr {
+                for left_context /*: &mut TryContext*/ in left.iter_mut() {
+                    left_context.holes.push((start_addr, end_addr));
+                }
+            }
+            if finally_body.always_exits() {
+                break;
+            }
+        }
+
+        while let Some(try_context) = left.pop() {
+            self.try_stack.push(try_context);
+        }
+    }
+
+    fn gen_try_stmt(&mut self, try_stmt: &TryStmt<'s>) {
+        let finally_body: Option<&CompoundStmt<'s>> = try_stmt.finally_body.as_ref();
+        self.try_stack.push(TryContext {
+            finally_body: finally_body.map(|finally_body: &CompoundStmt<'s>| finally_body as _),
+            caught_depth: self.caught_regs.len(),
+            holes: Vec::new()
+        });
+
+        let try_start: usize = self.code.len();
+        self.gen_compound_stmt(&try_stmt.body);
+        if self.code.len() == try_start {
+            // nothing can be thrown from an empty block
+            self.try_stack.pop();
+            if let Some(finally_body) = finally_body {
+                self.gen_compound_stmt(finally_body);
+            }
+            return;
+        }
+        let try_end: usize = self.code.len() - 1;
+
+        let mut to_finally: Vec<usize> = Vec::with_capacity(try_stmt.catch_clauses.len() + 1);
+        if !try_stmt.body.always_exits() {
+            to_finally.push(self.emit_jump(Insc::Jump(0)));
+        }
+
+        let mut catch_handlers: Vec<(HandlerKind, usize)> =
+            Vec::with_capacity(try_stmt.catch_clauses.len());
+        for catch_clause /*: &CatchClause*/ in try_stmt.catch_clauses.iter() {
+            let handler_addr: usize = self.gen_catch_clause(catch_clause);
+            if !catch_clause.body.always_exits() {
+                to_finally.push(self.emit_jump(Insc::Jump(0)));
+            }
+
+            let kind: HandlerKind = match unsafe { catch_clause.catch_type.as_ref() } {
+                TyckInfo::Plain(type_id) => HandlerKind::Typed(*type_id),
+                TyckInfo::AnyType => HandlerKind::CatchAll,
+                _ => unreachable!("caught types should be plain types or `any`")
+            };
+            catch_handlers.push((kind, handler_addr));
+        }
+        let catch_end: usize = self.code.len() - 1;
+
+        let try_context: TryContext<'s> = self.try_stack.pop().unwrap();
+        for (kind, handler_addr) /*: (HandlerKind, usize)*/ in catch_handlers {
+            self.push_exc_handler(try_start, try_end, &try_context.holes, kind, handler_addr);
+        }
+
+        let finally_body: &CompoundStmt<'s> = if let Some(finally_body) = finally_body {
+            finally_body
+        } else {
+            for jump /*: usize*/ in to_finally {
+                self.patch_jump(jump);
+            }
+            return;
+        };
+
+        // the normal path, reached by falling through the `try` block or `catch` clauses
+        let mut to_end: Option<usize> = None;
+        if !to_finally.is_empty() {
+            for jump /*: usize*/ in to_finally {
+                self.patch_jump(jump);
+            }
+            self.gen_compound_stmt(finally_body);
+            if !finally_body.always_exits() {
+                to_end = Some(self.emit_jump(Insc::Jump(0)));
+            }
+        }
+
+        // the exceptional path, which throws the exception again after the `finally` block
+        let handler_addr: usize = self.code.len();
+        if self.push_exc_handler(
+            try_start,
+            catch_end,
+            &try_context.holes,
+            HandlerKind::Finally,
+            handler_addr
+        ) {
+            let mark: usize = self.next_reg;
+            let exception_reg: usize = self.alloc_reg();
+            self.code.push(Insc::Move(usize::MAX, exception_reg));
+            self.catch_moves.push(handler_addr);
+            self.gen_compound_stmt(finally_body);
+            if !finally_body.always_exits() {
+                self.code.push(Insc::Rethrow(exception_reg));
+            }
+            self.next_reg = mark;
+        }
+
+        if let Some(to_end) = to_end {
+            self.patch_jump(to_end);
+        }
+    }
+
+    /// Adds handlers covering instructions `[start, end]` except `holes`. Returns whether any
+    /// handler got added.
+    fn push_exc_handler(
+        &mut self,
+        start: usize,
+        end: usize,
+        holes: &[(usize, usize)],
+        kind: HandlerKind,
+        handler_addr: usize
+    ) -> bool {
+        let mut added: bool = false;
+        let mut start: usize = start;
+        for (hole_start, hole_end) /*: (usize, usize)*/ in holes.iter().copied() {
+            if hole_start > end {
+                break;
+            }
+            if hole_start > start {
+                self.exc_handlers.push(
+                    ExceptionHandlingBlock::new(start, hole_start - 1, kind, handler_addr)
+                );
+                added = true;
+            }
+            start = start.max(hole_end);
+        }
+        if start <= end {
+            self.exc_handlers.push(ExceptionHandlingBlock::new(start, end, kind, handler_addr));
+            added = true;
+        }
+        added
+    }
+
+    /// Generates a catch clause, returns the address of its handler
+    fn gen_catch_clause(&mut self, catch_clause: &CatchClause<'s>) -> usize {
+        let mark: usize = self.next_reg;
+        let catch_var: &ObjectDecl<'s> = catch_clause.catch_var.get_tricky(self.arena);
+        let var_reg: usize = self.alloc_reg();
+        self.local_regs.insert(catch_var as *const ObjectDecl<'s>, var_reg);
+
+        // the VM puts caught exceptions into the last register of the frame, whose index is not
+        // known until the whole function gets generated
+        let handler_addr: usize = self.code.len();
+        self.code.push(Insc::Move(usize::MAX, var_reg));
+        self.catch_moves.push(handler_addr);
+
+        if catch_clause.rethrown {
+            // the catch variable may be assigned, so keep the caught exception elsewhere
+            let caught_reg: usize = self.alloc_reg();
+            self.code.push(Insc::Move(var_reg, caught_reg));
+            self.caught_regs.push(caught_reg);
+            self.gen_compound_stmt(&catch_clause.body);
+            self.caught_regs.pop();
+        } else {
+            self.gen_compound_stmt(&catch_clause.body);
+        }
+        self.next_reg = mark;
+        handler_addr
+    }
+
+    fn gen_spawn_stmt(&mut self, spawn_stmt: &SpawnStmt<'s>) {
+        if !self.async_enabled() {
+            self.diag_async_disabled(spawn_stmt.concrete.spawn_kwd_range);
+            return;
+        }
+
+        #[cfg(feature = "async")]
+        {
+            let func_call_expr: &FuncCallExpr<'s> = match &spawn_stmt.func_call {
+                Expr::FuncCallExpr(func_call_expr) => func_call_expr.get_tricky(self.arena),
+                _ => unreachable!("spawned expressions should be function calls")
+            };
+            let func_decl: *const FuncDecl<'s> = match &func_call_expr.func {
+                Either::Left(func_decl) => func_decl.get_tricky(self.arena),
+                Either::Right(_) => unreachable!("spawned functions should be script functions")
+            };
+            let func_id: usize = if let FuncRef::Script(func_id) = self.func_refs[&func_decl] {
+                func_id
+            } else {
+                unreachable!("spawned functions should be script functions")
+            };
+            let param_types: Vec<NonNull<TyckInfo>> = self.param_types(func_call_expr);
+            let args: Vec<usize> =
+                self.gen_args(&func_call_expr.args, &param_types, func_call_expr.tyck_args);
+
+            // the join handle is not used for now
+            let join_handle: usize = self.alloc_reg();
+            let args: &'static [usize] = self.make_slice(&args);
+            let dests: &'static [usize] = self.make_slice(&[join_handle]);
+            self.code.push(Insc::Spawn(func_id, args));
+            self.code.push(Insc::Await(0, dests));
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! # Code generation
+//!
+//! Lowers declarations checked by `sema` into executable programs of the virtual machines.
+
+#[cfg(feature = "al31f-builtin-ops")] pub mod al31f;
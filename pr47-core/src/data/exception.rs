@@ -1,15 +1,30 @@
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::ptr::NonNull;
 
 use crate::data::Value;
 use crate::data::tyck::TyckInfo;
+use crate::data::wrapper::{
+    OWN_INFO_COLLECT_MASK,
+    OWN_INFO_GLOBAL_MASK,
+    OWN_INFO_MOVE_MASK,
+    OWN_INFO_OWNED_MASK,
+    OWN_INFO_READ_MASK,
+    OWN_INFO_WRITE_MASK
+};
 
 #[cfg(feature = "async-astd")] use std::convert::Infallible as JoinError;
 #[cfg(feature = "async-tokio")] use tokio::task::JoinError;
 
+/// Errors detected by the VM itself
+///
+/// Scripts may catch these by the types in `builtins::vm_error`, except for fatal ones, see
+/// `builtins::vm_error::catchable_type_id`.
 pub enum UncheckedException {
     AlreadyAwaited { promise: Value },
     ArgCountMismatch { func_id: usize, expected: usize, got: usize },
+    RetCountMismatch { func_id: usize, expected: usize, got: usize },
+    /// A value called is neither a function nor a closure
+    NotCallable { value: Value },
     DivideByZero,
     InvalidBinaryOp { bin_op: char, lhs: Value, rhs: Value },
     InvalidCastOp { dest_type: &'static str, src: Value },
@@ -19,10 +34,106 @@ pub enum UncheckedException {
     OverloadCallFailure { overload_table: usize },
     UnexpectedNull { value: Value },
     IndexOutOfBounds { indexed: Value, index: i64 },
+    /// Calling `func_id` would exceed the stack limits of the thread, see
+    /// `AL31F::with_stack_limits`
+    StackOverflow { func_id: usize, depth: usize },
+    /// The run executed more instructions than its budget, see `AL31F::with_fuel`
+    OutOfFuel { fuel: u64 },
+    /// Managed objects occupy more memory than the quota even after collection, see
+    /// `AL31F::with_memory_quota`
+    OutOfMemory { usage: usize, quota: usize },
+    /// The host asked the VM to stop, see `InterruptHandle`
+    Interrupted,
     #[cfg(feature = "async")]
     JoinError { inner: JoinError }
 }
 
+/// Describes the exception like `type check failed: expected vector<int>, got string`
+///
+/// Values carried are described by their type names only, see `Value::type_name`. Type
+/// information and container vtables referred belong to the program, so the program should
+/// still be alive.
+impl Display for UncheckedException {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UncheckedException::AlreadyAwaited { promise } =>
+                write!(f, "{} already awaited", promise.type_name()),
+            UncheckedException::ArgCountMismatch { func_id, expected, got } => write!(
+                f,
+                "argument count mismatch calling function #{}: expected {}, got {}",
+                func_id,
+                expected,
+                got
+            ),
+            UncheckedException::RetCountMismatch { func_id, expected, got } => write!(
+                f,
+                "return value count mismatch calling function #{}: expected {}, got {}",
+                func_id,
+                expected,
+                got
+            ),
+            UncheckedException::NotCallable { value } =>
+                write!(f, "{} is not callable", value.type_name()),
+            UncheckedException::DivideByZero => write!(f, "divide by zero"),
+            UncheckedException::InvalidBinaryOp { bin_op, lhs, rhs } => write!(
+                f,
+                "invalid operands to binary `{}`: {} and {}",
+                bin_op,
+                lhs.type_name(),
+                rhs.type_name()
+            ),
+            UncheckedException::InvalidCastOp { dest_type, src } =>
+                write!(f, "cannot cast {} to {}", src.type_name(), dest_type),
+            UncheckedException::InvalidUnaryOp { unary_op, src } =>
+                write!(f, "invalid operand to unary `{}`: {}", unary_op, src.type_name()),
+            UncheckedException::OwnershipCheckFailure { object, expected_mask } => {
+                let names: Vec<&str> = OWNERSHIP_MASK_NAMES.iter()
+                    .filter(|(mask, _): &&(u8, &str)| expected_mask & mask != 0)
+                    .map(|(_, name): &(u8, &str)| *name)
+                    .collect();
+                write!(f, "ownership check failed: {} object should be ", object.type_name())?;
+                match names.split_last() {
+                    Some((last, [])) => write!(f, "{}", last),
+                    Some((last, init)) => write!(f, "{} or {}", init.join(", "), last),
+                    None => write!(f, "nothing")
+                }
+            },
+            UncheckedException::TypeCheckFailure { object, expected_type } => write!(
+                f,
+                "type check failed: expected {}, got {}",
+                unsafe { expected_type.as_ref() },
+                object.type_name()
+            ),
+            UncheckedException::OverloadCallFailure { overload_table } => write!(
+                f,
+                "no overload candidate accepts the arguments (overload table #{})",
+                overload_table
+            ),
+            UncheckedException::UnexpectedNull { .. } => write!(f, "unexpected null value"),
+            UncheckedException::IndexOutOfBounds { indexed, index } =>
+                write!(f, "index {} out of bounds of {}", index, indexed.type_name()),
+            UncheckedException::StackOverflow { func_id, depth } =>
+                write!(f, "stack overflow calling function #{} at depth {}", func_id, depth),
+            UncheckedException::OutOfFuel { fuel } =>
+                write!(f, "instruction budget of {} exhausted", fuel),
+            UncheckedException::OutOfMemory { usage, quota } =>
+                write!(f, "memory quota of {} bytes exceeded, {} bytes in use", quota, usage),
+            UncheckedException::Interrupted => write!(f, "interrupted by host"),
+            #[cfg(feature = "async")]
+            UncheckedException::JoinError { inner } => write!(f, "cannot join task: {}", inner)
+        }
+    }
+}
+
+const OWNERSHIP_MASK_NAMES: [(u8, &str); 6] = [
+    (OWN_INFO_GLOBAL_MASK, "global"),
+    (OWN_INFO_READ_MASK, "readable"),
+    (OWN_INFO_WRITE_MASK, "writable"),
+    (OWN_INFO_MOVE_MASK, "movable"),
+    (OWN_INFO_COLLECT_MASK, "collectable"),
+    (OWN_INFO_OWNED_MASK, "owned")
+];
+
 pub type CheckedException = Value;
 
 pub enum ExceptionInner {
@@ -32,10 +143,19 @@ pub enum ExceptionInner {
 
 impl Debug for ExceptionInner {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // TODO should we provide prettier printing?
         match self {
-            ExceptionInner::Unchecked(_) => write!(f, "ExceptionInner::Unchecked"),
-            ExceptionInner::Checked(_) => write!(f, "ExceptionInner::Checked")
+            ExceptionInner::Unchecked(e) => write!(f, "ExceptionInner::Unchecked({})", e),
+            ExceptionInner::Checked(e) =>
+                write!(f, "ExceptionInner::Checked({})", e.type_name())
+        }
+    }
+}
+
+impl Display for ExceptionInner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExceptionInner::Unchecked(e) => write!(f, "{}", e),
+            ExceptionInner::Checked(e) => write!(f, "exception of type {}", e.type_name())
         }
     }
 }
@@ -51,3 +171,78 @@ impl StackTrace {
         Self { func_id, insc_ptr }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use std::ptr::NonNull;
+
+    use crate::builtins::vec::VMGenericVec;
+    use crate::data::Value;
+    use crate::data::tyck::{TyckInfo, TyckInfoPool};
+    use crate::data::wrapper::{DynBase, OWN_INFO_READ_MASK, OWN_INFO_WRITE_MASK};
+
+    use super::UncheckedException;
+
+    #[test]
+    fn test_unchecked_exception_display() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let int_type: NonNull<TyckInfo> = tyck_info_pool.get_int_type();
+        let int_vec_type: NonNull<TyckInfo> = tyck_info_pool.create_container_type(
+            TypeId::of::<VMGenericVec>(),
+            &[int_type]
+        );
+        let nullable_int_vec_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_nullable_type(int_vec_type);
+        let func_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_function_type(&[int_type, nullable_int_vec_type], &[], &[]);
+        assert_eq!(
+            unsafe { func_type.as_ref() }.to_string(),
+            "func(int, vector<int>?) -> ()"
+        );
+
+        let string: Value = Value::new_owned("abc".to_string());
+        assert_eq!(
+            UncheckedException::TypeCheckFailure {
+                object: string,
+                expected_type: int_vec_type
+            }.to_string(),
+            "type check failed: expected vector<int>, got string"
+        );
+        assert_eq!(
+            UncheckedException::InvalidBinaryOp {
+                bin_op: '+',
+                lhs: Value::new_int(1),
+                rhs: string
+            }.to_string(),
+            "invalid operands to binary `+`: int and string"
+        );
+        assert_eq!(
+            UncheckedException::OwnershipCheckFailure {
+                object: string,
+                expected_mask: OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK
+            }.to_string(),
+            "ownership check failed: string object should be readable or writable"
+        );
+        assert_eq!(
+            UncheckedException::UnexpectedNull { value: Value::new_null() }.to_string(),
+            "unexpected null value"
+        );
+        assert_eq!(
+            UncheckedException::StackOverflow { func_id: 3, depth: 1024 }.to_string(),
+            "stack overflow calling function #3 at depth 1024"
+        );
+        assert_eq!(
+            UncheckedException::OutOfFuel { fuel: 1000 }.to_string(),
+            "instruction budget of 1000 exhausted"
+        );
+        assert_eq!(
+            UncheckedException::OutOfMemory { usage: 2048, quota: 1024 }.to_string(),
+            "memory quota of 1024 bytes exceeded, 2048 bytes in use"
+        );
+        assert_eq!(UncheckedException::Interrupted.to_string(), "interrupted by host");
+
+        let boxed: Box<dyn DynBase> = unsafe { Box::from_raw(string.get_as_dyn_base()) };
+        drop(boxed);
+    }
+}
@@ -22,6 +22,8 @@ pub type ChildrenFn = unsafe fn(this: *const ()) -> ChildrenType;
 
 pub type DropFn = unsafe fn(this: *mut());
 
+pub type SizeFn = unsafe fn(this: *const ()) -> usize;
+
 pub type GenericTypeCtor = fn() -> *mut Wrapper<()>;
 
 pub struct GenericTypeVT {
@@ -32,7 +34,8 @@ pub struct GenericTypeVT {
     #[cfg(not(debug_assertions))]
     pub move_out_fn: MoveOutFn,
     pub children_fn: ChildrenFn,
-    pub drop_fn: DropFn
+    pub drop_fn: DropFn,
+    pub size_fn: SizeFn
 }
 
 impl GenericTypeVT {
@@ -42,14 +45,16 @@ impl GenericTypeVT {
         type_name: impl ToString,
         move_out_fn: MoveOutCkFn,
         children_fn: ChildrenFn,
-        drop_fn: DropFn
+        drop_fn: DropFn,
+        size_fn: SizeFn
     ) -> Self {
         Self {
             tyck_info,
             type_name: type_name.to_string(),
             move_out_fn,
             children_fn,
-            drop_fn
+            drop_fn,
+            size_fn
         }
     }
 
@@ -59,14 +64,16 @@ impl GenericTypeVT {
         type_name: impl ToString,
         move_out_fn: MoveOutFn,
         children_fn: ChildrenFn,
-        drop_fn: DropFn
+        drop_fn: DropFn,
+        size_fn: SizeFn
     ) -> Self {
         Self {
             tyck_info,
             type_name: type_name.to_string(),
             move_out_fn,
             children_fn,
-            drop_fn
+            drop_fn,
+            size_fn
         }
     }
 }
@@ -89,7 +96,7 @@ pub mod gen_impls {
     use xjbutil::void::Void;
 
     use crate::data::traits::{ChildrenType, StaticBase};
-    use crate::data::wrapper::{OwnershipInfo, Wrapper};
+    use crate::data::wrapper::{DynBase, OwnershipInfo, Wrapper};
 
     #[cfg(debug_assertions)] use std::any::TypeId;
 
@@ -139,4 +146,13 @@ pub mod gen_impls {
         let boxed: Box<Wrapper<T>> = Box::from_raw(this as *mut _);
         drop(boxed);
     }
+
+    #[inline(always)]
+    pub unsafe fn generic_size<T>(this: *const ()) -> usize
+        where T: 'static,
+              Void: StaticBase<T>
+    {
+        let this: &Wrapper<T> = &*(this as *const Wrapper<_>);
+        this.dyn_size()
+    }
 }
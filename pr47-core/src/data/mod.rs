@@ -15,14 +15,17 @@ use xjbutil::wide_ptr::WidePointer;
 
 use crate::data::generic::{GENERIC_TYPE_MASK, GenericTypeVT};
 use crate::data::traits::StaticBase;
-use crate::data::value_typed::{VALUE_TYPE_MASK, ValueTypedData};
+use crate::data::value_typed::{
+    VALUE_TYPE_MASK,
+    VALUE_TYPE_TAG_MASK,
+    ValueTypeTag,
+    ValueTypedData
+};
 use crate::data::wrapper::{DynBase, OwnershipInfo, Wrapper};
 
 #[cfg(any(test, feature = "bench"))]
 use std::fmt::{Debug, Formatter};
 use xjbutil::provenance_ignore;
-#[cfg(any(test, feature = "bench"))]
-use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
 
 pub const TAG_BITS_MASK: u8 = 0b00000_111;
 pub const TAG_BITS_MASK_USIZE: usize = TAG_BITS_MASK as usize;
@@ -171,6 +174,33 @@ impl Value {
         }
     }
 
+    /// Get the name of the type of a `Value`, like `int`, `string` or `vector`
+    ///
+    /// Only the `Value` itself and the vtables it points to are read, never the referenced data,
+    /// so the name is still available after the referenced data got collected. Vtables of
+    /// containers belong to the program, which should still be alive.
+    pub fn type_name(&self) -> String {
+        if self.is_null() {
+            "null".to_string()
+        } else if self.is_value() {
+            unsafe {
+                match ValueTypeTag::unsafe_from((self.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+                    ValueTypeTag::Int => "int".to_string(),
+                    ValueTypeTag::Float => "float".to_string(),
+                    ValueTypeTag::Bool => "bool".to_string()
+                }
+            }
+        } else if self.is_container() {
+            unsafe {
+                let vt: *const GenericTypeVT = self.ptr_repr.trivia as *const GenericTypeVT;
+                (*vt).type_name.clone()
+            }
+        } else {
+            // `dyn_type_name` only dispatches through the vtable, never touching the wrapper
+            unsafe { (*self.ptr).dyn_type_name() }
+        }
+    }
+
     /// Assuming that `self` may be a custom pointer, get the untagged pointer
     #[inline(always)] pub unsafe fn untagged_ptr_field(&self) -> usize {
         self.ptr_repr.ptr & PTR_BITS_MASK_USIZE
@@ -293,6 +323,18 @@ impl Value {
         self.ptr
     }
 
+    /// Given that `self` **MUST** be a reference, assuming that `self` may be a custom pointer,
+    /// get the bytes of memory occupied by the referenced object
+    pub unsafe fn memory_size(&self) -> usize {
+        debug_assert!(self.is_ref());
+        if self.is_container() {
+            let vt: *const GenericTypeVT = self.ptr_repr.trivia as *const _;
+            ((*vt).size_fn)(self.untagged_ptr_field() as *const ())
+        } else {
+            (*self.get_as_dyn_base()).dyn_size()
+        }
+    }
+
     /// Given that `self` **MUST** be a reference, assuming that `self` may be a custom pointer,
     /// get a pointer to the referenced data
     #[cfg_attr(not(debug_assertions), inline)]
@@ -31,6 +31,9 @@ pub trait StaticBase<T: 'static> {
     }
 
     #[inline] fn children(_vself: *const T) -> ChildrenType { None }
+
+    /// Bytes of heap memory owned by `vself`, not counting `size_of::<T>()` itself
+    #[inline] fn heap_size(_vself: *const T) -> usize { 0 }
 }
 
 // impl !StaticBase<i64> for Void {}
@@ -44,6 +47,10 @@ impl StaticBase<String> for Void {
     fn type_name() -> String {
         "string".into()
     }
+
+    #[inline] fn heap_size(vself: *const String) -> usize {
+        unsafe { (*vself).capacity() }
+    }
 }
 
 pub trait VMType<T: 'static> {}
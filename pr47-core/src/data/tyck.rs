@@ -1,6 +1,7 @@
 use std::any::TypeId;
 use std::collections::HashSet;
 use std::cmp::{Eq, PartialEq};
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::hint::unreachable_unchecked;
 use std::mem::{discriminant, forget};
@@ -9,13 +10,58 @@ use std::ptr::NonNull;
 use xjbutil::korobka::Korobka;
 use xjbutil::std_ext::{BoxedExt, VecExt};
 
+use crate::builtins::closure::Closure;
 use crate::builtins::object::Object;
+use crate::builtins::vec::VMGenericVec;
+use crate::builtins::vm_error::{vm_error_type_id, vm_error_type_name};
+
+/// Builtin types which can be referred by name outside the compiler, e.g. in bytecode and assembly
+///
+/// Error types in `builtins::vm_error` can be referred by name as well.
+const BUILTIN_TYPES: [(&str, fn() -> TypeId); 8] = [
+    ("int", TypeId::of::<i64>),
+    ("float", TypeId::of::<f64>),
+    ("char", TypeId::of::<char>),
+    ("bool", TypeId::of::<bool>),
+    ("string", TypeId::of::<String>),
+    ("object", TypeId::of::<Object>),
+    ("vector", TypeId::of::<VMGenericVec>),
+    ("closure", TypeId::of::<Closure>)
+];
+
+pub fn builtin_type_name(type_id: TypeId) -> Option<&'static str> {
+    BUILTIN_TYPES.iter()
+        .find(|(_, builtin_type_id)| builtin_type_id() == type_id)
+        .map(|(type_name, _)| *type_name)
+        .or_else(|| vm_error_type_name(type_id))
+}
+
+pub fn builtin_type_id(type_name: &str) -> Option<TypeId> {
+    BUILTIN_TYPES.iter()
+        .find(|(builtin_type_name, _)| *builtin_type_name == type_name)
+        .map(|(_, type_id)| type_id())
+        .or_else(|| vm_error_type_id(type_name))
+}
 
 pub struct ContainerTyckInfo {
     pub type_id: TypeId,
     pub params: NonNull<[NonNull<TyckInfo>]>
 }
 
+impl ContainerTyckInfo {
+    pub unsafe fn unsafe_to_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for ContainerTyckInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}<", builtin_type_name(self.type_id).unwrap_or("<unknown>"))?;
+        fmt_types(f, unsafe { self.params.as_ref() })?;
+        write!(f, ">")
+    }
+}
+
 pub struct FunctionTyckInfo {
     pub params: NonNull<[NonNull<TyckInfo>]>,
     pub rets: NonNull<[NonNull<TyckInfo>]>,
@@ -54,6 +100,47 @@ impl TyckInfo {
             unreachable_unchecked()
         }
     }
+
+    pub unsafe fn unsafe_to_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Formats the type like `vector<int?>` or `func(int, string) -> (bool)`. Types other than
+/// builtin types are shown as `<unknown>`.
+impl Display for TyckInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TyckInfo::AnyType => write!(f, "any"),
+            TyckInfo::Plain(type_id) =>
+                write!(f, "{}", builtin_type_name(*type_id).unwrap_or("<unknown>")),
+            TyckInfo::Nullable(base) => write!(f, "{}?", unsafe { base.as_ref() }),
+            TyckInfo::Container(container_tyck_info) => container_tyck_info.fmt(f),
+            TyckInfo::Function(FunctionTyckInfo { params, rets, exceptions }) => unsafe {
+                write!(f, "func(")?;
+                fmt_types(f, params.as_ref())?;
+                write!(f, ") -> (")?;
+                fmt_types(f, rets.as_ref())?;
+                write!(f, ")")?;
+                if !exceptions.as_ref().is_empty() {
+                    write!(f, " throws (")?;
+                    fmt_types(f, exceptions.as_ref())?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn fmt_types(f: &mut Formatter<'_>, types: &[NonNull<TyckInfo>]) -> std::fmt::Result {
+    for (i, tyck_info) /*: (usize, &NonNull<TyckInfo>)*/ in types.iter().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", unsafe { tyck_info.as_ref() })?;
+    }
+    Ok(())
 }
 
 impl Drop for TyckInfo {
@@ -266,6 +353,19 @@ impl TyckInfoPool {
         unsafe { NonNull::new_unchecked(&self.commons.tyck_info_any as *const _ as *mut _) }
     }
 
+    /// Gets a plain builtin type by its name, see `builtin_type_name`
+    pub fn get_builtin_type(&mut self, type_name: &str) -> Option<NonNull<TyckInfo>> {
+        match type_name {
+            "int" => Some(self.get_int_type()),
+            "float" => Some(self.get_float_type()),
+            "char" => Some(self.get_char_type()),
+            "bool" => Some(self.get_bool_type()),
+            "string" => Some(self.get_string_type()),
+            "object" => Some(self.get_object_type()),
+            _ => builtin_type_id(type_name).map(|type_id| self.create_plain_type(type_id))
+        }
+    }
+
     pub fn create_plain_type(&mut self, type_id: TypeId) -> NonNull<TyckInfo> {
         let tyck_info: TyckInfo = TyckInfo::Plain(type_id);
         if let Some(tyck_info /*: &Korobka<TyckInfo>*/) = self.pool.get(&tyck_info) {
@@ -1,5 +1,5 @@
 use std::any::TypeId;
-use std::mem::{ManuallyDrop, MaybeUninit};
+use std::mem::{ManuallyDrop, MaybeUninit, size_of};
 use std::ptr::addr_of;
 
 use xjbutil::unchecked::UnsafeFrom;
@@ -155,6 +155,9 @@ pub trait DynBase {
     unsafe fn move_out(&mut self, out: *mut ());
 
     fn children(&self) -> ChildrenType;
+
+    /// Bytes of memory occupied by this wrapper and the data it owns
+    fn dyn_size(&self) -> usize;
 }
 
 impl<T: 'static> DynBase for Wrapper<T> where Void: StaticBase<T> {
@@ -197,4 +200,14 @@ impl<T: 'static> DynBase for Wrapper<T> where Void: StaticBase<T> {
         };
         <Void as StaticBase<T>>::children(vself)
     }
+
+    #[inline]
+    fn dyn_size(&self) -> usize {
+        let heap_size: usize = if (self.ownership_info & OWN_INFO_OWNED_MASK) != 0 {
+            <Void as StaticBase<T>>::heap_size(unsafe { self.data.owned.as_ptr() })
+        } else {
+            0
+        };
+        size_of::<Self>() + heap_size
+    }
 }
@@ -14,7 +14,6 @@ pub const err_unexpected_eoi: u32 = 2006;
 pub const err_import_decl_disallow_attr: u32 = 2007;
 pub const err_export_decl_disallow_attr: u32 = 2008;
 pub const err_expected_any_of_0_got_1: u32 = 2009;
-pub const err_no_top_level_var_decl: u32 = 2010;
 pub const err_expected_unqual_id: u32 = 2011;
 pub const err_missing_type_got_0: u32 = 2012;
 pub const err_bad_num_literal_hex_oct_bin: u32 = 2013;
@@ -24,6 +23,51 @@ pub const err_bad_escape: u32 = 2016;
 pub const err_unclosed_string: u32 = 2017;
 pub const err_duplicate_syntax_action_name_0: u32 = 2018;
 pub const err_undefined_identifier_0: u32 = 2019;
+pub const err_try_without_catch: u32 = 2020;
+pub const err_spawn_non_call_expr: u32 = 2021;
+pub const err_invalid_unary_operand_0: u32 = 2022;
+pub const err_invalid_binary_operands_0: u32 = 2023;
+pub const err_not_assignable: u32 = 2024;
+pub const err_assign_to_const_0: u32 = 2025;
+pub const err_assign_type_mismatch: u32 = 2026;
+pub const err_undefined_type_0: u32 = 2027;
+pub const err_undefined_module_0: u32 = 2028;
+pub const err_overloaded_func_as_value_0: u32 = 2029;
+pub const err_no_matching_overload_0: u32 = 2030;
+pub const err_ambiguous_overload_0: u32 = 2031;
+pub const err_arg_count_mismatch_0_1: u32 = 2032;
+pub const err_arg_type_mismatch_0: u32 = 2033;
+pub const err_not_callable: u32 = 2034;
+pub const err_not_single_value: u32 = 2035;
+pub const err_not_subscriptable: u32 = 2036;
+pub const err_invalid_subscript_index: u32 = 2037;
+pub const err_field_ref_non_object: u32 = 2038;
+pub const err_invalid_cast: u32 = 2039;
+pub const err_void_type_not_allowed: u32 = 2040;
+pub const err_deduced_type_not_allowed: u32 = 2041;
+pub const err_generic_param_count_0_1: u32 = 2042;
+pub const err_const_divide_by_zero: u32 = 2043;
+pub const err_const_shift_overflow_0: u32 = 2044;
+pub const err_const_invalid_cast: u32 = 2045;
+pub const err_redefinition_0: u32 = 2046;
+pub const err_init_type_mismatch: u32 = 2047;
+pub const err_top_level_const_not_constant_0: u32 = 2048;
+pub const err_condition_not_bool: u32 = 2049;
+pub const err_return_count_mismatch_0_1: u32 = 2050;
+pub const err_return_type_mismatch_0: u32 = 2051;
+pub const err_missing_return_0: u32 = 2052;
+pub const err_invalid_throw_type: u32 = 2053;
+pub const err_invalid_catch_type: u32 = 2054;
+pub const err_spawn_non_script_func: u32 = 2055;
+pub const err_ffi_func_as_value_0: u32 = 2056;
+pub const err_unresolved_ffi_func_0: u32 = 2057;
+pub const err_async_ffi_ret_type_0: u32 = 2058;
+pub const err_async_disabled: u32 = 2059;
+pub const err_bad_char_literal: u32 = 2060;
+pub const err_unclosed_char_literal: u32 = 2061;
+pub const err_bad_unicode_escape_0: u32 = 2062;
+pub const err_unclosed_raw_string_0: u32 = 2063;
+pub const err_rethrow_outside_catch: u32 = 2064;
 
 // warnings
 pub const warn_commence_placeholder: u32 = 4000;
@@ -54,7 +98,6 @@ pub const fn diag_message(code: u32) -> &'static str {
             err_import_decl_disallow_attr => "cannot add attribute to `import`s",
             err_export_decl_disallow_attr => "cannot add attribute to `export`s",
             err_expected_any_of_0_got_1 => "expected any of ?0, got ?1",
-            err_no_top_level_var_decl => "variable declaration cannot appear at top level",
             err_expected_unqual_id => "expected unqualified identifier",
             err_missing_type_got_0 => "missing type specifier, got ?0",
             err_bad_num_literal_hex_oct_bin =>
@@ -65,6 +108,54 @@ pub const fn diag_message(code: u32) -> &'static str {
             err_unclosed_string => "unclosed string literal",
             err_duplicate_syntax_action_name_0 => "duplicate syntax action name `?0`",
             err_undefined_identifier_0 => "undefined identifier `?0`",
+            err_try_without_catch =>
+                "`try` block requires at least one `catch` clause or a `finally` clause",
+            err_spawn_non_call_expr => "`spawn` requires a function call expression",
+            err_invalid_unary_operand_0 => "invalid operand type for unary operator ?0",
+            err_invalid_binary_operands_0 => "invalid operand types for binary operator ?0",
+            err_not_assignable => "expression is not assignable",
+            err_assign_to_const_0 => "cannot assign to constant `?0`",
+            err_assign_type_mismatch => "assigned value does not match the type of assignee",
+            err_undefined_type_0 => "undefined type `?0`",
+            err_undefined_module_0 => "undefined module `?0`",
+            err_overloaded_func_as_value_0 => "overloaded function `?0` cannot be used as a value",
+            err_no_matching_overload_0 => "no matching overload for function `?0`",
+            err_ambiguous_overload_0 => "ambiguous call to overloaded function `?0`",
+            err_arg_count_mismatch_0_1 => "expected ?0 argument(s), got ?1",
+            err_arg_type_mismatch_0 => "mismatched type for argument ?0",
+            err_not_callable => "expression is not callable",
+            err_not_single_value => "function call used as a value should return exactly one value",
+            err_not_subscriptable => "expression cannot be subscripted",
+            err_invalid_subscript_index => "invalid subscript index type",
+            err_field_ref_non_object => "cannot access field of non-object expression",
+            err_invalid_cast => "invalid type cast",
+            err_void_type_not_allowed => "`void` type is not allowed here",
+            err_deduced_type_not_allowed => "`auto` type is not allowed here",
+            err_generic_param_count_0_1 => "expected ?0 generic type parameter(s), got ?1",
+            err_const_divide_by_zero => "division by zero in constant expression",
+            err_const_shift_overflow_0 => "shift amount ?0 is out of range in constant expression",
+            err_const_invalid_cast => "constant value cannot be converted to the target type",
+            err_redefinition_0 => "redefinition of `?0`",
+            err_init_type_mismatch => "initializer does not match the declared type",
+            err_top_level_const_not_constant_0 =>
+                "initializer of top-level constant `?0` is not a constant expression",
+            err_condition_not_bool => "condition should be of `bool` type",
+            err_return_count_mismatch_0_1 => "expected ?0 return value(s), got ?1",
+            err_return_type_mismatch_0 => "mismatched type for return value ?0",
+            err_missing_return_0 => "function `?0` may reach its end without returning a value",
+            err_invalid_throw_type => "only values of reference types like `object` can be thrown",
+            err_invalid_catch_type => "only reference types like `object` can be caught",
+            err_spawn_non_script_func => "`spawn` requires a function defined in script",
+            err_ffi_func_as_value_0 => "host function `?0` cannot be used as a value",
+            err_unresolved_ffi_func_0 => "no host function named `?0` is registered",
+            err_async_ffi_ret_type_0 =>
+                "async host function `?0` should be declared to return exactly one `any` value",
+            err_async_disabled => "asynchronous operations are not enabled",
+            err_bad_char_literal => "character literal should contain exactly one character",
+            err_unclosed_char_literal => "unclosed character literal",
+            err_bad_unicode_escape_0 => "bad unicode escape sequence `\\u?0`",
+            err_unclosed_raw_string_0 => "unclosed raw string literal, expected `?0` to close it",
+            err_rethrow_outside_catch => "`rethrow` can only be used inside a `catch` clause",
             _ => "INVALID_ERROR_CODE"
         }
     } else /* if code > note_commence_placeholder */ {
@@ -35,6 +35,18 @@ impl SourceManager {
         file_id as u32
     }
 
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn file_name(&self, file_id: u32) -> &str {
+        &self.files[file_id as usize]
+    }
+
+    pub fn file_content(&self, file_id: u32) -> &str {
+        &self.file_contents[file_id as usize]
+    }
+
     pub fn compute_coord(&self, file_id: u32, file_offset: u32) -> (&str, SourceCoord) {
         let file_id: usize = file_id as usize;
         let file_offset: usize = file_offset as usize;
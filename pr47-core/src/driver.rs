@@ -0,0 +1,241 @@
+//! # Compiler driver
+//!
+//! Ties the lexer, parser, syntax actions, sema and code generator together, so that embedders
+//! can turn source files into a `CompiledProgram` with one call:
+//!
+//! ```rust,ignore
+//! let mut compiler: Compiler<DefaultAlloc> = Compiler::new().with_ffi_registry(ffi_registry);
+//! compiler.add_file("main.47", source);
+//! let program: CompiledProgram<DefaultAlloc> = compiler.compile()?;
+//! ```
+//!
+//! All source files share one global scope. File ids in diagnostics are the ones returned by
+//! `Compiler::add_file`, and can be resolved with `Compiler::source_manager`. So are file ids in
+//! line tables of compiled programs, which `Exception::render_backtrace` needs to resolve.
+
+use std::cell::RefCell;
+
+use crate::codegen::al31f::CodegenAL31F;
+use crate::data::tyck::TyckInfoPool;
+use crate::diag::{DiagContext, Diagnostic};
+use crate::diag::source::SourceManager;
+use crate::parse::parser::Parser;
+use crate::parse::syntax_action::{SyntaxActionApplier, TokenLitArena};
+use crate::sema::arena::Arena;
+use crate::sema::decl_context::DeclContext;
+use crate::sema::phase2::SemaPhase2;
+use crate::sema::scope::{Scope, ScopeKind};
+use crate::syntax::ConcreteProgram;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::CompiledProgram;
+use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+pub struct Compiler<A: Alloc> {
+    source_manager: SourceManager,
+    ffi_registry: FFIRegistry<A>,
+    #[cfg(feature = "optimized-rtlc")]
+    rtlc: bool,
+    #[cfg(feature = "async")]
+    allow_async: bool
+}
+
+impl<A: Alloc> Compiler<A> {
+    pub fn new() -> Self {
+        Self {
+            source_manager: SourceManager::new(),
+            ffi_registry: FFIRegistry::new(),
+            #[cfg(feature = "optimized-rtlc")]
+            rtlc: true,
+            #[cfg(feature = "async")]
+            allow_async: true
+        }
+    }
+
+    /// Sets the host functions available to scripts
+    #[must_use] pub fn with_ffi_registry(mut self, ffi_registry: FFIRegistry<A>) -> Self {
+        self.ffi_registry = ffi_registry;
+        self
+    }
+
+    /// Sets whether host functions are called with run time lifetime checking (RTLC). Enabled by
+    /// default.
+    #[cfg(feature = "optimized-rtlc")]
+    #[must_use] pub fn with_rtlc(mut self, rtlc: bool) -> Self {
+        self.rtlc = rtlc;
+        self
+    }
+
+    /// Sets whether `await`, `spawn` and async host functions are allowed. Enabled by default.
+    #[cfg(feature = "async")]
+    #[must_use] pub fn with_async(mut self, allow_async: bool) -> Self {
+        self.allow_async = allow_async;
+        self
+    }
+
+    /// Adds a source file, returns its file id
+    pub fn add_file(&mut self, file_name: impl ToString, file_content: impl ToString) -> u32 {
+        self.source_manager.add_file(file_name, file_content)
+    }
+
+    pub fn source_manager(&self) -> &SourceManager {
+        &self.source_manager
+    }
+
+    /// Consumes the compiler but keeps its source files, so that backtraces of compiled programs
+    /// can still be rendered
+    pub fn into_source_manager(self) -> SourceManager {
+        self.source_manager
+    }
+
+    pub fn ffi_registry_mut(&mut self) -> &mut FFIRegistry<A> {
+        &mut self.ffi_registry
+    }
+
+    /// Compiles all added source files into one program. Returns all diagnostics collected if
+    /// there is any error.
+    pub fn compile(&self) -> Result<CompiledProgram<A>, Vec<Diagnostic>> {
+        let token_lit_arena: TokenLitArena = TokenLitArena::new();
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut program: ConcreteProgram = ConcreteProgram::new();
+        for file_id /*: usize*/ in 0..self.source_manager.file_count() {
+            let file_id: u32 = file_id as u32;
+            let source: &str = self.source_manager.file_content(file_id);
+            let mut parser: Parser = Parser::new(file_id, source, &diag);
+            let file_program: ConcreteProgram = parser.parse();
+            program.global_attrs.extend(file_program.global_attrs);
+            program.decls.extend(file_program.decls);
+        }
+        let mut diag: DiagContext = diag.into_inner();
+
+        SyntaxActionApplier::new(&token_lit_arena, &mut diag).apply_actions(&mut program);
+        if diag.has_error() {
+            return Err(diag.clear_reset());
+        }
+
+        // sema trees borrow the concrete syntax tree for its whole lifetime, which the borrow
+        // checker cannot prove for a local variable whose destructor also uses that lifetime. So
+        // the tree is put onto the heap and freed manually after sema trees are gone.
+        let program: *mut ConcreteProgram = Box::into_raw(Box::new(program));
+        let result: Result<CompiledProgram<A>, Vec<Diagnostic>> =
+            self.compile_program(unsafe { &*program }, &mut diag);
+        drop(unsafe { Box::from_raw(program) });
+        result
+    }
+
+    fn compile_program<'s>(
+        &self,
+        program: &'s ConcreteProgram<'s>,
+        diag: &mut DiagContext
+    ) -> Result<CompiledProgram<A>, Vec<Diagnostic>> {
+        let mut arena: Arena = Arena::new();
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let mut sema: SemaPhase2 = SemaPhase2::new(
+            Scope::new(ScopeKind::Global),
+            &mut arena,
+            &mut tyck_info_pool,
+            diag
+        ).with_exception_types(self.ffi_registry.exception_types());
+        let decl_context: DeclContext = sema.check_program(program);
+        if diag.has_error() {
+            return Err(diag.clear_reset());
+        }
+
+        let codegen: CodegenAL31F<A> =
+            CodegenAL31F::new(&arena, tyck_info_pool, &self.ffi_registry, diag);
+        #[cfg(feature = "optimized-rtlc")]
+        let codegen: CodegenAL31F<A> = codegen.with_rtlc(self.rtlc);
+        #[cfg(feature = "async")]
+        let codegen: CodegenAL31F<A> = codegen.with_async(self.allow_async);
+        let compiled_program: Option<CompiledProgram<A>> = codegen.generate(&decl_context);
+        compiled_program.ok_or_else(|| diag.clear_reset())
+    }
+}
+
+/// Compiles source files given as `(file_name, file_content)` pairs, with default options
+pub fn compile<A: Alloc>(
+    files: &[(&str, &str)],
+    ffi_registry: FFIRegistry<A>
+) -> Result<CompiledProgram<A>, Vec<Diagnostic>> {
+    let mut compiler: Compiler<A> = Compiler::new().with_ffi_registry(ffi_registry);
+    for (file_name, file_content) /*: &(&str, &str)*/ in files.iter() {
+        compiler.add_file(file_name, file_content);
+    }
+    compiler.compile()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::data::Value;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::executor::vm_run_function_sync;
+    use crate::vm::al31f::exception::Exception;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+    use super::Compiler;
+
+    #[test]
+    fn test_driver_compile_files() {
+        let program: CompiledProgram<DefaultAlloc> = super::compile(&[
+            ("main.47", r#"
+                func main(): int {
+                    return square(SIDE) + 1;
+                }
+            "#),
+            ("util.47", r#"
+                const SIDE = 6;
+
+                func square(x int): int {
+                    return x * x;
+                }
+            "#)
+        ], FFIRegistry::new()).unwrap_or_else(|_| panic!("should compile"));
+
+        let result: Vec<Value> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), &program, 0, &[])
+        }.unwrap_or_else(|_| panic!("should not throw"));
+        assert_eq!(result.len(), 1);
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 37);
+    }
+
+    #[test]
+    fn test_driver_render_backtrace() {
+        let mut compiler: Compiler<DefaultAlloc> = Compiler::new();
+        compiler.add_file("main.47", "func main(): int {\n    return fib(3);\n}\n");
+        compiler.add_file("fib.47", r#"func fib(n int): int {
+    if (n < 2) {
+        return n / 0;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+"#);
+        let program: CompiledProgram<DefaultAlloc> =
+            compiler.compile().unwrap_or_else(|_| panic!("should compile"));
+
+        let exception: Exception = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), &program, 0, &[])
+        }.err().unwrap_or_else(|| panic!("should throw"));
+        assert_eq!(
+            exception.render_backtrace(&program, compiler.source_manager()),
+            "at fib (fib.47:3:16)\n\
+             at fib (fib.47:5:12)\n\
+             at fib (fib.47:5:12)\n\
+             at main (main.47:2:12)\n"
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    #[should_panic]
+    fn test_driver_async_disabled() {
+        let mut compiler: Compiler<DefaultAlloc> = Compiler::new().with_async(false);
+        compiler.add_file("main.47", r#"
+            func worker() {}
+
+            func main() {
+                spawn worker();
+            }
+        "#);
+        let _ = compiler.compile();
+    }
+}
@@ -23,12 +23,16 @@ pub mod ffi;
 pub mod util;
 pub mod vm;
 
+#[cfg(feature = "compiler")] pub mod codegen;
 #[cfg(feature = "compiler")] pub mod diag;
 #[cfg(feature = "compiler")] pub mod parse;
 #[cfg(feature = "compiler")] pub mod syntax;
 #[cfg(feature = "compiler")] pub mod sema;
 #[cfg(feature = "std47")]    pub mod std47;
 
+#[cfg(all(feature = "compiler", feature = "al31f-builtin-ops"))] pub mod driver;
+#[cfg(all(feature = "compiler", feature = "al31f-builtin-ops"))] pub mod repl;
+
 #[cfg(all(feature = "al31f-builtin-ops", not(feature = "al31f")))]
 compile_error!("using `al31f-builtin-ops` without `al31f` is meaningless");
 
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::iter::Peekable;
-use std::str::CharIndices;
+use std::str::{CharIndices, Chars};
 
 use phf::phf_map;
 
@@ -31,6 +31,7 @@ static DEFAULT_KEYWORDS_MAP: phf::Map<&'static str, TokenInner<'static>> = phf_m
     "do" => TokenInner::KwdDo,
     "else" => TokenInner::KwdElse,
     "export" => TokenInner::KwdExport,
+    "finally" => TokenInner::KwdFinally,
     "float" => TokenInner::KwdFloat,
     "func" => TokenInner::KwdFunc,
     "if" => TokenInner::KwdIf,
@@ -38,6 +39,7 @@ static DEFAULT_KEYWORDS_MAP: phf::Map<&'static str, TokenInner<'static>> = phf_m
     "int" => TokenInner::KwdInt,
     "object" => TokenInner::KwdObject,
     "open" => TokenInner::KwdOpen,
+    "rethrow" => TokenInner::KwdRethrow,
     "return" => TokenInner::KwdReturn,
     "spawn" => TokenInner::KwdSpawn,
     "string" => TokenInner::KwdString,
@@ -488,34 +490,74 @@ impl<'a, 'b> Lexer<'a, 'b> {
     }
 
     pub fn lex_char_lit(&mut self) -> Token<'a> {
-        todo!("인민의 운명을 한몸에 안고")
+        let start_loc: SourceLoc = self.current_loc();
+        self.next_char();
+
+        let mut lit: Option<char> = None;
+        let mut char_count: usize = 0;
+        while let Some((ch, _)) = self.cur_char() {
+            if ch == '\'' {
+                self.next_char();
+                let end_loc: SourceLoc = self.current_loc();
+                if char_count != 1 {
+                    self.diag.borrow_mut()
+                        .diag(start_loc, diag_data::err_bad_char_literal)
+                        .add_mark(SourceRange::from_loc_pair(start_loc, end_loc).into())
+                        .emit();
+                }
+                return Token::new_lit_char(
+                    lit.unwrap_or(' '),
+                    SourceRange::from_loc_pair(start_loc, end_loc)
+                );
+            }
+
+            if ch == '\n' {
+                break;
+            }
+
+            let decoded: Option<char> = if ch == '\\' {
+                self.next_char();
+                self.lex_escape()
+            } else {
+                self.next_char();
+                Some(ch)
+            };
+            if lit.is_none() {
+                lit = decoded;
+            }
+            char_count += 1;
+        }
+
+        let end_loc: SourceLoc = self.current_loc();
+        self.diag.borrow_mut()
+            .diag(end_loc, diag_data::err_unclosed_char_literal)
+            .add_mark(end_loc.into())
+            .emit();
+        Token::new_lit_char(lit.unwrap_or(' '), SourceRange::from_loc_pair(start_loc, end_loc))
     }
 
     pub fn lex_string_lit(&mut self) -> Token<'a> {
         let start_loc: SourceLoc = self.current_loc();
         self.next_char();
+        let body_start_offset: u32 = self.current_loc().offset;
 
         while let Some((ch, _)) = self.cur_char() {
             if ch == '"' {
                 let string_end_loc: SourceLoc = self.current_loc();
                 self.next_char();
 
-                let str: &'a str = unsafe { self.slice_source(start_loc.offset, string_end_loc.offset) };
-                return Token::new_lit_str(str, SourceRange::from_loc_pair(start_loc, self.current_loc()));
+                let str: &'a str = unsafe {
+                    self.slice_source(body_start_offset, string_end_loc.offset)
+                };
+                return Token::new_lit_str(
+                    str,
+                    SourceRange::from_loc_pair(start_loc, self.current_loc())
+                );
             }
 
             if ch == '\\' {
                 self.next_char();
-                if let Some((ch, _)) = self.cur_char() {
-                    match ch {
-                        'n' | 't' | 'r' | 'f' | 'v' | '"' | '\'' | '\\' => self.next_char(),
-                        _ => self.diag.borrow_mut()
-                            .diag(self.current_loc(), diag_data::err_bad_escape)
-                            .add_mark(self.current_loc().into())
-                            .add_arg(ch)
-                            .emit(),
-                    }
-                }
+                self.lex_escape();
             } else {
                 self.next_char();
             }
@@ -526,13 +568,112 @@ impl<'a, 'b> Lexer<'a, 'b> {
             .diag(self.current_loc(), diag_data::err_unclosed_string)
             .add_mark(string_end_loc.into())
             .emit();
-        let str: &'a str = unsafe { self.slice_source(start_loc.offset, string_end_loc.offset) };
+        let str: &'a str = unsafe { self.slice_source(body_start_offset, string_end_loc.offset) };
 
         Token::new_lit_str(str, SourceRange::from_loc_pair(start_loc, string_end_loc))
     }
 
     pub fn lex_raw_string_lit(&mut self) -> Token<'a> {
-        todo!("모두다 꽃펴주실 분")
+        let start_loc: SourceLoc = self.current_loc();
+        let fence_len: usize = self.skip_backticks();
+        let content_start_loc: SourceLoc = self.current_loc();
+
+        while let Some((ch, _)) = self.cur_char() {
+            if ch == '`' {
+                let content_end_loc: SourceLoc = self.current_loc();
+                if self.skip_backticks() == fence_len {
+                    let str: &'a str = unsafe {
+                        self.slice_source(content_start_loc.offset, content_end_loc.offset)
+                    };
+                    return Token::new_lit_raw_str(
+                        str,
+                        SourceRange::from_loc_pair(start_loc, self.current_loc())
+                    );
+                }
+            } else {
+                self.next_char();
+            }
+        }
+
+        let string_end_loc: SourceLoc = self.current_loc();
+        self.diag.borrow_mut()
+            .diag(string_end_loc, diag_data::err_unclosed_raw_string_0)
+            .add_mark(string_end_loc.into())
+            .add_arg("`".repeat(fence_len))
+            .emit();
+        let str: &'a str = unsafe {
+            self.slice_source(content_start_loc.offset, string_end_loc.offset)
+        };
+
+        Token::new_lit_raw_str(str, SourceRange::from_loc_pair(start_loc, string_end_loc))
+    }
+
+    fn skip_backticks(&mut self) -> usize {
+        let mut count: usize = 0;
+        while let Some(('`', _)) = self.cur_char() {
+            self.next_char();
+            count += 1;
+        }
+        count
+    }
+
+    fn lex_escape(&mut self) -> Option<char> {
+        let (ch, _) = self.cur_char()?;
+        if ch == 'u' {
+            return self.lex_unicode_escape();
+        }
+
+        let decoded: Option<char> = simple_escape(ch);
+        if decoded.is_none() {
+            self.diag.borrow_mut()
+                .diag(self.current_loc(), diag_data::err_bad_escape)
+                .add_mark(self.current_loc().into())
+                .add_arg(ch)
+                .emit();
+        }
+        self.next_char();
+        decoded
+    }
+
+    fn lex_unicode_escape(&mut self) -> Option<char> {
+        let escape_loc: SourceLoc = self.current_loc();
+        self.next_char();
+
+        let mut escape: String = String::new();
+        let mut closed: bool = false;
+        if let Some(('{', _)) = self.cur_char() {
+            escape.push('{');
+            self.next_char();
+            while let Some((ch, _)) = self.cur_char() {
+                if ch.is_ascii_hexdigit() {
+                    escape.push(ch);
+                    self.next_char();
+                } else {
+                    break;
+                }
+            }
+            if let Some(('}', _)) = self.cur_char() {
+                escape.push('}');
+                self.next_char();
+                closed = true;
+            }
+        }
+
+        let hex_digits: &str = escape.trim_start_matches('{').trim_end_matches('}');
+        let decoded: Option<char> = if closed && !hex_digits.is_empty() && hex_digits.len() <= 6 {
+            u32::from_str_radix(hex_digits, 16).ok().and_then(char::from_u32)
+        } else {
+            None
+        };
+
+        if decoded.is_none() {
+            self.diag.borrow_mut()
+                .diag(escape_loc, diag_data::err_bad_unicode_escape_0)
+                .add_mark(SourceRange::from_loc_pair(escape_loc, self.current_loc()).into())
+                .add_arg(escape)
+                .emit();
+        }
+        decoded
     }
 
     fn lex_single_char_sym(&mut self, location: SourceLoc, token: TokenInner<'a>) -> Token<'a> {
@@ -630,3 +771,123 @@ impl<'a, 'b> Lexer<'a, 'b> {
         }
     }
 }
+
+/// Decodes the escape `\{ch}`, except for unicode escapes
+fn simple_escape(ch: char) -> Option<char> {
+    match ch {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        'f' => Some('\x0c'),
+        'v' => Some('\x0b'),
+        '"' | '\'' | '\\' => Some(ch),
+        _ => None
+    }
+}
+
+/// Decodes escapes in the body of a regular string literal, as given by `TokenInner::LitStr`
+///
+/// Malformed escapes have been diagnosed by the lexer already, so they are simply dropped here.
+pub fn unescape_str(body: &str) -> String {
+    let mut decoded: String = String::with_capacity(body.len());
+    let mut chars: Chars = body.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            decoded.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => {
+                let rest: &str = chars.as_str();
+                if let Some(hex_end) = rest.strip_prefix('{').and_then(|s: &str| s.find('}')) {
+                    let escape: Option<char> = u32::from_str_radix(&rest[1..=hex_end], 16)
+                        .ok()
+                        .and_then(char::from_u32);
+                    decoded.extend(escape);
+                    chars = rest[hex_end + 2..].chars();
+                }
+            },
+            Some(ch) => decoded.extend(simple_escape(ch)),
+            None => {}
+        }
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use crate::diag::DiagContext;
+    use crate::parse::lexer::{Lexer, unescape_str};
+    use crate::syntax::token::TokenInner;
+
+    fn lex_all(source: &str) -> Vec<TokenInner<'_>> {
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut lexer: Lexer = Lexer::new(0, source, &diag);
+        let mut tokens: Vec<TokenInner> = Vec::new();
+        loop {
+            let token: TokenInner = lexer.next_token().token_inner;
+            if token == TokenInner::EndOfInput {
+                break;
+            }
+            tokens.push(token);
+        }
+        assert!(!diag.borrow().has_error());
+        tokens
+    }
+
+    #[test]
+    fn test_lex_char_lit() {
+        assert_eq!(
+            lex_all(r"'a' '\n' '\'' '\\' '\u{48}' '\u{1F600}' '字'"),
+            vec![
+                TokenInner::LitChar('a'),
+                TokenInner::LitChar('\n'),
+                TokenInner::LitChar('\''),
+                TokenInner::LitChar('\\'),
+                TokenInner::LitChar('H'),
+                TokenInner::LitChar('\u{1F600}'),
+                TokenInner::LitChar('字'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_raw_string_lit() {
+        assert_eq!(
+            lex_all("`C:\\path\\n` ``a ` b`` ```\nline 1\n``line 2``\n```"),
+            vec![
+                TokenInner::LitRawStr("C:\\path\\n"),
+                TokenInner::LitRawStr("a ` b"),
+                TokenInner::LitRawStr("\nline 1\n``line 2``\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_string_unicode_escape() {
+        let tokens: Vec<TokenInner> = lex_all(r#""\u{48}\u{10FFFF}\t" "\"a\\b\"\n""#);
+        let bodies: Vec<String> = tokens.iter()
+            .map(|token: &TokenInner| if let TokenInner::LitStr(body) = token {
+                unescape_str(body)
+            } else {
+                panic!("should be string literals")
+            })
+            .collect();
+        assert_eq!(bodies, vec!["H\u{10FFFF}\t", "\"a\\b\"\n"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lex_bad_unicode_escape() {
+        lex_all(r"'\u{D800}'");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lex_bad_char_lit() {
+        lex_all("'ab'");
+    }
+}
@@ -176,6 +176,7 @@ const TOP_LEVEL_DECL_FIRST: &[TokenInner<'static>] = &[
     TokenInner::KwdFunc,
     TokenInner::KwdImport,
     TokenInner::KwdOpen,
+    TokenInner::KwdVar,
 ];
 
 const ATTR_FIRST: &[TokenInner<'static>] = &[TokenInner::SymHash];
@@ -188,3 +189,27 @@ const TOP_LEVEL_FIRST: &[&[TokenInner<'static>]] = &[
 const TOP_LEVEL_DECL_FAILSAFE: &[&[TokenInner<'static>]] = &[
     TOP_LEVEL_DECL_FIRST
 ];
+
+const STMT_FIRST: &[TokenInner<'static>] = &[
+    TokenInner::KwdConst,
+    TokenInner::KwdDo,
+    TokenInner::KwdIf,
+    TokenInner::KwdRethrow,
+    TokenInner::KwdReturn,
+    TokenInner::KwdSpawn,
+    TokenInner::KwdThrow,
+    TokenInner::KwdTry,
+    TokenInner::KwdVar,
+    TokenInner::KwdWhile,
+    TokenInner::SymLBrace
+];
+
+const STMT_END: &[TokenInner<'static>] = &[
+    TokenInner::SymSemicolon,
+    TokenInner::SymRBrace
+];
+
+const STMT_FAILSAFE: &[&[TokenInner<'static>]] = &[
+    STMT_FIRST,
+    STMT_END
+];
@@ -6,7 +6,7 @@ use xjbutil::defer;
 use crate::awa;
 use crate::diag::diag_data;
 use crate::diag::location::SourceRange;
-use crate::parse::lexer::LexerMode;
+use crate::parse::lexer::{LexerMode, unescape_str};
 use crate::syntax::attr::{AttrAssignLikeItem, AttrCallLikeItem, AttrItem, AttrValue, Attribute};
 use crate::syntax::id::Identifier;
 use crate::syntax::token::{Token, TokenInner};
@@ -141,7 +141,10 @@ impl<'s, 'd> Parser<'s, 'd> {
             TokenInner::LitInt(int_value) => Some(AttrValue::int_value(int_value as _, range)),
             TokenInner::LitFloat(float_value) => Some(AttrValue::float_value(float_value, range)),
             TokenInner::LitChar(char_value) => Some(AttrValue::char_value(char_value, range)),
-            TokenInner::LitStr(str_value) => Some(AttrValue::string_value(str_value, range)),
+            TokenInner::LitStr(str_value) =>
+                Some(AttrValue::string_value(unescape_str(str_value), range)),
+            TokenInner::LitRawStr(str_value) =>
+                Some(AttrValue::string_value(str_value.to_string(), range)),
             TokenInner::KwdTrue => Some(AttrValue::bool_value(true, range)),
             TokenInner::KwdFalse => Some(AttrValue::bool_value(false, range)),
             _ => {
@@ -350,6 +350,22 @@ mod test {
         dbg!(func);
     }
 
+    #[test]
+    fn test_parse_func_with_body() {
+        let source: &str = "func foo(bar int) int { var baz = bar * 2; return baz; }";
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(
+            0, source, &diag
+        );
+
+        let kwd_token: Token = parser.consume_token();
+        let func: ConcreteFuncDecl = parser.parse_func_decl(kwd_token, &[]).unwrap();
+        assert_eq!(func.func_body.as_ref().unwrap().stmts.len(), 2);
+
+        dbg!(func);
+    }
+
     #[test]
     fn test_parse_export() {
         let source: &str = "export (foo, bar::baz);";
@@ -216,6 +216,11 @@ impl<'s, 'd> Parser<'s, 'd> {
                     lit, self.consume_token().range
                 )))
             },
+            TokenInner::LitRawStr(lit) => {
+                Some(ConcreteExpr::LiteralExpr(ConcreteLiteralExpr::new_lit_raw_str(
+                    lit, self.consume_token().range
+                )))
+            },
             TokenInner::LitChar(lit) => {
                 Some(ConcreteExpr::LiteralExpr(ConcreteLiteralExpr::new_lit_char(
                     lit, self.consume_token().range
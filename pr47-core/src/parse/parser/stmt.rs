@@ -1,14 +1,450 @@
-use super::Parser;
+use super::{Parser, STMT_FAILSAFE, STMT_FIRST, STMT_END};
 
-use crate::syntax::stmt::ConcreteCompoundStmt;
+use xjbutil::defer;
+
+use crate::diag::diag_data;
+use crate::diag::location::{SourceLoc, SourceRange};
+use crate::parse::lexer::LexerMode;
+use crate::syntax::decl::{ConcreteDecl, ConcreteObjectDecl};
+use crate::syntax::expr::ConcreteExpr;
+use crate::syntax::id::Identifier;
+use crate::syntax::stmt::{
+    ConcreteCatchClause,
+    ConcreteCompoundStmt,
+    ConcreteDoWhileStmt,
+    ConcreteIfStmt,
+    ConcreteRethrowStmt,
+    ConcreteReturnStmt,
+    ConcreteSpawnStmt,
+    ConcreteStmt,
+    ConcreteThrowStmt,
+    ConcreteTryStmt,
+    ConcreteWhileStmt
+};
 use crate::syntax::token::{Token, TokenInner};
+use crate::syntax::ty::ConcreteType;
 
 impl<'s, 'd> Parser<'s, 'd> {
     pub fn parse_compound_stmt(
         &mut self,
-        _lbrace_token: Token<'s>,
-        _failsafe_set: &[&[TokenInner<'_>]]
+        lbrace_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
     ) -> Option<ConcreteCompoundStmt<'s>> {
-        todo!();
+        #[cfg(debug_assertions)] assert_eq!(lbrace_token.token_inner, TokenInner::SymLBrace);
+
+        let this: &mut Self = self;
+        defer!(|this: &mut Self| this.lexer.pop_lexer_mode(), this);
+        this.lexer.push_lexer_mode(LexerMode::LexDecl);
+
+        let mut stmt_failsafe_set: Vec<&[TokenInner<'_>]> =
+            Vec::with_capacity(STMT_FAILSAFE.len() + failsafe_set.len());
+        stmt_failsafe_set.extend_from_slice(STMT_FAILSAFE);
+        stmt_failsafe_set.extend_from_slice(failsafe_set);
+
+        let mut stmts: Vec<ConcreteStmt<'s>> = Vec::new();
+        loop {
+            if this.current_token().is_eoi() {
+                this.diag_unexpected_eoi(this.current_token().range);
+                return None;
+            }
+
+            match this.current_token().token_inner {
+                TokenInner::SymRBrace => break,
+                TokenInner::SymSemicolon => {
+                    let _ = this.consume_token();
+                },
+                _ => if let Some(stmt) = this.parse_stmt(&stmt_failsafe_set) {
+                    stmts.push(stmt);
+                } else {
+                    let current: TokenInner = this.current_token().token_inner;
+                    if !this.current_token().is_eoi()
+                        && !STMT_FIRST.contains(&current)
+                        && !STMT_END.contains(&current)
+                    {
+                        // stopped at something from the outer failsafe set: this block cannot
+                        // be continued, let the caller recover.
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let rbrace_range: SourceRange = this.consume_token().range;
+        Some(ConcreteCompoundStmt {
+            stmts,
+            left_brace_loc: lbrace_token.range.left(),
+            right_brace_loc: rbrace_range.left()
+        })
+    }
+
+    pub fn parse_stmt(&mut self, failsafe_set: &[&[TokenInner<'_>]])
+        -> Option<ConcreteStmt<'s>>
+    {
+        match self.current_token().token_inner {
+            TokenInner::SymLBrace => {
+                let lbrace_token: Token<'s> = self.consume_token();
+                self.parse_compound_stmt(lbrace_token, failsafe_set)
+                    .map(ConcreteStmt::CompoundStmt)
+            },
+            TokenInner::KwdConst => {
+                let const_token: Token<'s> = self.consume_token();
+                let kwd_loc: SourceLoc = const_token.range.left();
+                let decl: ConcreteObjectDecl<'s> =
+                    self.parse_object_decl(const_token, failsafe_set)?;
+                Some(ConcreteStmt::DeclStmt(Box::new(ConcreteDecl::ConstDecl(decl)), kwd_loc))
+            },
+            TokenInner::KwdVar => {
+                let var_token: Token<'s> = self.consume_token();
+                let kwd_loc: SourceLoc = var_token.range.left();
+                let decl: ConcreteObjectDecl<'s> =
+                    self.parse_object_decl(var_token, failsafe_set)?;
+                Some(ConcreteStmt::DeclStmt(Box::new(ConcreteDecl::VarDecl(decl)), kwd_loc))
+            },
+            TokenInner::KwdIf => {
+                let if_token: Token<'s> = self.consume_token();
+                self.parse_if_stmt(if_token, failsafe_set).map(ConcreteStmt::IfStmt)
+            },
+            TokenInner::KwdWhile => {
+                let while_token: Token<'s> = self.consume_token();
+                self.parse_while_stmt(while_token, failsafe_set).map(ConcreteStmt::WhileStmt)
+            },
+            TokenInner::KwdDo => {
+                let do_token: Token<'s> = self.consume_token();
+                self.parse_do_while_stmt(do_token, failsafe_set).map(ConcreteStmt::DoWhileStmt)
+            },
+            TokenInner::KwdReturn => {
+                let return_token: Token<'s> = self.consume_token();
+                self.parse_return_stmt(return_token, failsafe_set).map(ConcreteStmt::ReturnStmt)
+            },
+            TokenInner::KwdThrow => {
+                let throw_token: Token<'s> = self.consume_token();
+                self.parse_throw_stmt(throw_token, failsafe_set).map(ConcreteStmt::ThrowStmt)
+            },
+            TokenInner::KwdRethrow => {
+                let rethrow_token: Token<'s> = self.consume_token();
+                let semicolon_range: SourceRange =
+                    self.expect_n_consume(TokenInner::SymSemicolon, failsafe_set)?.range;
+                Some(ConcreteStmt::RethrowStmt(ConcreteRethrowStmt {
+                    rethrow_kwd_range: rethrow_token.range,
+                    semicolon_loc: semicolon_range.left()
+                }))
+            },
+            TokenInner::KwdTry => {
+                let try_token: Token<'s> = self.consume_token();
+                self.parse_try_stmt(try_token, failsafe_set).map(ConcreteStmt::TryStmt)
+            },
+            TokenInner::KwdSpawn => {
+                let spawn_token: Token<'s> = self.consume_token();
+                self.parse_spawn_stmt(spawn_token, failsafe_set).map(ConcreteStmt::SpawnStmt)
+            },
+            _ => {
+                let expr: ConcreteExpr<'s> = self.parse_expression(failsafe_set)?;
+                let semicolon_range: SourceRange =
+                    self.expect_n_consume(TokenInner::SymSemicolon, failsafe_set)?.range;
+                Some(ConcreteStmt::ExprStmt(expr, semicolon_range.left()))
+            }
+        }
+    }
+
+    pub fn parse_if_stmt(&mut self, if_token: Token<'s>, failsafe_set: &[&[TokenInner<'_>]])
+        -> Option<ConcreteIfStmt<'s>>
+    {
+        let cond_expr: ConcreteExpr<'s> = self.parse_expression_no_assign(failsafe_set)?;
+        let then_clause: ConcreteCompoundStmt<'s> = self.parse_block(failsafe_set)?;
+
+        let (else_clause, else_kwd_range): (Option<Box<ConcreteStmt<'s>>>, Option<SourceRange>) =
+            if self.current_token().token_inner == TokenInner::KwdElse {
+                let else_kwd_range: SourceRange = self.consume_token().range;
+                let else_clause: ConcreteStmt<'s> =
+                    if self.current_token().token_inner == TokenInner::KwdIf {
+                        let if_token: Token<'s> = self.consume_token();
+                        ConcreteStmt::IfStmt(self.parse_if_stmt(if_token, failsafe_set)?)
+                    } else {
+                        ConcreteStmt::CompoundStmt(self.parse_block(failsafe_set)?)
+                    };
+                (Some(Box::new(else_clause)), Some(else_kwd_range))
+            } else {
+                (None, None)
+            };
+
+        Some(ConcreteIfStmt {
+            cond_expr,
+            then_clause,
+            else_clause,
+            if_kwd_range: if_token.range,
+            else_kwd_range
+        })
+    }
+
+    pub fn parse_while_stmt(
+        &mut self,
+        while_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteWhileStmt<'s>> {
+        let cond_expr: ConcreteExpr<'s> = self.parse_expression_no_assign(failsafe_set)?;
+        let body: ConcreteCompoundStmt<'s> = self.parse_block(failsafe_set)?;
+        Some(ConcreteWhileStmt {
+            cond_expr,
+            body,
+            while_kwd_range: while_token.range
+        })
+    }
+
+    pub fn parse_do_while_stmt(
+        &mut self,
+        do_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteDoWhileStmt<'s>> {
+        let body: ConcreteCompoundStmt<'s> = self.parse_block(failsafe_set)?;
+        let while_kwd_range: SourceRange =
+            self.expect_n_consume(TokenInner::KwdWhile, failsafe_set)?.range;
+        let cond_expr: ConcreteExpr<'s> = self.parse_expression_no_assign(failsafe_set)?;
+        let semicolon_range: SourceRange =
+            self.expect_n_consume(TokenInner::SymSemicolon, failsafe_set)?.range;
+        Some(ConcreteDoWhileStmt {
+            body,
+            cond_expr,
+            do_kwd_range: do_token.range,
+            while_kwd_range,
+            semicolon_loc: semicolon_range.left()
+        })
+    }
+
+    pub fn parse_return_stmt(
+        &mut self,
+        return_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteReturnStmt<'s>> {
+        let (return_values, semicolon_range): (Vec<ConcreteExpr<'s>>, SourceRange) =
+            self.parse_list_alike(
+                Self::parse_expression_no_assign,
+                failsafe_set,
+                TokenInner::SymComma,
+                TokenInner::SymSemicolon,
+                failsafe_set
+            )?;
+        Some(ConcreteReturnStmt {
+            return_values,
+            return_kwd_range: return_token.range,
+            semicolon_loc: semicolon_range.left()
+        })
+    }
+
+    pub fn parse_throw_stmt(
+        &mut self,
+        throw_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteThrowStmt<'s>> {
+        let thrown_expr: ConcreteExpr<'s> = self.parse_expression_no_assign(failsafe_set)?;
+        let semicolon_range: SourceRange =
+            self.expect_n_consume(TokenInner::SymSemicolon, failsafe_set)?.range;
+        Some(ConcreteThrowStmt {
+            thrown_expr,
+            throw_kwd_range: throw_token.range,
+            semicolon_loc: semicolon_range.left()
+        })
+    }
+
+    pub fn parse_try_stmt(&mut self, try_token: Token<'s>, failsafe_set: &[&[TokenInner<'_>]])
+        -> Option<ConcreteTryStmt<'s>>
+    {
+        let body: ConcreteCompoundStmt<'s> = self.parse_block(failsafe_set)?;
+        let mut catch_clauses: Vec<ConcreteCatchClause<'s>> = Vec::new();
+        while self.current_token().token_inner == TokenInner::KwdCatch {
+            let catch_token: Token<'s> = self.consume_token();
+            catch_clauses.push(self.parse_catch_clause(catch_token, failsafe_set)?);
+        }
+
+        let mut finally_body: Option<ConcreteCompoundStmt<'s>> = None;
+        let mut finally_kwd_range: Option<SourceRange> = None;
+        if self.current_token().token_inner == TokenInner::KwdFinally {
+            let finally_token: Token<'s> = self.consume_token();
+            finally_body = Some(self.parse_block(failsafe_set)?);
+            finally_kwd_range = Some(finally_token.range);
+        }
+
+        if catch_clauses.is_empty() && finally_body.is_none() {
+            self.diag.borrow_mut()
+                .diag(self.current_token().range.left(), diag_data::err_try_without_catch)
+                .add_mark(try_token.range.into())
+                .emit();
+            return None;
+        }
+
+        Some(ConcreteTryStmt {
+            body,
+            catch_clauses,
+            finally_body,
+            try_kwd_range: try_token.range,
+            finally_kwd_range
+        })
+    }
+
+    pub fn parse_catch_clause(
+        &mut self,
+        catch_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteCatchClause<'s>> {
+        let lparen_loc: SourceLoc =
+            self.expect_n_consume(TokenInner::SymLParen, failsafe_set)?.range.left();
+        let catch_var: Identifier<'s> = self.parse_unqual_ident_with_skip(failsafe_set)?;
+        let _ = self.skip_optional(TokenInner::SymColon);
+        let catch_type: ConcreteType<'s> = self.parse_type(failsafe_set)?;
+        let rparen_loc: SourceLoc =
+            self.expect_n_consume(TokenInner::SymRParen, failsafe_set)?.range.left();
+        let body: ConcreteCompoundStmt<'s> = self.parse_block(failsafe_set)?;
+        Some(ConcreteCatchClause {
+            catch_var,
+            catch_type,
+            body,
+            catch_kwd_range: catch_token.range,
+            lparen_loc,
+            rparen_loc
+        })
+    }
+
+    pub fn parse_spawn_stmt(
+        &mut self,
+        spawn_token: Token<'s>,
+        failsafe_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteSpawnStmt<'s>> {
+        let expr: ConcreteExpr<'s> = self.parse_expression_no_assign(failsafe_set)?;
+        let func_call = if let ConcreteExpr::FuncCallExpr(func_call) = expr {
+            func_call
+        } else {
+            self.diag.borrow_mut()
+                .diag(spawn_token.range.left(), diag_data::err_spawn_non_call_expr)
+                .add_mark(spawn_token.range.into())
+                .emit();
+            self.skip_to_any_of(failsafe_set);
+            return None;
+        };
+        let semicolon_range: SourceRange =
+            self.expect_n_consume(TokenInner::SymSemicolon, failsafe_set)?.range;
+        Some(ConcreteSpawnStmt {
+            func_call,
+            spawn_kwd_range: spawn_token.range,
+            semicolon_loc: semicolon_range.left()
+        })
+    }
+
+    fn parse_block(&mut self, failsafe_set: &[&[TokenInner<'_>]])
+        -> Option<ConcreteCompoundStmt<'s>>
+    {
+        let lbrace_token: Token<'s> = self.expect_n_consume(TokenInner::SymLBrace, failsafe_set)?;
+        self.parse_compound_stmt(lbrace_token, failsafe_set)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use crate::diag::DiagContext;
+    use crate::parse::parser::Parser;
+    use crate::syntax::stmt::{ConcreteCompoundStmt, ConcreteStmt};
+    use crate::syntax::token::Token;
+
+    #[test]
+    fn test_parse_compound_stmt() {
+        let source: &str = "{ var a = 1; const b int = 2; a = a + b; foo(a, b).await; ; }";
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(0, source, &diag);
+
+        let lbrace_token: Token = parser.consume_token();
+        let stmt: ConcreteCompoundStmt = parser.parse_compound_stmt(lbrace_token, &[]).unwrap();
+        assert_eq!(stmt.stmts.len(), 4);
+        assert!(parser.current_token().is_eoi());
+
+        dbg!(stmt);
+    }
+
+    #[test]
+    fn test_parse_control_flow_stmt() {
+        let source: &str = r#"{
+            if a < b {
+                return a;
+            } else if a == b {
+                return;
+            } else {
+                while a > b { a -= 1; }
+            }
+            do { b = b * 2; } while b < 100;
+            try {
+                throw foo();
+            } catch (e: object) {
+                spawn bar(e);
+            } catch (e string) {
+                return e, b;
+            }
+        }"#;
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(0, source, &diag);
+
+        let lbrace_token: Token = parser.consume_token();
+        let stmt: ConcreteCompoundStmt = parser.parse_compound_stmt(lbrace_token, &[]).unwrap();
+        assert_eq!(stmt.stmts.len(), 3);
+        assert!(matches!(stmt.stmts[0], ConcreteStmt::IfStmt(_)));
+        assert!(matches!(stmt.stmts[1], ConcreteStmt::DoWhileStmt(_)));
+        if let ConcreteStmt::TryStmt(try_stmt) = &stmt.stmts[2] {
+            assert_eq!(try_stmt.catch_clauses.len(), 2);
+        } else {
+            panic!()
+        }
+
+        dbg!(stmt);
+    }
+
+    #[test]
+    fn test_parse_finally_and_rethrow() {
+        let source: &str = r#"{
+            try {
+                foo();
+            } catch (e any) {
+                rethrow;
+            } finally {
+                bar();
+            }
+            try { foo(); } finally { bar(); }
+        }"#;
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(0, source, &diag);
+
+        let lbrace_token: Token = parser.consume_token();
+        let stmt: ConcreteCompoundStmt = parser.parse_compound_stmt(lbrace_token, &[]).unwrap();
+        assert_eq!(stmt.stmts.len(), 2);
+        if let ConcreteStmt::TryStmt(try_stmt) = &stmt.stmts[0] {
+            assert_eq!(try_stmt.catch_clauses.len(), 1);
+            assert!(matches!(
+                try_stmt.catch_clauses[0].body.stmts[0],
+                ConcreteStmt::RethrowStmt(_)
+            ));
+            assert!(try_stmt.finally_body.is_some());
+        } else {
+            panic!()
+        }
+        if let ConcreteStmt::TryStmt(try_stmt) = &stmt.stmts[1] {
+            assert!(try_stmt.catch_clauses.is_empty());
+            assert!(try_stmt.finally_body.is_some());
+        } else {
+            panic!()
+        }
+
+        dbg!(stmt);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_parse_try_without_catch() {
+        let source: &str = "{ try { foo(); } }";
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(0, source, &diag);
+
+        let lbrace_token: Token = parser.consume_token();
+        let _ = parser.parse_compound_stmt(lbrace_token, &[]);
     }
 }
@@ -78,7 +78,7 @@ impl<'s, 'd> SyntaxActionApplier<'s, 'd> {
         }
     }
 
-    pub fn apply_actions(&mut self, program: &'s mut ConcreteProgram<'s>) {
+    pub fn apply_actions(&mut self, program: &mut ConcreteProgram<'s>) {
         let mut imported_items: HashMap<&'s str, (&'s str, &'s str)> = HashMap::new();
         for decl in program.decls.iter_mut() {
             match decl {
@@ -96,7 +96,7 @@ impl<'s, 'd> SyntaxActionApplier<'s, 'd> {
     fn resolve_import(
         &mut self,
         imported_items: &mut HashMap<&'s str, (&'s str, &'s str)>,
-        import: &'s mut ConcreteImportDecl<'s>
+        import: &mut ConcreteImportDecl<'s>
     ) {
         if let Identifier::Qual(tokens) = &import.import_path {
             if tokens.len() == 2 {
@@ -0,0 +1,420 @@
+//! # Interactive REPL
+//!
+//! Compiles code one chunk at a time against an accumulated global `Scope`, and runs each chunk
+//! on a persistent VM whose heap outlives every single evaluation. A chunk can be:
+//!
+//!   - declarations, starting with `func`, `const`, `var`, `import`, `export` or an attribute,
+//!     which are kept for later chunks. Variables declared by `var` become global variables, and
+//!     get initialized right away;
+//!   - statements, ending with `;` or `}`, which are run for their side effects;
+//!   - an expression, whose value gets run and pretty-printed.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::marker::PhantomPinned;
+use std::mem::replace;
+use std::ptr::NonNull;
+
+use xjbutil::unchecked::{UncheckedSendSync, UnsafeFrom};
+
+use crate::builtins::object::Object;
+use crate::builtins::vec::VMGenericVec;
+use crate::codegen::al31f::CodegenAL31F;
+use crate::data::Value;
+use crate::data::generic::GenericTypeVT;
+use crate::data::tyck::TyckInfoPool;
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::data::wrapper::DynBase;
+use crate::diag::{DiagContext, Diagnostic};
+use crate::diag::source::SourceManager;
+use crate::parse::lexer::Lexer;
+use crate::parse::parser::Parser;
+use crate::parse::syntax_action::{SyntaxActionApplier, TokenLitArena};
+use crate::sema::arena::Arena;
+use crate::sema::decl_context::DeclContext;
+use crate::sema::phase2::SemaPhase2;
+use crate::sema::scope::{Scope, ScopeKind};
+use crate::syntax::ConcreteProgram;
+use crate::syntax::token::TokenInner;
+use crate::vm::al31f::AL31F;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::CompiledProgram;
+use crate::vm::al31f::exception::Exception;
+use crate::vm::al31f::executor::{VMThread, vm_thread_init_globals, vm_thread_run_function};
+use crate::vm::al31f::ffi_registry::FFIRegistry;
+use crate::vm::al31f::stack::Stack;
+
+#[cfg(feature = "async")] use crate::util::serializer::CoroutineContext;
+
+/// Name of the function wrapping statement and expression chunks
+const EVAL_FUNC_NAME: &str = "__repl_eval";
+
+pub enum ReplError {
+    Diagnostics(Vec<Diagnostic>),
+    Exception(Exception)
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum ChunkKind {
+    Decl,
+    Stmt,
+    Expr
+}
+
+pub struct Repl<A: Alloc> {
+    // Fields get dropped in declaration order. Each field may refer to the ones declared after it,
+    // but never the ones before it.
+    thread: Box<VMThread<A>>,
+    /// Values on the heap may refer to constants of previously run programs, so these programs
+    /// are kept. Every program is generated from all declarations so far, and the types they refer
+    /// to are owned by the type pool of the last one.
+    programs: Vec<CompiledProgram<A>>,
+
+    scope: Scope<'static>,
+    decl_context: DeclContext<'static>,
+    arena: Arena<'static>,
+    /// Type pool used before any program gets generated
+    tyck_info_pool: TyckInfoPool,
+    /// Boxed so that sema trees referring to them stay valid when the vector grows
+    #[allow(clippy::vec_box)]
+    concrete_programs: Vec<Box<ConcreteProgram<'static>>>,
+    token_lit_arena: Box<TokenLitArena>,
+    source_manager: SourceManager,
+
+    ffi_registry: FFIRegistry<A>
+}
+
+impl<A: Alloc> Repl<A> {
+    pub fn new(alloc: A, ffi_registry: FFIRegistry<A>) -> Self {
+        #[cfg(feature = "async")]
+        let vm: CoroutineContext<AL31F<A>> =
+            pollster::block_on(CoroutineContext::main_context(AL31F::new(alloc)));
+        #[cfg(not(feature = "async"))]
+        let vm: AL31F<A> = AL31F::new(alloc);
+
+        let mut thread: Box<VMThread<A>> = Box::new(VMThread {
+            vm,
+            // set before running each chunk
+            program: NonNull::dangling(),
+            stack: Stack::new(),
+            _phantom: PhantomPinned
+        });
+        unsafe {
+            #[cfg(feature = "async")]
+            thread.vm.get_shared_data_mut().alloc.add_stack(&thread.stack);
+            #[cfg(not(feature = "async"))]
+            thread.vm.alloc.add_stack(&thread.stack);
+        }
+
+        Self {
+            thread,
+            programs: Vec::new(),
+
+            scope: Scope::new(ScopeKind::Global),
+            decl_context: DeclContext {
+                object_decls: Vec::new(),
+                func_decls: Vec::new()
+            },
+            arena: Arena::new(),
+            tyck_info_pool: TyckInfoPool::new(),
+            concrete_programs: Vec::new(),
+            token_lit_arena: Box::new(TokenLitArena::new()),
+            source_manager: SourceManager::new(),
+
+            ffi_registry
+        }
+    }
+
+    /// Chunks are added as files named `<repl:N>`, with statement and expression chunks wrapped
+    /// into a function
+    pub fn source_manager(&self) -> &SourceManager {
+        &self.source_manager
+    }
+
+    /// The program generated for the last run chunk
+    pub fn last_program(&self) -> Option<&CompiledProgram<A>> {
+        self.programs.last()
+    }
+
+    /// Compiles and runs a chunk. Returns the pretty-printed value if the chunk is an expression.
+    /// Nothing declared by the chunk is kept if there is any error.
+    pub fn eval(&mut self, chunk: &str) -> Result<Option<String>, ReplError> {
+        let chunk_kind: ChunkKind = classify_chunk(chunk);
+        let source: String = match chunk_kind {
+            ChunkKind::Decl => chunk.to_string(),
+            ChunkKind::Stmt => format!("func {}() {{ {} }}", EVAL_FUNC_NAME, chunk),
+            ChunkKind::Expr => format!("func {}(): any {{ return {}; }}", EVAL_FUNC_NAME, chunk)
+        };
+        let file_name: String = format!("<repl:{}>", self.source_manager.file_count() + 1);
+        let file_id: u32 = self.source_manager.add_file(file_name, source);
+
+        // Sources, syntax trees and sema trees are never removed before `self` gets dropped, so
+        // they are treated as `'static` here
+        let source: &'static str =
+            unsafe { &*(self.source_manager.file_content(file_id) as *const str) };
+        let token_lit_arena: &'static TokenLitArena =
+            unsafe { &*(self.token_lit_arena.as_ref() as *const TokenLitArena) };
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut program: ConcreteProgram<'static> = Parser::new(file_id, source, &diag).parse();
+        let mut diag: DiagContext = diag.into_inner();
+        SyntaxActionApplier::new(token_lit_arena, &mut diag).apply_actions(&mut program);
+        if diag.has_error() {
+            return Err(ReplError::Diagnostics(diag.clear_reset()));
+        }
+        self.concrete_programs.push(Box::new(program));
+        let program: &'static ConcreteProgram<'static> = unsafe {
+            &*(self.concrete_programs.last().unwrap().as_ref() as *const ConcreteProgram)
+        };
+
+        let scope_snapshot: Scope<'static> = self.scope.clone();
+        let scope: Scope<'static> = replace(&mut self.scope, Scope::new(ScopeKind::Global));
+        let tyck_info_pool: &mut TyckInfoPool = match self.programs.last_mut() {
+            Some(last_program) => &mut last_program.tyck_info_pool,
+            None => &mut self.tyck_info_pool
+        };
+        let mut sema: SemaPhase2 = SemaPhase2::new(
+            scope,
+            &mut self.arena,
+            tyck_info_pool,
+            &mut diag
+        ).with_exception_types(self.ffi_registry.exception_types());
+        let chunk_decls: DeclContext<'static> = sema.check_program(program);
+        self.scope = replace(sema.scope_mut(), Scope::new(ScopeKind::Global));
+        if diag.has_error() {
+            self.scope = scope_snapshot;
+            return Err(ReplError::Diagnostics(diag.clear_reset()));
+        }
+
+        let mut decl_context: DeclContext<'static> = DeclContext {
+            object_decls: self.decl_context.object_decls.clone(),
+            func_decls: self.decl_context.func_decls.clone()
+        };
+        decl_context.object_decls.extend(chunk_decls.object_decls.iter().copied());
+        decl_context.func_decls.extend(chunk_decls.func_decls.iter().copied());
+        // global variables declared by earlier chunks come first, and keep their values
+        let initialized_globals: usize = self.decl_context.object_decls.iter()
+            .filter(|object_decl| !object_decl.get(&self.arena).is_const)
+            .count();
+        let chunk_has_globals: bool = chunk_decls.object_decls.iter()
+            .any(|object_decl| !object_decl.get(&self.arena).is_const);
+
+        // sema trees of later chunks refer to types in the same pool, so the pool moves into the
+        // new program
+        let tyck_info_pool: TyckInfoPool = match self.programs.last_mut() {
+            Some(last_program) => replace(&mut last_program.tyck_info_pool, TyckInfoPool::new()),
+            None => replace(&mut self.tyck_info_pool, TyckInfoPool::new())
+        };
+        let codegen: CodegenAL31F<A> =
+            CodegenAL31F::new(&self.arena, tyck_info_pool, &self.ffi_registry, &mut diag)
+                .with_initialized_globals(initialized_globals);
+        let compiled_program: CompiledProgram<A> = match codegen.try_generate(&decl_context) {
+            Ok(compiled_program) => compiled_program,
+            Err(tyck_info_pool) => {
+                match self.programs.last_mut() {
+                    Some(last_program) => last_program.tyck_info_pool = tyck_info_pool,
+                    None => self.tyck_info_pool = tyck_info_pool
+                }
+                self.scope = scope_snapshot;
+                return Err(ReplError::Diagnostics(diag.clear_reset()));
+            }
+        };
+
+        let func_id: Option<usize> = compiled_program.func_ids.get(EVAL_FUNC_NAME).copied();
+        self.programs.push(compiled_program);
+        self.thread.program = NonNull::from(self.programs.last().unwrap());
+        // the outermost frame of last run is never popped, nor are frames left by exceptions
+        self.thread.stack.values.clear();
+        self.thread.stack.frames.clear();
+
+        if chunk_kind == ChunkKind::Decl {
+            if chunk_has_globals {
+                let result: Result<(), Exception> = unsafe {
+                    pollster::block_on(vm_thread_init_globals::<A, true>(&mut self.thread))
+                };
+                if let Err(exception) = result {
+                    self.scope = scope_snapshot;
+                    return Err(ReplError::Exception(exception));
+                }
+            }
+            self.decl_context = decl_context;
+            return Ok(None);
+        }
+
+        // the wrapper function should not be visible to later chunks
+        self.scope = scope_snapshot;
+
+        let func_id: usize = func_id.unwrap();
+        let rets: Vec<Value> = unsafe {
+            let fut = vm_thread_run_function::<A, true>(
+                UncheckedSendSync::new((&mut self.thread, func_id, &[]))
+            ).map_err(ReplError::Exception)?;
+            pollster::block_on(fut).into_inner().map_err(ReplError::Exception)?
+        };
+
+        if chunk_kind == ChunkKind::Expr {
+            Ok(Some(unsafe { format_value(rets[0]) }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn classify_chunk(chunk: &str) -> ChunkKind {
+    let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+    let mut lexer: Lexer = Lexer::new(0, chunk, &diag);
+    match lexer.next_token().token_inner {
+        TokenInner::KwdFunc
+        | TokenInner::KwdConst
+        | TokenInner::KwdVar
+        | TokenInner::KwdImport
+        | TokenInner::KwdExport
+        | TokenInner::SymHash => ChunkKind::Decl,
+        _ => {
+            let chunk: &str = chunk.trim_end();
+            if chunk.ends_with(';') || chunk.ends_with('}') {
+                ChunkKind::Stmt
+            } else {
+                ChunkKind::Expr
+            }
+        }
+    }
+}
+
+/// Pretty-prints a value for REPL output. Strings are quoted, and contents of objects and vectors
+/// get printed recursively.
+pub unsafe fn format_value(value: Value) -> String {
+    let mut output: String = String::new();
+    let mut visiting: Vec<usize> = Vec::new();
+    format_value_impl(value, &mut visiting, &mut output);
+    output
+}
+
+unsafe fn format_value_impl(value: Value, visiting: &mut Vec<usize>, output: &mut String) {
+    if value.is_null() {
+        output.push_str("null");
+        return;
+    }
+
+    if value.is_value() {
+        let _ = match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+            ValueTypeTag::Int => write!(output, "{}", value.vt_data.inner.int_value),
+            ValueTypeTag::Float => write!(output, "{:?}", value.vt_data.inner.float_value),
+            ValueTypeTag::Bool => write!(output, "{}", value.vt_data.inner.bool_value)
+        };
+        return;
+    }
+
+    let ptr: usize = value.untagged_ptr_field();
+    if visiting.contains(&ptr) {
+        // reference cycle
+        output.push_str("...");
+        return;
+    }
+
+    if value.is_container() {
+        let vt: &GenericTypeVT = &*(value.ptr_repr.trivia as *const GenericTypeVT);
+        if vt.tyck_info.as_ref().type_id != TypeId::of::<VMGenericVec>() {
+            let _ = write!(output, "<{}>", vt.type_name);
+            return;
+        }
+
+        let vec: &VMGenericVec = &*(value.get_as_mut_ptr::<VMGenericVec>() as *const _);
+        visiting.push(ptr);
+        output.push('[');
+        for (i, elem) /*: (usize, &Value)*/ in vec.inner.iter().enumerate() {
+            if i != 0 {
+                output.push_str(", ");
+            }
+            format_value_impl(*elem, visiting, output);
+        }
+        output.push(']');
+        visiting.pop();
+        return;
+    }
+
+    let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
+    let type_id: TypeId = (*dyn_base).dyn_type_id();
+    if type_id == TypeId::of::<String>() {
+        let _ = write!(output, "{:?}", &*(value.get_as_mut_ptr::<String>() as *const String));
+    } else if type_id == TypeId::of::<Object>() {
+        let object: &Object = &*(value.get_as_mut_ptr::<Object>() as *const Object);
+        let mut fields: Vec<(&String, &Value)> = object.fields.iter().collect();
+        fields.sort_by_key(|(name, _)| *name);
+
+        visiting.push(ptr);
+        output.push_str("object {");
+        for (i, (name, field)) /*: (usize, (&String, &Value))*/ in fields.into_iter().enumerate() {
+            output.push_str(if i == 0 { " " } else { ", " });
+            let _ = write!(output, "{}: ", name);
+            format_value_impl(*field, visiting, output);
+        }
+        output.push_str(if object.fields.is_empty() { "}" } else { " }" });
+        visiting.pop();
+    } else {
+        let _ = write!(output, "<{}>", (*dyn_base).dyn_type_name());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::builtins::object::Object;
+    use crate::data::Value;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+    use super::{Repl, format_value};
+
+    fn eval(repl: &mut Repl<DefaultAlloc>, chunk: &str) -> Option<String> {
+        repl.eval(chunk).unwrap_or_else(|_| panic!("chunk `{}` should run", chunk))
+    }
+
+    #[test]
+    fn test_repl_incremental() {
+        let mut repl: Repl<DefaultAlloc> = Repl::new(DefaultAlloc::new(), FFIRegistry::new());
+        assert_eq!(eval(&mut repl, "1 + 2 * 3"), Some("7".to_string()));
+        assert_eq!(eval(&mut repl, "const K = 3;"), None);
+        assert_eq!(eval(&mut repl, r#"
+            func sq(x int): int {
+                return x * x;
+            }
+        "#), None);
+        assert_eq!(eval(&mut repl, "sq(K) + 1"), Some("10".to_string()));
+        assert_eq!(eval(&mut repl, "func half(x float): float { return x / 2.0e0; }"), None);
+        assert_eq!(eval(&mut repl, "half(5.0e0)"), Some("2.5".to_string()));
+        assert_eq!(eval(&mut repl, "sq(2) == 4"), Some("true".to_string()));
+        assert_eq!(eval(&mut repl, r#""ab" + "c""#), Some(r#""abc""#.to_string()));
+    }
+
+    #[test]
+    fn test_repl_globals() {
+        let mut repl: Repl<DefaultAlloc> = Repl::new(DefaultAlloc::new(), FFIRegistry::new());
+        assert_eq!(eval(&mut repl, "var x = 5;"), None);
+        assert_eq!(eval(&mut repl, "x + 1"), Some("6".to_string()));
+        assert_eq!(eval(&mut repl, "x += 2;"), None);
+        assert_eq!(eval(&mut repl, "var s = `x is`;"), None);
+        // declaring another variable does not initialize `x` again
+        assert_eq!(eval(&mut repl, "x"), Some("7".to_string()));
+        assert_eq!(eval(&mut repl, "s"), Some(r#""x is""#.to_string()));
+        // a variable whose initializer throws is not kept, so it can be declared again
+        assert!(repl.eval("var y = 1 / (x - 7);").is_err());
+        assert_eq!(eval(&mut repl, "var y = x * 2;"), None);
+        assert_eq!(eval(&mut repl, "y"), Some("14".to_string()));
+
+        let program: &CompiledProgram<DefaultAlloc> = repl.last_program().unwrap();
+        assert_eq!(program.global_names.len(), 3);
+    }
+
+    #[test]
+    fn test_repl_format_value() {
+        let mut object: Object = Object::new();
+        object.fields.insert("b".to_string(), Value::new_owned("text".to_string()));
+        object.fields.insert("a".to_string(), Value::new_float(1.0));
+        let object: Value = Value::new_owned(object);
+        let output: String = unsafe { format_value(object) };
+        assert_eq!(output, r#"object { a: 1.0, b: "text" }"#);
+        assert_eq!(unsafe { format_value(Value::new_null()) }, "null");
+    }
+}
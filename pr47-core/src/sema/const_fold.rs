@@ -0,0 +1,208 @@
+//! # Compile-time constant folding
+//!
+//! Folding follows the run time semantics of the `al31f` VM: integer arithmetic wraps around,
+//! float-to-int casts saturate, while operations the VM would raise an `UncheckedException` for
+//! (division by zero, shifting out of range, casting incompatible values) are reported as
+//! `ConstFoldError`s, so that sema can diagnose them at compile time.
+//!
+//! There's no `char` variant in `Value`, so `char` constants are represented as single-character
+//! strings. Callers should check static types before folding operations that could confuse the
+//! two.
+
+use xjbutil::value::Value;
+
+use crate::sema::expr::{ResolvedBinaryOp, ResolvedUnaryOp};
+use crate::syntax::expr::LiteralExprContent;
+
+#[cfg_attr(test, derive(Debug))]
+pub enum ConstFoldError {
+    DivideByZero,
+    ShiftOverflow(i64),
+    InvalidCast
+}
+
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy)]
+pub enum CastTarget {
+    Int,
+    Float,
+    Bool
+}
+
+pub fn literal_value(content: &LiteralExprContent) -> Value {
+    match content {
+        LiteralExprContent::Int(i) => Value::Int(*i as i64),
+        LiteralExprContent::Float(f) => Value::Float(*f),
+        LiteralExprContent::Char(ch) => Value::String(ch.to_string()),
+        LiteralExprContent::String(s) => Value::String(s.clone()),
+        LiteralExprContent::Boolean(b) => Value::Bool(*b)
+    }
+}
+
+/// Gets the code point of a folded `char` as an `int`, since `char`s are folded as strings
+pub fn char_code_point(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::Int(s.chars().next().map_or(0, |ch: char| ch as i64)),
+        value => value
+    }
+}
+
+pub fn fold_unary(op: ResolvedUnaryOp, operand: &Value) -> Option<Value> {
+    use ResolvedUnaryOp::*;
+
+    match (op, operand) {
+        (Positive, Value::Int(_) | Value::Float(_)) => Some(operand.clone()),
+        (Negation | IntNegation, Value::Int(i)) => Some(Value::Int(i.wrapping_neg())),
+        (Negation | FloatNegation, Value::Float(f)) => Some(Value::Float(-f)),
+        (LogicalNot | LogicalNotRTTI, Value::Bool(b)) => Some(Value::Bool(!b)),
+        (BitwiseReverse | BitwiseReverseRTTI, Value::Int(i)) => Some(Value::Int(!i)),
+        _ => None
+    }
+}
+
+pub fn fold_binary(
+    op: ResolvedBinaryOp,
+    lhs: &Value,
+    rhs: &Value
+) -> Result<Option<Value>, ConstFoldError> {
+    use ResolvedBinaryOp::*;
+
+    Ok(match (op, lhs, rhs) {
+        (Mul | IntMul, Value::Int(l), Value::Int(r)) => Some(Value::Int(l.wrapping_mul(*r))),
+        (Mul | FloatMul, Value::Float(l), Value::Float(r)) => Some(Value::Float(l * r)),
+
+        (Div | IntDiv, Value::Int(l), Value::Int(r)) =>
+            Some(Value::Int(l.checked_div(*r).ok_or(ConstFoldError::DivideByZero)?)),
+        (Div | FloatDiv, Value::Float(l), Value::Float(r)) => Some(Value::Float(l / r)),
+
+        (IntMod | IntModRTTI, Value::Int(l), Value::Int(r)) =>
+            Some(Value::Int(l.checked_rem(*r).ok_or(ConstFoldError::DivideByZero)?)),
+
+        (Add | IntAdd, Value::Int(l), Value::Int(r)) => Some(Value::Int(l.wrapping_add(*r))),
+        (Add | FloatAdd, Value::Float(l), Value::Float(r)) => Some(Value::Float(l + r)),
+        (StringAdd, Value::String(l), Value::String(r)) => Some(Value::String(format!("{}{}", l, r))),
+
+        (Minus | IntMinus, Value::Int(l), Value::Int(r)) => Some(Value::Int(l.wrapping_sub(*r))),
+        (Minus | FloatMinus, Value::Float(l), Value::Float(r)) => Some(Value::Float(l - r)),
+
+        (GreaterThan | IntGreaterThan, Value::Int(l), Value::Int(r)) => Some(Value::Bool(l > r)),
+        (GreaterThan | FloatGreaterThan, Value::Float(l), Value::Float(r)) =>
+            Some(Value::Bool(l > r)),
+        (LessThan | IntLessThan, Value::Int(l), Value::Int(r)) => Some(Value::Bool(l < r)),
+        (LessThan | FloatLessThan, Value::Float(l), Value::Float(r)) => Some(Value::Bool(l < r)),
+        (GreaterThanOrEqual | IntGreaterThanOrEqual, Value::Int(l), Value::Int(r)) =>
+            Some(Value::Bool(l >= r)),
+        (GreaterThanOrEqual | FloatGreaterThanOrEqual, Value::Float(l), Value::Float(r)) =>
+            Some(Value::Bool(l >= r)),
+        (LessThanOrEqual | IntLessThanOrEqual, Value::Int(l), Value::Int(r)) =>
+            Some(Value::Bool(l <= r)),
+        (LessThanOrEqual | FloatLessThanOrEqual, Value::Float(l), Value::Float(r)) =>
+            Some(Value::Bool(l <= r)),
+
+        (Equal | NotEqual, Value::Int(_), Value::Int(_))
+        | (Equal | NotEqual, Value::Float(_), Value::Float(_))
+        | (Equal | NotEqual, Value::Bool(_), Value::Bool(_))
+        | (Equal | NotEqual, Value::String(_), Value::String(_)) =>
+            Some(Value::Bool((lhs == rhs) == matches!(op, Equal))),
+
+        (BitwiseAnd | BitwiseAndRTTI, Value::Int(l), Value::Int(r)) => Some(Value::Int(l & r)),
+        (BitwiseOr | BitwiseOrRTTI, Value::Int(l), Value::Int(r)) => Some(Value::Int(l | r)),
+        (BitwiseXor | BitwiseXorRTTI, Value::Int(l), Value::Int(r)) => Some(Value::Int(l ^ r)),
+
+        (LogicalAnd | LogicalAndRTTI, Value::Bool(l), Value::Bool(r)) =>
+            Some(Value::Bool(*l && *r)),
+        (LogicalOr | LogicalOrRTTI, Value::Bool(l), Value::Bool(r)) => Some(Value::Bool(*l || *r)),
+        (LogicalXor | LogicalXorRTTI, Value::Bool(l), Value::Bool(r)) => Some(Value::Bool(l ^ r)),
+
+        (ShiftLeft | ShiftLeftRTTI, Value::Int(l), Value::Int(r)) =>
+            Some(Value::Int(l << check_shift_amount(*r)?)),
+        (ShiftRight | ShiftRightRTTI, Value::Int(l), Value::Int(r)) =>
+            Some(Value::Int(l >> check_shift_amount(*r)?)),
+
+        _ => None
+    })
+}
+
+pub fn fold_cast(value: &Value, target: CastTarget) -> Result<Value, ConstFoldError> {
+    match (target, value) {
+        (CastTarget::Int, Value::Int(_)) => Ok(value.clone()),
+        (CastTarget::Int, Value::Float(f)) => Ok(Value::Int(*f as i64)),
+        (CastTarget::Int, Value::Bool(b)) => Ok(Value::Int(*b as i64)),
+        (CastTarget::Float, Value::Int(i)) => Ok(Value::Float(*i as f64)),
+        (CastTarget::Float, Value::Float(_)) => Ok(value.clone()),
+        (CastTarget::Bool, Value::Int(i)) => Ok(Value::Bool(*i != 0)),
+        (CastTarget::Bool, Value::Bool(_)) => Ok(value.clone()),
+        _ => Err(ConstFoldError::InvalidCast)
+    }
+}
+
+fn check_shift_amount(amount: i64) -> Result<i64, ConstFoldError> {
+    if (0..64).contains(&amount) {
+        Ok(amount)
+    } else {
+        Err(ConstFoldError::ShiftOverflow(amount))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use xjbutil::value::Value;
+
+    use crate::sema::const_fold::{CastTarget, ConstFoldError, fold_binary, fold_cast, fold_unary};
+    use crate::sema::expr::{ResolvedBinaryOp, ResolvedUnaryOp};
+
+    #[test]
+    fn test_fold_arith() {
+        assert_eq!(
+            fold_binary(ResolvedBinaryOp::IntAdd, &Value::Int(i64::MAX), &Value::Int(1)).unwrap(),
+            Some(Value::Int(i64::MIN))
+        );
+        assert_eq!(
+            fold_binary(ResolvedBinaryOp::FloatMul, &Value::Float(1.5), &Value::Float(2.0))
+                .unwrap(),
+            Some(Value::Float(3.0))
+        );
+        assert_eq!(
+            fold_binary(
+                ResolvedBinaryOp::StringAdd,
+                &Value::String("114".into()),
+                &Value::String("514".into())
+            ).unwrap(),
+            Some(Value::String("114514".into()))
+        );
+        assert_eq!(
+            fold_binary(ResolvedBinaryOp::NotEqual, &Value::Int(1), &Value::Int(2)).unwrap(),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(
+            fold_binary(ResolvedBinaryOp::ShiftLeft, &Value::Int(1), &Value::Int(4)).unwrap(),
+            Some(Value::Int(16))
+        );
+        assert_eq!(
+            fold_binary(ResolvedBinaryOp::Add, &Value::Int(1), &Value::Float(2.0)).unwrap(),
+            None
+        );
+        assert_eq!(fold_unary(ResolvedUnaryOp::BitwiseReverse, &Value::Int(0)), Some(Value::Int(-1)));
+    }
+
+    #[test]
+    fn test_fold_errors() {
+        assert!(matches!(
+            fold_binary(ResolvedBinaryOp::IntDiv, &Value::Int(1), &Value::Int(0)),
+            Err(ConstFoldError::DivideByZero)
+        ));
+        assert!(matches!(
+            fold_binary(ResolvedBinaryOp::IntMod, &Value::Int(i64::MIN), &Value::Int(-1)),
+            Err(ConstFoldError::DivideByZero)
+        ));
+        assert!(matches!(
+            fold_binary(ResolvedBinaryOp::ShiftRight, &Value::Int(1), &Value::Int(64)),
+            Err(ConstFoldError::ShiftOverflow(64))
+        ));
+        assert!(matches!(
+            fold_cast(&Value::String("a".into()), CastTarget::Int),
+            Err(ConstFoldError::InvalidCast)
+        ));
+        assert_eq!(fold_cast(&Value::Float(2.5), CastTarget::Int).unwrap(), Value::Int(2));
+    }
+}
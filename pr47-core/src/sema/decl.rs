@@ -4,15 +4,18 @@ use crate::data::tyck::TyckInfo;
 use crate::sema::decl_context::DeclContext;
 use crate::sema::expr::Expr;
 use crate::sema::scope::Scope;
+use crate::sema::stmt::CompoundStmt;
 use crate::syntax::decl::{ConcreteFuncDecl, ConcreteObjectDecl};
 
 pub struct ObjectDecl<'s> {
     pub name: &'s str,
     pub is_const: bool,
     pub ty: NonNull<TyckInfo>,
-    pub init: Expr<'s>,
+    /// `None` for function parameters and `catch` variables
+    pub init: Option<Expr<'s>>,
 
-    pub concrete: &'s ConcreteObjectDecl<'s>
+    /// `None` for function parameters and `catch` variables
+    pub concrete: Option<&'s ConcreteObjectDecl<'s>>
 }
 
 pub struct FuncDecl<'s> {
@@ -20,11 +23,18 @@ pub struct FuncDecl<'s> {
     pub param_decl_context: DeclContext<'s>,
     pub ret_types: Vec<NonNull<TyckInfo>>,
     pub exception_spec: Vec<NonNull<TyckInfo>>,
-    pub func_body: (), // TODO
+    /// `None` if the body is not checked yet, or the function is provided by host (FFI)
+    pub func_body: Option<CompoundStmt<'s>>,
 
     pub concrete: &'s ConcreteFuncDecl<'s>
 }
 
+impl<'s> FuncDecl<'s> {
+    pub fn is_ffi(&self) -> bool {
+        self.concrete.func_body.is_none()
+    }
+}
+
 pub struct ModuleDecl<'s> {
     pub name: &'s str,
     pub scope: Scope<'s>
@@ -1,11 +1,13 @@
 use std::hint::unreachable_unchecked;
 
-use crate::sema::decl::{FuncDecl, ObjectDecl};
+use crate::sema::decl::{FuncDecl, ModuleDecl, ObjectDecl};
 use crate::sema::expr::{
     AsExpr,
+    AssignExpr,
     AwaitExpr,
     BinaryExpr,
     FieldRefExpr,
+    FuncCallExpr,
     IdRefExpr,
     LiteralExpr,
     SubscriptExpr,
@@ -15,10 +17,13 @@ use crate::sema::expr::{
 pub enum ASTNode<'s> {
     ObjectDeclNode(ObjectDecl<'s>),
     FuncDeclNode(FuncDecl<'s>),
+    ModuleDeclNode(ModuleDecl<'s>),
     LiteralExprNode(LiteralExpr<'s>),
     IdRefExprNode(IdRefExpr<'s>),
     UnaryExprNode(UnaryExpr<'s>),
     BinaryExprNode(BinaryExpr<'s>),
+    AssignExprNode(AssignExpr<'s>),
+    FuncCallExprNode(FuncCallExpr<'s>),
     SubscriptExprNode(SubscriptExpr<'s>),
     FieldRefExprNode(FieldRefExpr<'s>),
     AwaitExprNode(AwaitExpr<'s>),
@@ -54,10 +59,13 @@ macro_rules! impl_dyn_cast {
 
 impl_dyn_cast!(ObjectDeclNode, ObjectDecl);
 impl_dyn_cast!(FuncDeclNode, FuncDecl);
+impl_dyn_cast!(ModuleDeclNode, ModuleDecl);
 impl_dyn_cast!(LiteralExprNode, LiteralExpr);
 impl_dyn_cast!(IdRefExprNode, IdRefExpr);
 impl_dyn_cast!(UnaryExprNode, UnaryExpr);
 impl_dyn_cast!(BinaryExprNode, BinaryExpr);
+impl_dyn_cast!(AssignExprNode, AssignExpr);
+impl_dyn_cast!(FuncCallExprNode, FuncCallExpr);
 impl_dyn_cast!(SubscriptExprNode, SubscriptExpr);
 impl_dyn_cast!(FieldRefExprNode, FieldRefExpr);
 impl_dyn_cast!(AwaitExprNode, AwaitExpr);
@@ -4,6 +4,7 @@ use xjbutil::either::Either;
 use xjbutil::value::Value;
 
 use crate::data::tyck::TyckInfo;
+use crate::diag::location::SourceRange;
 use crate::sema::arena::{Arena, ArenaPtr};
 use crate::sema::decl::{FuncDecl, ObjectDecl};
 use crate::syntax::expr::{
@@ -11,6 +12,7 @@ use crate::syntax::expr::{
     ConcreteAwaitExpr,
     ConcreteBinaryExpr,
     ConcreteFieldRefExpr,
+    ConcreteFuncCallExpr,
     ConcreteLiteralExpr,
     ConcreteSubscriptExpr,
     ConcreteUnaryExpr,
@@ -18,12 +20,15 @@ use crate::syntax::expr::{
 };
 use crate::syntax::id::Identifier;
 
+#[derive(Clone, Copy)]
 pub enum Expr<'s> {
     LiteralExpr(ArenaPtr<'s, LiteralExpr<'s>>),
     IdRefExpr(ArenaPtr<'s, IdRefExpr<'s>>),
     UnaryExpr(ArenaPtr<'s, UnaryExpr<'s>>),
     BinaryExpr(ArenaPtr<'s, BinaryExpr<'s>>),
-    SubscriptExpr(ArenaPtr<'s, BinaryExpr<'s>>),
+    AssignExpr(ArenaPtr<'s, AssignExpr<'s>>),
+    FuncCallExpr(ArenaPtr<'s, FuncCallExpr<'s>>),
+    SubscriptExpr(ArenaPtr<'s, SubscriptExpr<'s>>),
     FieldRefExpr(ArenaPtr<'s, FieldRefExpr<'s>>),
     AwaitExpr(ArenaPtr<'s, AwaitExpr<'s>>),
     AsExpr(ArenaPtr<'s, AsExpr<'s>>)
@@ -39,6 +44,8 @@ impl<'s> Expr<'s> {
             Expr::IdRefExpr(expr) => &expr.get_tricky(arena).maybe_constant_folding,
             Expr::UnaryExpr(expr) => &expr.get_tricky(arena).maybe_constant_folding,
             Expr::BinaryExpr(expr) => &expr.get_tricky(arena).maybe_constant_folding,
+            Expr::AssignExpr(_) => return None,
+            Expr::FuncCallExpr(_) => return None,
             Expr::SubscriptExpr(expr) => &expr.get_tricky(arena).maybe_constant_folding,
             Expr::FieldRefExpr(expr) => &expr.get_tricky(arena).maybe_constant_folding,
             Expr::AwaitExpr(_) => return None,
@@ -55,19 +62,42 @@ impl<'s> Expr<'s> {
             Expr::IdRefExpr(expr) => Some(expr.get_tricky(arena).ty),
             Expr::UnaryExpr(expr) => expr.get_tricky(arena).ty,
             Expr::BinaryExpr(expr) => expr.get_tricky(arena).ty,
+            Expr::AssignExpr(expr) => expr.get_tricky(arena).ty,
+            Expr::FuncCallExpr(expr) => expr.get_tricky(arena).ret_types.as_ref().and_then(
+                |ret_types: &Vec<NonNull<TyckInfo>>| if ret_types.len() == 1 {
+                    Some(ret_types[0])
+                } else {
+                    None
+                }
+            ),
             Expr::SubscriptExpr(expr) => expr.get_tricky(arena).ty,
             Expr::FieldRefExpr(expr) => expr.get_tricky(arena).ty,
             Expr::AwaitExpr(expr) => Some(expr.get_tricky(arena).ty),
             Expr::AsExpr(expr) => Some(expr.get_tricky(arena).as_type)
         }
     }
+
+    pub fn source_range(&self, arena: &Arena<'s>) -> SourceRange {
+        match self {
+            Expr::LiteralExpr(expr) => expr.get_tricky(arena).concrete.range,
+            Expr::IdRefExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::UnaryExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::BinaryExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::AssignExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::FuncCallExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::SubscriptExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::FieldRefExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::AwaitExpr(expr) => expr.get_tricky(arena).concrete.source_range(),
+            Expr::AsExpr(expr) => expr.get_tricky(arena).concrete.source_range()
+        }
+    }
 }
 
 pub struct LiteralExpr<'s> {
-    pub content: LiteralExprContent<'s>,
+    pub content: LiteralExprContent,
     pub ty: NonNull<TyckInfo>,
 
-    pub concrete: &'s ConcreteLiteralExpr<'s>
+    pub concrete: &'s ConcreteLiteralExpr
 }
 
 pub struct IdRefExpr<'s> {
@@ -88,7 +118,9 @@ pub enum ResolvedUnaryOp {
     IntNegation,
     FloatNegation,
     LogicalNot,
-    BitwiseReverse
+    LogicalNotRTTI,
+    BitwiseReverse,
+    BitwiseReverseRTTI
 }
 
 pub struct UnaryExpr<'s> {
@@ -155,7 +187,12 @@ pub enum ResolvedBinaryOp {
     LogicalXor,
     LogicalAndRTTI,
     LogicalOrRTTI,
-    LogicalXorRTTI
+    LogicalXorRTTI,
+
+    ShiftLeft,
+    ShiftRight,
+    ShiftLeftRTTI,
+    ShiftRightRTTI
 }
 
 pub struct BinaryExpr<'s> {
@@ -168,6 +205,31 @@ pub struct BinaryExpr<'s> {
     pub concrete: &'s ConcreteBinaryExpr<'s>
 }
 
+/// Assignment `lhs = rhs`, or compound assignment like `lhs += rhs`
+pub struct AssignExpr<'s> {
+    pub lhs: Expr<'s>,
+    pub rhs: Expr<'s>,
+    /// For compound assignments, the operator applied to the old value of `lhs` and `rhs`, and
+    /// the type of its result. Subexpressions of `lhs` should be evaluated only once.
+    pub compound_op: Option<(ResolvedBinaryOp, Option<NonNull<TyckInfo>>)>,
+    pub ty: Option<NonNull<TyckInfo>>,
+
+    pub concrete: &'s ConcreteBinaryExpr<'s>
+}
+
+pub struct FuncCallExpr<'s> {
+    pub func: Either<ArenaPtr<'s, FuncDecl<'s>>, Expr<'s>>,
+    pub args: Vec<Expr<'s>>,
+    /// `None` if the callee is of `any` type, so nothing is known until run time
+    pub ret_types: Option<Vec<NonNull<TyckInfo>>>,
+    pub tyck_args: bool,
+    /// Candidates to select from by argument types at run time, empty if the overload got
+    /// resolved at compile time. `func` is the first candidate in that case.
+    pub overloads: Vec<ArenaPtr<'s, FuncDecl<'s>>>,
+
+    pub concrete: &'s ConcreteFuncCallExpr<'s>
+}
+
 #[cfg_attr(test, derive(Debug))]
 #[derive(Clone, Copy)]
 #[repr(u8)]
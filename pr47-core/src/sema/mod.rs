@@ -1,7 +1,9 @@
 pub mod arena;
+pub mod const_fold;
 pub mod decl;
 pub mod decl_context;
 pub mod expr;
 pub mod dyn_cast;
 pub mod phase2;
 pub mod scope;
+pub mod stmt;
@@ -1,17 +1,49 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::mem::replace;
 use std::ptr::NonNull;
 
+use xjbutil::either::Either;
 use xjbutil::value::Value;
 
+use crate::builtins::vec::VMGenericVec;
+use crate::builtins::vm_error::vm_error_type_id;
 use crate::data::tyck::{TyckInfo, TyckInfoPool};
 use crate::diag::{diag_data, DiagContext};
+use crate::diag::location::{SourceLoc, SourceRange};
 use crate::sema::arena::{Arena, ArenaPtr};
-use crate::sema::decl::ObjectDecl;
-use crate::sema::expr::{Expr, IdRefExpr, LiteralExpr};
+use crate::sema::const_fold::{
+    CastTarget,
+    ConstFoldError,
+    char_code_point,
+    fold_binary,
+    fold_cast,
+    fold_unary,
+    literal_value
+};
+use crate::sema::decl::{FuncDecl, ObjectDecl};
+use crate::sema::expr::{
+    AsExpr,
+    AssignExpr,
+    AwaitExpr,
+    BinaryExpr,
+    Expr,
+    FieldRefExpr,
+    FuncCallExpr,
+    IdRefExpr,
+    LiteralExpr,
+    ResolvedBinaryOp,
+    ResolvedUnaryOp,
+    SubscriptExpr,
+    SubscriptMode,
+    UnaryExpr
+};
 use crate::sema::scope::{Scope, ScopeKind};
 use crate::syntax::expr::{
     ConcreteAsExpr,
     ConcreteAwaitExpr,
     ConcreteBinaryExpr,
+    ConcreteExpr,
     ConcreteFieldRefExpr,
     ConcreteFuncCallExpr,
     ConcreteLiteralExpr,
@@ -20,23 +52,690 @@ use crate::syntax::expr::{
     LiteralExprContent
 };
 use crate::syntax::id::Identifier;
-use crate::syntax::visitor::ExprVisitor;
+use crate::syntax::token::{Token, TokenInner};
+use crate::syntax::ty::{ConcreteGenericType, ConcreteNullableType};
+use crate::syntax::visitor::{ExprVisitor, TypeVisitor};
+
+mod decl;
+mod stmt;
 
 pub struct SemaPhase2<'s, 'd> {
     scope: Scope<'s>,
-    arena: &'s mut Arena<'s>,
-    tyck_info_pool: &'s mut TyckInfoPool,
+    arena: &'d mut Arena<'s>,
+    tyck_info_pool: &'d mut TyckInfoPool,
+    /// The function whose body is being checked
+    current_func: Option<ArenaPtr<'s, FuncDecl<'s>>>,
+    /// Whether `rethrow` is used for each enclosing `catch` clause, innermost last
+    catch_rethrows: Vec<bool>,
+    /// Host exception types, nameable in scripts
+    exception_types: Option<&'d HashMap<String, TypeId>>,
 
     diag: &'d mut DiagContext
 }
 
-#[allow(unused)]
+/// Rough classification of operand types, used for resolving operators
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum OperandKind {
+    Int,
+    Float,
+    Bool,
+    Char,
+    String,
+    /// `any`, or something only known at run time
+    Dynamic,
+    Other
+}
+
+/// The result of resolving an identifier
+enum LookupResult<'s> {
+    Object(ArenaPtr<'s, ObjectDecl<'s>>),
+    Functions(Vec<ArenaPtr<'s, FuncDecl<'s>>>)
+}
+
+impl<'s, 'd> SemaPhase2<'s, 'd> {
+    pub fn new(
+        scope: Scope<'s>,
+        arena: &'d mut Arena<'s>,
+        tyck_info_pool: &'d mut TyckInfoPool,
+        diag: &'d mut DiagContext
+    ) -> Self {
+        Self {
+            scope,
+            arena,
+            tyck_info_pool,
+            current_func: None,
+            catch_rethrows: Vec::new(),
+            exception_types: None,
+            diag
+        }
+    }
+
+    /// Makes host exception types, usually from `FFIRegistry::exception_types`, nameable in
+    /// scripts
+    #[must_use] pub fn with_exception_types(
+        mut self,
+        exception_types: &'d HashMap<String, TypeId>
+    ) -> Self {
+        self.exception_types = Some(exception_types);
+        self
+    }
+
+    pub fn arena(&self) -> &Arena<'s> {
+        self.arena
+    }
+
+    pub fn scope(&self) -> &Scope<'s> {
+        &self.scope
+    }
+
+    pub fn scope_mut(&mut self) -> &mut Scope<'s> {
+        &mut self.scope
+    }
+
+    fn enter_scope(&mut self, scope_kind: ScopeKind) {
+        let parent: Scope<'s> = replace(&mut self.scope, Scope::new(scope_kind));
+        self.scope = Scope::with_parent(scope_kind, Box::new(parent));
+    }
+
+    fn leave_scope(&mut self) {
+        let scope: Scope<'s> = replace(&mut self.scope, Scope::new(ScopeKind::Global));
+        self.scope = *scope.pop_self().expect("leaving the outermost scope");
+    }
+
+    /// Visits an expression whose value is going to be used. Function calls not returning
+    /// exactly one value are rejected.
+    pub fn visit_operand(&mut self, expr: &'s ConcreteExpr<'s>) -> Option<Expr<'s>> {
+        let ret: Expr<'s> = self.visit_expr(expr)?;
+        if let Expr::FuncCallExpr(func_call_expr) = ret {
+            let func_call_expr: &FuncCallExpr<'s> = func_call_expr.get_tricky(self.arena);
+            if let Some(ret_types /*: &Vec<_>*/) = &func_call_expr.ret_types {
+                if ret_types.len() != 1 {
+                    let lparen_loc: SourceLoc = func_call_expr.concrete.lparen_loc;
+                    self.diag.diag(lparen_loc, diag_data::err_not_single_value)
+                        .add_mark(lparen_loc.into())
+                        .emit();
+                    return None;
+                }
+            }
+        }
+        Some(ret)
+    }
+
+    fn operand_kind(&self, ty: Option<NonNull<TyckInfo>>) -> OperandKind {
+        let ty: NonNull<TyckInfo> = if let Some(ty) = ty { ty } else {
+            return OperandKind::Dynamic;
+        };
+
+        if ty == self.tyck_info_pool.get_int_type() {
+            OperandKind::Int
+        } else if ty == self.tyck_info_pool.get_float_type() {
+            OperandKind::Float
+        } else if ty == self.tyck_info_pool.get_bool_type() {
+            OperandKind::Bool
+        } else if ty == self.tyck_info_pool.get_char_type() {
+            OperandKind::Char
+        } else if ty == self.tyck_info_pool.get_string_type() {
+            OperandKind::String
+        } else if unsafe { ty.as_ref() }.is_any() {
+            OperandKind::Dynamic
+        } else {
+            OperandKind::Other
+        }
+    }
+
+    /// Checks if a value of type `src` can be used where `dest` is expected. Returns `Some(false)`
+    /// if it can be statically confirmed, `Some(true)` if run time checking is required, and
+    /// `None` if the types are incompatible.
+    fn check_assignable(
+        &self,
+        dest: Option<NonNull<TyckInfo>>,
+        src: Option<NonNull<TyckInfo>>
+    ) -> Option<bool> {
+        let (dest, src): (NonNull<TyckInfo>, NonNull<TyckInfo>) = match (dest, src) {
+            (None, _) => return Some(false),
+            (Some(_), None) => return Some(true),
+            (Some(dest), Some(src)) => (dest, src)
+        };
+
+        if dest == src || unsafe { dest.as_ref() }.is_any() {
+            Some(false)
+        } else if unsafe { src.as_ref() }.is_any() {
+            Some(true)
+        } else if let TyckInfo::Nullable(underlying) = unsafe { dest.as_ref() } {
+            if *underlying == src { Some(false) } else { None }
+        } else {
+            None
+        }
+    }
+
+    fn lookup_ident(&mut self, id: &'s Identifier<'s>) -> Option<LookupResult<'s>> {
+        let result: Result<Option<LookupResult<'s>>, &'s str> = match id {
+            Identifier::Unqual(token) => {
+                let name: &'s str = token.get_str_value();
+                Ok(if let Some(object_decl) = self.scope.lookup_var_decl(name) {
+                    Some(LookupResult::Object(object_decl))
+                } else {
+                    self.scope.lookup_func_decls(name)
+                        .map(|func_decls| LookupResult::Functions(func_decls.clone()))
+                })
+            },
+            Identifier::Qual(tokens) => {
+                let (name_token, module_path): (&Token<'s>, &[Token<'s>]) =
+                    tokens.split_last().unwrap();
+                let module_name: &'s str = module_path[0].get_str_value();
+                if let Some(mut module) = self.scope.lookup_module_decl(module_name) {
+                    let mut failed_module: Option<&'s str> = None;
+                    for module_token /*: &Token*/ in module_path[1..].iter() {
+                        let module_name: &'s str = module_token.get_str_value();
+                        if let Some(submodule) = module.get_tricky(self.arena)
+                            .scope
+                            .module_decls
+                            .get(module_name)
+                        {
+                            module = *submodule;
+                        } else {
+                            failed_module = Some(module_name);
+                            break;
+                        }
+                    }
+
+                    if let Some(failed_module) = failed_module {
+                        Err(failed_module)
+                    } else {
+                        let name: &'s str = name_token.get_str_value();
+                        let module_scope: &Scope<'s> = &module.get_tricky(self.arena).scope;
+                        Ok(if let Some(object_decl) = module_scope.object_decls.get(name) {
+                            Some(LookupResult::Object(*object_decl))
+                        } else {
+                            module_scope.func_decls.get(name)
+                                .map(|func_decls| LookupResult::Functions(func_decls.clone()))
+                        })
+                    }
+                } else {
+                    Err(module_name)
+                }
+            }
+        };
+
+        match result {
+            Ok(Some(result)) => Some(result),
+            Ok(None) => {
+                let range: SourceRange = id.source_range();
+                self.diag.diag(range.left(), diag_data::err_undefined_identifier_0)
+                    .add_arg(ident_to_string(id))
+                    .add_mark(range.into())
+                    .emit();
+                None
+            },
+            Err(module_name) => {
+                let range: SourceRange = id.source_range();
+                self.diag.diag(range.left(), diag_data::err_undefined_module_0)
+                    .add_arg(module_name)
+                    .add_mark(range.into())
+                    .emit();
+                None
+            }
+        }
+    }
+
+    fn func_decl_param_types(&self, func_decl: ArenaPtr<'s, FuncDecl<'s>>)
+        -> Vec<NonNull<TyckInfo>>
+    {
+        func_decl.get_tricky(self.arena)
+            .param_decl_context
+            .object_decls
+            .iter()
+            .map(|param: &ArenaPtr<'s, ObjectDecl<'s>>| param.get_tricky(self.arena).ty)
+            .collect()
+    }
+
+    fn func_decl_type(&mut self, func_decl: ArenaPtr<'s, FuncDecl<'s>>) -> NonNull<TyckInfo> {
+        let param_types: Vec<NonNull<TyckInfo>> = self.func_decl_param_types(func_decl);
+        let func_decl: &FuncDecl<'s> = func_decl.get_tricky(self.arena);
+        self.tyck_info_pool.create_function_type(
+            &param_types,
+            &func_decl.ret_types,
+            &func_decl.exception_spec
+        )
+    }
+
+    /// Checks arguments against parameters, returns whether run time type checking is required,
+    /// or `Err` with the index of the first mismatched argument.
+    fn check_args(
+        &self,
+        param_types: &[NonNull<TyckInfo>],
+        args: &[Expr<'s>]
+    ) -> Result<bool, usize> {
+        let mut tyck_args: bool = false;
+        for (i, (param_type, arg)) /*: (usize, (&NonNull<TyckInfo>, &Expr))*/ in
+            param_types.iter().zip(args.iter()).enumerate()
+        {
+            match self.check_assignable(Some(*param_type), arg.get_type(self.arena)) {
+                Some(tyck) => tyck_args |= tyck,
+                None => return Err(i)
+            }
+        }
+        Ok(tyck_args)
+    }
+
+    /// Resolves a call to overloaded functions, returns the selected candidate and whether run
+    /// time type checking is required. When the candidate cannot be decided until argument types
+    /// are known at run time, all the candidates to select from are returned.
+    fn resolve_overload(
+        &mut self,
+        id: &'s Identifier<'s>,
+        candidates: &[ArenaPtr<'s, FuncDecl<'s>>],
+        args: &[Expr<'s>],
+        lparen_loc: SourceLoc
+    ) -> Option<(Vec<ArenaPtr<'s, FuncDecl<'s>>>, bool)> {
+        let mut exact_matches: Vec<ArenaPtr<'s, FuncDecl<'s>>> = Vec::new();
+        let mut tyck_matches: Vec<ArenaPtr<'s, FuncDecl<'s>>> = Vec::new();
+
+        for candidate /*: &ArenaPtr<FuncDecl>*/ in candidates.iter() {
+            let param_types: Vec<NonNull<TyckInfo>> = self.func_decl_param_types(*candidate);
+            if param_types.len() != args.len() {
+                continue;
+            }
+            match self.check_args(&param_types, args) {
+                Ok(false) => exact_matches.push(*candidate),
+                Ok(true) => tyck_matches.push(*candidate),
+                Err(_) => {}
+            }
+        }
+
+        let (matches, tyck_args): (&[ArenaPtr<'s, FuncDecl<'s>>], bool) =
+            if !exact_matches.is_empty() {
+                (&exact_matches, false)
+            } else {
+                (&tyck_matches, true)
+            };
+
+        match matches.len() {
+            1 => return Some((vec![matches[0]], tyck_args)),
+            0 => {},
+            _ if tyck_args && self.dispatchable_at_run_time(matches) =>
+                return Some((matches.to_vec(), false)),
+            _ => {
+                self.diag.diag(lparen_loc, diag_data::err_ambiguous_overload_0)
+                    .add_arg(ident_to_string(id))
+                    .add_mark(id.source_range().into())
+                    .emit();
+                return None;
+            }
+        }
+
+        if candidates.len() == 1 {
+            let param_types: Vec<NonNull<TyckInfo>> = self.func_decl_param_types(candidates[0]);
+            self.diag_arg_mismatch(&param_types, args, lparen_loc);
+        } else {
+            self.diag.diag(lparen_loc, diag_data::err_no_matching_overload_0)
+                .add_arg(ident_to_string(id))
+                .add_mark(id.source_range().into())
+                .emit();
+        }
+        None
+    }
+
+    /// Checks whether candidates can be selected from at run time: all of them should be script
+    /// functions, returning the same count of values
+    fn dispatchable_at_run_time(&self, candidates: &[ArenaPtr<'s, FuncDecl<'s>>]) -> bool {
+        let ret_count: usize = candidates[0].get_tricky(self.arena).ret_types.len();
+        candidates.iter().all(|candidate: &ArenaPtr<'s, FuncDecl<'s>>| {
+            let func_decl: &FuncDecl<'s> = candidate.get_tricky(self.arena);
+            !func_decl.is_ffi() && func_decl.ret_types.len() == ret_count
+        })
+    }
+
+    /// Gets return types of a call selecting from `candidates` at run time, returned values are
+    /// typed `any` where candidates disagree
+    fn overload_ret_types(
+        &self,
+        candidates: &[ArenaPtr<'s, FuncDecl<'s>>]
+    ) -> Vec<NonNull<TyckInfo>> {
+        let mut ret_types: Vec<NonNull<TyckInfo>> =
+            candidates[0].get_tricky(self.arena).ret_types.clone();
+        for candidate /*: &ArenaPtr<FuncDecl>*/ in candidates[1..].iter() {
+            for (ret_type, candidate_ret_type) /*: (&mut NonNull<TyckInfo>, &NonNull<TyckInfo>)*/
+                in ret_types.iter_mut().zip(candidate.get_tricky(self.arena).ret_types.iter())
+            {
+                if *ret_type != *candidate_ret_type {
+                    *ret_type = self.tyck_info_pool.get_any_type();
+                }
+            }
+        }
+        ret_types
+    }
+
+    fn diag_arg_mismatch(
+        &mut self,
+        param_types: &[NonNull<TyckInfo>],
+        args: &[Expr<'s>],
+        lparen_loc: SourceLoc
+    ) {
+        if param_types.len() != args.len() {
+            self.diag.diag(lparen_loc, diag_data::err_arg_count_mismatch_0_1)
+                .add_arg(param_types.len())
+                .add_arg(args.len())
+                .add_mark(lparen_loc.into())
+                .emit();
+        } else if let Err(idx) = self.check_args(param_types, args) {
+            self.diag.diag(lparen_loc, diag_data::err_arg_type_mismatch_0)
+                .add_arg(idx + 1)
+                .add_mark(lparen_loc.into())
+                .emit();
+        }
+    }
+
+    fn make_id_ref_expr(
+        &mut self,
+        id: &'s Identifier<'s>,
+        lookup_result: LookupResult<'s>
+    ) -> Option<Expr<'s>> {
+        let (decl, ty, maybe_constant_folding) = match lookup_result {
+            LookupResult::Object(object_decl) => {
+                let object_decl_ref: &ObjectDecl<'s> = object_decl.get_tricky(self.arena);
+                let ty: NonNull<TyckInfo> = object_decl_ref.ty;
+                let maybe_constant_folding: Option<Value> = match &object_decl_ref.init {
+                    Some(init) if object_decl_ref.is_const => self.const_value(init),
+                    _ => None
+                };
+                (Either::Left(object_decl), ty, maybe_constant_folding)
+            },
+            LookupResult::Functions(func_decls) => {
+                if func_decls.len() != 1 {
+                    let range: SourceRange = id.source_range();
+                    self.diag.diag(range.left(), diag_data::err_overloaded_func_as_value_0)
+                        .add_arg(ident_to_string(id))
+                        .add_mark(range.into())
+                        .emit();
+                    return None;
+                }
+                if func_decls[0].get_tricky(self.arena).is_ffi() {
+                    let range: SourceRange = id.source_range();
+                    self.diag.diag(range.left(), diag_data::err_ffi_func_as_value_0)
+                        .add_arg(ident_to_string(id))
+                        .add_mark(range.into())
+                        .emit();
+                    return None;
+                }
+                let ty: NonNull<TyckInfo> = self.func_decl_type(func_decls[0]);
+                (Either::Right(func_decls[0]), ty, None)
+            }
+        };
+
+        Some(Expr::IdRefExpr(ArenaPtr::new_in(IdRefExpr {
+            decl,
+            ty,
+            maybe_constant_folding,
+            concrete: id
+        }, self.arena)))
+    }
+
+    fn resolve_binary_op(
+        &mut self,
+        op: TokenInner<'s>,
+        op_range: SourceRange,
+        lhs_type: Option<NonNull<TyckInfo>>,
+        rhs_type: Option<NonNull<TyckInfo>>
+    ) -> Option<(ResolvedBinaryOp, Option<NonNull<TyckInfo>>)> {
+        use OperandKind::*;
+        use ResolvedBinaryOp::*;
+
+        let int_type: Option<NonNull<TyckInfo>> = Some(self.tyck_info_pool.get_int_type());
+        let float_type: Option<NonNull<TyckInfo>> = Some(self.tyck_info_pool.get_float_type());
+        let bool_type: Option<NonNull<TyckInfo>> = Some(self.tyck_info_pool.get_bool_type());
+        let string_type: Option<NonNull<TyckInfo>> = Some(self.tyck_info_pool.get_string_type());
+
+        let lhs_kind: OperandKind = self.operand_kind(lhs_type);
+        let rhs_kind: OperandKind = self.operand_kind(rhs_type);
+        let has_dynamic: bool = lhs_kind == Dynamic || rhs_kind == Dynamic;
+        let numeric = |kind: OperandKind| matches!(kind, Int | Float | Dynamic);
+        let integral = |kind: OperandKind| matches!(kind, Int | Dynamic);
+        let boolean = |kind: OperandKind| matches!(kind, Bool | Dynamic);
+
+        let arith = |int_op: ResolvedBinaryOp,
+                     float_op: ResolvedBinaryOp,
+                     rtti_op: ResolvedBinaryOp| {
+            match (lhs_kind, rhs_kind) {
+                (Int, Int) => Some((int_op, int_type)),
+                (Float, Float) => Some((float_op, float_type)),
+                _ if has_dynamic && numeric(lhs_kind) && numeric(rhs_kind) => Some((rtti_op, None)),
+                _ => None
+            }
+        };
+        let compare = |int_op: ResolvedBinaryOp,
+                       float_op: ResolvedBinaryOp,
+                       rtti_op: ResolvedBinaryOp| {
+            match (lhs_kind, rhs_kind) {
+                // `char`s are represented as their code points at run time
+                (Int, Int) | (Char, Char) => Some((int_op, bool_type)),
+                (Float, Float) => Some((float_op, bool_type)),
+                _ if has_dynamic && numeric(lhs_kind) && numeric(rhs_kind) =>
+                    Some((rtti_op, bool_type)),
+                _ => None
+            }
+        };
+        let int_only = |int_op: ResolvedBinaryOp, rtti_op: ResolvedBinaryOp| {
+            match (lhs_kind, rhs_kind) {
+                (Int, Int) => Some((int_op, int_type)),
+                _ if integral(lhs_kind) && integral(rhs_kind) => Some((rtti_op, int_type)),
+                _ => None
+            }
+        };
+        let logic = |bool_op: ResolvedBinaryOp, rtti_op: ResolvedBinaryOp| {
+            match (lhs_kind, rhs_kind) {
+                (Bool, Bool) => Some((bool_op, bool_type)),
+                _ if boolean(lhs_kind) && boolean(rhs_kind) => Some((rtti_op, bool_type)),
+                _ => None
+            }
+        };
+
+        let resolved: Option<(ResolvedBinaryOp, Option<NonNull<TyckInfo>>)> = match op {
+            TokenInner::SymPlus => if lhs_kind == String && rhs_kind == String {
+                Some((StringAdd, string_type))
+            } else {
+                arith(IntAdd, FloatAdd, Add)
+            },
+            TokenInner::SymMinus => arith(IntMinus, FloatMinus, Minus),
+            TokenInner::SymAster => arith(IntMul, FloatMul, Mul),
+            TokenInner::SymSlash => arith(IntDiv, FloatDiv, Div),
+            TokenInner::SymPercent => int_only(IntMod, IntModRTTI),
+            TokenInner::SymLt => compare(IntLessThan, FloatLessThan, LessThan),
+            TokenInner::SymGt => compare(IntGreaterThan, FloatGreaterThan, GreaterThan),
+            TokenInner::SymLe =>
+                compare(IntLessThanOrEqual, FloatLessThanOrEqual, LessThanOrEqual),
+            TokenInner::SymGe =>
+                compare(IntGreaterThanOrEqual, FloatGreaterThanOrEqual, GreaterThanOrEqual),
+            TokenInner::SymDEq | TokenInner::SymNe => {
+                let comparable: bool =
+                    self.check_assignable(lhs_type, rhs_type).is_some()
+                    || self.check_assignable(rhs_type, lhs_type).is_some();
+                if !comparable {
+                    None
+                } else if let TokenInner::SymDEq = op {
+                    Some((Equal, bool_type))
+                } else {
+                    Some((NotEqual, bool_type))
+                }
+            },
+            TokenInner::SymAmp => int_only(BitwiseAnd, BitwiseAndRTTI),
+            TokenInner::SymPipe => int_only(BitwiseOr, BitwiseOrRTTI),
+            TokenInner::SymCaret => int_only(BitwiseXor, BitwiseXorRTTI),
+            TokenInner::SymDLt => int_only(ShiftLeft, ShiftLeftRTTI),
+            TokenInner::SymDGt => int_only(ShiftRight, ShiftRightRTTI),
+            TokenInner::SymDAmp => logic(LogicalAnd, LogicalAndRTTI),
+            TokenInner::SymDPipe => logic(LogicalOr, LogicalOrRTTI),
+            TokenInner::SymDCaret => logic(LogicalXor, LogicalXorRTTI),
+            _ => unreachable!("not a binary operator")
+        };
+
+        if resolved.is_none() {
+            self.diag.diag(op_range.left(), diag_data::err_invalid_binary_operands_0)
+                .add_arg2(op)
+                .add_mark(op_range.into())
+                .emit();
+        }
+        resolved
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        binary_expr: &'s ConcreteBinaryExpr<'s>
+    ) -> Option<Expr<'s>> {
+        let op_range: SourceRange = binary_expr.op.range;
+        let lhs: Option<Expr<'s>> = self.visit_expr(&binary_expr.lhs);
+        let rhs: Option<Expr<'s>> = self.visit_operand(&binary_expr.rhs);
+        let (lhs, rhs): (Expr<'s>, Expr<'s>) = (lhs?, rhs?);
+
+        match lhs {
+            Expr::IdRefExpr(id_ref_expr) => {
+                let id_ref_expr: &IdRefExpr<'s> = id_ref_expr.get_tricky(self.arena);
+                match id_ref_expr.decl {
+                    Either::Left(object_decl) => {
+                        let object_decl: &ObjectDecl<'s> = object_decl.get_tricky(self.arena);
+                        if object_decl.is_const {
+                            let range: SourceRange = id_ref_expr.concrete.source_range();
+                            self.diag.diag(op_range.left(), diag_data::err_assign_to_const_0)
+                                .add_arg(object_decl.name)
+                                .add_mark(range.into())
+                                .emit();
+                            return None;
+                        }
+                    },
+                    Either::Right(_) => {
+                        self.diag.diag(op_range.left(), diag_data::err_not_assignable)
+                            .add_mark(id_ref_expr.concrete.source_range().into())
+                            .emit();
+                        return None;
+                    }
+                }
+            },
+            Expr::SubscriptExpr(_) | Expr::FieldRefExpr(_) => {},
+            _ => {
+                self.diag.diag(op_range.left(), diag_data::err_not_assignable)
+                    .add_mark(op_range.into())
+                    .emit();
+                return None;
+            }
+        }
+
+        let lhs_type: Option<NonNull<TyckInfo>> = lhs.get_type(self.arena);
+        let arith_op: Option<TokenInner<'s>> = match binary_expr.op.token_inner {
+            TokenInner::SymEq => None,
+            TokenInner::SymPlusEq => Some(TokenInner::SymPlus),
+            TokenInner::SymMinusEq => Some(TokenInner::SymMinus),
+            TokenInner::SymAsterEq => Some(TokenInner::SymAster),
+            TokenInner::SymSlashEq => Some(TokenInner::SymSlash),
+            TokenInner::SymPercentEq => Some(TokenInner::SymPercent),
+            _ => unreachable!("not an assignment operator")
+        };
+
+        let compound_op: Option<(ResolvedBinaryOp, Option<NonNull<TyckInfo>>)> =
+            if let Some(arith_op) = arith_op {
+                Some(self.resolve_binary_op(
+                    arith_op,
+                    op_range,
+                    lhs_type,
+                    rhs.get_type(self.arena)
+                )?)
+            } else {
+                None
+            };
+        let value_type: Option<NonNull<TyckInfo>> = match compound_op {
+            Some((_, op_type)) => op_type,
+            None => rhs.get_type(self.arena)
+        };
+
+        if self.check_assignable(lhs_type, value_type).is_none() {
+            self.diag.diag(op_range.left(), diag_data::err_assign_type_mismatch)
+                .add_mark(op_range.into())
+                .emit();
+            return None;
+        }
+
+        Some(Expr::AssignExpr(ArenaPtr::new_in(AssignExpr {
+            lhs,
+            rhs,
+            compound_op,
+            ty: lhs_type,
+            concrete: binary_expr
+        }, self.arena)))
+    }
+
+    /// Gets the compile-time value of an expression, if there is one
+    fn const_value(&self, expr: &Expr<'s>) -> Option<Value> {
+        if let Expr::LiteralExpr(literal_expr) = expr {
+            Some(literal_value(&literal_expr.get_tricky(self.arena).content))
+        } else {
+            expr.get_const_fold_value(self.arena).cloned()
+        }
+    }
+
+    /// Folds a binary expression with constant operands. The outer `Option` is `None` if folding
+    /// failed with an error, the inner one is `None` if the expression cannot be folded.
+    fn fold_binary_expr(
+        &mut self,
+        op: ResolvedBinaryOp,
+        lhs: Expr<'s>,
+        rhs: Expr<'s>,
+        op_range: SourceRange
+    ) -> Option<Option<Value>> {
+        if let ResolvedBinaryOp::Equal | ResolvedBinaryOp::NotEqual = op {
+            // `char`s are folded as strings, so only fold comparisons when static types are known
+            if self.operand_kind(lhs.get_type(self.arena)) == OperandKind::Dynamic
+                || self.operand_kind(rhs.get_type(self.arena)) == OperandKind::Dynamic
+            {
+                return Some(None);
+            }
+        }
+
+        let (lhs_value, rhs_value): (Value, Value) =
+            match (self.const_value(&lhs), self.const_value(&rhs)) {
+                (Some(lhs_value), Some(rhs_value)) => (lhs_value, rhs_value),
+                _ => return Some(None)
+            };
+        let (lhs_value, rhs_value): (Value, Value) =
+            if self.operand_kind(lhs.get_type(self.arena)) == OperandKind::Char {
+                (char_code_point(lhs_value), char_code_point(rhs_value))
+            } else {
+                (lhs_value, rhs_value)
+            };
+
+        match fold_binary(op, &lhs_value, &rhs_value) {
+            Ok(value) => Some(value),
+            Err(ConstFoldError::DivideByZero) => {
+                self.diag.diag(op_range.left(), diag_data::err_const_divide_by_zero)
+                    .add_mark(op_range.into())
+                    .emit();
+                None
+            },
+            Err(ConstFoldError::ShiftOverflow(amount)) => {
+                self.diag.diag(op_range.left(), diag_data::err_const_shift_overflow_0)
+                    .add_arg(amount)
+                    .add_mark(op_range.into())
+                    .emit();
+                None
+            },
+            Err(ConstFoldError::InvalidCast) => unreachable!()
+        }
+    }
+
+    fn is_vector_type(ty: NonNull<TyckInfo>) -> Option<NonNull<TyckInfo>> {
+        if let TyckInfo::Container(container) = unsafe { ty.as_ref() } {
+            if container.type_id == TypeId::of::<VMGenericVec>() {
+                return Some(unsafe { container.params.as_ref()[0] });
+            }
+        }
+        None
+    }
+}
+
 impl<'s, 'd> ExprVisitor<'s> for SemaPhase2<'s, 'd> {
     type ExprResult = Option<Expr<'s>>;
 
     fn visit_literal_expr(
         &mut self,
-        literal_expr: &'s ConcreteLiteralExpr<'s>
+        literal_expr: &'s ConcreteLiteralExpr
     ) -> Self::ExprResult {
         let ty: NonNull<TyckInfo> = match literal_expr.content {
             LiteralExprContent::Int(_) => self.tyck_info_pool.get_int_type(),
@@ -47,7 +746,7 @@ impl<'s, 'd> ExprVisitor<'s> for SemaPhase2<'s, 'd> {
         };
 
         let literal_expr: ArenaPtr<'s, LiteralExpr<'s>> = ArenaPtr::new_in(LiteralExpr {
-            content: literal_expr.content,
+            content: literal_expr.content.clone(),
             ty,
             concrete: literal_expr
         }, self.arena);
@@ -55,34 +754,744 @@ impl<'s, 'd> ExprVisitor<'s> for SemaPhase2<'s, 'd> {
     }
 
     fn visit_id_ref_expr(&mut self, id: &'s Identifier<'s>) -> Self::ExprResult {
-        todo!()
+        let lookup_result: LookupResult<'s> = self.lookup_ident(id)?;
+        self.make_id_ref_expr(id, lookup_result)
     }
 
     fn visit_unary_expr(&mut self, unary_expr: &'s ConcreteUnaryExpr<'s>) -> Self::ExprResult {
-        todo!()
+        use OperandKind::*;
+        use ResolvedUnaryOp::*;
+
+        let operand: Expr<'s> = self.visit_operand(&unary_expr.operand)?;
+        let operand_type: Option<NonNull<TyckInfo>> = operand.get_type(self.arena);
+        let int_type: Option<NonNull<TyckInfo>> = Some(self.tyck_info_pool.get_int_type());
+        let bool_type: Option<NonNull<TyckInfo>> = Some(self.tyck_info_pool.get_bool_type());
+
+        let (op, ty): (ResolvedUnaryOp, Option<NonNull<TyckInfo>>) =
+            match (unary_expr.op.token_inner, self.operand_kind(operand_type)) {
+                (TokenInner::SymPlus, Int | Float) => (Positive, operand_type),
+                (TokenInner::SymPlus, Dynamic) => (Positive, None),
+                (TokenInner::SymMinus, Int) => (IntNegation, operand_type),
+                (TokenInner::SymMinus, Float) => (FloatNegation, operand_type),
+                (TokenInner::SymMinus, Dynamic) => (Negation, None),
+                (TokenInner::SymExclaim, Bool) => (LogicalNot, bool_type),
+                (TokenInner::SymExclaim, Dynamic) => (LogicalNotRTTI, bool_type),
+                (TokenInner::SymTilde, Int) => (BitwiseReverse, int_type),
+                (TokenInner::SymTilde, Dynamic) => (BitwiseReverseRTTI, int_type),
+                _ => {
+                    let op_range: SourceRange = unary_expr.op.range;
+                    self.diag.diag(op_range.left(), diag_data::err_invalid_unary_operand_0)
+                        .add_arg2(unary_expr.op.token_inner)
+                        .add_mark(op_range.into())
+                        .emit();
+                    return None;
+                }
+            };
+
+        let maybe_constant_folding: Option<Value> = self.const_value(&operand)
+            .and_then(|value: Value| fold_unary(op, &value));
+        Some(Expr::UnaryExpr(ArenaPtr::new_in(UnaryExpr {
+            op,
+            operand,
+            ty,
+            maybe_constant_folding,
+            concrete: unary_expr
+        }, self.arena)))
     }
 
     fn visit_binary_expr(&mut self, binary_expr: &'s ConcreteBinaryExpr<'s>) -> Self::ExprResult {
-        todo!()
+        match binary_expr.op.token_inner {
+            TokenInner::SymEq | TokenInner::SymPlusEq | TokenInner::SymMinusEq |
+            TokenInner::SymAsterEq | TokenInner::SymSlashEq | TokenInner::SymPercentEq => {
+                return self.visit_assign_expr(binary_expr);
+            },
+            _ => {}
+        }
+
+        let lhs: Option<Expr<'s>> = self.visit_operand(&binary_expr.lhs);
+        let rhs: Option<Expr<'s>> = self.visit_operand(&binary_expr.rhs);
+        let (lhs, rhs): (Expr<'s>, Expr<'s>) = (lhs?, rhs?);
+
+        let (op, ty): (ResolvedBinaryOp, Option<NonNull<TyckInfo>>) = self.resolve_binary_op(
+            binary_expr.op.token_inner,
+            binary_expr.op.range,
+            lhs.get_type(self.arena),
+            rhs.get_type(self.arena)
+        )?;
+
+        let maybe_constant_folding: Option<Value> =
+            self.fold_binary_expr(op, lhs, rhs, binary_expr.op.range)?;
+        Some(Expr::BinaryExpr(ArenaPtr::new_in(BinaryExpr {
+            op,
+            lhs,
+            rhs,
+            ty,
+            maybe_constant_folding,
+            concrete: binary_expr
+        }, self.arena)))
     }
 
-    fn visit_func_call_expr(&mut self, func_call_expr: &'s ConcreteFuncCallExpr<'s>) -> Self::ExprResult {
-        todo!()
+    fn visit_func_call_expr(
+        &mut self,
+        func_call_expr: &'s ConcreteFuncCallExpr<'s>
+    ) -> Self::ExprResult {
+        let mut args: Vec<Expr<'s>> = Vec::with_capacity(func_call_expr.args.len());
+        let mut arg_failed: bool = false;
+        for arg /*: &ConcreteExpr*/ in func_call_expr.args.iter() {
+            if let Some(arg) = self.visit_operand(arg) {
+                args.push(arg);
+            } else {
+                arg_failed = true;
+            }
+        }
+
+        let lparen_loc: SourceLoc = func_call_expr.lparen_loc;
+        let callee: Expr<'s> = if let ConcreteExpr::IdRefExpr(id) = func_call_expr.func.as_ref() {
+            match self.lookup_ident(id)? {
+                LookupResult::Functions(func_decls) => {
+                    if arg_failed {
+                        return None;
+                    }
+
+                    let (mut candidates, tyck_args)
+                        : (Vec<ArenaPtr<'s, FuncDecl<'s>>>, bool) =
+                        self.resolve_overload(id, &func_decls, &args, lparen_loc)?;
+                    let ret_types: Vec<NonNull<TyckInfo>> = self.overload_ret_types(&candidates);
+                    let func_decl: ArenaPtr<'s, FuncDecl<'s>> = candidates[0];
+                    if candidates.len() == 1 {
+                        candidates.clear();
+                    }
+                    return Some(Expr::FuncCallExpr(ArenaPtr::new_in(FuncCallExpr {
+                        func: Either::Left(func_decl),
+                        args,
+                        ret_types: Some(ret_types),
+                        tyck_args,
+                        overloads: candidates,
+                        concrete: func_call_expr
+                    }, self.arena)));
+                },
+                lookup_result => self.make_id_ref_expr(id, lookup_result)?
+            }
+        } else {
+            self.visit_operand(&func_call_expr.func)?
+        };
+
+        if arg_failed {
+            return None;
+        }
+
+        let callee_type: Option<NonNull<TyckInfo>> = callee.get_type(self.arena);
+        let (ret_types, tyck_args): (Option<Vec<NonNull<TyckInfo>>>, bool) =
+            match callee_type.map(|ty: NonNull<TyckInfo>| unsafe { ty.as_ref() }) {
+                None | Some(TyckInfo::AnyType) => (None, true),
+                Some(TyckInfo::Function(function_tyck_info)) => {
+                    let param_types: &[NonNull<TyckInfo>] =
+                        unsafe { function_tyck_info.params.as_ref() };
+                    let ret_types: &[NonNull<TyckInfo>] =
+                        unsafe { function_tyck_info.rets.as_ref() };
+                    if param_types.len() != args.len() {
+                        self.diag_arg_mismatch(param_types, &args, lparen_loc);
+                        return None;
+                    }
+                    match self.check_args(param_types, &args) {
+                        Ok(tyck_args) => (Some(ret_types.to_vec()), tyck_args),
+                        Err(_) => {
+                            self.diag_arg_mismatch(param_types, &args, lparen_loc);
+                            return None;
+                        }
+                    }
+                },
+                Some(_) => {
+                    self.diag.diag(lparen_loc, diag_data::err_not_callable)
+                        .add_mark(lparen_loc.into())
+                        .emit();
+                    return None;
+                }
+            };
+
+        Some(Expr::FuncCallExpr(ArenaPtr::new_in(FuncCallExpr {
+            func: Either::Right(callee),
+            args,
+            ret_types,
+            tyck_args,
+            overloads: Vec::new(),
+            concrete: func_call_expr
+        }, self.arena)))
     }
 
-    fn visit_subscript_expr(&mut self, subscript_expr: &'s ConcreteSubscriptExpr<'s>) -> Self::ExprResult {
-        todo!()
+    fn visit_subscript_expr(
+        &mut self,
+        subscript_expr: &'s ConcreteSubscriptExpr<'s>
+    ) -> Self::ExprResult {
+        let base: Option<Expr<'s>> = self.visit_operand(&subscript_expr.base);
+        let index: Option<Expr<'s>> = self.visit_operand(&subscript_expr.idx);
+        let (base, index): (Expr<'s>, Expr<'s>) = (base?, index?);
+
+        let lbracket_loc: SourceLoc = subscript_expr.lbracket_loc;
+        let base_type: Option<NonNull<TyckInfo>> = base.get_type(self.arena);
+        let index_kind: OperandKind = self.operand_kind(index.get_type(self.arena));
+
+        let (mode, ty, tyck_base, expected_index_kind)
+            : (SubscriptMode, Option<NonNull<TyckInfo>>, bool, Option<OperandKind>) =
+            if self.operand_kind(base_type) == OperandKind::Dynamic {
+                (SubscriptMode::Undetermined, None, true, None)
+            } else if let Some(elem_type) = Self::is_vector_type(base_type.unwrap()) {
+                (SubscriptMode::ArrayIndex, Some(elem_type), false, Some(OperandKind::Int))
+            } else if base_type == Some(self.tyck_info_pool.get_object_type()) {
+                (SubscriptMode::ObjectIndex, None, false, Some(OperandKind::String))
+            } else {
+                self.diag.diag(lbracket_loc, diag_data::err_not_subscriptable)
+                    .add_mark(lbracket_loc.into())
+                    .emit();
+                return None;
+            };
+
+        let tyck_index: bool = match expected_index_kind {
+            None => false,
+            Some(_) if index_kind == OperandKind::Dynamic => true,
+            Some(expected_index_kind) if index_kind == expected_index_kind => false,
+            Some(_) => {
+                self.diag.diag(lbracket_loc, diag_data::err_invalid_subscript_index)
+                    .add_mark(lbracket_loc.into())
+                    .emit();
+                return None;
+            }
+        };
+
+        Some(Expr::SubscriptExpr(ArenaPtr::new_in(SubscriptExpr {
+            mode,
+            base,
+            index,
+            ty,
+            tyck_base,
+            tyck_index,
+            maybe_constant_folding: None,
+            concrete: subscript_expr
+        }, self.arena)))
     }
 
-    fn visit_field_ref_expr(&mut self, field_ref_expr: &'s ConcreteFieldRefExpr<'s>) -> Self::ExprResult {
-        todo!()
+    fn visit_field_ref_expr(
+        &mut self,
+        field_ref_expr: &'s ConcreteFieldRefExpr<'s>
+    ) -> Self::ExprResult {
+        let base: Expr<'s> = self.visit_operand(&field_ref_expr.base)?;
+        let base_type: Option<NonNull<TyckInfo>> = base.get_type(self.arena);
+
+        let tyck_base: bool = if self.operand_kind(base_type) == OperandKind::Dynamic {
+            true
+        } else if base_type == Some(self.tyck_info_pool.get_object_type()) {
+            false
+        } else {
+            let dot_loc: SourceLoc = field_ref_expr.dot_loc;
+            self.diag.diag(dot_loc, diag_data::err_field_ref_non_object)
+                .add_mark(field_ref_expr.id.source_range().into())
+                .emit();
+            return None;
+        };
+
+        let field: &'s str = match &field_ref_expr.id {
+            Identifier::Unqual(token) => token.get_str_value(),
+            // the parser only accepts unqualified identifiers here
+            Identifier::Qual(_) => unreachable!()
+        };
+
+        Some(Expr::FieldRefExpr(ArenaPtr::new_in(FieldRefExpr {
+            base,
+            field,
+            ty: None,
+            tyck_base,
+            maybe_constant_folding: None,
+            concrete: field_ref_expr
+        }, self.arena)))
     }
 
     fn visit_as_expr(&mut self, as_expr: &'s ConcreteAsExpr<'s>) -> Self::ExprResult {
-        todo!()
+        use OperandKind::*;
+
+        let expr: Option<Expr<'s>> = self.visit_operand(&as_expr.operand);
+        let as_type: Option<NonNull<TyckInfo>> = self.visit_type(&as_expr.dest_type);
+        let (expr, as_type): (Expr<'s>, NonNull<TyckInfo>) = (expr?, as_type?);
+
+        let src_type: Option<NonNull<TyckInfo>> = expr.get_type(self.arena);
+        let castable: bool = match (self.operand_kind(src_type), self.operand_kind(Some(as_type))) {
+            (_, Dynamic) | (Dynamic, _) => true,
+            (Int | Float | Bool | Char, Int) => true,
+            (Int | Float, Float) => true,
+            (Int | Bool, Bool) => true,
+            _ => self.check_assignable(Some(as_type), src_type).is_some()
+        };
+
+        if !castable {
+            let as_range: SourceRange = as_expr.as_range;
+            self.diag.diag(as_range.left(), diag_data::err_invalid_cast)
+                .add_mark(as_range.into())
+                .emit();
+            return None;
+        }
+
+        let maybe_constant_folding: Option<Value> = if let Some(value) = self.const_value(&expr) {
+            let value: Value = if self.operand_kind(src_type) == Char {
+                char_code_point(value)
+            } else {
+                value
+            };
+            let target: Option<CastTarget> = match self.operand_kind(Some(as_type)) {
+                Int => Some(CastTarget::Int),
+                Float => Some(CastTarget::Float),
+                Bool => Some(CastTarget::Bool),
+                _ => None
+            };
+            if let Some(target) = target {
+                if let Ok(value) = fold_cast(&value, target) {
+                    Some(value)
+                } else {
+                    let as_range: SourceRange = as_expr.as_range;
+                    self.diag.diag(as_range.left(), diag_data::err_const_invalid_cast)
+                        .add_mark(as_range.into())
+                        .emit();
+                    return None;
+                }
+            } else if self.check_assignable(Some(as_type), src_type) == Some(false) {
+                Some(value)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        Some(Expr::AsExpr(ArenaPtr::new_in(AsExpr {
+            expr,
+            as_type,
+            maybe_constant_folding,
+            concrete: as_expr
+        }, self.arena)))
     }
 
     fn visit_await_expr(&mut self, await_expr: &'s ConcreteAwaitExpr<'s>) -> Self::ExprResult {
-        todo!()
+        // there is no static typing for promises yet, so awaited values are always `any`
+        let expr: Expr<'s> = self.visit_operand(&await_expr.base)?;
+        Some(Expr::AwaitExpr(ArenaPtr::new_in(AwaitExpr {
+            expr,
+            ty: self.tyck_info_pool.get_any_type(),
+            tyck_expr: false,
+            concrete: await_expr
+        }, self.arena)))
+    }
+}
+
+impl<'s, 'd> TypeVisitor<'s> for SemaPhase2<'s, 'd> {
+    type TypeResult = Option<NonNull<TyckInfo>>;
+
+    fn visit_primitive_type(&mut self, primitive_type: &'s Token<'s>) -> Self::TypeResult {
+        Some(match primitive_type.token_inner {
+            TokenInner::KwdAny => self.tyck_info_pool.get_any_type(),
+            TokenInner::KwdBool => self.tyck_info_pool.get_bool_type(),
+            TokenInner::KwdChar => self.tyck_info_pool.get_char_type(),
+            TokenInner::KwdFloat => self.tyck_info_pool.get_float_type(),
+            TokenInner::KwdInt => self.tyck_info_pool.get_int_type(),
+            TokenInner::KwdObject => self.tyck_info_pool.get_object_type(),
+            TokenInner::KwdString => self.tyck_info_pool.get_string_type(),
+            TokenInner::KwdVoid => {
+                let range: SourceRange = primitive_type.range;
+                self.diag.diag(range.left(), diag_data::err_void_type_not_allowed)
+                    .add_mark(range.into())
+                    .emit();
+                return None;
+            },
+            _ => unreachable!("not a primitive type")
+        })
+    }
+
+    fn visit_generic_type(
+        &mut self,
+        generic_type: &'s ConcreteGenericType<'s>
+    ) -> Self::TypeResult {
+        #[cfg(debug_assertions)]
+        assert_eq!(generic_type.base.token_inner, TokenInner::KwdVector);
+
+        if generic_type.inner.len() != 1 {
+            self.diag.diag(generic_type.left_angle, diag_data::err_generic_param_count_0_1)
+                .add_arg(1)
+                .add_arg(generic_type.inner.len())
+                .add_mark(generic_type.base.range.into())
+                .emit();
+            return None;
+        }
+
+        let elem_type: NonNull<TyckInfo> = self.visit_type(&generic_type.inner[0])?;
+        Some(self.tyck_info_pool.create_container_type(
+            TypeId::of::<VMGenericVec>(),
+            &[elem_type]
+        ))
+    }
+
+    fn visit_nullable_type(
+        &mut self,
+        nullable_type: &'s ConcreteNullableType<'s>
+    ) -> Self::TypeResult {
+        let inner: NonNull<TyckInfo> = self.visit_type(&nullable_type.inner)?;
+        Some(self.tyck_info_pool.create_nullable_type(inner))
+    }
+
+    fn visit_deduced_type(&mut self, deduced_type_source_range: SourceRange) -> Self::TypeResult {
+        self.diag.diag(deduced_type_source_range.left(), diag_data::err_deduced_type_not_allowed)
+            .add_mark(deduced_type_source_range.into())
+            .emit();
+        None
+    }
+
+    fn visit_user_type(&mut self, user_type: &'s Identifier<'s>) -> Self::TypeResult {
+        let ty: Option<NonNull<TyckInfo>> = match user_type {
+            Identifier::Unqual(token) => {
+                let name: &str = token.get_str_value();
+                self.scope.lookup_type(name).or_else(|| {
+                    let exception_types: Option<&HashMap<String, TypeId>> = self.exception_types;
+                    vm_error_type_id(name)
+                        .or_else(|| exception_types?.get(name).copied())
+                        .map(|type_id: TypeId| self.tyck_info_pool.create_plain_type(type_id))
+                })
+            },
+            Identifier::Qual(_) => None
+        };
+
+        if ty.is_none() {
+            let range: SourceRange = user_type.source_range();
+            self.diag.diag(range.left(), diag_data::err_undefined_type_0)
+                .add_arg(ident_to_string(user_type))
+                .add_mark(range.into())
+                .emit();
+        }
+        ty
+    }
+}
+
+fn ident_to_string(id: &Identifier<'_>) -> String {
+    match id {
+        Identifier::Unqual(token) => token.get_str_value().to_string(),
+        Identifier::Qual(tokens) => tokens.iter()
+            .map(Token::get_str_value)
+            .collect::<Vec<&str>>()
+            .join("::")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::ptr::NonNull;
+
+    use xjbutil::either::Either;
+    use xjbutil::value::Value;
+
+    use crate::data::tyck::{TyckInfo, TyckInfoPool};
+    use crate::diag::DiagContext;
+    use crate::parse::parser::Parser;
+    use crate::sema::arena::{Arena, ArenaPtr};
+    use crate::sema::decl::ObjectDecl;
+    use crate::sema::expr::{Expr, ResolvedBinaryOp, ResolvedUnaryOp, SubscriptMode};
+    use crate::sema::phase2::SemaPhase2;
+    use crate::sema::scope::{Scope, ScopeKind};
+    use crate::sema::decl_context::DeclContext;
+    use crate::syntax::ConcreteProgram;
+    use crate::syntax::decl::{ConcreteDecl, ConcreteObjectDecl};
+    use crate::syntax::expr::ConcreteExpr;
+    use crate::syntax::stmt::ConcreteStmt;
+    use crate::syntax::visitor::{ExprVisitor, TypeVisitor};
+
+    // concrete syntax trees are invariant over their lifetime, leaking them is the easiest way
+    // to make them outlive the arena in tests
+
+    /// Parses an object declaration, returns it and whether it is a constant
+    fn parse_decl(source: &'static str) -> (&'static ConcreteObjectDecl<'static>, bool) {
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(0, source, &diag);
+        match parser.parse_stmt(&[]).unwrap() {
+            ConcreteStmt::DeclStmt(decl, _) => match *decl {
+                ConcreteDecl::ConstDecl(decl) => (Box::leak(Box::new(decl)), true),
+                ConcreteDecl::VarDecl(decl) => (Box::leak(Box::new(decl)), false),
+                _ => panic!("should be an object declaration")
+            },
+            _ => panic!("should be a declaration statement")
+        }
+    }
+
+    fn parse_expr(source: &'static str) -> &'static ConcreteExpr<'static> {
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(0, source, &diag);
+        Box::leak(Box::new(parser.parse_expression(&[]).unwrap()))
+    }
+
+    fn parse_program(source: &'static str) -> &'static ConcreteProgram<'static> {
+        let diag: &'static RefCell<DiagContext> =
+            Box::leak(Box::new(RefCell::new(DiagContext::new())));
+        let mut parser: Parser = Parser::new(0, source, diag);
+        Box::leak(Box::new(parser.parse()))
+    }
+
+    fn check_program(source: &'static str) {
+        let program: &ConcreteProgram = parse_program(source);
+
+        let mut arena: Arena = Arena::new();
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let mut diag: DiagContext = DiagContext::new();
+        let mut sema: SemaPhase2 = SemaPhase2::new(
+            Scope::new(ScopeKind::Global),
+            &mut arena,
+            &mut tyck_info_pool,
+            &mut diag
+        );
+        let decl_context: DeclContext = sema.check_program(program);
+        assert_eq!(decl_context.func_decls.len(), 2);
+        assert!(!diag.has_error());
+    }
+
+    /// Checks `exprs` in a function scope where `decls` are declared, then passes the checked
+    /// expressions to `f`
+    fn check_exprs(
+        decls: &[&'static str],
+        exprs: &[&'static str],
+        f: impl FnOnce(&Arena<'static>, &[Expr<'static>])
+    ) {
+        let mut arena: Arena = Arena::new();
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let mut diag: DiagContext = DiagContext::new();
+        let mut sema: SemaPhase2 = SemaPhase2::new(
+            Scope::new(ScopeKind::Function),
+            &mut arena,
+            &mut tyck_info_pool,
+            &mut diag
+        );
+        for decl /*: &&str*/ in decls.iter() {
+            let (decl, is_const): (&ConcreteObjectDecl, bool) = parse_decl(decl);
+            declare(&mut sema, decl, is_const);
+        }
+
+        let exprs: Vec<Expr> = exprs.iter()
+            .map(|expr: &&'static str| sema.visit_expr(parse_expr(expr)).unwrap())
+            .collect();
+        f(sema.arena(), &exprs);
+    }
+
+    fn declare<'s>(
+        sema: &mut SemaPhase2<'s, '_>,
+        concrete: &'s ConcreteObjectDecl<'s>,
+        is_const: bool
+    ) {
+        let init: Expr<'s> = sema.visit_expr(&concrete.init_expr).unwrap();
+        let ty: NonNull<TyckInfo> = if let Some(obj_type) = &concrete.obj_type {
+            sema.visit_type(obj_type).unwrap()
+        } else {
+            init.get_type(sema.arena()).unwrap()
+        };
+        let name: &'s str = concrete.name.as_unqual().unwrap().get_str_value();
+        let decl: ArenaPtr<'s, ObjectDecl<'s>> = ArenaPtr::new_in(ObjectDecl {
+            name,
+            is_const,
+            ty,
+            init: Some(init),
+            concrete: Some(concrete)
+        }, sema.arena());
+        sema.scope_mut().object_decls.insert(name, decl);
+    }
+
+    fn expect_binary_op<'s>(arena: &Arena<'s>, expr: &Expr<'s>, expected: ResolvedBinaryOp) {
+        if let Expr::BinaryExpr(binary_expr) = expr {
+            assert_eq!(binary_expr.get(arena).op as u8, expected as u8);
+        } else {
+            panic!("should be a binary expression")
+        }
+    }
+
+    #[test]
+    fn test_sema_binary_expr() {
+        let decls: &[&str] = &[
+            "var a = 1;",
+            "var b = 2.0e0;",
+            "var c any = 3;",
+            "var d vector<int> = c;",
+            "var e = \"114514\";"
+        ];
+        let exprs: &[&str] = &[
+            "a + a * 2",
+            "b - b",
+            "a + c",
+            "d[a] << a",
+            "e + e",
+            "-b",
+            "!(a < a)",
+            "c[a]",
+            "a += 1",
+            "b as int"
+        ];
+
+        check_exprs(decls, exprs, |arena: &Arena, exprs: &[Expr]| {
+            expect_binary_op(arena, &exprs[0], ResolvedBinaryOp::IntAdd);
+            expect_binary_op(arena, &exprs[1], ResolvedBinaryOp::FloatMinus);
+            expect_binary_op(arena, &exprs[2], ResolvedBinaryOp::Add);
+            assert!(exprs[2].get_type(arena).is_none());
+            expect_binary_op(arena, &exprs[3], ResolvedBinaryOp::ShiftLeft);
+            expect_binary_op(arena, &exprs[4], ResolvedBinaryOp::StringAdd);
+            if let Expr::UnaryExpr(unary_expr) = &exprs[5] {
+                assert_eq!(unary_expr.get(arena).op as u8, ResolvedUnaryOp::FloatNegation as u8);
+            } else {
+                panic!("should be an unary expression")
+            }
+            if let Expr::UnaryExpr(unary_expr) = &exprs[6] {
+                assert_eq!(unary_expr.get(arena).op as u8, ResolvedUnaryOp::LogicalNot as u8);
+            } else {
+                panic!("should be an unary expression")
+            }
+            if let Expr::SubscriptExpr(subscript_expr) = &exprs[7] {
+                let subscript_expr = subscript_expr.get(arena);
+                assert_eq!(subscript_expr.mode as u8, SubscriptMode::Undetermined as u8);
+                assert!(subscript_expr.tyck_base);
+            } else {
+                panic!("should be a subscript expression")
+            }
+            if let Expr::AssignExpr(assign_expr) = &exprs[8] {
+                let assign_expr = assign_expr.get(arena);
+                assert!(matches!(assign_expr.lhs, Expr::IdRefExpr(_)));
+                assert!(matches!(assign_expr.rhs, Expr::LiteralExpr(_)));
+                let (op, _) = assign_expr.compound_op.unwrap();
+                assert_eq!(op as u8, ResolvedBinaryOp::IntAdd as u8);
+            } else {
+                panic!("should be an assignment expression")
+            }
+            assert!(matches!(exprs[9], Expr::AsExpr(_)));
+        });
+    }
+
+    #[test]
+    fn test_sema_id_ref_expr() {
+        check_exprs(&["const a = 1;"], &["a"], |arena: &Arena, exprs: &[Expr]| {
+            if let Expr::IdRefExpr(id_ref_expr) = &exprs[0] {
+                assert!(matches!(id_ref_expr.get(arena).decl, Either::Left(_)));
+            } else {
+                panic!("should be an id-ref expression")
+            }
+        });
+    }
+
+    #[test]
+    fn test_sema_constant_folding() {
+        let exprs: &[&str] = &[
+            "a * 7",
+            "-a as float",
+            "(a << 2) == 24",
+            "\"114\" + \"514\" != \"1919810\"",
+            "a as any",
+            "s",
+            "\"a\\tb\\u{5B57}\" + `\\n`",
+            "s == \"ab\""
+        ];
+        let decls: &[&str] = &["const a = 6;", "const s = \"a\" + \"b\";"];
+
+        check_exprs(decls, exprs, |arena: &Arena, exprs: &[Expr]| {
+            assert_eq!(exprs[0].get_const_fold_value(arena), Some(&Value::Int(42)));
+            assert_eq!(exprs[1].get_const_fold_value(arena), Some(&Value::Float(-6.0)));
+            assert_eq!(exprs[2].get_const_fold_value(arena), Some(&Value::Bool(true)));
+            assert_eq!(exprs[3].get_const_fold_value(arena), Some(&Value::Bool(true)));
+            assert_eq!(exprs[4].get_const_fold_value(arena), Some(&Value::Int(6)));
+            // escapes in regular string literals are decoded, raw ones are kept
+            assert_eq!(exprs[5].get_const_fold_value(arena), Some(&Value::String("ab".into())));
+            assert_eq!(
+                exprs[6].get_const_fold_value(arena),
+                Some(&Value::String("a\tb\u{5B57}\\n".into()))
+            );
+            assert_eq!(exprs[7].get_const_fold_value(arena), Some(&Value::Bool(true)));
+        });
+    }
+
+    #[test]
+    fn test_sema_char_expr() {
+        let exprs: &[&str] = &["c < 'b'", "c as int", "'a' >= 'b'", "'a' as int", "'a' == 'a'"];
+
+        check_exprs(&["var c = 'a';"], exprs, |arena: &Arena, exprs: &[Expr]| {
+            expect_binary_op(arena, &exprs[0], ResolvedBinaryOp::IntLessThan);
+            assert!(exprs[0].get_const_fold_value(arena).is_none());
+            assert!(matches!(exprs[1], Expr::AsExpr(_)));
+            assert!(exprs[1].get_const_fold_value(arena).is_none());
+            assert_eq!(exprs[2].get_const_fold_value(arena), Some(&Value::Bool(false)));
+            assert_eq!(exprs[3].get_const_fold_value(arena), Some(&Value::Int(97)));
+            assert_eq!(exprs[4].get_const_fold_value(arena), Some(&Value::Bool(true)));
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sema_const_shift_overflow() {
+        check_exprs(&[], &["1 << 64"], |_, _| {});
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sema_assign_to_const() {
+        check_exprs(&["const a = 1;"], &["a = 2"], |_, _| {});
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sema_invalid_binary_operands() {
+        check_exprs(&[], &["1 + true"], |_, _| {});
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sema_undefined_identifier() {
+        check_exprs(&[], &["foo::bar"], |_, _| {});
+    }
+
+    #[test]
+    fn test_sema_check_program() {
+        check_program(r#"
+            const LIMIT = 10 * 10;
+
+            func sum(n int): int {
+                var s = 0;
+                var i = 0;
+                while i < n {
+                    if i > LIMIT {
+                        throw "too large";
+                    }
+                    s += i;
+                    i += 1;
+                }
+                return s;
+            }
+
+            func main(): int {
+                try {
+                    return sum(10);
+                } catch (e string) {
+                    return -1;
+                }
+            }
+        "#);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sema_missing_return() {
+        check_program(r#"
+            func abs(x int): int {
+                if x < 0 {
+                    return -x;
+                }
+            }
+
+            func main() {}
+        "#);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sema_condition_not_bool() {
+        check_program(r#"
+            func loop_forever() {
+                while 1 {}
+            }
+
+            func main() {}
+        "#);
     }
 }
@@ -0,0 +1,268 @@
+use super::SemaPhase2;
+
+use std::ptr::NonNull;
+
+use xjbutil::either::Either;
+
+use crate::data::tyck::TyckInfo;
+use crate::diag::diag_data;
+use crate::diag::location::{SourceLoc, SourceRange};
+use crate::sema::arena::ArenaPtr;
+use crate::sema::decl::{FuncDecl, ObjectDecl};
+use crate::sema::decl_context::DeclContext;
+use crate::sema::expr::Expr;
+use crate::sema::scope::ScopeKind;
+use crate::sema::stmt::CompoundStmt;
+use crate::syntax::ConcreteProgram;
+use crate::syntax::decl::{ConcreteDecl, ConcreteFuncDecl, ConcreteObjectDecl, FunctionParam};
+use crate::syntax::id::Identifier;
+use crate::syntax::stmt::ConcreteCompoundStmt;
+use crate::syntax::ty::ConcreteType;
+use crate::syntax::visitor::{DeclVisitor, TypeVisitor};
+
+impl<'s, 'd> SemaPhase2<'s, 'd> {
+    /// Checks all declarations of a program. Function signatures are collected first so that
+    /// functions can be called before being declared, then constants and global variables are
+    /// checked in order, and function bodies get checked at last.
+    pub fn check_program(&mut self, program: &'s ConcreteProgram<'s>) -> DeclContext<'s> {
+        let mut decl_context: DeclContext<'s> = DeclContext {
+            object_decls: Vec::new(),
+            func_decls: Vec::new()
+        };
+
+        for decl /*: &ConcreteDecl*/ in program.decls.iter() {
+            if let ConcreteDecl::FuncDecl(func_decl) = decl {
+                if let Some(func_decl) = self.declare_func(func_decl) {
+                    decl_context.func_decls.push(func_decl);
+                }
+            }
+        }
+
+        for decl /*: &ConcreteDecl*/ in program.decls.iter() {
+            let object_decl: Option<ArenaPtr<'s, ObjectDecl<'s>>> = match decl {
+                ConcreteDecl::ConstDecl(const_decl) => self.check_object_decl(const_decl, true),
+                ConcreteDecl::VarDecl(var_decl) => self.check_object_decl(var_decl, false),
+                _ => continue
+            };
+            if let Some(object_decl) = object_decl {
+                decl_context.object_decls.push(object_decl);
+            }
+        }
+
+        for func_decl /*: &ArenaPtr<FuncDecl>*/ in decl_context.func_decls.iter() {
+            self.check_func_body(*func_decl);
+        }
+
+        decl_context
+    }
+
+    /// Checks the signature of a function and adds it to current scope, without checking its
+    /// body.
+    pub fn declare_func(
+        &mut self,
+        func_decl: &'s ConcreteFuncDecl<'s>
+    ) -> Option<ArenaPtr<'s, FuncDecl<'s>>> {
+        let name: &'s str = unqual_name(&func_decl.func_name);
+        if self.scope.object_decls.contains_key(name) {
+            self.diag_redefinition(&func_decl.func_name);
+            return None;
+        }
+
+        let mut failed: bool = false;
+        let mut param_decls: Vec<ArenaPtr<'s, ObjectDecl<'s>>> =
+            Vec::with_capacity(func_decl.func_param_list.len());
+        for (i, param) /*: (usize, &FunctionParam)*/ in
+            func_decl.func_param_list.iter().enumerate()
+        {
+            let param_name: &'s str = unqual_name(&param.param_name);
+            let duplicate: bool = func_decl.func_param_list[..i].iter()
+                .any(|prev_param: &FunctionParam<'s>|
+                    unqual_name(&prev_param.param_name) == param_name);
+            if duplicate {
+                self.diag_redefinition(&param.param_name);
+                failed = true;
+                continue;
+            }
+
+            let ty: Option<NonNull<TyckInfo>> = if let Some(param_type) = &param.param_type {
+                self.visit_type(param_type)
+            } else {
+                Some(self.tyck_info_pool.get_any_type())
+            };
+            if let Some(ty) = ty {
+                param_decls.push(ArenaPtr::new_in(ObjectDecl {
+                    name: param_name,
+                    is_const: false,
+                    ty,
+                    init: None,
+                    concrete: None
+                }, self.arena));
+            } else {
+                failed = true;
+            }
+        }
+
+        let mut ret_types: Vec<NonNull<TyckInfo>> =
+            Vec::with_capacity(func_decl.func_return_types.len());
+        for ret_type /*: &ConcreteType*/ in func_decl.func_return_types.iter() {
+            if let Some(ret_type) = self.visit_type(ret_type) {
+                ret_types.push(ret_type);
+            } else {
+                failed = true;
+            }
+        }
+
+        let mut exception_spec: Vec<NonNull<TyckInfo>> = Vec::new();
+        if let Some(concrete_exception_spec) = &func_decl.exception_spec {
+            for exc_type /*: &ConcreteType*/ in concrete_exception_spec.exc_list.iter() {
+                if let Some(exc_type) = self.visit_type(exc_type) {
+                    exception_spec.push(exc_type);
+                } else {
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            return None;
+        }
+
+        let func_decl: ArenaPtr<'s, FuncDecl<'s>> = ArenaPtr::new_in(FuncDecl {
+            name,
+            param_decl_context: DeclContext {
+                object_decls: param_decls,
+                func_decls: Vec::new()
+            },
+            ret_types,
+            exception_spec,
+            func_body: None,
+            concrete: func_decl
+        }, self.arena);
+        self.scope.func_decls.entry(name).or_default().push(func_decl);
+        Some(func_decl)
+    }
+
+    /// Checks the body of a function declared with `declare_func`
+    pub fn check_func_body(&mut self, func_decl: ArenaPtr<'s, FuncDecl<'s>>) {
+        let func_decl_ref: &FuncDecl<'s> = func_decl.get_tricky(self.arena);
+        let concrete_body: &'s ConcreteCompoundStmt<'s> =
+            if let Some(concrete_body) = &func_decl_ref.concrete.func_body {
+                concrete_body
+            } else {
+                return;
+            };
+        let params: Vec<ArenaPtr<'s, ObjectDecl<'s>>> =
+            func_decl_ref.param_decl_context.object_decls.clone();
+
+        self.enter_scope(ScopeKind::Function);
+        for param /*: ArenaPtr<ObjectDecl>*/ in params {
+            let name: &'s str = param.get_tricky(self.arena).name;
+            self.scope.object_decls.insert(name, param);
+        }
+        let outer_func: Option<ArenaPtr<'s, FuncDecl<'s>>> = self.current_func.replace(func_decl);
+
+        let body: Option<CompoundStmt<'s>> = self.check_compound_stmt(concrete_body);
+
+        self.current_func = outer_func;
+        self.leave_scope();
+
+        let body: CompoundStmt<'s> = if let Some(body) = body { body } else { return; };
+        let func_decl_ref: &FuncDecl<'s> = func_decl.get_tricky(self.arena);
+        if !func_decl_ref.ret_types.is_empty() && !body.always_exits() {
+            let rbrace_loc: SourceLoc = concrete_body.right_brace_loc;
+            self.diag.diag(rbrace_loc, diag_data::err_missing_return_0)
+                .add_arg(func_decl_ref.name)
+                .add_mark(rbrace_loc.into())
+                .emit();
+            return;
+        }
+
+        func_decl.get_mut(self.arena).func_body = Some(body);
+    }
+
+    fn check_object_decl(
+        &mut self,
+        object_decl: &'s ConcreteObjectDecl<'s>,
+        is_const: bool
+    ) -> Option<ArenaPtr<'s, ObjectDecl<'s>>> {
+        let init: Option<Expr<'s>> = self.visit_operand(&object_decl.init_expr);
+        let declared_type: Option<Option<NonNull<TyckInfo>>> = object_decl.obj_type.as_ref()
+            .map(|obj_type: &'s ConcreteType<'s>| self.visit_type(obj_type));
+        let init: Expr<'s> = init?;
+
+        let init_type: Option<NonNull<TyckInfo>> = init.get_type(self.arena);
+        let ty: NonNull<TyckInfo> = match declared_type {
+            Some(declared_type) => {
+                let declared_type: NonNull<TyckInfo> = declared_type?;
+                if self.check_assignable(Some(declared_type), init_type).is_none() {
+                    let eq_range: SourceRange = object_decl.eq_range;
+                    self.diag.diag(eq_range.left(), diag_data::err_init_type_mismatch)
+                        .add_mark(eq_range.into())
+                        .emit();
+                    return None;
+                }
+                declared_type
+            },
+            None => init_type.unwrap_or_else(|| self.tyck_info_pool.get_any_type())
+        };
+
+        let name: &'s str = unqual_name(&object_decl.name);
+        if is_const && self.current_func.is_none() && self.const_value(&init).is_none() {
+            let range: SourceRange = object_decl.name.source_range();
+            self.diag.diag(range.left(), diag_data::err_top_level_const_not_constant_0)
+                .add_arg(name)
+                .add_mark(range.into())
+                .emit();
+            return None;
+        }
+
+        if self.scope.check_name_collision(name) {
+            self.diag_redefinition(&object_decl.name);
+            return None;
+        }
+
+        let decl: ArenaPtr<'s, ObjectDecl<'s>> = ArenaPtr::new_in(ObjectDecl {
+            name,
+            is_const,
+            ty,
+            init: Some(init),
+            concrete: Some(object_decl)
+        }, self.arena);
+        self.scope.object_decls.insert(name, decl);
+        Some(decl)
+    }
+
+    fn diag_redefinition(&mut self, id: &'s Identifier<'s>) {
+        let range: SourceRange = id.source_range();
+        self.diag.diag(range.left(), diag_data::err_redefinition_0)
+            .add_arg(unqual_name(id))
+            .add_mark(range.into())
+            .emit();
+    }
+}
+
+impl<'s, 'd> DeclVisitor<'s> for SemaPhase2<'s, 'd> {
+    type DeclResult = Option<Either<ArenaPtr<'s, ObjectDecl<'s>>, ArenaPtr<'s, FuncDecl<'s>>>>;
+
+    fn visit_const_decl(&mut self, const_decl: &'s ConcreteObjectDecl<'s>) -> Self::DeclResult {
+        self.check_object_decl(const_decl, true).map(Either::Left)
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &'s ConcreteFuncDecl<'s>) -> Self::DeclResult {
+        let func_decl: ArenaPtr<'s, FuncDecl<'s>> = self.declare_func(func_decl)?;
+        self.check_func_body(func_decl);
+        Some(Either::Right(func_decl))
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &'s ConcreteObjectDecl<'s>) -> Self::DeclResult {
+        self.check_object_decl(var_decl, false).map(Either::Left)
+    }
+}
+
+/// Declared names are always unqualified, since the parser rejects qualified ones
+fn unqual_name<'s>(id: &Identifier<'s>) -> &'s str {
+    match id {
+        Identifier::Unqual(token) => token.get_str_value(),
+        Identifier::Qual(_) => unreachable!("declared names should be unqualified")
+    }
+}
@@ -0,0 +1,362 @@
+use super::{OperandKind, SemaPhase2};
+
+use std::ptr::NonNull;
+
+use xjbutil::either::Either;
+
+use crate::data::tyck::TyckInfo;
+use crate::diag::diag_data;
+use crate::diag::location::{SourceLoc, SourceRange};
+use crate::sema::arena::ArenaPtr;
+use crate::sema::decl::{FuncDecl, ObjectDecl};
+use crate::sema::expr::{Expr, FuncCallExpr};
+use crate::sema::scope::ScopeKind;
+use crate::sema::stmt::{
+    CatchClause,
+    CompoundStmt,
+    DoWhileStmt,
+    IfStmt,
+    RethrowStmt,
+    ReturnStmt,
+    SpawnStmt,
+    Stmt,
+    ThrowStmt,
+    TryStmt,
+    WhileStmt
+};
+use crate::syntax::decl::ConcreteDecl;
+use crate::syntax::expr::ConcreteExpr;
+use crate::syntax::stmt::{
+    ConcreteCatchClause,
+    ConcreteCompoundStmt,
+    ConcreteDoWhileStmt,
+    ConcreteIfStmt,
+    ConcreteRethrowStmt,
+    ConcreteReturnStmt,
+    ConcreteSpawnStmt,
+    ConcreteThrowStmt,
+    ConcreteTryStmt,
+    ConcreteWhileStmt
+};
+use crate::syntax::visitor::{DeclVisitor, ExprVisitor, StmtVisitor, TypeVisitor};
+
+impl<'s, 'd> SemaPhase2<'s, 'd> {
+    pub fn check_compound_stmt(
+        &mut self,
+        compound_stmt: &'s ConcreteCompoundStmt<'s>
+    ) -> Option<CompoundStmt<'s>> {
+        self.enter_scope(ScopeKind::Local);
+
+        let mut failed: bool = false;
+        let mut stmts: Vec<Stmt<'s>> = Vec::with_capacity(compound_stmt.stmts.len());
+        for stmt /*: &ConcreteStmt*/ in compound_stmt.stmts.iter() {
+            if let Some(stmt) = self.visit_stmt(stmt) {
+                stmts.push(stmt);
+            } else {
+                failed = true;
+            }
+        }
+
+        self.leave_scope();
+
+        if failed {
+            None
+        } else {
+            Some(CompoundStmt {
+                stmts,
+                concrete: compound_stmt
+            })
+        }
+    }
+
+    /// Checks the condition of `if`, `while` and `do-while` statements, returns the condition
+    /// expression and whether run time type checking is required
+    fn check_condition(
+        &mut self,
+        cond_expr: &'s ConcreteExpr<'s>,
+        kwd_range: SourceRange
+    ) -> Option<(Expr<'s>, bool)> {
+        let cond: Expr<'s> = self.visit_operand(cond_expr)?;
+        match self.operand_kind(cond.get_type(self.arena)) {
+            OperandKind::Bool => Some((cond, false)),
+            OperandKind::Dynamic => Some((cond, true)),
+            _ => {
+                self.diag.diag(kwd_range.left(), diag_data::err_condition_not_bool)
+                    .add_mark(kwd_range.into())
+                    .emit();
+                None
+            }
+        }
+    }
+
+    /// Only heap objects can be thrown, since the VM locates exception handlers by the run time
+    /// type of thrown objects. Value types (`int`, `float`, `char` and `bool`) and nullable types
+    /// are rejected.
+    fn is_throwable(&self, ty: NonNull<TyckInfo>) -> bool {
+        matches!(unsafe { ty.as_ref() }, TyckInfo::Plain(_))
+            && !matches!(
+                self.operand_kind(Some(ty)),
+                OperandKind::Int | OperandKind::Float | OperandKind::Char | OperandKind::Bool
+            )
+    }
+
+    fn check_catch_clause(
+        &mut self,
+        catch_clause: &'s ConcreteCatchClause<'s>
+    ) -> Option<CatchClause<'s>> {
+        // `any` catches all exceptions
+        let catch_type: NonNull<TyckInfo> = self.visit_type(&catch_clause.catch_type)?;
+        if !unsafe { catch_type.as_ref() }.is_any() && !self.is_throwable(catch_type) {
+            let lparen_loc: SourceLoc = catch_clause.lparen_loc;
+            self.diag.diag(lparen_loc, diag_data::err_invalid_catch_type)
+                .add_mark(catch_clause.catch_var.source_range().into())
+                .emit();
+            return None;
+        }
+
+        let name: &'s str = catch_clause.catch_var.as_unqual()
+            .expect("catch variables should be unqualified")
+            .get_str_value();
+        let catch_var: ArenaPtr<'s, ObjectDecl<'s>> = ArenaPtr::new_in(ObjectDecl {
+            name,
+            is_const: false,
+            ty: catch_type,
+            init: None,
+            concrete: None
+        }, self.arena);
+
+        self.enter_scope(ScopeKind::Local);
+        self.scope.object_decls.insert(name, catch_var);
+        self.catch_rethrows.push(false);
+        let body: Option<CompoundStmt<'s>> = self.check_compound_stmt(&catch_clause.body);
+        let rethrown: bool = self.catch_rethrows.pop().unwrap();
+        self.leave_scope();
+
+        Some(CatchClause {
+            catch_var,
+            catch_type,
+            body: body?,
+            rethrown,
+            concrete: catch_clause
+        })
+    }
+}
+
+impl<'s, 'd> StmtVisitor<'s> for SemaPhase2<'s, 'd> {
+    type StmtResult = Option<Stmt<'s>>;
+
+    fn visit_compound_stmt(
+        &mut self,
+        compound_stmt: &'s ConcreteCompoundStmt<'s>
+    ) -> Self::StmtResult {
+        self.check_compound_stmt(compound_stmt).map(Stmt::CompoundStmt)
+    }
+
+    fn visit_expr_stmt(&mut self, expr: &'s ConcreteExpr<'s>) -> Self::StmtResult {
+        self.visit_expr(expr).map(Stmt::ExprStmt)
+    }
+
+    fn visit_decl_stmt(&mut self, decl: &'s ConcreteDecl<'s>) -> Self::StmtResult {
+        match self.visit_decl(decl) {
+            Some(Some(Either::Left(object_decl))) => Some(Stmt::DeclStmt(object_decl)),
+            Some(None) => None,
+            // the parser only accepts constant and variable declarations in statements
+            _ => unreachable!()
+        }
+    }
+
+    fn visit_if_stmt(&mut self, if_stmt: &'s ConcreteIfStmt<'s>) -> Self::StmtResult {
+        let cond: Option<(Expr<'s>, bool)> =
+            self.check_condition(&if_stmt.cond_expr, if_stmt.if_kwd_range);
+        let then_clause: Option<CompoundStmt<'s>> =
+            self.check_compound_stmt(&if_stmt.then_clause);
+        let else_clause: Option<Option<Stmt<'s>>> =
+            if let Some(else_clause) = &if_stmt.else_clause {
+                self.visit_stmt(else_clause).map(Some)
+            } else {
+                Some(None)
+            };
+        let ((cond, tyck_cond), then_clause, else_clause) = (cond?, then_clause?, else_clause?);
+
+        Some(Stmt::IfStmt(Box::new(IfStmt {
+            cond,
+            tyck_cond,
+            then_clause,
+            else_clause,
+            concrete: if_stmt
+        })))
+    }
+
+    fn visit_while_stmt(&mut self, while_stmt: &'s ConcreteWhileStmt<'s>) -> Self::StmtResult {
+        let cond: Option<(Expr<'s>, bool)> =
+            self.check_condition(&while_stmt.cond_expr, while_stmt.while_kwd_range);
+        let body: Option<CompoundStmt<'s>> = self.check_compound_stmt(&while_stmt.body);
+        let ((cond, tyck_cond), body) = (cond?, body?);
+
+        Some(Stmt::WhileStmt(Box::new(WhileStmt {
+            cond,
+            tyck_cond,
+            body,
+            concrete: while_stmt
+        })))
+    }
+
+    fn visit_do_while_stmt(
+        &mut self,
+        do_while_stmt: &'s ConcreteDoWhileStmt<'s>
+    ) -> Self::StmtResult {
+        let body: Option<CompoundStmt<'s>> = self.check_compound_stmt(&do_while_stmt.body);
+        let cond: Option<(Expr<'s>, bool)> = 
+            self.check_condition(&do_while_stmt.cond_expr, do_while_stmt.while_kwd_range);
+        let (body, (cond, tyck_cond)) = (body?, cond?);
+
+        Some(Stmt::DoWhileStmt(Box::new(DoWhileStmt {
+            body,
+            cond,
+            tyck_cond,
+            concrete: do_while_stmt
+        })))
+    }
+
+    fn visit_return_stmt(&mut self, return_stmt: &'s ConcreteReturnStmt<'s>) -> Self::StmtResult {
+        let mut values: Vec<Expr<'s>> = Vec::with_capacity(return_stmt.return_values.len());
+        let mut failed: bool = false;
+        for return_value /*: &ConcreteExpr*/ in return_stmt.return_values.iter() {
+            if let Some(value) = self.visit_operand(return_value) {
+                values.push(value);
+            } else {
+                failed = true;
+            }
+        }
+        if failed {
+            return None;
+        }
+
+        let current_func: ArenaPtr<'s, FuncDecl<'s>> =
+            self.current_func.expect("return statement outside function");
+        let ret_types: Vec<NonNull<TyckInfo>> =
+            current_func.get_tricky(self.arena).ret_types.clone();
+        let return_kwd_range: SourceRange = return_stmt.return_kwd_range;
+        if ret_types.len() != values.len() {
+            self.diag.diag(return_kwd_range.left(), diag_data::err_return_count_mismatch_0_1)
+                .add_arg(ret_types.len())
+                .add_arg(values.len())
+                .add_mark(return_kwd_range.into())
+                .emit();
+            return None;
+        }
+
+        let mut tyck_values: bool = false;
+        for (i, (ret_type, value)) /*: (usize, (&NonNull<TyckInfo>, &Expr))*/ in
+            ret_types.iter().zip(values.iter()).enumerate()
+        {
+            match self.check_assignable(Some(*ret_type), value.get_type(self.arena)) {
+                Some(tyck) => tyck_values |= tyck,
+                None => {
+                    self.diag.diag(return_kwd_range.left(), diag_data::err_return_type_mismatch_0)
+                        .add_arg(i + 1)
+                        .add_mark(return_kwd_range.into())
+                        .emit();
+                    return None;
+                }
+            }
+        }
+
+        Some(Stmt::ReturnStmt(ReturnStmt {
+            values,
+            tyck_values,
+            concrete: return_stmt
+        }))
+    }
+
+    fn visit_throw_stmt(&mut self, throw_stmt: &'s ConcreteThrowStmt<'s>) -> Self::StmtResult {
+        let thrown: Expr<'s> = self.visit_operand(&throw_stmt.thrown_expr)?;
+        let throwable: bool = match thrown.get_type(self.arena) {
+            Some(ty) => self.is_throwable(ty),
+            None => false
+        };
+        if !throwable {
+            let throw_kwd_range: SourceRange = throw_stmt.throw_kwd_range;
+            self.diag.diag(throw_kwd_range.left(), diag_data::err_invalid_throw_type)
+                .add_mark(throw_kwd_range.into())
+                .emit();
+            return None;
+        }
+
+        Some(Stmt::ThrowStmt(ThrowStmt {
+            thrown,
+            concrete: throw_stmt
+        }))
+    }
+
+    fn visit_rethrow_stmt(&mut self, rethrow_stmt: &'s ConcreteRethrowStmt) -> Self::StmtResult {
+        if let Some(rethrown) = self.catch_rethrows.last_mut() {
+            *rethrown = true;
+        } else {
+            let rethrow_kwd_range: SourceRange = rethrow_stmt.rethrow_kwd_range;
+            self.diag.diag(rethrow_kwd_range.left(), diag_data::err_rethrow_outside_catch)
+                .add_mark(rethrow_kwd_range.into())
+                .emit();
+            return None;
+        }
+
+        Some(Stmt::RethrowStmt(RethrowStmt { concrete: rethrow_stmt }))
+    }
+
+    fn visit_try_stmt(&mut self, try_stmt: &'s ConcreteTryStmt<'s>) -> Self::StmtResult {
+        let body: Option<CompoundStmt<'s>> = self.check_compound_stmt(&try_stmt.body);
+        let mut catch_clauses: Vec<CatchClause<'s>> =
+            Vec::with_capacity(try_stmt.catch_clauses.len());
+        let mut failed: bool = false;
+        for catch_clause /*: &ConcreteCatchClause*/ in try_stmt.catch_clauses.iter() {
+            if let Some(catch_clause) = self.check_catch_clause(catch_clause) {
+                catch_clauses.push(catch_clause);
+            } else {
+                failed = true;
+            }
+        }
+        let finally_body: Option<Option<CompoundStmt<'s>>> =
+            if let Some(finally_body) = &try_stmt.finally_body {
+                self.check_compound_stmt(finally_body).map(Some)
+            } else {
+                Some(None)
+            };
+        let (body, finally_body) = (body?, finally_body?);
+        if failed {
+            return None;
+        }
+
+        Some(Stmt::TryStmt(Box::new(TryStmt {
+            body,
+            catch_clauses,
+            finally_body,
+            concrete: try_stmt
+        })))
+    }
+
+    fn visit_spawn_stmt(&mut self, spawn_stmt: &'s ConcreteSpawnStmt<'s>) -> Self::StmtResult {
+        let func_call: Expr<'s> = self.visit_func_call_expr(&spawn_stmt.func_call)?;
+        let spawnable: bool = if let Expr::FuncCallExpr(func_call_expr) = func_call {
+            let func_call_expr: &FuncCallExpr<'s> = func_call_expr.get_tricky(self.arena);
+            func_call_expr.overloads.is_empty() && matches!(
+                func_call_expr.func,
+                Either::Left(func_decl) if !func_decl.get_tricky(self.arena).is_ffi()
+            )
+        } else {
+            false
+        };
+
+        if !spawnable {
+            let spawn_kwd_range: SourceRange = spawn_stmt.spawn_kwd_range;
+            self.diag.diag(spawn_kwd_range.left(), diag_data::err_spawn_non_script_func)
+                .add_mark(spawn_kwd_range.into())
+                .emit();
+            return None;
+        }
+
+        Some(Stmt::SpawnStmt(SpawnStmt {
+            func_call,
+            concrete: spawn_stmt
+        }))
+    }
+}
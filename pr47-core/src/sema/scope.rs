@@ -23,6 +23,7 @@ pub enum LookupFailure {
     ItemNotFound
 }
 
+#[derive(Clone)]
 pub struct Scope<'s> {
     pub scope_kind: ScopeKind,
     pub parent: Option<Box<Scope<'s>>>,
@@ -72,6 +73,36 @@ impl<'s> Scope<'s> {
         }
     }
 
+    pub fn lookup_func_decls(&self, name: &str) -> Option<&Vec<ArenaPtr<'s, FuncDecl<'s>>>> {
+        if let Some(decls) = self.func_decls.get(name) {
+            Some(decls)
+        } else if let Some(parent) = &self.parent {
+            parent.lookup_func_decls(name)
+        } else {
+            None
+        }
+    }
+
+    pub fn lookup_module_decl(&self, name: &str) -> Option<ArenaPtr<'s, ModuleDecl<'s>>> {
+        if let Some(decl) = self.module_decls.get(name) {
+            Some(*decl)
+        } else if let Some(parent) = &self.parent {
+            parent.lookup_module_decl(name)
+        } else {
+            None
+        }
+    }
+
+    pub fn lookup_type(&self, name: &str) -> Option<NonNull<TyckInfo>> {
+        if let Some(ty) = self.types.get(name) {
+            Some(*ty)
+        } else if let Some(parent) = &self.parent {
+            parent.lookup_type(name)
+        } else {
+            None
+        }
+    }
+
     pub fn check_name_collision(&self, name: &str) -> bool {
         self.object_decls.contains_key(name) || self.func_decls.contains_key(name)
     }
@@ -0,0 +1,136 @@
+use std::ptr::NonNull;
+
+use crate::data::tyck::TyckInfo;
+use crate::sema::arena::ArenaPtr;
+use crate::sema::decl::ObjectDecl;
+use crate::sema::expr::Expr;
+use crate::syntax::stmt::{
+    ConcreteCatchClause,
+    ConcreteCompoundStmt,
+    ConcreteDoWhileStmt,
+    ConcreteIfStmt,
+    ConcreteRethrowStmt,
+    ConcreteReturnStmt,
+    ConcreteSpawnStmt,
+    ConcreteThrowStmt,
+    ConcreteTryStmt,
+    ConcreteWhileStmt
+};
+
+pub enum Stmt<'s> {
+    CompoundStmt(CompoundStmt<'s>),
+    ExprStmt(Expr<'s>),
+    DeclStmt(ArenaPtr<'s, ObjectDecl<'s>>),
+    IfStmt(Box<IfStmt<'s>>),
+    WhileStmt(Box<WhileStmt<'s>>),
+    DoWhileStmt(Box<DoWhileStmt<'s>>),
+    ReturnStmt(ReturnStmt<'s>),
+    ThrowStmt(ThrowStmt<'s>),
+    RethrowStmt(RethrowStmt<'s>),
+    TryStmt(Box<TryStmt<'s>>),
+    SpawnStmt(SpawnStmt<'s>)
+}
+
+impl<'s> Stmt<'s> {
+    /// Checks if control flow never reaches the end of this statement
+    pub fn always_exits(&self) -> bool {
+        match self {
+            Stmt::CompoundStmt(compound_stmt) => compound_stmt.always_exits(),
+            Stmt::IfStmt(if_stmt) => if let Some(else_clause) = &if_stmt.else_clause {
+                if_stmt.then_clause.always_exits() && else_clause.always_exits()
+            } else {
+                false
+            },
+            Stmt::ReturnStmt(_) | Stmt::ThrowStmt(_) | Stmt::RethrowStmt(_) => true,
+            Stmt::TryStmt(try_stmt) => {
+                let finally_exits: bool = try_stmt.finally_body.as_ref()
+                    .is_some_and(CompoundStmt::always_exits);
+                finally_exits || (try_stmt.body.always_exits() && try_stmt.catch_clauses
+                    .iter()
+                    .all(|catch_clause: &CatchClause<'s>| catch_clause.body.always_exits()))
+            },
+            _ => false
+        }
+    }
+}
+
+pub struct CompoundStmt<'s> {
+    pub stmts: Vec<Stmt<'s>>,
+
+    pub concrete: &'s ConcreteCompoundStmt<'s>
+}
+
+impl<'s> CompoundStmt<'s> {
+    pub fn always_exits(&self) -> bool {
+        self.stmts.iter().any(Stmt::always_exits)
+    }
+}
+
+pub struct IfStmt<'s> {
+    pub cond: Expr<'s>,
+    pub tyck_cond: bool,
+    pub then_clause: CompoundStmt<'s>,
+    /// Either a `Stmt::CompoundStmt` or another `Stmt::IfStmt`
+    pub else_clause: Option<Stmt<'s>>,
+
+    pub concrete: &'s ConcreteIfStmt<'s>
+}
+
+pub struct WhileStmt<'s> {
+    pub cond: Expr<'s>,
+    pub tyck_cond: bool,
+    pub body: CompoundStmt<'s>,
+
+    pub concrete: &'s ConcreteWhileStmt<'s>
+}
+
+pub struct DoWhileStmt<'s> {
+    pub body: CompoundStmt<'s>,
+    pub cond: Expr<'s>,
+    pub tyck_cond: bool,
+
+    pub concrete: &'s ConcreteDoWhileStmt<'s>
+}
+
+pub struct ReturnStmt<'s> {
+    pub values: Vec<Expr<'s>>,
+    pub tyck_values: bool,
+
+    pub concrete: &'s ConcreteReturnStmt<'s>
+}
+
+pub struct ThrowStmt<'s> {
+    pub thrown: Expr<'s>,
+
+    pub concrete: &'s ConcreteThrowStmt<'s>
+}
+
+pub struct RethrowStmt<'s> {
+    pub concrete: &'s ConcreteRethrowStmt
+}
+
+pub struct CatchClause<'s> {
+    pub catch_var: ArenaPtr<'s, ObjectDecl<'s>>,
+    /// Either a plain type, or `any` catching all exceptions
+    pub catch_type: NonNull<TyckInfo>,
+    pub body: CompoundStmt<'s>,
+    /// Whether `body` contains `rethrow` statements for this clause
+    pub rethrown: bool,
+
+    pub concrete: &'s ConcreteCatchClause<'s>
+}
+
+pub struct TryStmt<'s> {
+    pub body: CompoundStmt<'s>,
+    pub catch_clauses: Vec<CatchClause<'s>>,
+    pub finally_body: Option<CompoundStmt<'s>>,
+
+    pub concrete: &'s ConcreteTryStmt<'s>
+}
+
+pub struct SpawnStmt<'s> {
+    /// Always a `Expr::FuncCallExpr` calling a function defined in script
+    pub func_call: Expr<'s>,
+
+    pub concrete: &'s ConcreteSpawnStmt<'s>
+}
@@ -74,7 +74,7 @@ pub enum AttrValueInner<'a> {
     IntLiteral(i64),
     FloatLiteral(f64),
     CharLiteral(char),
-    StringLiteral(&'a str),
+    StringLiteral(String),
     BoolLiteral(bool)
 }
 
@@ -107,7 +107,7 @@ impl<'a> AttrValue<'a> {
         }
     }
 
-    pub fn string_value(value: &'a str, range: SourceRange) -> Self {
+    pub fn string_value(value: String, range: SourceRange) -> Self {
         Self {
             inner: AttrValueInner::StringLiteral(value),
             range
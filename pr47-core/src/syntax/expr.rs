@@ -66,6 +66,7 @@
 //! ```
 
 use crate::diag::location::{SourceLoc, SourceRange};
+use crate::parse::lexer::unescape_str;
 use crate::syntax::id::Identifier;
 use crate::syntax::token::Token;
 use crate::syntax::ty::ConcreteType;
@@ -73,7 +74,7 @@ use crate::syntax::ty::ConcreteType;
 #[cfg(test)] use std::fmt::{Debug, Formatter};
 
 pub enum ConcreteExpr<'a> {
-    LiteralExpr(ConcreteLiteralExpr<'a>),
+    LiteralExpr(ConcreteLiteralExpr),
     IdRefExpr(Identifier<'a>),
     UnaryExpr(ConcreteUnaryExpr<'a>),
     BinaryExpr(ConcreteBinaryExpr<'a>),
@@ -103,23 +104,41 @@ impl<'a> Debug for ConcreteExpr<'a> {
     }
 }
 
+impl<'a> ConcreteExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        match self {
+            ConcreteExpr::LiteralExpr(expr) => expr.range,
+            ConcreteExpr::IdRefExpr(expr) => expr.source_range(),
+            ConcreteExpr::UnaryExpr(expr) => expr.source_range(),
+            ConcreteExpr::BinaryExpr(expr) => expr.source_range(),
+            ConcreteExpr::FuncCallExpr(expr) => expr.source_range(),
+            ConcreteExpr::SubscriptExpr(expr) => expr.source_range(),
+            ConcreteExpr::FieldRefExpr(expr) => expr.source_range(),
+            ConcreteExpr::AsExpr(expr) => expr.source_range(),
+            ConcreteExpr::AwaitExpr(expr) => expr.source_range(),
+            ConcreteExpr::ParenthesizedExpr(expr) => expr.source_range()
+        }
+    }
+}
+
 #[cfg_attr(test, derive(Debug))]
-pub struct ConcreteLiteralExpr<'a> {
-    pub content: LiteralExprContent<'a>,
+pub struct ConcreteLiteralExpr {
+    pub content: LiteralExprContent,
     pub range: SourceRange
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 #[cfg_attr(test, derive(Debug))]
-pub enum LiteralExprContent<'a> {
+pub enum LiteralExprContent {
     Int(u64),
     Float(f64),
     Char(char),
-    String(&'a str),
+    /// Value of the string literal, with escapes decoded
+    String(String),
     Boolean(bool)
 }
 
-impl<'a> ConcreteLiteralExpr<'a> {
+impl ConcreteLiteralExpr {
     pub fn new_lit_int(lit: u64, range: SourceRange) -> Self {
         ConcreteLiteralExpr {
             content: LiteralExprContent::Int(lit),
@@ -139,9 +158,16 @@ impl<'a> ConcreteLiteralExpr<'a> {
         }
     }
 
-    pub fn new_lit_str(lit: &'a str, range: SourceRange) -> Self {
+    /// Creates a string literal from the body of a regular string literal token
+    pub fn new_lit_str(lit: &str, range: SourceRange) -> Self {
+        ConcreteLiteralExpr {
+            content: LiteralExprContent::String(unescape_str(lit)), range
+        }
+    }
+
+    pub fn new_lit_raw_str(lit: &str, range: SourceRange) -> Self {
         ConcreteLiteralExpr {
-            content: LiteralExprContent::String(lit), range
+            content: LiteralExprContent::String(lit.to_string()), range
         }
     }
 
@@ -158,6 +184,12 @@ pub struct ConcreteUnaryExpr<'a> {
     pub operand: Box<ConcreteExpr<'a>>,
 }
 
+impl<'a> ConcreteUnaryExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(self.op.range.left(), self.operand.source_range().right())
+    }
+}
+
 #[cfg_attr(test, derive(Debug))]
 pub struct ConcreteBinaryExpr<'a> {
     pub op: Token<'a>,
@@ -165,6 +197,15 @@ pub struct ConcreteBinaryExpr<'a> {
     pub rhs: Box<ConcreteExpr<'a>>,
 }
 
+impl<'a> ConcreteBinaryExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(
+            self.lhs.source_range().left(),
+            self.rhs.source_range().right()
+        )
+    }
+}
+
 #[cfg_attr(test, derive(Debug))]
 pub struct ConcreteFuncCallExpr<'a> {
     pub func: Box<ConcreteExpr<'a>>,
@@ -174,6 +215,12 @@ pub struct ConcreteFuncCallExpr<'a> {
     pub rparen_loc: SourceLoc
 }
 
+impl<'a> ConcreteFuncCallExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(self.func.source_range().left(), self.rparen_loc)
+    }
+}
+
 #[cfg_attr(test, derive(Debug))]
 pub struct ConcreteSubscriptExpr<'a> {
     pub base: Box<ConcreteExpr<'a>>,
@@ -183,6 +230,12 @@ pub struct ConcreteSubscriptExpr<'a> {
     pub rbracket_loc: SourceLoc
 }
 
+impl<'a> ConcreteSubscriptExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(self.base.source_range().left(), self.rbracket_loc)
+    }
+}
+
 #[cfg_attr(test, derive(Debug))]
 pub struct ConcreteFieldRefExpr<'a> {
     pub base: Box<ConcreteExpr<'a>>,
@@ -191,12 +244,27 @@ pub struct ConcreteFieldRefExpr<'a> {
     pub dot_loc: SourceLoc
 }
 
+impl<'a> ConcreteFieldRefExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(
+            self.base.source_range().left(),
+            self.id.source_range().right()
+        )
+    }
+}
+
 pub struct ConcreteAwaitExpr<'a> {
     pub base: Box<ConcreteExpr<'a>>,
     pub dot_loc: SourceLoc,
     pub await_range: SourceRange
 }
 
+impl<'a> ConcreteAwaitExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(self.base.source_range().left(), self.await_range.right())
+    }
+}
+
 pub struct ConcreteAsExpr<'a> {
     pub operand: Box<ConcreteExpr<'a>>,
     pub dest_type: ConcreteType<'a>,
@@ -204,12 +272,25 @@ pub struct ConcreteAsExpr<'a> {
     pub as_range: SourceRange
 }
 
+impl<'a> ConcreteAsExpr<'a> {
+    /// Range from the operand to the `as` keyword, since types do not record where they end
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(self.operand.source_range().left(), self.as_range.right())
+    }
+}
+
 pub struct ConcreteParenthesizedExpr<'a> {
     pub inner: Box<ConcreteExpr<'a>>,
     pub lparen_loc: SourceLoc,
     pub rparen_loc: SourceLoc
 }
 
+impl<'a> ConcreteParenthesizedExpr<'a> {
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::from_loc_pair(self.lparen_loc, self.rparen_loc)
+    }
+}
+
 #[cfg(test)]
 impl<'a> Debug for ConcreteAwaitExpr<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -1,13 +1,77 @@
-use crate::diag::location::SourceLoc;
+//! # Concrete syntax tree of statements
+//!
+//! Statement syntax:
+//! ```text
+//! statement ::= compound-statement
+//!             | expression-statement
+//!             | declaration-statement
+//!             | if-statement
+//!             | while-statement
+//!             | do-while-statement
+//!             | return-statement
+//!             | throw-statement
+//!             | rethrow-statement
+//!             | try-statement
+//!             | spawn-statement
+//!
+//! compound-statement ::= '{' statement-list '}'
+//!
+//! statement-list ::= statement statement-list
+//!                  | NIL
+//!
+//! expression-statement ::= expression ';'
+//!
+//! declaration-statement ::= const-declaration
+//!                         | var-declaration
+//!
+//! if-statement ::= 'if' binary-expression compound-statement
+//!                | 'if' binary-expression compound-statement 'else' compound-statement
+//!                | 'if' binary-expression compound-statement 'else' if-statement
+//!
+//! while-statement ::= 'while' binary-expression compound-statement
+//!
+//! do-while-statement ::= 'do' compound-statement 'while' binary-expression ';'
+//!
+//! return-statement ::= 'return' expression-list ';'
+//!
+//! throw-statement ::= 'throw' binary-expression ';'
+//!
+//! rethrow-statement ::= 'rethrow' ';'
+//!
+//! try-statement ::= 'try' compound-statement catch-clause-list
+//!                 | 'try' compound-statement catch-clause-list finally-clause
+//!                 | 'try' compound-statement finally-clause
+//!
+//! catch-clause-list ::= catch-clause catch-clause-list
+//!                     | catch-clause
+//!
+//! catch-clause ::= 'catch' '(' ID type ')' compound-statement
+//!                | 'catch' '(' ID ':' type ')' compound-statement
+//!
+//! finally-clause ::= 'finally' compound-statement
+//!
+//! spawn-statement ::= 'spawn' postfix-expression '(' expression-list ')' ';'
+//! ```
 
+use crate::diag::location::{SourceLoc, SourceRange};
 use crate::syntax::decl::ConcreteDecl;
-use crate::syntax::expr::ConcreteExpr;
+use crate::syntax::expr::{ConcreteExpr, ConcreteFuncCallExpr};
+use crate::syntax::id::Identifier;
+use crate::syntax::ty::ConcreteType;
 
 #[cfg_attr(test, derive(Debug))]
 pub enum ConcreteStmt<'a> {
     CompoundStmt(ConcreteCompoundStmt<'a>),
     ExprStmt(ConcreteExpr<'a>, SourceLoc),
-    DeclStmt(ConcreteDecl<'a>, SourceLoc)
+    DeclStmt(Box<ConcreteDecl<'a>>, SourceLoc),
+    IfStmt(ConcreteIfStmt<'a>),
+    WhileStmt(ConcreteWhileStmt<'a>),
+    DoWhileStmt(ConcreteDoWhileStmt<'a>),
+    ReturnStmt(ConcreteReturnStmt<'a>),
+    ThrowStmt(ConcreteThrowStmt<'a>),
+    RethrowStmt(ConcreteRethrowStmt),
+    TryStmt(ConcreteTryStmt<'a>),
+    SpawnStmt(ConcreteSpawnStmt<'a>)
 }
 
 #[cfg_attr(test, derive(Debug))]
@@ -17,3 +81,84 @@ pub struct ConcreteCompoundStmt<'a> {
     pub left_brace_loc: SourceLoc,
     pub right_brace_loc: SourceLoc
 }
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteIfStmt<'a> {
+    pub cond_expr: ConcreteExpr<'a>,
+    pub then_clause: ConcreteCompoundStmt<'a>,
+    /// Either a `ConcreteStmt::CompoundStmt` or another `ConcreteStmt::IfStmt`
+    pub else_clause: Option<Box<ConcreteStmt<'a>>>,
+
+    pub if_kwd_range: SourceRange,
+    pub else_kwd_range: Option<SourceRange>
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteWhileStmt<'a> {
+    pub cond_expr: ConcreteExpr<'a>,
+    pub body: ConcreteCompoundStmt<'a>,
+
+    pub while_kwd_range: SourceRange
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteDoWhileStmt<'a> {
+    pub body: ConcreteCompoundStmt<'a>,
+    pub cond_expr: ConcreteExpr<'a>,
+
+    pub do_kwd_range: SourceRange,
+    pub while_kwd_range: SourceRange,
+    pub semicolon_loc: SourceLoc
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteReturnStmt<'a> {
+    pub return_values: Vec<ConcreteExpr<'a>>,
+
+    pub return_kwd_range: SourceRange,
+    pub semicolon_loc: SourceLoc
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteThrowStmt<'a> {
+    pub thrown_expr: ConcreteExpr<'a>,
+
+    pub throw_kwd_range: SourceRange,
+    pub semicolon_loc: SourceLoc
+}
+
+/// Throws the exception caught by the innermost enclosing `catch` clause again
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteRethrowStmt {
+    pub rethrow_kwd_range: SourceRange,
+    pub semicolon_loc: SourceLoc
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteCatchClause<'a> {
+    pub catch_var: Identifier<'a>,
+    pub catch_type: ConcreteType<'a>,
+    pub body: ConcreteCompoundStmt<'a>,
+
+    pub catch_kwd_range: SourceRange,
+    pub lparen_loc: SourceLoc,
+    pub rparen_loc: SourceLoc
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteTryStmt<'a> {
+    pub body: ConcreteCompoundStmt<'a>,
+    pub catch_clauses: Vec<ConcreteCatchClause<'a>>,
+    pub finally_body: Option<ConcreteCompoundStmt<'a>>,
+
+    pub try_kwd_range: SourceRange,
+    pub finally_kwd_range: Option<SourceRange>
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteSpawnStmt<'a> {
+    pub func_call: ConcreteFuncCallExpr<'a>,
+
+    pub spawn_kwd_range: SourceRange,
+    pub semicolon_loc: SourceLoc
+}
@@ -24,6 +24,7 @@ pub enum TokenInner<'a> {
     KwdElse,
     KwdExport,
     KwdFalse,
+    KwdFinally,
     KwdFloat,
     KwdFunc,
     KwdIf,
@@ -31,6 +32,7 @@ pub enum TokenInner<'a> {
     KwdInt,
     KwdObject,
     KwdOpen,
+    KwdRethrow,
     KwdReturn,
     KwdSpawn,
     KwdString,
@@ -49,7 +51,11 @@ pub enum TokenInner<'a> {
     LitFloat(f64),
     LitInt(u64),
     LitSignedInt(i64),
+    /// Body of a regular string literal, between the quotes and with escapes not yet decoded,
+    /// see `parse::lexer::unescape_str`
     LitStr(&'a str),
+    /// Content of a raw string literal, which is used as is
+    LitRawStr(&'a str),
 
     RsvAsm,
     RsvAttribute,
@@ -156,6 +162,10 @@ impl<'a> Token<'a> {
         Self::new(TokenInner::LitStr(lit), range)
     }
 
+    pub fn new_lit_raw_str(lit: &'a str, range: SourceRange) -> Self {
+        Self::new(TokenInner::LitRawStr(lit), range)
+    }
+
     pub fn new_lit_str_in<const DS: usize, const A: usize>(
         lit: &str,
         arena: &'a mut SliceArena<DS, A>,
@@ -215,6 +225,7 @@ impl<'a> Display for Token<'a> {
             KwdElse => write!(f, "⟨else⟩"),
             KwdExport => write!(f, "⟨export⟩"),
             KwdFalse => write!(f, "⟨false⟩"),
+            KwdFinally => write!(f, "⟨finally⟩"),
             KwdFloat => write!(f, "⟨float⟩"),
             KwdFunc => write!(f, "⟨func⟩"),
             KwdIf => write!(f, "⟨if⟩"),
@@ -222,6 +233,7 @@ impl<'a> Display for Token<'a> {
             KwdInt => write!(f, "⟨int⟩"),
             KwdObject => write!(f, "⟨object⟩"),
             KwdOpen => write!(f, "⟨open⟩"),
+            KwdRethrow => write!(f, "⟨rethrow⟩"),
             KwdReturn => write!(f, "⟨return⟩"),
             KwdString => write!(f, "⟨string⟩"),
             KwdSpawn => write!(f, "⟨spawn⟩"),
@@ -241,6 +253,7 @@ impl<'a> Display for Token<'a> {
             LitInt(num) => write!(f, "⟨num, {}i⟩", num),
             LitSignedInt(num) => write!(f, "⟨num, {}i⟩", num),
             LitStr(str) => write!(f, "⟨str, \"{}\"⟩", str),
+            LitRawStr(str) => write!(f, "⟨raw str, `{}`⟩", str),
 
             RsvAsm => write!(f, "⟨asm⟩"),
             RsvAttribute => write!(f, "⟨attribute⟩"),
@@ -325,6 +338,7 @@ impl<'a> Display2 for TokenInner<'a> {
             TokenInner::KwdElse => write!(fmt, "'else'"),
             TokenInner::KwdExport => write!(fmt, "'export'"),
             TokenInner::KwdFalse => write!(fmt, "'false'"),
+            TokenInner::KwdFinally => write!(fmt, "'finally'"),
             TokenInner::KwdFloat => write!(fmt, "'float'"),
             TokenInner::KwdFunc => write!(fmt, "'func'"),
             TokenInner::KwdIf => write!(fmt, "'if'"),
@@ -332,6 +346,7 @@ impl<'a> Display2 for TokenInner<'a> {
             TokenInner::KwdInt => write!(fmt, "'int'"),
             TokenInner::KwdObject => write!(fmt, "'object'"),
             TokenInner::KwdOpen => write!(fmt, "'open'"),
+            TokenInner::KwdRethrow => write!(fmt, "'rethrow'"),
             TokenInner::KwdReturn => write!(fmt, "'any'"),
             TokenInner::KwdSpawn => write!(fmt, "'spawn'"),
             TokenInner::KwdString => write!(fmt, "'string'"),
@@ -351,6 +366,7 @@ impl<'a> Display2 for TokenInner<'a> {
             TokenInner::LitInt(_) => write!(fmt, "integer literal"),
             TokenInner::LitSignedInt(_) => write!(fmt, "integer literal"),
             TokenInner::LitStr(_) => write!(fmt, "string literal"),
+            TokenInner::LitRawStr(_) => write!(fmt, "raw string literal"),
 
             TokenInner::RsvAsm => write!(fmt, "'asm'"),
             TokenInner::RsvAttribute => write!(fmt, "'attribute'"),
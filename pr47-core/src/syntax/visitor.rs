@@ -13,7 +13,18 @@ use crate::syntax::expr::{
     ConcreteUnaryExpr
 };
 use crate::syntax::id::Identifier;
-use crate::syntax::stmt::{ConcreteCompoundStmt, ConcreteStmt};
+use crate::syntax::stmt::{
+    ConcreteCompoundStmt,
+    ConcreteDoWhileStmt,
+    ConcreteIfStmt,
+    ConcreteRethrowStmt,
+    ConcreteReturnStmt,
+    ConcreteSpawnStmt,
+    ConcreteStmt,
+    ConcreteThrowStmt,
+    ConcreteTryStmt,
+    ConcreteWhileStmt
+};
 use crate::syntax::token::Token;
 use crate::syntax::ty::{ConcreteGenericType, ConcreteNullableType, ConcreteType};
 
@@ -56,7 +67,7 @@ pub trait ExprVisitor<'s> {
 
     fn visit_literal_expr(
         &mut self,
-        literal_expr: &'s ConcreteLiteralExpr<'s>
+        literal_expr: &'s ConcreteLiteralExpr
     ) -> Self::ExprResult;
     fn visit_id_ref_expr(&mut self, id: &'s Identifier<'s>) -> Self::ExprResult;
     fn visit_unary_expr(
@@ -93,7 +104,15 @@ pub trait StmtVisitor<'s> {
         match stmt {
             ConcreteStmt::CompoundStmt(compound_stmt) => self.visit_compound_stmt(compound_stmt),
             ConcreteStmt::ExprStmt(expr_stmt, _) => self.visit_expr_stmt(expr_stmt),
-            ConcreteStmt::DeclStmt(decl_stmt, _) => self.visit_decl_stmt(decl_stmt)
+            ConcreteStmt::DeclStmt(decl_stmt, _) => self.visit_decl_stmt(decl_stmt),
+            ConcreteStmt::IfStmt(if_stmt) => self.visit_if_stmt(if_stmt),
+            ConcreteStmt::WhileStmt(while_stmt) => self.visit_while_stmt(while_stmt),
+            ConcreteStmt::DoWhileStmt(do_while_stmt) => self.visit_do_while_stmt(do_while_stmt),
+            ConcreteStmt::ReturnStmt(return_stmt) => self.visit_return_stmt(return_stmt),
+            ConcreteStmt::ThrowStmt(throw_stmt) => self.visit_throw_stmt(throw_stmt),
+            ConcreteStmt::RethrowStmt(rethrow_stmt) => self.visit_rethrow_stmt(rethrow_stmt),
+            ConcreteStmt::TryStmt(try_stmt) => self.visit_try_stmt(try_stmt),
+            ConcreteStmt::SpawnStmt(spawn_stmt) => self.visit_spawn_stmt(spawn_stmt)
         }
     }
 
@@ -104,6 +123,17 @@ pub trait StmtVisitor<'s> {
 
     fn visit_expr_stmt(&mut self, expr: &'s ConcreteExpr<'s>) -> Self::StmtResult;
     fn visit_decl_stmt(&mut self, decl: &'s ConcreteDecl<'s>) -> Self::StmtResult;
+    fn visit_if_stmt(&mut self, if_stmt: &'s ConcreteIfStmt<'s>) -> Self::StmtResult;
+    fn visit_while_stmt(&mut self, while_stmt: &'s ConcreteWhileStmt<'s>) -> Self::StmtResult;
+    fn visit_do_while_stmt(
+        &mut self,
+        do_while_stmt: &'s ConcreteDoWhileStmt<'s>
+    ) -> Self::StmtResult;
+    fn visit_return_stmt(&mut self, return_stmt: &'s ConcreteReturnStmt<'s>) -> Self::StmtResult;
+    fn visit_throw_stmt(&mut self, throw_stmt: &'s ConcreteThrowStmt<'s>) -> Self::StmtResult;
+    fn visit_rethrow_stmt(&mut self, rethrow_stmt: &'s ConcreteRethrowStmt) -> Self::StmtResult;
+    fn visit_try_stmt(&mut self, try_stmt: &'s ConcreteTryStmt<'s>) -> Self::StmtResult;
+    fn visit_spawn_stmt(&mut self, spawn_stmt: &'s ConcreteSpawnStmt<'s>) -> Self::StmtResult;
 }
 
 pub trait TypeVisitor<'s> {
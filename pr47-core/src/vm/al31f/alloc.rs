@@ -1,4 +1,5 @@
 pub mod default_alloc;
+pub mod incremental_alloc;
 pub mod no_gc_alloc;
 
 use xjbutil::flex::FlexArray;
@@ -16,6 +17,10 @@ pub trait Alloc: 'static + Send + Sync {
     /// Remove one stack from `Alloc` management
     unsafe fn remove_stack(&mut self, stack: *const Stack);
 
+    /// Set global variables, scanned every turn like stacks. `globals` should stay valid until
+    /// replaced by another call.
+    unsafe fn set_globals(&mut self, globals: *const [Value]);
+
     /// Make the object denoted by `data` pointer managed
     unsafe fn add_managed(&mut self, data: Value);
 
@@ -34,4 +39,20 @@ pub trait Alloc: 'static + Send + Sync {
 
     /// Allow or disallow garbage collection
     fn set_gc_allowed(&mut self, allowed: bool);
+
+    /// Account for a managed object growing (or shrinking) by `delta` bytes after being added.
+    /// Like `add_managed`, a collection is forced if this brings usage beyond quota.
+    unsafe fn adjust_usage(&mut self, delta: isize);
+
+    /// Bytes of memory occupied by managed objects. Objects growing after being added without
+    /// `adjust_usage` may only be accounted for at the next collection.
+    fn usage(&self) -> usize;
+
+    /// Limit `usage` to `quota` bytes, `None` for no limit. Once an allocation would exceed the
+    /// quota, a collection is forced; the VM raises an out-of-memory exception if usage is still
+    /// beyond quota after that.
+    fn set_quota(&mut self, quota: Option<usize>);
+
+    /// Get the limit set by `set_quota`
+    fn quota(&self) -> Option<usize>;
 }
@@ -9,12 +9,15 @@ use crate::vm::al31f::stack::Stack;
 /// Default allocator for `AL31F`, with STW GC.
 pub struct DefaultAlloc {
     stacks: Vec<*const Stack>,
+    globals: *const [Value],
     managed: Vec<Value>,
     pinned: Vec<AllocPin>,
     debt: usize,
     pin_debt: usize,
     max_debt: usize,
     max_pin_debt: usize,
+    usage: usize,
+    quota: Option<usize>,
     gc_allowed: bool
 }
 
@@ -42,12 +45,15 @@ impl DefaultAlloc {
     pub fn with_max_debt(max_debt: usize, max_pin_debt: usize) -> Self {
         Self {
             stacks: Vec::new(),
+            globals: &[],
             managed: Vec::new(),
             pinned: Vec::new(),
             debt: 0,
             pin_debt: 0,
             max_debt,
             max_pin_debt,
+            usage: 0,
+            quota: None,
             gc_allowed: false
         }
     }
@@ -107,13 +113,20 @@ impl Alloc for DefaultAlloc {
         let _removed = self.stacks.remove(self.stacks.binary_search(&stack).unwrap_unchecked());
     }
 
+    unsafe fn set_globals(&mut self, globals: *const [Value]) {
+        self.globals = globals;
+    }
+
     #[inline(never)]
     unsafe fn add_managed(&mut self, data: Value) {
-        if self.max_debt < self.debt && self.gc_allowed {
+        let size: usize = data.memory_size();
+        let over_quota: bool = matches!(self.quota, Some(quota) if self.usage + size > quota);
+        if (self.max_debt < self.debt || over_quota) && self.gc_allowed {
             self.collect();
         }
         self.managed.push(data);
         self.debt += 1;
+        self.usage += size;
     }
 
     #[inline(always)]
@@ -161,6 +174,12 @@ impl Alloc for DefaultAlloc {
             }
         }
 
+        for global /*: &Value*/ in (*self.globals).iter() {
+            if !global.is_null() && !global.is_value() {
+                to_scan.push_back(*global);
+            }
+        }
+
         for pin /*: &AllocPin*/ in self.pinned.iter() {
             for pinned_object /*: &Value*/ in pin.flex().iter() {
                 if !pinned_object.is_null() && !pinned_object.is_value() {
@@ -205,6 +224,7 @@ impl Alloc for DefaultAlloc {
             }
         }
 
+        let mut usage: usize = 0;
         self.managed.retain(|value: &Value| {
             let ownership_info: u8 = value.ownership_info() as u8;
             let gc_info: u8 = value.gc_info() as u8;
@@ -223,15 +243,44 @@ impl Alloc for DefaultAlloc {
                 }
                 false
             } else {
+                usage += value.memory_size();
                 true
             }
         });
+        self.usage = usage;
     }
 
     #[inline(always)]
     fn set_gc_allowed(&mut self, allowed: bool) {
         self.gc_allowed = allowed;
     }
+
+    #[inline(never)]
+    unsafe fn adjust_usage(&mut self, delta: isize) {
+        if delta < 0 {
+            self.usage = self.usage.saturating_sub(delta.unsigned_abs());
+            return;
+        }
+        self.usage += delta as usize;
+        if matches!(self.quota, Some(quota) if self.usage > quota) && self.gc_allowed {
+            self.collect();
+        }
+    }
+
+    #[inline(always)]
+    fn usage(&self) -> usize {
+        self.usage
+    }
+
+    #[inline(always)]
+    fn set_quota(&mut self, quota: Option<usize>) {
+        self.quota = quota;
+    }
+
+    #[inline(always)]
+    fn quota(&self) -> Option<usize> {
+        self.quota
+    }
 }
 
 #[cfg(test)]
@@ -295,6 +344,44 @@ mod test {
         }
     }
 
+    #[test] fn test_default_collector_usage() {
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let mut stack: Stack = Stack::new();
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 2, &[]) };
+
+        let str1: Value = Value::new_owned::<String>(String::with_capacity(1000));
+        let mut container: TestContainer<String> = TestContainer::new();
+        container.inner.elements.reserve_exact(100);
+        let vt: GenericTypeVT = create_test_container_vt::<String>(&mut tyck_info_pool);
+        let container: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(container)).as_ptr() as *mut Wrapper<()>,
+            &vt
+        );
+
+        unsafe {
+            alloc.add_stack(&stack);
+            assert_eq!(alloc.usage(), 0);
+
+            alloc.add_managed(str1);
+            let str1_size: usize = alloc.usage();
+            assert!(str1_size >= 1000);
+
+            alloc.add_managed(container);
+            assert_eq!(alloc.usage(), str1_size + container.memory_size());
+
+            stack_slice.set_value(0, str1);
+            stack_slice.set_value(1, container);
+            alloc.collect();
+            assert_eq!(alloc.usage(), str1_size + container.memory_size());
+
+            stack_slice.set_value(0, Value::new_null());
+            alloc.collect();
+            assert_eq!(alloc.usage(), container.memory_size());
+        }
+    }
+
     #[test] fn test_default_collector_custom_vt() {
         let mut alloc: DefaultAlloc = DefaultAlloc::new();
         let mut stack: Stack = Stack::new();
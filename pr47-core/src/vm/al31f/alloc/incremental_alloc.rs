@@ -0,0 +1,573 @@
+use crate::data::Value;
+use crate::data::generic::GenericTypeVT;
+use crate::data::traits::ChildrenType;
+use crate::data::wrapper::{DynBase, OWN_INFO_COLLECT_MASK, OWN_INFO_GLOBAL_MASK};
+use crate::vm::al31f::alloc::{Alloc, AllocPin};
+use crate::vm::al31f::alloc::default_alloc::{DEFAULT_MAX_DEBT, DEFAULT_MAX_PIN_DEBT};
+use crate::vm::al31f::stack::Stack;
+
+/// Incremental allocator for `AL31F`, with tri-color GC.
+///
+/// Instead of stopping the world for a whole collection, each allocation performs a bounded
+/// amount of marking or sweeping. Storing a value into a managed object during marking should
+/// call `mark_object` on the value, which serves as the write barrier. Stacks, global variables
+/// and pins are not guarded by the barrier, so they get scanned again when marking finishes.
+pub struct IncrementalAlloc {
+    stacks: Vec<*const Stack>,
+    globals: *const [Value],
+    managed: Vec<Value>,
+    pinned: Vec<AllocPin>,
+    gray: Vec<Value>,
+    phase: GCPhase,
+    /// `gc_info` of black objects in the current cycle, alternates between `GC_BLACK_EVEN` and
+    /// `GC_BLACK_ODD` so that starting a cycle turns all objects white at once
+    black: u8,
+    sweep_cursor: usize,
+    /// Bytes occupied by objects the ongoing sweep decided to keep
+    sweep_usage: usize,
+    debt: usize,
+    pin_debt: usize,
+    max_debt: usize,
+    max_pin_debt: usize,
+    step_size: usize,
+    usage: usize,
+    quota: Option<usize>,
+    gc_allowed: bool
+}
+
+enum GCPhase {
+    Idle,
+    Mark,
+    Sweep
+}
+
+const GC_WHITE: u8 = 0;
+const GC_BLACK_EVEN: u8 = 1;
+const GC_BLACK_ODD: u8 = 2;
+const GC_GRAY: u8 = 3;
+
+/// Objects marked or swept per allocation
+pub const DEFAULT_STEP_SIZE: usize = 64;
+
+impl IncrementalAlloc {
+    pub fn new() -> Self {
+        Self::with_params(DEFAULT_MAX_DEBT, DEFAULT_MAX_PIN_DEBT, DEFAULT_STEP_SIZE)
+    }
+
+    /// A cycle starts after `max_debt` allocations, then each allocation marks or sweeps at most
+    /// `step_size` objects until the cycle finishes
+    pub fn with_params(max_debt: usize, max_pin_debt: usize, step_size: usize) -> Self {
+        Self {
+            stacks: Vec::new(),
+            globals: &[],
+            managed: Vec::new(),
+            pinned: Vec::new(),
+            gray: Vec::new(),
+            phase: GCPhase::Idle,
+            black: GC_BLACK_EVEN,
+            sweep_cursor: 0,
+            sweep_usage: 0,
+            debt: 0,
+            pin_debt: 0,
+            max_debt,
+            max_pin_debt,
+            step_size: step_size.max(1),
+            usage: 0,
+            quota: None,
+            gc_allowed: false
+        }
+    }
+
+    #[cfg(test)]
+    pub fn contains_ptr(&self, ptr: xjbutil::wide_ptr::WidePointer) -> bool {
+        self.managed.iter().map(|x| unsafe { x.ptr_repr }).any(|x| x == ptr)
+    }
+
+    unsafe fn cleanup_pins(&mut self) {
+        self.pinned.retain(|pinned: &AllocPin| *pinned.fixed());
+        self.pin_debt = 0;
+    }
+
+    /// Turns `value` gray if it is a white, collectable object
+    #[inline(always)]
+    unsafe fn shade(gray: &mut Vec<Value>, black: u8, value: Value) {
+        if value.is_null() || value.is_value() {
+            return;
+        }
+
+        let ownership_info: u8 = value.ownership_info() as u8;
+        if (ownership_info & OWN_INFO_COLLECT_MASK == 0) ||
+            (ownership_info & OWN_INFO_GLOBAL_MASK != 0)
+        {
+            return;
+        }
+
+        let gc_info: u8 = value.gc_info();
+        if gc_info != black && gc_info != GC_GRAY {
+            value.set_gc_info(GC_GRAY);
+            gray.push(value);
+        }
+    }
+
+    unsafe fn shade_roots(&mut self) {
+        let (gray, black): (&mut Vec<Value>, u8) = (&mut self.gray, self.black);
+
+        for stack /*: &*const Stack*/ in self.stacks.iter() {
+            #[cfg(debug_assertions)]
+            for stack_value /*: &Value*/ in (**stack).values.iter().flatten() {
+                Self::shade(gray, black, *stack_value);
+            }
+
+            #[cfg(not(debug_assertions))]
+            for stack_value /*: &Value*/ in (**stack).values.iter() {
+                Self::shade(gray, black, *stack_value);
+            }
+        }
+
+        for global /*: &Value*/ in (*self.globals).iter() {
+            Self::shade(gray, black, *global);
+        }
+
+        for pin /*: &AllocPin*/ in self.pinned.iter() {
+            for pinned_object /*: &Value*/ in pin.flex().iter() {
+                Self::shade(gray, black, *pinned_object);
+            }
+        }
+    }
+
+    /// Turns a gray object black, shading its children
+    unsafe fn blacken(&mut self, value: Value) {
+        value.set_gc_info(self.black);
+        let children: ChildrenType = if !value.is_container() {
+            let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
+            (*dyn_base).children()
+        } else {
+            let container_vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+            let data: *const () = value.get_as_mut_ptr() as *const ();
+            ((*container_vt).children_fn)(data)
+        };
+
+        if let Some(children /*: Box<dyn Iterator>*/) = children {
+            for child /*: Value*/ in children {
+                Self::shade(&mut self.gray, self.black, child);
+            }
+        }
+    }
+
+    unsafe fn start_cycle(&mut self) {
+        self.cleanup_pins();
+        self.debt = 0;
+        self.black = if self.black == GC_BLACK_EVEN { GC_BLACK_ODD } else { GC_BLACK_EVEN };
+        self.phase = GCPhase::Mark;
+        self.shade_roots();
+    }
+
+    unsafe fn mark_step(&mut self, budget: usize) {
+        for _ in 0..budget {
+            if let Some(value) = self.gray.pop() {
+                self.blacken(value);
+            } else {
+                break;
+            }
+        }
+
+        if self.gray.is_empty() {
+            // roots may have changed since the cycle started, so finish marking with them at once
+            self.shade_roots();
+            while let Some(value) = self.gray.pop() {
+                self.blacken(value);
+            }
+
+            self.phase = GCPhase::Sweep;
+            self.sweep_cursor = 0;
+            self.sweep_usage = 0;
+        }
+    }
+
+    unsafe fn sweep_step(&mut self, budget: usize) {
+        for _ in 0..budget {
+            if self.sweep_cursor >= self.managed.len() {
+                break;
+            }
+
+            let value: Value = *self.managed.get_unchecked(self.sweep_cursor);
+            let ownership_info: u8 = value.ownership_info() as u8;
+            if value.gc_info() != self.black &&
+                (ownership_info & OWN_INFO_COLLECT_MASK != 0) &&
+                (ownership_info & OWN_INFO_GLOBAL_MASK == 0)
+            {
+                self.managed.swap_remove(self.sweep_cursor);
+                self.usage = self.usage.saturating_sub(value.memory_size());
+                if value.is_container() {
+                    let container: *mut () = value.untagged_ptr_field() as *mut _;
+                    let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+                    ((*vt).drop_fn)(container);
+                } else {
+                    let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
+                    let boxed: Box<dyn DynBase> = Box::from_raw(dyn_base);
+                    drop(boxed);
+                }
+            } else {
+                self.sweep_usage += value.memory_size();
+                self.sweep_cursor += 1;
+            }
+        }
+
+        if self.sweep_cursor >= self.managed.len() {
+            self.usage = self.sweep_usage;
+            self.phase = GCPhase::Idle;
+        }
+    }
+
+    unsafe fn step(&mut self) {
+        match self.phase {
+            GCPhase::Idle => if self.debt > self.max_debt {
+                self.start_cycle();
+            },
+            GCPhase::Mark => self.mark_step(self.step_size),
+            GCPhase::Sweep => self.sweep_step(self.step_size)
+        }
+    }
+
+    unsafe fn finish_cycle(&mut self) {
+        if let GCPhase::Mark = self.phase {
+            self.mark_step(usize::MAX);
+        }
+        if let GCPhase::Sweep = self.phase {
+            self.sweep_step(usize::MAX);
+        }
+    }
+}
+
+impl Default for IncrementalAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for IncrementalAlloc {
+    fn drop(&mut self) {
+        for value /*: &Value*/ in self.managed.iter() {
+            let ownership_info: u8 = unsafe { value.ownership_info() as u8 };
+
+            if ownership_info & OWN_INFO_COLLECT_MASK == 0 {
+                panic!("failed to re-claim object {:?} on destruction, ownership_info = {:0b}",
+                       unsafe { value.ptr_repr },
+                       ownership_info);
+            }
+
+            if value.is_container() {
+                unsafe {
+                    let container: *mut () = value.untagged_ptr_field() as *mut _;
+                    let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+                    ((*vt).drop_fn)(container);
+                }
+            } else {
+                let boxed: Box<dyn DynBase> = unsafe {
+                    let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
+                    Box::from_raw(dyn_base)
+                };
+                drop(boxed);
+            }
+        }
+    }
+}
+
+unsafe impl Send for IncrementalAlloc {}
+unsafe impl Sync for IncrementalAlloc {}
+
+impl Alloc for IncrementalAlloc {
+    unsafe fn add_stack(&mut self, stack: *const Stack) {
+        self.stacks.push(stack);
+        self.stacks.sort();
+    }
+
+    unsafe fn remove_stack(&mut self, stack: *const Stack) {
+        let _removed = self.stacks.remove(self.stacks.binary_search(&stack).unwrap_unchecked());
+    }
+
+    unsafe fn set_globals(&mut self, globals: *const [Value]) {
+        self.globals = globals;
+    }
+
+    #[inline(never)]
+    unsafe fn add_managed(&mut self, data: Value) {
+        let size: usize = data.memory_size();
+        let over_quota: bool = matches!(self.quota, Some(quota) if self.usage + size > quota);
+        if self.gc_allowed {
+            if over_quota {
+                self.collect();
+            } else {
+                self.step();
+            }
+        }
+
+        // objects allocated during marking may hold unmarked children, so they get scanned;
+        // otherwise they survive the ongoing sweep, and become white once the next cycle starts
+        if let GCPhase::Mark = self.phase {
+            data.set_gc_info(GC_WHITE);
+            Self::shade(&mut self.gray, self.black, data);
+        } else {
+            data.set_gc_info(self.black);
+        }
+        self.managed.push(data);
+        self.debt += 1;
+        self.usage += size;
+    }
+
+    #[inline(always)]
+    unsafe fn mark_object(&mut self, data: Value) {
+        if let GCPhase::Mark = self.phase {
+            Self::shade(&mut self.gray, self.black, data);
+        }
+    }
+
+    #[inline(never)]
+    unsafe fn pin_objects(&mut self, pinned: &[Value]) -> *mut bool {
+        self.pin_debt += 1;
+        if self.pin_debt > self.max_pin_debt {
+            self.cleanup_pins();
+        }
+
+        let pin: AllocPin = AllocPin::new(true, pinned);
+        let ret_ptr: *mut bool = pin.as_ptr().ptr_fixed.as_ptr();
+        self.pinned.push(pin);
+        ret_ptr
+    }
+
+    /// Finishes the ongoing cycle, then runs a whole new one, so that objects unreachable now
+    /// all get freed
+    #[inline(never)]
+    unsafe fn collect(&mut self) {
+        self.finish_cycle();
+        self.start_cycle();
+        self.finish_cycle();
+    }
+
+    #[inline(always)]
+    fn set_gc_allowed(&mut self, allowed: bool) {
+        self.gc_allowed = allowed;
+    }
+
+    #[inline(never)]
+    unsafe fn adjust_usage(&mut self, delta: isize) {
+        if delta < 0 {
+            self.usage = self.usage.saturating_sub(delta.unsigned_abs());
+            return;
+        }
+        self.usage += delta as usize;
+        if matches!(self.quota, Some(quota) if self.usage > quota) && self.gc_allowed {
+            self.collect();
+        }
+    }
+
+    #[inline(always)]
+    fn usage(&self) -> usize {
+        self.usage
+    }
+
+    #[inline(always)]
+    fn set_quota(&mut self, quota: Option<usize>) {
+        self.quota = quota;
+    }
+
+    #[inline(always)]
+    fn quota(&self) -> Option<usize> {
+        self.quota
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::builtins::test_container::TestContainer;
+    use crate::data::Value;
+    use crate::vm::al31f::alloc::Alloc;
+    use crate::vm::al31f::alloc::incremental_alloc::{GCPhase, IncrementalAlloc};
+    use crate::vm::al31f::stack::{Stack, StackSlice};
+
+    #[cfg(feature = "al31f-builtin-ops")] use crate::vm::al31f::asm::assemble;
+    #[cfg(feature = "al31f-builtin-ops")] use crate::vm::al31f::compiled::CompiledProgram;
+    #[cfg(feature = "al31f-builtin-ops")] use crate::vm::al31f::executor::vm_run_function_sync;
+    #[cfg(feature = "al31f-builtin-ops")] use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+    #[test] fn test_incremental_collector_simple() {
+        let mut alloc: IncrementalAlloc = IncrementalAlloc::new();
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 3, &[]) };
+
+        let str1: Value = Value::new_owned::<String>("114".into());
+        let str2: Value = Value::new_owned::<String>("514".into());
+        let str3: Value = Value::new_owned::<String>("1919810".into());
+
+        let mut container: TestContainer<String> = TestContainer::new();
+        container.inner.elements.push(str1);
+        container.inner.elements.push(str2);
+        container.inner.elements.push(str3);
+
+        let container: Value = Value::new_owned::<TestContainer<String>>(container);
+
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(str1);
+            alloc.add_managed(str2);
+            alloc.add_managed(str3);
+            alloc.add_managed(container);
+
+            stack_slice.set_value(0, container);
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+            assert!(alloc.contains_ptr(str2.ptr_repr));
+            assert!(alloc.contains_ptr(str3.ptr_repr));
+            assert!(alloc.contains_ptr(container.ptr_repr));
+
+            stack_slice.set_value(0, str1);
+            stack_slice.set_value(1, str2);
+            stack_slice.set_value(2, str3);
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+            assert!(alloc.contains_ptr(str2.ptr_repr));
+            assert!(alloc.contains_ptr(str3.ptr_repr));
+            assert!(!alloc.contains_ptr(container.ptr_repr));
+
+            stack_slice.set_value(1, Value::new_null());
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+            assert!(!alloc.contains_ptr(str2.ptr_repr));
+            assert!(alloc.contains_ptr(str3.ptr_repr));
+            assert!(!alloc.contains_ptr(container.ptr_repr));
+            assert_eq!(alloc.usage(), str1.memory_size() + str3.memory_size());
+        }
+    }
+
+    #[test] fn test_incremental_collector_steps() {
+        let mut alloc: IncrementalAlloc = IncrementalAlloc::with_params(4, 128, 2);
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 1, &[]) };
+
+        let mut container: TestContainer<String> = TestContainer::new();
+        let mut kept: Vec<Value> = Vec::new();
+        for i /*: usize*/ in 0..16 {
+            let s: Value = Value::new_owned::<String>(i.to_string());
+            container.inner.elements.push(s);
+            kept.push(s);
+        }
+        let container: Value = Value::new_owned::<TestContainer<String>>(container);
+
+        unsafe {
+            alloc.add_stack(&stack);
+            for s /*: &Value*/ in kept.iter() {
+                alloc.add_managed(*s);
+            }
+            alloc.add_managed(container);
+            stack_slice.set_value(0, container);
+            alloc.set_gc_allowed(true);
+
+            let mut garbage: Vec<Value> = Vec::new();
+            let mut marking_allocs: usize = 0;
+            for i /*: usize*/ in 0..256 {
+                let s: Value = Value::new_owned::<String>(i.to_string());
+                alloc.add_managed(s);
+                garbage.push(s);
+                if let GCPhase::Mark = alloc.phase {
+                    marking_allocs += 1;
+                }
+            }
+            // marking is spread over allocations, instead of being done at once
+            assert!(marking_allocs > 1);
+
+            for s /*: &Value*/ in kept.iter() {
+                assert!(alloc.contains_ptr(s.ptr_repr));
+            }
+            assert!(alloc.contains_ptr(container.ptr_repr));
+            assert!(garbage.iter().any(|s: &Value| !alloc.contains_ptr(s.ptr_repr)));
+        }
+    }
+
+    #[test] fn test_incremental_collector_write_barrier() {
+        let mut alloc: IncrementalAlloc = IncrementalAlloc::with_params(0, 128, 1);
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 2, &[]) };
+
+        let moved: Value = Value::new_owned::<String>("moved".into());
+        let mut source: TestContainer<String> = TestContainer::new();
+        source.inner.elements.push(moved);
+        let source: Value = Value::new_owned::<TestContainer<String>>(source);
+        let target: Value = Value::new_owned::<TestContainer<String>>(TestContainer::new());
+
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(moved);
+            alloc.add_managed(source);
+            alloc.add_managed(target);
+            stack_slice.set_value(0, source);
+            stack_slice.set_value(1, target);
+
+            // roots are scanned in order, so `target` gets blackened first
+            alloc.start_cycle();
+            alloc.mark_step(1);
+            assert!(matches!(alloc.phase, GCPhase::Mark));
+            assert_eq!(target.gc_info(), alloc.black);
+
+            // move the only reference to `moved` from the unscanned `source` to the black `target`
+            (*source.get_as_mut_ptr::<TestContainer<String>>()).inner.elements.clear();
+            alloc.mark_object(moved);
+            (*target.get_as_mut_ptr::<TestContainer<String>>()).inner.elements.push(moved);
+
+            alloc.finish_cycle();
+            assert!(matches!(alloc.phase, GCPhase::Idle));
+            assert!(alloc.contains_ptr(moved.ptr_repr));
+            assert!(alloc.contains_ptr(source.ptr_repr));
+            assert!(alloc.contains_ptr(target.ptr_repr));
+
+            stack_slice.set_value(1, Value::new_null());
+            alloc.collect();
+            assert!(!alloc.contains_ptr(moved.ptr_repr));
+            assert!(alloc.contains_ptr(source.ptr_repr));
+            assert!(!alloc.contains_ptr(target.ptr_repr));
+        }
+    }
+
+    #[cfg(feature = "al31f-builtin-ops")]
+    #[test] fn test_incremental_collector_program() {
+        let program: CompiledProgram<IncrementalAlloc> = assemble(r#"
+            .const "x"
+            .func main entry L.main args 0 rets 1 stack 8
+
+            L.main:
+                %0 = create-container <vector<string>>
+                %1 = int $0
+                %2 = int $1
+                %3 = int $20000
+                %4 = load 0
+            L.fill:
+                %5 = lt int %1, %3
+                if not %5 goto L.filled
+                %6 = str-concat %4, %4
+                %7 = new object
+                vec-push %0, %6
+                %1 = add int %1, %2
+                goto L.fill
+            L.filled:
+                %1 = int $0
+                %7 = int $0
+            L.sum:
+                %5 = lt int %1, %3
+                if not %5 goto L.done
+                %6 = vec-index %0, %1
+                %5 = str-len %6
+                %7 = add int %7, %5
+                %1 = add int %1, %2
+                goto L.sum
+            L.done:
+                ret %7
+        "#, &FFIRegistry::new()).unwrap();
+
+        let alloc: IncrementalAlloc = IncrementalAlloc::with_params(64, 128, 4);
+        let result: Vec<Value> = unsafe { vm_run_function_sync(alloc, &program, 0, &[]) }
+            .unwrap_or_else(|_| panic!("should not throw"));
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 40000);
+    }
+}
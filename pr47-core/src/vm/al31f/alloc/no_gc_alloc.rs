@@ -3,13 +3,17 @@ use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::stack::Stack;
 
 pub struct NoGCAlloc {
-    managed: Vec<Value>
+    managed: Vec<Value>,
+    usage: usize,
+    quota: Option<usize>
 }
 
 impl NoGCAlloc {
     pub fn new() -> Self {
         Self {
-            managed: vec![]
+            managed: vec![],
+            usage: 0,
+            quota: None
         }
     }
 }
@@ -27,7 +31,10 @@ impl Alloc for NoGCAlloc {
 
     #[inline(always)] unsafe fn remove_stack(&mut self, _stack: *const Stack) {}
 
+    #[inline(always)] unsafe fn set_globals(&mut self, _globals: *const [Value]) {}
+
     unsafe fn add_managed(&mut self, data: Value) {
+        self.usage += data.memory_size();
         self.managed.push(data);
     }
 
@@ -40,6 +47,20 @@ impl Alloc for NoGCAlloc {
     #[inline(always)] unsafe fn collect(&mut self) {}
 
     #[inline(always)] fn set_gc_allowed(&mut self, _allowed: bool) {}
+
+    #[inline(always)] unsafe fn adjust_usage(&mut self, delta: isize) {
+        if delta < 0 {
+            self.usage = self.usage.saturating_sub(delta.unsigned_abs());
+        } else {
+            self.usage += delta as usize;
+        }
+    }
+
+    #[inline(always)] fn usage(&self) -> usize { self.usage }
+
+    #[inline(always)] fn set_quota(&mut self, quota: Option<usize>) { self.quota = quota; }
+
+    #[inline(always)] fn quota(&self) -> Option<usize> { self.quota }
 }
 
 unsafe impl Send for NoGCAlloc {}
@@ -0,0 +1,1331 @@
+//! ## `asm.rs`: textual assembler and disassembler for compiled programs
+//!
+//! The assembly form (`.p47asm`) is line based. Instructions are written the same way as
+//! `Insc::unsafe_to_string` prints them, and directives describe everything else:
+//!
+//! ```text
+//! ; comments start with a semicolon
+//! .const "hello"                  ; constant pool entries, in order: null, int 42,
+//!                                 ; float 1.5, bool true or a string literal
+//! .global counter                 ; global variables, in order
+//! .ffi print                      ; host functions, bound by name against an FFIRegistry
+//! .async-ffi fetch
+//! .init F.0                       ; the init procedure, defaults to function 0
+//! .overload F.f_int, F.f_str      ; overload tables, referred as `O.<id>`
+//! .func fib entry L.fib args 1 rets 1 stack 4 params int
+//! .catch L.try_start L.try_end string L.handler
+//!
+//! L.fib:
+//!     %1 = int $2
+//!     %2 = lt int %0, %1
+//!     if %2 goto L.base
+//!     ...
+//! L.base:
+//!     ret %0
+//! ```
+//!
+//! Functions get their ids in the order of `.func` directives, and `_` names an anonymous
+//! function. Overload tables are numbered likewise in the order of `.overload` directives.
+//! `.catch` adds an exception handler to the last declared function, catching exceptions of the
+//! given type, all exceptions (`any`), or all exceptions for a `finally` block (`finally`).
+//! Handlers are tried in the order of `.catch` directives. Functions and host functions may be
+//! referred either by id (`F.0`) or by name (`F.fib`). Labels are names of instruction
+//! addresses, and the disassembler uses `L.<address>` for all of them.
+
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::ptr::NonNull;
+
+use xjbutil::slice_arena::SliceArena;
+use xjbutil::unchecked::UnsafeFrom;
+
+use crate::builtins::closure::create_closure_vt;
+use crate::builtins::vec::{VMGenericVec, create_vm_vec_vt, vec_ctor};
+use crate::data::Value;
+use crate::data::generic::GenericTypeVT;
+use crate::data::tyck::{TyckInfo, TyckInfoPool, builtin_type_id, builtin_type_name};
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::data::wrapper::{
+    OWN_INFO_COLLECT_MASK,
+    OWN_INFO_GLOBAL_MASK,
+    OWN_INFO_MOVE_MASK,
+    OWN_INFO_OWNED_MASK,
+    OWN_INFO_READ_MASK,
+    OWN_INFO_WRITE_MASK,
+    OwnershipInfo
+};
+use crate::ffi::sync_fn::Function as FFIFunction;
+use crate::vm::al31f::Combustor;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    DebugInfo,
+    ExceptionHandlingBlock,
+    HandlerKind,
+    OverloadTable
+};
+use crate::vm::al31f::ffi_registry::FFIRegistry;
+use crate::vm::al31f::insc::Insc;
+
+#[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
+#[cfg(feature = "async")] use crate::vm::al31f::{AL31F, AsyncCombustor};
+
+#[derive(Debug)]
+pub struct AsmError {
+    /// 1-based line number
+    pub line: usize,
+    pub message: String
+}
+
+type AsmResult<T> = Result<T, AsmError>;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Reg(usize),
+    Imm(&'a str),
+    Str(String),
+    Punct(char),
+    Arrow
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' || ch == '-'
+}
+
+fn tokenize(line: &str, line_no: usize) -> AsmResult<Vec<Token<'_>>> {
+    let error = |message: &str| AsmError { line: line_no, message: message.to_string() };
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        match ch {
+            ';' => break,
+            ' ' | '\t' | '\r' => {},
+            '-' if matches!(chars.peek(), Some((_, '>'))) => {
+                chars.next();
+                tokens.push(Token::Arrow);
+            },
+            '%' => {
+                let mut end: usize = start + 1;
+                while let Some((idx, ch)) = chars.peek() {
+                    if !ch.is_ascii_digit() {
+                        break;
+                    }
+                    end = *idx + 1;
+                    chars.next();
+                }
+                let reg: usize = line[start + 1..end].parse()
+                    .map_err(|_| error("expected register number after `%`"))?;
+                tokens.push(Token::Reg(reg));
+            },
+            '$' => {
+                let mut end: usize = start + 1;
+                while let Some((idx, ch)) = chars.peek() {
+                    if ch.is_whitespace() || *ch == ',' || *ch == ';' {
+                        break;
+                    }
+                    end = *idx + ch.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Imm(&line[start + 1..end]));
+            },
+            '"' => {
+                let mut value: String = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => value.push(unescape(&mut chars, line_no)?),
+                        Some((_, ch)) => value.push(ch),
+                        None => return Err(error("unterminated string literal"))
+                    }
+                }
+                tokens.push(Token::Str(value));
+            },
+            '=' | ',' | '[' | ']' | '<' | '>' | '(' | ')' | '?' | ':' => {
+                tokens.push(Token::Punct(ch));
+            },
+            _ if is_word_char(ch) => {
+                let mut end: usize = start + ch.len_utf8();
+                while let Some((idx, ch)) = chars.peek() {
+                    if !is_word_char(*ch) {
+                        break;
+                    }
+                    end = *idx + ch.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Word(&line[start..end]));
+            },
+            _ => return Err(error(&format!("unexpected character `{}`", ch)))
+        }
+    }
+    Ok(tokens)
+}
+
+/// Unescapes escape sequences printed by `str`'s `Debug` implementation
+fn unescape(
+    chars: &mut impl Iterator<Item = (usize, char)>,
+    line_no: usize
+) -> AsmResult<char> {
+    let error = || AsmError { line: line_no, message: "invalid escape sequence".to_string() };
+    let escaped: char = match chars.next().ok_or_else(error)?.1 {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        '\\' => '\\',
+        '\'' => '\'',
+        '"' => '"',
+        'u' => {
+            if chars.next().map(|(_, ch)| ch) != Some('{') {
+                return Err(error());
+            }
+            let mut code: String = String::new();
+            loop {
+                match chars.next().ok_or_else(error)?.1 {
+                    '}' => break,
+                    ch => code.push(ch)
+                }
+            }
+            u32::from_str_radix(&code, 16).ok().and_then(char::from_u32).ok_or_else(error)?
+        },
+        _ => return Err(error())
+    };
+    Ok(escaped)
+}
+
+/// Constants are kept symbolic until the whole program is assembled, so that nothing leaks on
+/// errors
+enum AsmConst {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String)
+}
+
+/// Assembles a program from its textual form, binding host functions against `ffi_registry`
+pub fn assemble<A: Alloc>(
+    source: &str,
+    ffi_registry: &FFIRegistry<A>
+) -> Result<CompiledProgram<A>, AsmError> {
+    let mut lines: Vec<(usize, Vec<Token>)> = Vec::new();
+    for (i, line) /*: (usize, &str)*/ in source.lines().enumerate() {
+        let tokens: Vec<Token> = tokenize(line, i + 1)?;
+        if !tokens.is_empty() {
+            lines.push((i + 1, tokens));
+        }
+    }
+
+    let mut assembler: Assembler<A> = Assembler::new(ffi_registry);
+    assembler.collect_symbols(&lines)?;
+    for (line_no, tokens) /*: &(usize, Vec<Token>)*/ in lines.iter() {
+        let mut parser: LineParser = LineParser { tokens, pos: 0, line_no: *line_no };
+        assembler.assemble_line(&mut parser)?;
+    }
+    Ok(assembler.finish())
+}
+
+struct Assembler<'r, A: Alloc> {
+    ffi_registry: &'r FFIRegistry<A>,
+
+    labels: HashMap<String, usize>,
+    func_names: HashMap<String, usize>,
+    ffi_names: HashMap<String, usize>,
+    async_ffi_names: HashMap<String, usize>,
+
+    slice_arena: SliceArena<8192, 8>,
+    tyck_info_pool: TyckInfoPool,
+    vts: Vec<GenericTypeVT>,
+    vt_ids: HashMap<(NonNull<TyckInfo>, bool), usize>,
+    /// `(insc_ptr, vt_id)` pairs, pointing instructions to their vtables once all vtables are
+    /// settled
+    vt_fixups: Vec<(usize, usize)>,
+
+    code: Vec<Insc>,
+    consts: Vec<AsmConst>,
+    global_names: Vec<String>,
+    init_proc: usize,
+    functions: Vec<CompiledFunction>,
+    func_ids: HashMap<String, usize>,
+    overload_tables: Vec<OverloadTable>,
+    ffi_funcs: Vec<&'static dyn FFIFunction<Combustor<A>>>,
+    ffi_func_names: Vec<String>,
+    #[cfg(feature = "async")]
+    async_ffi_funcs: Vec<&'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>>,
+    #[cfg(feature = "async")]
+    async_ffi_func_names: Vec<String>
+}
+
+impl<'r, A: Alloc> Assembler<'r, A> {
+    fn new(ffi_registry: &'r FFIRegistry<A>) -> Self {
+        Self {
+            ffi_registry,
+
+            labels: HashMap::new(),
+            func_names: HashMap::new(),
+            ffi_names: HashMap::new(),
+            async_ffi_names: HashMap::new(),
+
+            slice_arena: SliceArena::new(),
+            tyck_info_pool: TyckInfoPool::new(),
+            vts: Vec::new(),
+            vt_ids: HashMap::new(),
+            vt_fixups: Vec::new(),
+
+            code: Vec::new(),
+            consts: Vec::new(),
+            global_names: Vec::new(),
+            init_proc: 0,
+            functions: Vec::new(),
+            func_ids: HashMap::new(),
+            overload_tables: Vec::new(),
+            ffi_funcs: Vec::new(),
+            ffi_func_names: Vec::new(),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: Vec::new(),
+            #[cfg(feature = "async")]
+            async_ffi_func_names: Vec::new()
+        }
+    }
+
+    /// Collects labels and names of functions, so that they can be referred before declared
+    fn collect_symbols(&mut self, lines: &[(usize, Vec<Token>)]) -> AsmResult<()> {
+        let mut insc_count: usize = 0;
+        let mut func_count: usize = 0;
+        for (line_no, tokens) /*: &(usize, Vec<Token>)*/ in lines {
+            let error = |message: String| AsmError { line: *line_no, message };
+            match (&tokens[0], tokens.get(1)) {
+                (Token::Word(".func"), Some(Token::Word(name))) => {
+                    if *name != "_"
+                        && self.func_names.insert(name.to_string(), func_count).is_some()
+                    {
+                        return Err(error(format!("duplicate function `{}`", name)));
+                    }
+                    func_count += 1;
+                },
+                (Token::Word(".ffi"), Some(Token::Word(name))) => {
+                    let ffi_func_id: usize = self.ffi_names.len();
+                    self.ffi_names.insert(name.to_string(), ffi_func_id);
+                },
+                (Token::Word(".async-ffi"), Some(Token::Word(name))) => {
+                    let async_ffi_func_id: usize = self.async_ffi_names.len();
+                    self.async_ffi_names.insert(name.to_string(), async_ffi_func_id);
+                },
+                (Token::Word(directive), _) if directive.starts_with('.') => {},
+                (Token::Word(label), Some(Token::Punct(':'))) if label.starts_with("L.") => {
+                    if tokens.len() != 2 {
+                        return Err(error("labels should be on their own lines".to_string()));
+                    }
+                    if self.labels.insert(label.to_string(), insc_count).is_some() {
+                        return Err(error(format!("duplicate label `{}`", label)));
+                    }
+                },
+                _ => insc_count += 1
+            }
+        }
+        Ok(())
+    }
+
+    fn assemble_line(&mut self, p: &mut LineParser) -> AsmResult<()> {
+        match p.peek() {
+            Some(Token::Word(directive)) if directive.starts_with('.') => {
+                p.pos += 1;
+                self.assemble_directive(directive, p)
+            },
+            Some(Token::Word(label)) if label.starts_with("L.")
+                && p.tokens.get(1) == Some(&Token::Punct(':')) => Ok(()),
+            _ => {
+                let insc: Insc = self.assemble_insc(p)?;
+                p.expect_end()?;
+                self.code.push(insc);
+                Ok(())
+            }
+        }
+    }
+
+    fn assemble_directive(&mut self, directive: &str, p: &mut LineParser) -> AsmResult<()> {
+        match directive {
+            ".const" => {
+                let value: AsmConst = match p.next()? {
+                    Token::Str(value) => AsmConst::Str(value.clone()),
+                    Token::Word("null") => AsmConst::Null,
+                    Token::Word("int") => AsmConst::Int(p.parse_word()?),
+                    Token::Word("float") => AsmConst::Float(p.parse_word()?),
+                    Token::Word("bool") => AsmConst::Bool(p.parse_word()?),
+                    _ => return Err(p.error("expected constant"))
+                };
+                self.consts.push(value);
+            },
+            ".global" => self.global_names.push(p.word()?.to_string()),
+            ".ffi" => {
+                let name: &str = p.word()?;
+                let ffi_func: &'static dyn FFIFunction<Combustor<A>> = self.ffi_registry
+                    .get_func(name)
+                    .ok_or_else(|| p.error(&format!("unresolved host function `{}`", name)))?;
+                self.ffi_funcs.push(ffi_func);
+                self.ffi_func_names.push(name.to_string());
+            },
+            ".async-ffi" => {
+                let name: &str = p.word()?;
+                #[cfg(feature = "async")]
+                {
+                    let async_ffi_func: &'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>
+                        = self.ffi_registry.get_async_func(name).ok_or_else(|| {
+                            p.error(&format!("unresolved async host function `{}`", name))
+                        })?;
+                    self.async_ffi_funcs.push(async_ffi_func);
+                    self.async_ffi_func_names.push(name.to_string());
+                }
+                #[cfg(not(feature = "async"))]
+                return Err(p.error(&format!("unresolved async host function `{}`", name)));
+            },
+            ".init" => self.init_proc = self.func_ref(p)?,
+            ".func" => self.assemble_func(p)?,
+            ".overload" => {
+                let mut candidates: Vec<usize> = vec![self.func_ref(p)?];
+                while p.eat(&Token::Punct(',')) {
+                    candidates.push(self.func_ref(p)?);
+                }
+                self.overload_tables.push(OverloadTable::new(candidates.into_boxed_slice()));
+            },
+            ".catch" => {
+                let insc_ptr_start: usize = self.label(p)?;
+                let insc_ptr_end: usize = self.label(p)?;
+                let type_name: &str = p.word()?;
+                let kind: HandlerKind = match type_name {
+                    "any" => HandlerKind::CatchAll,
+                    "finally" => HandlerKind::Finally,
+                    _ => HandlerKind::Typed(
+                        builtin_type_id(type_name)
+                            .or_else(|| self.ffi_registry.exception_types().get(type_name).copied())
+                            .ok_or_else(|| p.error(&format!("unknown type `{}`", type_name)))?
+                    )
+                };
+                let handler_addr: usize = self.label(p)?;
+                let exc_handler: ExceptionHandlingBlock = ExceptionHandlingBlock::new(
+                    insc_ptr_start,
+                    insc_ptr_end,
+                    kind,
+                    handler_addr
+                );
+
+                let function: &mut CompiledFunction = self.functions.last_mut()
+                    .ok_or_else(|| p.error("`.catch` should follow a `.func`"))?;
+                let mut exc_handlers: Vec<ExceptionHandlingBlock> =
+                    function.exc_handlers.take().map_or_else(Vec::new, Vec::from);
+                exc_handlers.push(exc_handler);
+                function.exc_handlers = Some(exc_handlers.into_boxed_slice());
+            },
+            _ => return Err(p.error(&format!("unknown directive `{}`", directive)))
+        }
+        p.expect_end()
+    }
+
+    fn assemble_func(&mut self, p: &mut LineParser) -> AsmResult<()> {
+        let name: &str = p.word()?;
+        let mut start_addr: Option<usize> = None;
+        let (mut arg_count, mut ret_count, mut stack_size): (usize, usize, usize) = (0, 0, 0);
+        let mut param_tyck_info: Vec<Option<NonNull<TyckInfo>>> = Vec::new();
+        while !p.at_end() {
+            match p.word()? {
+                "entry" => start_addr = Some(self.label(p)?),
+                "args" => arg_count = p.parse_word()?,
+                "rets" => ret_count = p.parse_word()?,
+                "stack" => stack_size = p.parse_word()?,
+                "params" => loop {
+                    if p.eat(&Token::Word("-")) {
+                        param_tyck_info.push(None);
+                    } else {
+                        param_tyck_info.push(Some(self.parse_type(p)?));
+                    }
+                    if !p.eat(&Token::Punct(',')) {
+                        break;
+                    }
+                },
+                attr => return Err(p.error(&format!("unknown function attribute `{}`", attr)))
+            }
+        }
+
+        let start_addr: usize = start_addr.ok_or_else(|| p.error("function has no `entry`"))?;
+        if name != "_" {
+            self.func_ids.insert(name.to_string(), self.functions.len());
+        }
+        self.functions.push(CompiledFunction::new(
+            start_addr,
+            arg_count,
+            ret_count,
+            stack_size,
+            param_tyck_info.into_boxed_slice()
+        ));
+        Ok(())
+    }
+
+    fn label(&self, p: &mut LineParser) -> AsmResult<usize> {
+        let label: &str = p.word()?;
+        self.labels.get(label)
+            .copied()
+            .ok_or_else(|| p.error(&format!("undefined label `{}`", label)))
+    }
+
+    /// Resolves `F.<id>` or `F.<name>` against `names`
+    fn resolve_ref(p: &mut LineParser, names: &HashMap<String, usize>) -> AsmResult<usize> {
+        let word: &str = p.word()?;
+        let name: &str = word.strip_prefix("F.")
+            .ok_or_else(|| p.error(&format!("expected function reference, got `{}`", word)))?;
+        if let Ok(id) = name.parse::<usize>() {
+            return Ok(id);
+        }
+        names.get(name).copied().ok_or_else(|| p.error(&format!("unknown function `{}`", name)))
+    }
+
+    fn func_ref(&self, p: &mut LineParser) -> AsmResult<usize> {
+        Self::resolve_ref(p, &self.func_names)
+    }
+
+    fn parse_type(&mut self, p: &mut LineParser) -> AsmResult<NonNull<TyckInfo>> {
+        let mut tyck_info: NonNull<TyckInfo> = match p.word()? {
+            "any" => self.tyck_info_pool.get_any_type(),
+            "func" => {
+                let params: Vec<NonNull<TyckInfo>> = self.parse_types(p, '(', ')')?;
+                p.expect(&Token::Arrow)?;
+                let rets: Vec<NonNull<TyckInfo>> = self.parse_types(p, '(', ')')?;
+                let exceptions: Vec<NonNull<TyckInfo>> = if p.eat(&Token::Word("throws")) {
+                    self.parse_types(p, '(', ')')?
+                } else {
+                    Vec::new()
+                };
+                self.tyck_info_pool.create_function_type(&params, &rets, &exceptions)
+            },
+            type_name => {
+                if p.peek() == Some(&Token::Punct('<')) {
+                    let type_id: TypeId = builtin_type_id(type_name)
+                        .ok_or_else(|| p.error(&format!("unknown type `{}`", type_name)))?;
+                    let params: Vec<NonNull<TyckInfo>> = self.parse_types(p, '<', '>')?;
+                    self.tyck_info_pool.create_container_type(type_id, &params)
+                } else {
+                    self.tyck_info_pool.get_builtin_type(type_name)
+                        .ok_or_else(|| p.error(&format!("unknown type `{}`", type_name)))?
+                }
+            }
+        };
+        while p.eat(&Token::Punct('?')) {
+            tyck_info = self.tyck_info_pool.create_nullable_type(tyck_info);
+        }
+        Ok(tyck_info)
+    }
+
+    fn parse_types(
+        &mut self,
+        p: &mut LineParser,
+        open: char,
+        close: char
+    ) -> AsmResult<Vec<NonNull<TyckInfo>>> {
+        p.expect(&Token::Punct(open))?;
+        let mut types: Vec<NonNull<TyckInfo>> = Vec::new();
+        if p.eat(&Token::Punct(close)) {
+            return Ok(types);
+        }
+        loop {
+            types.push(self.parse_type(p)?);
+            if p.eat(&Token::Punct(close)) {
+                return Ok(types);
+            }
+            p.expect(&Token::Punct(','))?;
+        }
+    }
+
+    /// Parses a type in angle brackets, like `<vector<int>>`
+    fn parse_bracketed_type(&mut self, p: &mut LineParser) -> AsmResult<NonNull<TyckInfo>> {
+        p.expect(&Token::Punct('<'))?;
+        let tyck_info: NonNull<TyckInfo> = self.parse_type(p)?;
+        p.expect(&Token::Punct('>'))?;
+        Ok(tyck_info)
+    }
+
+    /// Gets the vtable of a vector or closure type for the instruction being assembled. The
+    /// returned pointer is a placeholder until the program is finished.
+    fn vt(&mut self, p: &LineParser, tyck_info: NonNull<TyckInfo>, closure: bool)
+        -> AsmResult<NonNull<GenericTypeVT>>
+    {
+        let vt_id: usize = if let Some(vt_id) = self.vt_ids.get(&(tyck_info, closure)) {
+            *vt_id
+        } else {
+            let vt: GenericTypeVT = match unsafe { tyck_info.as_ref() } {
+                TyckInfo::Container(container) if closure => unsafe {
+                    create_closure_vt(&mut self.tyck_info_pool, container.params.as_ref())
+                },
+                TyckInfo::Container(container)
+                    if container.type_id == TypeId::of::<VMGenericVec>()
+                        && unsafe { container.params.as_ref() }.len() == 1 =>
+                {
+                    let elem_type: NonNull<TyckInfo> = unsafe { container.params.as_ref()[0] };
+                    create_vm_vec_vt(&mut self.tyck_info_pool, elem_type)
+                },
+                _ => return Err(p.error("unsupported container type"))
+            };
+            self.vts.push(vt);
+            self.vt_ids.insert((tyck_info, closure), self.vts.len() - 1);
+            self.vts.len() - 1
+        };
+        self.vt_fixups.push((self.code.len(), vt_id));
+        Ok(NonNull::dangling())
+    }
+
+    fn slice(&self, regs: &[usize]) -> &'static [usize] {
+        unsafe { self.slice_arena.unsafe_make(regs) }
+    }
+
+    fn assemble_insc(&mut self, p: &mut LineParser) -> AsmResult<Insc> {
+        match p.next()?.clone() {
+            Token::Reg(dst) => {
+                p.expect(&Token::Punct('='))?;
+                self.assemble_dst_insc(p, dst)
+            },
+            Token::Punct('[') => {
+                let dsts: Vec<usize> = p.reg_list()?;
+                p.expect(&Token::Punct(']'))?;
+                p.expect(&Token::Punct('='))?;
+                self.assemble_multi_dst_insc(p, &dsts)
+            },
+            Token::Word(op) => self.assemble_stmt_insc(p, op),
+            _ => Err(p.error("expected instruction"))
+        }
+    }
+
+    /// Assembles instructions like `%3 = add int %1, %2`
+    fn assemble_dst_insc(&mut self, p: &mut LineParser, dst: usize) -> AsmResult<Insc> {
+        let op: &str = match p.next()?.clone() {
+            Token::Reg(src) => return Ok(Insc::Move(src, dst)),
+            Token::Word(op) => op,
+            _ => return Err(p.error("expected operation"))
+        };
+
+        let insc: Insc = match op {
+            "add" | "sub" | "mul" | "div" | "mod" | "eq" | "ne" | "lt" | "le" | "band" | "bor"
+            | "bxor" | "and" | "or" | "shl" | "shr" => {
+                let kind: &str = p.kind()?;
+                let ctor: fn(usize, usize, usize) -> Insc = match (op, kind) {
+                    ("add", "int") => Insc::AddInt,
+                    ("add", "float") => Insc::AddFloat,
+                    ("add", "?") => Insc::AddAny,
+                    ("sub", "int") => Insc::SubInt,
+                    ("sub", "float") => Insc::SubFloat,
+                    ("sub", "?") => Insc::SubAny,
+                    ("mul", "int") => Insc::MulInt,
+                    ("mul", "float") => Insc::MulFloat,
+                    ("mul", "?") => Insc::MulAny,
+                    ("div", "int") => Insc::DivInt,
+                    ("div", "float") => Insc::DivFloat,
+                    ("div", "?") => Insc::DivAny,
+                    ("mod", "int") => Insc::ModInt,
+                    ("mod", "?") => Insc::ModAny,
+                    ("eq", "value") => Insc::EqValue,
+                    ("eq", "ref") => Insc::EqRef,
+                    ("eq", "?") => Insc::EqAny,
+                    ("ne", "value") => Insc::NeValue,
+                    ("ne", "ref") => Insc::NeRef,
+                    ("ne", "?") => Insc::NeAny,
+                    ("lt", "int") => Insc::LtInt,
+                    ("lt", "float") => Insc::LtFloat,
+                    ("lt", "?") => Insc::LtAny,
+                    ("le", "int") => Insc::LeInt,
+                    ("le", "float") => Insc::LeFloat,
+                    ("le", "?") => Insc::LeAny,
+                    ("band", "int") => Insc::BAndInt,
+                    ("band", "?") => Insc::BAndAny,
+                    ("bor", "int") => Insc::BOrInt,
+                    ("bor", "?") => Insc::BOrAny,
+                    ("bxor", "int") => Insc::BXorInt,
+                    ("bxor", "?") => Insc::BXorAny,
+                    ("and", "bool") => Insc::AndBool,
+                    ("and", "?") => Insc::AndAny,
+                    ("or", "bool") => Insc::OrBool,
+                    ("or", "?") => Insc::OrAny,
+                    ("shl", "int") => Insc::ShlInt,
+                    ("shl", "?") => Insc::ShlAny,
+                    ("shr", "int") => Insc::ShrInt,
+                    ("shr", "?") => Insc::ShrAny,
+                    _ => return Err(p.error(&format!("invalid operand kind `{} {}`", op, kind)))
+                };
+                let src1: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                ctor(src1, p.reg()?, dst)
+            },
+            "neg" | "not" | "bnot" => {
+                let kind: &str = p.kind()?;
+                let ctor: fn(usize, usize) -> Insc = match (op, kind) {
+                    ("neg", "int") => Insc::NegInt,
+                    ("neg", "float") => Insc::NegFloat,
+                    ("neg", "?") => Insc::NegAny,
+                    ("not", "bool") => Insc::NotBool,
+                    ("not", "?") => Insc::NotAny,
+                    ("bnot", "int") => Insc::BNotInt,
+                    ("bnot", "?") => Insc::BNotAny,
+                    _ => return Err(p.error(&format!("invalid operand kind `{} {}`", op, kind)))
+                };
+                ctor(p.reg()?, dst)
+            },
+            "cast" => {
+                let from: &str = p.kind()?;
+                let to: &str = p.kind()?;
+                let ctor: fn(usize, usize) -> Insc = match (from, to) {
+                    ("float", "int") => Insc::CastFloatInt,
+                    ("bool", "int") => Insc::CastBoolInt,
+                    ("?", "int") => Insc::CastAnyInt,
+                    ("int", "float") => Insc::CastIntFloat,
+                    ("?", "float") => Insc::CastAnyFloat,
+                    ("int", "bool") => Insc::CastIntBool,
+                    ("?", "bool") => Insc::CastAnyBool,
+                    _ => return Err(p.error(&format!("invalid cast from `{}` to `{}`", from, to)))
+                };
+                ctor(p.reg()?, dst)
+            },
+            "int" => Insc::MakeIntConst(p.imm()?, dst),
+            "float" => Insc::MakeFloatConst(p.imm()?, dst),
+            "bool" => Insc::MakeBoolConst(p.imm()?, dst),
+            "null" => Insc::MakeNull(dst),
+            "load" => Insc::LoadConst(p.parse_word()?, dst),
+            "load-global" => Insc::LoadGlobal(p.parse_word()?, dst),
+            "is-null" => Insc::IsNull(p.reg()?, dst),
+            "is-type" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::IsType(src, self.parse_bracketed_type(p)?, dst)
+            },
+            #[cfg(feature = "async")]
+            "ffi-call-async" => {
+                let async_ffi_func_id: usize = Self::resolve_ref(p, &self.async_ffi_names)?;
+                Insc::FFICallAsync(async_ffi_func_id, self.slice(&p.reg_list()?), dst)
+            },
+            "create-container" => {
+                let tyck_info: NonNull<TyckInfo> = self.parse_bracketed_type(p)?;
+                Insc::CreateContainer(vec_ctor, self.vt(p, tyck_info, false)?, dst)
+            },
+            "make-closure" => {
+                let func_id: usize = self.func_ref(p)?;
+                let tyck_info: NonNull<TyckInfo> = self.parse_bracketed_type(p)?;
+                let vt: NonNull<GenericTypeVT> = self.vt(p, tyck_info, true)?;
+                Insc::CreateClosure(func_id, self.slice(&p.reg_list()?), vt, dst)
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "new" => match p.word()? {
+                "string" => Insc::CreateString(dst),
+                "object" => Insc::CreateObject(dst),
+                _ => return Err(p.error("expected `string` or `object`"))
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "vec-index" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::VecIndex(src, p.reg()?, dst)
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "vec-len" => Insc::VecLen(p.reg()?, dst),
+            #[cfg(feature = "al31f-builtin-ops")]
+            "str-clone" => Insc::StrClone(p.reg()?, dst),
+            #[cfg(feature = "al31f-builtin-ops")]
+            "str-concat" => Insc::StrConcat(self.slice(&p.reg_list()?), dst),
+            #[cfg(feature = "al31f-builtin-ops")]
+            "str-len" => Insc::StrLen(p.reg()?, dst),
+            #[cfg(feature = "al31f-builtin-ops")]
+            "str-equals" => {
+                let src1: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::StrEquals(src1, p.reg()?, dst)
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "object-get" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                match p.next()?.clone() {
+                    Token::Reg(field) => Insc::ObjectGetDyn(src, field, dst),
+                    Token::Str(field) => Insc::ObjectGet(src, self.make_str(&field), dst),
+                    _ => return Err(p.error("expected field name"))
+                }
+            },
+            _ => return Err(p.error(&format!("unknown operation `{}`", op)))
+        };
+        Ok(insc)
+    }
+
+    /// Assembles instructions like `[%2, %3] = call F.0 %1`
+    fn assemble_multi_dst_insc(&mut self, p: &mut LineParser, dsts: &[usize]) -> AsmResult<Insc> {
+        let op: &str = p.word()?;
+        let insc: Insc = match op {
+            "call" => {
+                let func: Result<usize, usize> = if let Some(Token::Reg(func)) = p.peek() {
+                    let func: usize = *func;
+                    p.pos += 1;
+                    Err(func)
+                } else {
+                    Ok(self.func_ref(p)?)
+                };
+                let args: &'static [usize] = self.slice(&p.reg_list()?);
+                match func {
+                    Ok(func_id) => Insc::Call(func_id, args, self.slice(dsts)),
+                    Err(func) => Insc::CallPtr(func, args, self.slice(dsts))
+                }
+            },
+            "call-overload" => {
+                let word: &str = p.word()?;
+                let overload_table: usize = word.strip_prefix("O.")
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| p.error("expected overload table reference"))?;
+                Insc::CallOverload(overload_table, self.slice(&p.reg_list()?), self.slice(dsts))
+            },
+            "ffi-call" => {
+                let ffi_func_id: usize = Self::resolve_ref(p, &self.ffi_names)?;
+                Insc::FFICallRtlc(ffi_func_id, self.slice(&p.reg_list()?), self.slice(dsts))
+            },
+            #[cfg(feature = "optimized-rtlc")]
+            "ffi-call-unchecked" => {
+                let ffi_func_id: usize = Self::resolve_ref(p, &self.ffi_names)?;
+                Insc::FFICall(ffi_func_id, self.slice(&p.reg_list()?), self.slice(dsts))
+            },
+            #[cfg(feature = "async")]
+            "await" => Insc::Await(p.reg()?, self.slice(dsts)),
+            _ => return Err(p.error(&format!("unknown operation `{}`", op)))
+        };
+        Ok(insc)
+    }
+
+    /// Assembles instructions without destinations, like `goto L.1`
+    fn assemble_stmt_insc(&mut self, p: &mut LineParser, op: &str) -> AsmResult<Insc> {
+        let insc: Insc = match op {
+            "incr" => Insc::IncrInt(p.reg()?),
+            "decr" => Insc::DecrInt(p.reg()?),
+            "store" => {
+                let const_id: usize = p.parse_word()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::SaveConst(p.reg()?, const_id)
+            },
+            "store-global" => {
+                let global_id: usize = p.parse_word()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::StoreGlobal(p.reg()?, global_id)
+            },
+            "null-check" => Insc::NullCheck(p.reg()?),
+            "type-check" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::TypeCheck(src, self.parse_bracketed_type(p)?)
+            },
+            "ownership-info-check" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                p.expect(&Token::Punct('<'))?;
+                let flags: &str = p.word()?;
+                p.expect(&Token::Punct('>'))?;
+                Insc::OwnershipInfoCheck(src, parse_ownership_flags(flags).ok_or_else(|| {
+                    p.error(&format!("invalid ownership flags `{}`", flags))
+                })?)
+            },
+            "callable-check" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                let arg_count: usize = p.parse_word()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::CallableCheck(src, arg_count, p.parse_word()?)
+            },
+            "ret" => match p.peek() {
+                None => Insc::ReturnNothing,
+                Some(Token::Punct('[')) => {
+                    p.pos += 1;
+                    let srcs: Vec<usize> = p.reg_list()?;
+                    p.expect(&Token::Punct(']'))?;
+                    Insc::Return(self.slice(&srcs))
+                },
+                _ => Insc::ReturnOne(p.reg()?)
+            },
+            "raise" => Insc::Raise(p.reg()?),
+            "rethrow" => Insc::Rethrow(p.reg()?),
+            "if" => {
+                let negated: bool = p.eat(&Token::Word("not"));
+                let cond: usize = p.reg()?;
+                p.expect(&Token::Word("goto"))?;
+                let dest: usize = self.label(p)?;
+                if negated { Insc::JumpIfFalse(cond, dest) } else { Insc::JumpIfTrue(cond, dest) }
+            },
+            "goto" => Insc::Jump(self.label(p)?),
+            #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+            "spawn" => {
+                let func_id: usize = self.func_ref(p)?;
+                Insc::Spawn(func_id, self.slice(&p.reg_list()?))
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "vec-index-put" => {
+                let regs: Vec<usize> = p.reg_list()?;
+                if regs.len() != 3 {
+                    return Err(p.error("expected 3 registers"));
+                }
+                Insc::VecIndexPut(regs[0], regs[1], regs[2])
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "vec-push" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                Insc::VecPush(src, p.reg()?)
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            "object-put" => {
+                let src: usize = p.reg()?;
+                p.expect(&Token::Punct(','))?;
+                let field: Token = p.next()?.clone();
+                p.expect(&Token::Punct(','))?;
+                let value: usize = p.reg()?;
+                match field {
+                    Token::Reg(field) => Insc::ObjectPutDyn(src, field, value),
+                    Token::Str(field) => Insc::ObjectPut(src, self.make_str(&field), value),
+                    _ => return Err(p.error("expected field name"))
+                }
+            },
+            _ => return Err(p.error(&format!("unknown operation `{}`", op)))
+        };
+        Ok(insc)
+    }
+
+    #[cfg(feature = "al31f-builtin-ops")]
+    fn make_str(&self, s: &str) -> NonNull<str> {
+        unsafe {
+            let bytes: &'static [u8] = self.slice_arena.unsafe_make(s.as_bytes());
+            NonNull::from(std::str::from_utf8_unchecked(bytes))
+        }
+    }
+
+    fn finish(self) -> CompiledProgram<A> {
+        let mut code: Vec<Insc> = self.code;
+        let generic_vts: Box<[GenericTypeVT]> = self.vts.into_boxed_slice();
+        for (insc_ptr, vt_id) /*: (usize, usize)*/ in self.vt_fixups {
+            match &mut code[insc_ptr] {
+                Insc::CreateContainer(_, vt, _) | Insc::CreateClosure(_, _, vt, _) => {
+                    *vt = NonNull::from(&generic_vts[vt_id]);
+                },
+                _ => unreachable!()
+            }
+        }
+
+        let const_pool: Vec<Value> = self.consts.into_iter().map(|value| match value {
+            AsmConst::Null => Value::new_null(),
+            AsmConst::Int(value) => Value::new_int(value),
+            AsmConst::Float(value) => Value::new_float(value),
+            AsmConst::Bool(value) => Value::new_bool(value),
+            AsmConst::Str(value) => {
+                let value: Value = Value::new_owned(value);
+                unsafe { value.set_ownership_info(OwnershipInfo::GlobalConst); }
+                value
+            }
+        }).collect::<_>();
+
+        CompiledProgram {
+            slice_arena: self.slice_arena,
+            code: code.into_boxed_slice(),
+            const_pool: const_pool.into_boxed_slice(),
+            tyck_info_pool: self.tyck_info_pool,
+            global_names: self.global_names.into_boxed_slice(),
+            init_proc: self.init_proc,
+            functions: self.functions.into_boxed_slice(),
+            func_ids: self.func_ids,
+            generic_vts,
+            overload_tables: self.overload_tables.into_boxed_slice(),
+            exception_parents: self.ffi_registry.exception_parents().clone(),
+            exception_type_names: self.ffi_registry.exception_type_names().clone(),
+            debug_info: DebugInfo::default(),
+            ffi_funcs: self.ffi_funcs.into_boxed_slice(),
+            ffi_func_names: self.ffi_func_names.into_boxed_slice(),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: self.async_ffi_funcs.into_boxed_slice(),
+            #[cfg(feature = "async")]
+            async_ffi_func_names: self.async_ffi_func_names.into_boxed_slice()
+        }
+    }
+}
+
+const OWNERSHIP_FLAGS: [(char, u8); 6] = [
+    ('G', OWN_INFO_GLOBAL_MASK),
+    ('R', OWN_INFO_READ_MASK),
+    ('W', OWN_INFO_WRITE_MASK),
+    ('M', OWN_INFO_MOVE_MASK),
+    ('C', OWN_INFO_COLLECT_MASK),
+    ('O', OWN_INFO_OWNED_MASK)
+];
+
+/// Parses ownership flags printed like `-RW--O`
+fn parse_ownership_flags(flags: &str) -> Option<u8> {
+    if flags.chars().count() != OWNERSHIP_FLAGS.len() {
+        return None;
+    }
+
+    let mut mask: u8 = 0;
+    for (ch, (flag, flag_mask)) /*: (char, &(char, u8))*/ in
+        flags.chars().zip(OWNERSHIP_FLAGS.iter())
+    {
+        if ch == *flag {
+            mask |= flag_mask;
+        } else if ch != '-' {
+            return None;
+        }
+    }
+    Some(mask)
+}
+
+struct LineParser<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    pos: usize,
+    line_no: usize
+}
+
+impl<'t, 'a> LineParser<'t, 'a> {
+    fn error(&self, message: &str) -> AsmError {
+        AsmError { line: self.line_no, message: message.to_string() }
+    }
+
+    fn peek(&self) -> Option<&'t Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.tokens.len()
+    }
+
+    fn next(&mut self) -> AsmResult<&'t Token<'a>> {
+        let token: &'t Token<'a> = self.peek().ok_or_else(|| self.error("unexpected end of line"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> AsmResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", token)))
+        }
+    }
+
+    fn expect_end(&self) -> AsmResult<()> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.error(&format!("unexpected {:?}", self.tokens[self.pos])))
+        }
+    }
+
+    fn word(&mut self) -> AsmResult<&'a str> {
+        match self.next()? {
+            Token::Word(word) => Ok(*word),
+            token => Err(self.error(&format!("expected word, got {:?}", token)))
+        }
+    }
+
+    fn parse_word<T: std::str::FromStr>(&mut self) -> AsmResult<T> {
+        let word: &str = self.word()?;
+        word.parse().map_err(|_| self.error(&format!("invalid number `{}`", word)))
+    }
+
+    /// Reads operand kinds like `int` or `?`
+    fn kind(&mut self) -> AsmResult<&'a str> {
+        if self.eat(&Token::Punct('?')) {
+            Ok("?")
+        } else {
+            self.word()
+        }
+    }
+
+    fn imm<T: std::str::FromStr>(&mut self) -> AsmResult<T> {
+        match self.next()? {
+            Token::Imm(imm) => imm.parse()
+                .map_err(|_| self.error(&format!("invalid immediate `${}`", imm))),
+            token => Err(self.error(&format!("expected immediate, got {:?}", token)))
+        }
+    }
+
+    fn reg(&mut self) -> AsmResult<usize> {
+        match self.next()? {
+            Token::Reg(reg) => Ok(*reg),
+            token => Err(self.error(&format!("expected register, got {:?}", token)))
+        }
+    }
+
+    /// Reads a possibly empty list of registers separated by commas
+    fn reg_list(&mut self) -> AsmResult<Vec<usize>> {
+        let mut regs: Vec<usize> = Vec::new();
+        if let Some(Token::Reg(_)) = self.peek() {
+            regs.push(self.reg()?);
+            while self.eat(&Token::Punct(',')) {
+                regs.push(self.reg()?);
+            }
+        }
+        Ok(regs)
+    }
+}
+
+/// Disassembles a program into the form accepted by `assemble`
+pub unsafe fn disassemble<A: Alloc>(program: &CompiledProgram<A>) -> String {
+    let mut output: String = String::new();
+    for value /*: &Value*/ in program.const_pool.iter() {
+        let _ = writeln!(output, ".const {}", const_to_string(*value));
+    }
+    for global_name /*: &String*/ in program.global_names.iter() {
+        let _ = writeln!(output, ".global {}", global_name);
+    }
+    for (i, _) /*: (usize, _)*/ in program.ffi_funcs.iter().enumerate() {
+        match program.ffi_func_names.get(i) {
+            Some(name) => { let _ = writeln!(output, ".ffi {}", name); },
+            None => { let _ = writeln!(output, ".ffi <unknown>"); }
+        }
+    }
+    #[cfg(feature = "async")]
+    for (i, _) /*: (usize, _)*/ in program.async_ffi_funcs.iter().enumerate() {
+        match program.async_ffi_func_names.get(i) {
+            Some(name) => { let _ = writeln!(output, ".async-ffi {}", name); },
+            None => { let _ = writeln!(output, ".async-ffi <unknown>"); }
+        }
+    }
+    let _ = writeln!(output, ".init F.{}", program.init_proc);
+    for overload_table /*: &OverloadTable*/ in program.overload_tables.iter() {
+        let candidates: Vec<String> = overload_table.candidates.iter()
+            .map(|func_id: &usize| format!("F.{}", func_id))
+            .collect::<_>();
+        let _ = writeln!(output, ".overload {}", candidates.join(", "));
+    }
+
+    let mut func_names: HashMap<usize, &str> = HashMap::new();
+    for (func_name, func_id) /*: (&String, &usize)*/ in program.func_ids.iter() {
+        func_names.insert(*func_id, func_name);
+    }
+    let mut labels: BTreeSet<usize> = BTreeSet::new();
+    for (func_id, function) /*: (usize, &CompiledFunction)*/ in
+        program.functions.iter().enumerate()
+    {
+        labels.insert(function.start_addr);
+        let _ = write!(
+            output,
+            ".func {} entry L.{} args {} rets {} stack {}",
+            func_names.get(&func_id).copied().unwrap_or("_"),
+            function.start_addr,
+            function.arg_count,
+            function.ret_count,
+            function.stack_size
+        );
+        if !function.param_tyck_info.is_empty() {
+            let params: Vec<String> = function.param_tyck_info.iter()
+                .map(|param| match param {
+                    Some(param) => param.as_ref().unsafe_to_string(),
+                    None => "-".to_string()
+                })
+                .collect::<_>();
+            let _ = write!(output, " params {}", params.join(", "));
+        }
+        output.push('\n');
+
+        for exc_handler /*: &ExceptionHandlingBlock*/ in function.exc_handlers.iter().flatten() {
+            let (start, end): (usize, usize) = exc_handler.insc_ptr_range;
+            labels.extend([start, end, exc_handler.handler_addr].iter());
+            let _ = writeln!(
+                output,
+                ".catch L.{} L.{} {} L.{}",
+                start,
+                end,
+                match exc_handler.kind {
+                    HandlerKind::Typed(type_id) => builtin_type_name(type_id)
+                        .or_else(|| program.exception_type_names.get(&type_id).map(String::as_str))
+                        .unwrap_or("<unknown>"),
+                    HandlerKind::CatchAll => "any",
+                    HandlerKind::Finally => "finally"
+                },
+                exc_handler.handler_addr
+            );
+        }
+    }
+
+    for insc /*: &Insc*/ in program.code.iter() {
+        match insc {
+            Insc::JumpIfTrue(_, dest) | Insc::JumpIfFalse(_, dest) | Insc::Jump(dest) => {
+                labels.insert(*dest);
+            },
+            _ => {}
+        }
+    }
+
+    for (addr, insc) /*: (usize, &Insc)*/ in program.code.iter().enumerate() {
+        if labels.contains(&addr) {
+            let _ = writeln!(output, "L.{}:", addr);
+        }
+        let _ = writeln!(output, "    {}", insc.unsafe_to_string().trim_end());
+    }
+    if labels.contains(&program.code.len()) {
+        let _ = writeln!(output, "L.{}:", program.code.len());
+    }
+    output
+}
+
+unsafe fn const_to_string(value: Value) -> String {
+    if value.is_null() {
+        "null".to_string()
+    } else if value.is_value() {
+        match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+            ValueTypeTag::Int => format!("int {}", value.vt_data.inner.int_value),
+            ValueTypeTag::Float => format!("float {}", value.vt_data.inner.float_value),
+            ValueTypeTag::Bool => format!("bool {}", value.vt_data.inner.bool_value)
+        }
+    } else if !value.is_container()
+        && (*value.get_as_dyn_base()).dyn_type_id() == TypeId::of::<String>()
+    {
+        format!("{:?}", &*(value.get_as_mut_ptr::<String>() as *const String))
+    } else {
+        "<unsupported>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AsmError, assemble, disassemble};
+
+    use crate::data::Value;
+    use crate::driver::compile;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::executor::vm_run_function_sync;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+    use crate::vm::al31f::test_program::{
+        alloc_1m_program,
+        basic_fn_call_program,
+        basic_program,
+        exception_no_eh_program,
+        exception_program,
+        fibonacci_program
+    };
+
+    const FIBONACCI_ASM: &str = r#"
+        ; fibonacci(n) -> int
+        .func fibonacci entry L.fib args 1 rets 1 stack 4 params int
+
+        L.fib:
+            %1 = int $0
+            %2 = le int %0, %1
+            if %2 goto L.ret
+            %1 = int $1
+            %2 = eq value %0, %1
+            if %2 goto L.ret
+            %2 = sub int %0, %1
+            %1 = int $2
+            %3 = sub int %0, %1
+            [%2] = call F.fibonacci %2
+            [%3] = call F.0 %3
+            %1 = add int %2, %3
+        L.ret:
+            ret %1
+    "#;
+
+    fn assert_round_trip(program: CompiledProgram<DefaultAlloc>) {
+        let text: String = unsafe { disassemble(&program) };
+        let reassembled: CompiledProgram<DefaultAlloc> = assemble(&text, &FFIRegistry::new())
+            .unwrap_or_else(|e| panic!("should assemble: {:?}\n{}", e, text));
+        assert_eq!(unsafe { disassemble(&reassembled) }, text);
+    }
+
+    #[test]
+    fn test_asm_fibonacci() {
+        let program: CompiledProgram<DefaultAlloc> =
+            assemble(FIBONACCI_ASM, &FFIRegistry::new()).unwrap();
+        assert_eq!(program.func_ids.get("fibonacci"), Some(&0));
+
+        let result: Vec<Value> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), &program, 0, &[Value::new_int(10)])
+        }.unwrap_or_else(|_| panic!("should not throw"));
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 55);
+    }
+
+    #[test]
+    fn test_asm_round_trip() {
+        assert_round_trip(basic_program());
+        assert_round_trip(basic_fn_call_program());
+        assert_round_trip(fibonacci_program());
+        assert_round_trip(alloc_1m_program());
+        assert_round_trip(exception_program());
+        assert_round_trip(exception_no_eh_program());
+
+        assert_round_trip(compile(&[("main.47", r#"
+            var checked int = 0;
+
+            func check(x int): int {
+                checked += 1;
+                if x > 100 {
+                    throw "too large";
+                }
+                return x;
+            }
+
+            func guarded(x int): int {
+                try {
+                    return check(x) * 2;
+                } catch (e string) {
+                    return -1;
+                }
+            }
+
+            func cleanup(x int): int {
+                var r int = 0;
+                try {
+                    r = check(x);
+                } catch (e any) {
+                    r = 1;
+                    rethrow;
+                } finally {
+                    r = r + 1;
+                }
+                return r;
+            }
+        "#)], FFIRegistry::new()).unwrap_or_else(|_| panic!("should compile")));
+    }
+
+    #[cfg(feature = "al31f-builtin-ops")]
+    #[test]
+    fn test_asm_containers() {
+        let program: CompiledProgram<DefaultAlloc> = assemble(r#"
+            .const "x"
+            .func main entry L.main args 0 rets 1 stack 4
+
+            L.main:
+                %0 = create-container <vector<string>>
+                %1 = load 0
+                vec-push %0, %1
+                vec-push %0, %1
+                %2 = make-closure F.main <closure<func(int, int?) -> ()>> %0, %1
+                %3 = vec-len %0
+                ret %3
+        "#, &FFIRegistry::new()).unwrap();
+        let text: String = unsafe { disassemble(&program) };
+        assert!(text.contains("%0 = create-container <vector<string>>"));
+        assert!(text.contains("%2 = make-closure F.0 <closure<func(int, int?) -> ()>> %0, %1"));
+
+        let result: Vec<Value> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), &program, 0, &[])
+        }.unwrap_or_else(|_| panic!("should not throw"));
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 2);
+        assert_round_trip(program);
+    }
+
+    #[test]
+    fn test_asm_error() {
+        let source: &str =
+            ".func main entry L.main args 0 rets 0 stack 1\nL.main:\n    goto L.nowhere\n";
+        let error: AsmError = match assemble::<DefaultAlloc>(source, &FFIRegistry::new()) {
+            Ok(_) => panic!("should not assemble"),
+            Err(error) => error
+        };
+        assert_eq!(error.line, 3);
+        assert!(error.message.contains("L.nowhere"));
+    }
+}
@@ -0,0 +1,1397 @@
+//! ## `bytecode.rs`: on-disk format of compiled programs
+//!
+//! A `CompiledProgram` is full of pointers: instructions refer to slices in its `slice_arena`,
+//! type information in its `tyck_info_pool` and generic type vtables, and host functions are
+//! referred by trait object pointers. This module writes all these pieces symbolically, so that
+//! a program can be saved once and loaded again without running the front-end:
+//!
+//! - type information is written as a table of type trees, with builtin types written by name,
+//! - generic type vtables are rebuilt from their container types,
+//! - host functions are written by the names they were bound by, and bound again against the
+//!   `FFIRegistry` given by the host on loading,
+//! - exception families are not written at all, but taken from that `FFIRegistry` again,
+//! - debug information is kept as is. File ids in the line table only make sense against the
+//!   `SourceManager` the program was compiled with.
+//!
+//! All integers are little-endian. `usize`s are always written as 64-bit integers, so that
+//! programs saved on one platform can be loaded on another.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::ptr::NonNull;
+
+use xjbutil::slice_arena::SliceArena;
+use xjbutil::unchecked::UnsafeFrom;
+
+use crate::builtins::closure::{Closure, create_closure_vt};
+use crate::builtins::vec::{VMGenericVec, create_vm_vec_vt, vec_ctor};
+use crate::data::Value;
+use crate::data::generic::{GenericTypeCtor, GenericTypeVT};
+use crate::data::tyck::{
+    ContainerTyckInfo,
+    FunctionTyckInfo,
+    TyckInfo,
+    TyckInfoPool,
+    builtin_type_id,
+    builtin_type_name
+};
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::data::wrapper::OwnershipInfo;
+use crate::ffi::sync_fn::Function as FFIFunction;
+use crate::vm::al31f::Combustor;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    DebugInfo,
+    ExceptionHandlingBlock,
+    HandlerKind,
+    LineTableEntry,
+    OverloadTable
+};
+use crate::vm::al31f::ffi_registry::FFIRegistry;
+use crate::vm::al31f::insc::Insc;
+
+#[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
+#[cfg(feature = "async")] use crate::vm::al31f::{AL31F, AsyncCombustor};
+
+pub const BYTECODE_MAGIC: [u8; 4] = *b"P47B";
+pub const BYTECODE_VERSION: u32 = 6;
+
+#[derive(Debug)]
+pub enum SerializeError {
+    /// A type which has no symbolic name, usually a host type not registered as exception type
+    UnknownType { type_id: TypeId },
+    /// A generic container type which cannot be rebuilt on loading
+    UnknownContainer { type_name: String },
+    /// A constant which is neither a primitive value nor a string
+    UnsupportedConst { const_id: usize },
+    /// A host function whose name was not recorded
+    MissingFFIName { ffi_func_id: usize },
+    #[cfg(feature = "async")]
+    MissingAsyncFFIName { async_ffi_func_id: usize }
+}
+
+#[derive(Debug)]
+pub enum DeserializeError {
+    BadMagic,
+    UnsupportedVersion { version: u32 },
+    UnexpectedEnd,
+    Malformed { reason: &'static str },
+    UnknownType { type_name: String },
+    /// An instruction unknown, or not enabled by features of the current build
+    UnsupportedInsc { opcode: u8 },
+    UnresolvedFFIFunc { name: String }
+}
+
+const TYCK_ANY: u8 = 0;
+const TYCK_PLAIN: u8 = 1;
+const TYCK_NULLABLE: u8 = 2;
+const TYCK_CONTAINER: u8 = 3;
+const TYCK_FUNCTION: u8 = 4;
+
+const HANDLER_TYPED: u8 = 0;
+const HANDLER_CATCH_ALL: u8 = 1;
+const HANDLER_FINALLY: u8 = 2;
+
+const CONST_NULL: u8 = 0;
+const CONST_INT: u8 = 1;
+const CONST_FLOAT: u8 = 2;
+const CONST_BOOL: u8 = 3;
+const CONST_STRING: u8 = 4;
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>
+}
+
+impl Writer {
+    fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+
+    fn write_usizes(&mut self, values: &[usize]) {
+        self.write_usize(values.len());
+        for value /*: &usize*/ in values {
+            self.write_usize(*value);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DeserializeError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(DeserializeError::UnexpectedEnd);
+        }
+        let bytes: &'a [u8] = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DeserializeError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DeserializeError> {
+        let mut bytes: [u8; 4] = [0; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, DeserializeError> {
+        let mut bytes: [u8; 8] = [0; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, DeserializeError> {
+        let value: u64 = self.read_u64()?;
+        if value > usize::MAX as u64 {
+            return Err(DeserializeError::Malformed { reason: "integer too large for platform" });
+        }
+        Ok(value as usize)
+    }
+
+    /// Reads a length, which should not exceed the count of remaining bytes since every element
+    /// takes at least one byte
+    fn read_len(&mut self) -> Result<usize, DeserializeError> {
+        let len: usize = self.read_usize()?;
+        if len > self.bytes.len() - self.pos {
+            return Err(DeserializeError::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn read_i64(&mut self) -> Result<i64, DeserializeError> {
+        Ok(self.read_u64()? as i64)
+    }
+
+    fn read_f64(&mut self) -> Result<f64, DeserializeError> {
+        Ok(f64::from_bits(self.read_u64()?))
+    }
+
+    fn read_bool(&mut self) -> Result<bool, DeserializeError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DeserializeError::Malformed { reason: "invalid boolean" })
+        }
+    }
+
+    fn read_str(&mut self) -> Result<&'a str, DeserializeError> {
+        let len: usize = self.read_len()?;
+        std::str::from_utf8(self.read_bytes(len)?)
+            .map_err(|_| DeserializeError::Malformed { reason: "invalid UTF-8 string" })
+    }
+
+    fn read_usizes(&mut self) -> Result<Vec<usize>, DeserializeError> {
+        let len: usize = self.read_len()?;
+        let mut values: Vec<usize> = Vec::with_capacity(len);
+        for _ in 0..len {
+            values.push(self.read_usize()?);
+        }
+        Ok(values)
+    }
+}
+
+/// Serializes a program into bytecode
+pub fn serialize_program<A: Alloc>(
+    program: &CompiledProgram<A>
+) -> Result<Vec<u8>, SerializeError> {
+    let mut serializer: Serializer = Serializer::default();
+    unsafe { serializer.serialize(program) }
+}
+
+#[derive(Default)]
+struct Serializer {
+    tyck_table: Writer,
+    tyck_count: usize,
+    tyck_ids: HashMap<NonNull<TyckInfo>, usize>,
+
+    vt_table: Writer,
+    vt_count: usize,
+    vt_ids: HashMap<NonNull<GenericTypeVT>, usize>,
+
+    exception_type_names: HashMap<TypeId, String>
+}
+
+impl Serializer {
+    unsafe fn serialize<A: Alloc>(
+        &mut self,
+        program: &CompiledProgram<A>
+    ) -> Result<Vec<u8>, SerializeError> {
+        // type information and vtables are collected while writing the other parts, so they get
+        // written into separate buffers and put in front later
+        let mut body: Writer = Writer::default();
+        self.exception_type_names = program.exception_type_names.clone();
+
+        body.write_usize(program.const_pool.len());
+        for (const_id, value) /*: (usize, &Value)*/ in program.const_pool.iter().enumerate() {
+            Self::serialize_const(&mut body, const_id, *value)?;
+        }
+
+        body.write_usize(program.code.len());
+        for insc /*: &Insc*/ in program.code.iter() {
+            self.serialize_insc(&mut body, insc)?;
+        }
+
+        body.write_usize(program.global_names.len());
+        for global_name /*: &String*/ in program.global_names.iter() {
+            body.write_str(global_name);
+        }
+        body.write_usize(program.init_proc);
+        body.write_usize(program.functions.len());
+        for function /*: &CompiledFunction*/ in program.functions.iter() {
+            self.serialize_function(&mut body, function)?;
+        }
+
+        body.write_usize(program.overload_tables.len());
+        for overload_table /*: &OverloadTable*/ in program.overload_tables.iter() {
+            body.write_usizes(&overload_table.candidates);
+        }
+
+        let mut func_ids: Vec<(&String, &usize)> = program.func_ids.iter().collect::<_>();
+        func_ids.sort();
+        body.write_usize(func_ids.len());
+        for (func_name, func_id) /*: (&String, &usize)*/ in func_ids {
+            body.write_str(func_name);
+            body.write_usize(*func_id);
+        }
+
+        body.write_usize(program.debug_info.line_table.len());
+        for entry /*: &LineTableEntry*/ in program.debug_info.line_table.iter() {
+            body.write_usize(entry.insc_ptr);
+            body.write_u32(entry.file_id);
+            body.write_u32(entry.offset_begin);
+            body.write_u32(entry.offset_end);
+        }
+        body.write_usize(program.debug_info.func_names.len());
+        for func_name /*: &String*/ in program.debug_info.func_names.iter() {
+            body.write_str(func_name);
+        }
+
+        if program.ffi_func_names.len() != program.ffi_funcs.len() {
+            let ffi_func_id: usize = program.ffi_func_names.len().min(program.ffi_funcs.len());
+            return Err(SerializeError::MissingFFIName { ffi_func_id });
+        }
+        body.write_usize(program.ffi_func_names.len());
+        for ffi_func_name /*: &String*/ in program.ffi_func_names.iter() {
+            body.write_str(ffi_func_name);
+        }
+
+        #[cfg(feature = "async")]
+        {
+            let names_count: usize = program.async_ffi_func_names.len();
+            if names_count != program.async_ffi_funcs.len() {
+                let async_ffi_func_id: usize = names_count.min(program.async_ffi_funcs.len());
+                return Err(SerializeError::MissingAsyncFFIName { async_ffi_func_id });
+            }
+            body.write_usize(names_count);
+            for async_ffi_func_name /*: &String*/ in program.async_ffi_func_names.iter() {
+                body.write_str(async_ffi_func_name);
+            }
+        }
+        #[cfg(not(feature = "async"))]
+        body.write_usize(0);
+
+        let mut output: Writer = Writer::default();
+        output.buf.extend_from_slice(&BYTECODE_MAGIC);
+        output.write_u32(BYTECODE_VERSION);
+        output.write_usize(self.tyck_count);
+        output.buf.append(&mut self.tyck_table.buf);
+        output.write_usize(self.vt_count);
+        output.buf.append(&mut self.vt_table.buf);
+        output.buf.append(&mut body.buf);
+        Ok(output.buf)
+    }
+
+    unsafe fn serialize_const(
+        w: &mut Writer,
+        const_id: usize,
+        value: Value
+    ) -> Result<(), SerializeError> {
+        if value.is_null() {
+            w.write_u8(CONST_NULL);
+        } else if value.is_value() {
+            match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+                ValueTypeTag::Int => {
+                    w.write_u8(CONST_INT);
+                    w.write_u64(value.vt_data.inner.int_value as u64);
+                },
+                ValueTypeTag::Float => {
+                    w.write_u8(CONST_FLOAT);
+                    w.write_u64(value.vt_data.inner.float_value.to_bits());
+                },
+                ValueTypeTag::Bool => {
+                    w.write_u8(CONST_BOOL);
+                    w.write_bool(value.vt_data.inner.bool_value);
+                }
+            }
+        } else if !value.is_container()
+            && (*value.get_as_dyn_base()).dyn_type_id() == TypeId::of::<String>()
+        {
+            w.write_u8(CONST_STRING);
+            w.write_str(&*(value.get_as_mut_ptr::<String>() as *const String));
+        } else {
+            return Err(SerializeError::UnsupportedConst { const_id });
+        }
+        Ok(())
+    }
+
+    /// Adds a type to the type table if not added yet, returns its index in the table
+    unsafe fn tyck_id(&mut self, tyck_info: NonNull<TyckInfo>) -> Result<usize, SerializeError> {
+        if let Some(tyck_id) = self.tyck_ids.get(&tyck_info) {
+            return Ok(*tyck_id);
+        }
+
+        // children are added first, so that loading only refers to types already loaded
+        let mut entry: Writer = Writer::default();
+        match tyck_info.as_ref() {
+            TyckInfo::AnyType => entry.write_u8(TYCK_ANY),
+            TyckInfo::Plain(type_id) => {
+                entry.write_u8(TYCK_PLAIN);
+                entry.write_str(self.type_name(*type_id)?);
+            },
+            TyckInfo::Nullable(base) => {
+                let base: usize = self.tyck_id(*base)?;
+                entry.write_u8(TYCK_NULLABLE);
+                entry.write_usize(base);
+            },
+            TyckInfo::Container(ContainerTyckInfo { type_id, params }) => {
+                let params: Vec<usize> = self.tyck_ids_of(params.as_ref())?;
+                entry.write_u8(TYCK_CONTAINER);
+                entry.write_str(self.type_name(*type_id)?);
+                entry.write_usizes(&params);
+            },
+            TyckInfo::Function(FunctionTyckInfo { params, rets, exceptions }) => {
+                let params: Vec<usize> = self.tyck_ids_of(params.as_ref())?;
+                let rets: Vec<usize> = self.tyck_ids_of(rets.as_ref())?;
+                let exceptions: Vec<usize> = self.tyck_ids_of(exceptions.as_ref())?;
+                entry.write_u8(TYCK_FUNCTION);
+                entry.write_usizes(&params);
+                entry.write_usizes(&rets);
+                entry.write_usizes(&exceptions);
+            }
+        }
+
+        let tyck_id: usize = self.tyck_count;
+        self.tyck_table.buf.append(&mut entry.buf);
+        self.tyck_count += 1;
+        self.tyck_ids.insert(tyck_info, tyck_id);
+        Ok(tyck_id)
+    }
+
+    unsafe fn tyck_ids_of(
+        &mut self,
+        tyck_infos: &[NonNull<TyckInfo>]
+    ) -> Result<Vec<usize>, SerializeError> {
+        tyck_infos.iter().map(|tyck_info| self.tyck_id(*tyck_info)).collect::<_>()
+    }
+
+    /// Adds a vtable to the vtable table if not added yet, returns its index in the table
+    unsafe fn vt_id(&mut self, vt: NonNull<GenericTypeVT>) -> Result<usize, SerializeError> {
+        if let Some(vt_id) = self.vt_ids.get(&vt) {
+            return Ok(*vt_id);
+        }
+
+        let container_tyck_info: &ContainerTyckInfo = vt.as_ref().tyck_info.as_ref();
+        let container_type_id: TypeId = container_tyck_info.type_id;
+        if container_type_id != TypeId::of::<VMGenericVec>()
+            && container_type_id != TypeId::of::<Closure>()
+        {
+            return Err(SerializeError::UnknownContainer {
+                type_name: vt.as_ref().type_name.clone()
+            });
+        }
+
+        let params: Vec<usize> = self.tyck_ids_of(container_tyck_info.params.as_ref())?;
+        let mut entry: Writer = Writer::default();
+        entry.write_str(self.type_name(container_type_id)?);
+        entry.write_usizes(&params);
+        self.vt_table.buf.append(&mut entry.buf);
+
+        let vt_id: usize = self.vt_count;
+        self.vt_count += 1;
+        self.vt_ids.insert(vt, vt_id);
+        Ok(vt_id)
+    }
+
+    /// Host exception types are named as registered, and get resolved by name on loading
+    fn type_name(&self, type_id: TypeId) -> Result<&str, SerializeError> {
+        builtin_type_name(type_id)
+            .or_else(|| self.exception_type_names.get(&type_id).map(String::as_str))
+            .ok_or(SerializeError::UnknownType { type_id })
+    }
+
+    unsafe fn serialize_function(
+        &mut self,
+        w: &mut Writer,
+        function: &CompiledFunction
+    ) -> Result<(), SerializeError> {
+        w.write_usize(function.start_addr);
+        w.write_usize(function.arg_count);
+        w.write_usize(function.ret_count);
+        w.write_usize(function.stack_size);
+
+        w.write_usize(function.param_tyck_info.len());
+        for param_tyck_info /*: &Option<NonNull<TyckInfo>>*/ in function.param_tyck_info.iter() {
+            if let Some(param_tyck_info) = param_tyck_info {
+                let tyck_id: usize = self.tyck_id(*param_tyck_info)?;
+                w.write_bool(true);
+                w.write_usize(tyck_id);
+            } else {
+                w.write_bool(false);
+            }
+        }
+
+        if let Some(exc_handlers) = &function.exc_handlers {
+            w.write_bool(true);
+            w.write_usize(exc_handlers.len());
+            for exc_handler /*: &ExceptionHandlingBlock*/ in exc_handlers.iter() {
+                w.write_usize(exc_handler.insc_ptr_range.0);
+                w.write_usize(exc_handler.insc_ptr_range.1);
+                match exc_handler.kind {
+                    HandlerKind::Typed(type_id) => {
+                        w.write_u8(HANDLER_TYPED);
+                        w.write_str(self.type_name(type_id)?);
+                    },
+                    HandlerKind::CatchAll => w.write_u8(HANDLER_CATCH_ALL),
+                    HandlerKind::Finally => w.write_u8(HANDLER_FINALLY)
+                }
+                w.write_usize(exc_handler.handler_addr);
+            }
+        } else {
+            w.write_bool(false);
+        }
+        Ok(())
+    }
+
+    unsafe fn serialize_insc(&mut self, w: &mut Writer, insc: &Insc) -> Result<(), SerializeError> {
+        match insc {
+            Insc::Move(src, dst) => write_regs(w, 0, &[*src, *dst]),
+            Insc::AddInt(lhs, rhs, dst) => write_regs(w, 1, &[*lhs, *rhs, *dst]),
+            Insc::AddFloat(lhs, rhs, dst) => write_regs(w, 2, &[*lhs, *rhs, *dst]),
+            Insc::AddAny(lhs, rhs, dst) => write_regs(w, 3, &[*lhs, *rhs, *dst]),
+            Insc::IncrInt(pos) => write_regs(w, 4, &[*pos]),
+            Insc::DecrInt(pos) => write_regs(w, 5, &[*pos]),
+            Insc::SubInt(lhs, rhs, dst) => write_regs(w, 6, &[*lhs, *rhs, *dst]),
+            Insc::SubFloat(lhs, rhs, dst) => write_regs(w, 7, &[*lhs, *rhs, *dst]),
+            Insc::SubAny(lhs, rhs, dst) => write_regs(w, 8, &[*lhs, *rhs, *dst]),
+            Insc::MulInt(lhs, rhs, dst) => write_regs(w, 9, &[*lhs, *rhs, *dst]),
+            Insc::MulFloat(lhs, rhs, dst) => write_regs(w, 10, &[*lhs, *rhs, *dst]),
+            Insc::MulAny(lhs, rhs, dst) => write_regs(w, 11, &[*lhs, *rhs, *dst]),
+            Insc::DivInt(lhs, rhs, dst) => write_regs(w, 12, &[*lhs, *rhs, *dst]),
+            Insc::DivFloat(lhs, rhs, dst) => write_regs(w, 13, &[*lhs, *rhs, *dst]),
+            Insc::DivAny(lhs, rhs, dst) => write_regs(w, 14, &[*lhs, *rhs, *dst]),
+            Insc::ModInt(lhs, rhs, dst) => write_regs(w, 15, &[*lhs, *rhs, *dst]),
+            Insc::ModAny(lhs, rhs, dst) => write_regs(w, 16, &[*lhs, *rhs, *dst]),
+            Insc::EqValue(lhs, rhs, dst) => write_regs(w, 17, &[*lhs, *rhs, *dst]),
+            Insc::EqRef(lhs, rhs, dst) => write_regs(w, 18, &[*lhs, *rhs, *dst]),
+            Insc::EqAny(lhs, rhs, dst) => write_regs(w, 19, &[*lhs, *rhs, *dst]),
+            Insc::NeValue(lhs, rhs, dst) => write_regs(w, 20, &[*lhs, *rhs, *dst]),
+            Insc::NeRef(lhs, rhs, dst) => write_regs(w, 21, &[*lhs, *rhs, *dst]),
+            Insc::NeAny(lhs, rhs, dst) => write_regs(w, 22, &[*lhs, *rhs, *dst]),
+            Insc::LtInt(lhs, rhs, dst) => write_regs(w, 23, &[*lhs, *rhs, *dst]),
+            Insc::LtFloat(lhs, rhs, dst) => write_regs(w, 24, &[*lhs, *rhs, *dst]),
+            Insc::LtAny(lhs, rhs, dst) => write_regs(w, 25, &[*lhs, *rhs, *dst]),
+            Insc::LeInt(lhs, rhs, dst) => write_regs(w, 26, &[*lhs, *rhs, *dst]),
+            Insc::LeFloat(lhs, rhs, dst) => write_regs(w, 27, &[*lhs, *rhs, *dst]),
+            Insc::LeAny(lhs, rhs, dst) => write_regs(w, 28, &[*lhs, *rhs, *dst]),
+            Insc::BAndInt(lhs, rhs, dst) => write_regs(w, 29, &[*lhs, *rhs, *dst]),
+            Insc::BAndAny(lhs, rhs, dst) => write_regs(w, 30, &[*lhs, *rhs, *dst]),
+            Insc::BOrInt(lhs, rhs, dst) => write_regs(w, 31, &[*lhs, *rhs, *dst]),
+            Insc::BOrAny(lhs, rhs, dst) => write_regs(w, 32, &[*lhs, *rhs, *dst]),
+            Insc::BXorInt(lhs, rhs, dst) => write_regs(w, 33, &[*lhs, *rhs, *dst]),
+            Insc::BXorAny(lhs, rhs, dst) => write_regs(w, 34, &[*lhs, *rhs, *dst]),
+            Insc::BNotInt(src, dst) => write_regs(w, 35, &[*src, *dst]),
+            Insc::BNotAny(src, dst) => write_regs(w, 36, &[*src, *dst]),
+            Insc::NegInt(src, dst) => write_regs(w, 37, &[*src, *dst]),
+            Insc::NegFloat(src, dst) => write_regs(w, 38, &[*src, *dst]),
+            Insc::NegAny(src, dst) => write_regs(w, 39, &[*src, *dst]),
+            Insc::AndBool(lhs, rhs, dst) => write_regs(w, 40, &[*lhs, *rhs, *dst]),
+            Insc::AndAny(lhs, rhs, dst) => write_regs(w, 41, &[*lhs, *rhs, *dst]),
+            Insc::OrBool(lhs, rhs, dst) => write_regs(w, 42, &[*lhs, *rhs, *dst]),
+            Insc::OrAny(lhs, rhs, dst) => write_regs(w, 43, &[*lhs, *rhs, *dst]),
+            Insc::NotBool(src, dst) => write_regs(w, 44, &[*src, *dst]),
+            Insc::NotAny(src, dst) => write_regs(w, 45, &[*src, *dst]),
+            Insc::ShlInt(lhs, rhs, dst) => write_regs(w, 46, &[*lhs, *rhs, *dst]),
+            Insc::ShlAny(lhs, rhs, dst) => write_regs(w, 47, &[*lhs, *rhs, *dst]),
+            Insc::ShrInt(lhs, rhs, dst) => write_regs(w, 48, &[*lhs, *rhs, *dst]),
+            Insc::ShrAny(lhs, rhs, dst) => write_regs(w, 49, &[*lhs, *rhs, *dst]),
+            Insc::MakeIntConst(value, dst) => {
+                w.write_u8(50);
+                w.write_u64(*value as u64);
+                w.write_usize(*dst);
+            },
+            Insc::MakeFloatConst(value, dst) => {
+                w.write_u8(51);
+                w.write_u64(value.to_bits());
+                w.write_usize(*dst);
+            },
+            Insc::MakeBoolConst(value, dst) => {
+                w.write_u8(52);
+                w.write_bool(*value);
+                w.write_usize(*dst);
+            },
+            Insc::MakeNull(dst) => write_regs(w, 53, &[*dst]),
+            Insc::LoadConst(const_id, dst) => write_regs(w, 54, &[*const_id, *dst]),
+            Insc::SaveConst(src, const_id) => write_regs(w, 55, &[*src, *const_id]),
+            Insc::LoadGlobal(global_id, dst) => write_regs(w, 100, &[*global_id, *dst]),
+            Insc::StoreGlobal(src, global_id) => write_regs(w, 101, &[*src, *global_id]),
+            Insc::CallableCheck(src, arg_count, ret_count) =>
+                write_regs(w, 102, &[*src, *arg_count, *ret_count]),
+            Insc::CastFloatInt(src, dst) => write_regs(w, 56, &[*src, *dst]),
+            Insc::CastBoolInt(src, dst) => write_regs(w, 57, &[*src, *dst]),
+            Insc::CastAnyInt(src, dst) => write_regs(w, 58, &[*src, *dst]),
+            Insc::CastIntFloat(src, dst) => write_regs(w, 59, &[*src, *dst]),
+            Insc::CastAnyFloat(src, dst) => write_regs(w, 60, &[*src, *dst]),
+            Insc::CastIntBool(src, dst) => write_regs(w, 61, &[*src, *dst]),
+            Insc::CastAnyBool(src, dst) => write_regs(w, 62, &[*src, *dst]),
+            Insc::IsNull(src, dst) => write_regs(w, 63, &[*src, *dst]),
+            Insc::NullCheck(src) => write_regs(w, 64, &[*src]),
+            Insc::IsType(src, tyck_info, dst) => {
+                let tyck_id: usize = self.tyck_id(*tyck_info)?;
+                write_regs(w, 65, &[*src, tyck_id, *dst]);
+            },
+            Insc::TypeCheck(src, tyck_info) => {
+                let tyck_id: usize = self.tyck_id(*tyck_info)?;
+                write_regs(w, 66, &[*src, tyck_id]);
+            },
+            Insc::OwnershipInfoCheck(src, mask) => {
+                w.write_u8(67);
+                w.write_usize(*src);
+                w.write_u8(*mask);
+            },
+            Insc::Call(func_id, args, rets) => write_call(w, 68, *func_id, args, rets),
+            Insc::CallPtr(func, args, rets) => write_call(w, 69, *func, args, rets),
+            Insc::CallOverload(table, args, rets) => write_call(w, 70, *table, args, rets),
+            Insc::ReturnNothing => w.write_u8(71),
+            Insc::ReturnOne(src) => write_regs(w, 72, &[*src]),
+            Insc::Return(srcs) => {
+                w.write_u8(73);
+                w.write_usizes(srcs);
+            },
+            Insc::FFICallRtlc(ffi_func_id, args, rets) =>
+                write_call(w, 74, *ffi_func_id, args, rets),
+            #[cfg(feature = "optimized-rtlc")]
+            Insc::FFICall(ffi_func_id, args, rets) => write_call(w, 75, *ffi_func_id, args, rets),
+            #[cfg(feature = "async")]
+            Insc::FFICallAsync(async_ffi_func_id, args, dst) => {
+                w.write_u8(76);
+                w.write_usize(*async_ffi_func_id);
+                w.write_usizes(args);
+                w.write_usize(*dst);
+            },
+            #[cfg(feature = "async")]
+            Insc::Await(src, dsts) => {
+                w.write_u8(77);
+                w.write_usize(*src);
+                w.write_usizes(dsts);
+            },
+            #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+            Insc::Spawn(func_id, args) => {
+                w.write_u8(78);
+                w.write_usize(*func_id);
+                w.write_usizes(args);
+            },
+            Insc::Raise(src) => write_regs(w, 79, &[*src]),
+            Insc::JumpIfTrue(cond, dst) => write_regs(w, 80, &[*cond, *dst]),
+            Insc::JumpIfFalse(cond, dst) => write_regs(w, 81, &[*cond, *dst]),
+            Insc::Jump(dst) => write_regs(w, 82, &[*dst]),
+            // the constructor is decided by the container type, so only the vtable gets written
+            Insc::CreateContainer(_, vt, dst) => {
+                let vt_id: usize = self.vt_id(*vt)?;
+                write_regs(w, 83, &[vt_id, *dst]);
+            },
+            Insc::CreateClosure(func_id, captures, vt, dst) => {
+                let vt_id: usize = self.vt_id(*vt)?;
+                w.write_u8(84);
+                w.write_usize(*func_id);
+                w.write_usizes(captures);
+                w.write_usize(vt_id);
+                w.write_usize(*dst);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CreateString(dst) => write_regs(w, 85, &[*dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CreateObject(dst) => write_regs(w, 86, &[*dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecIndex(src, index, dst) => write_regs(w, 87, &[*src, *index, *dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecIndexPut(src, index, value) => write_regs(w, 88, &[*src, *index, *value]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecPush(src, value) => write_regs(w, 89, &[*src, *value]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecLen(src, dst) => write_regs(w, 90, &[*src, *dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrClone(src, dst) => write_regs(w, 91, &[*src, *dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrConcat(srcs, dst) => {
+                w.write_u8(92);
+                w.write_usizes(srcs);
+                w.write_usize(*dst);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrLen(src, dst) => write_regs(w, 93, &[*src, *dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrEquals(lhs, rhs, dst) => write_regs(w, 94, &[*lhs, *rhs, *dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectGet(src, field, dst) => {
+                w.write_u8(95);
+                w.write_usize(*src);
+                w.write_str(field.as_ref());
+                w.write_usize(*dst);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectGetDyn(src, field, dst) => write_regs(w, 96, &[*src, *field, *dst]),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectPut(src, field, value) => {
+                w.write_u8(97);
+                w.write_usize(*src);
+                w.write_str(field.as_ref());
+                w.write_usize(*value);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectPutDyn(src, field, value) => write_regs(w, 98, &[*src, *field, *value]),
+            Insc::Rethrow(src) => write_regs(w, 99, &[*src])
+        }
+        Ok(())
+    }
+}
+
+fn write_regs(w: &mut Writer, opcode: u8, regs: &[usize]) {
+    w.write_u8(opcode);
+    for reg /*: &usize*/ in regs {
+        w.write_usize(*reg);
+    }
+}
+
+fn write_call(w: &mut Writer, opcode: u8, callee: usize, args: &[usize], rets: &[usize]) {
+    w.write_u8(opcode);
+    w.write_usize(callee);
+    w.write_usizes(args);
+    w.write_usizes(rets);
+}
+
+/// Deserializes a program from bytecode, binding host functions against `ffi_registry`
+pub fn deserialize_program<A: Alloc>(
+    bytes: &[u8],
+    ffi_registry: &FFIRegistry<A>
+) -> Result<CompiledProgram<A>, DeserializeError> {
+    let mut r: Reader = Reader { bytes, pos: 0 };
+    if r.read_bytes(BYTECODE_MAGIC.len()).ok() != Some(&BYTECODE_MAGIC[..]) {
+        return Err(DeserializeError::BadMagic);
+    }
+    let version: u32 = r.read_u32()?;
+    if version != BYTECODE_VERSION {
+        return Err(DeserializeError::UnsupportedVersion { version });
+    }
+
+    let deserializer: Deserializer = Deserializer {
+        r,
+        slice_arena: SliceArena::new(),
+        tyck_info_pool: TyckInfoPool::new(),
+        tyck_infos: Vec::new(),
+        vts: Vec::new(),
+        vt_ctors: Vec::new(),
+        exception_types: ffi_registry.exception_types()
+    };
+    unsafe { deserializer.deserialize(ffi_registry) }
+}
+
+struct Deserializer<'a> {
+    r: Reader<'a>,
+    slice_arena: SliceArena<8192, 8>,
+    tyck_info_pool: TyckInfoPool,
+    tyck_infos: Vec<NonNull<TyckInfo>>,
+    vts: Vec<NonNull<GenericTypeVT>>,
+    vt_ctors: Vec<Option<GenericTypeCtor>>,
+    exception_types: &'a HashMap<String, TypeId>
+}
+
+impl<'a> Deserializer<'a> {
+    unsafe fn deserialize<A: Alloc>(
+        mut self,
+        ffi_registry: &FFIRegistry<A>
+    ) -> Result<CompiledProgram<A>, DeserializeError> {
+        let tyck_count: usize = self.r.read_len()?;
+        for _ in 0..tyck_count {
+            let tyck_info: NonNull<TyckInfo> = self.deserialize_tyck_info()?;
+            self.tyck_infos.push(tyck_info);
+        }
+
+        let generic_vts: Box<[GenericTypeVT]> = self.deserialize_vts()?;
+
+        let const_count: usize = self.r.read_len()?;
+        let mut const_pool: Vec<Value> = Vec::with_capacity(const_count);
+        for _ in 0..const_count {
+            match self.deserialize_const() {
+                Ok(value) => const_pool.push(value),
+                Err(e) => {
+                    // strings already loaded are owned by nothing yet
+                    drop(Self::make_program_shell::<A>(const_pool));
+                    return Err(e);
+                }
+            }
+        }
+        // from now on the constant pool gets freed by the program on errors
+        let mut program: CompiledProgram<A> = Self::make_program_shell(const_pool);
+
+        let insc_count: usize = self.r.read_len()?;
+        let mut code: Vec<Insc> = Vec::with_capacity(insc_count);
+        for _ in 0..insc_count {
+            code.push(self.deserialize_insc()?);
+        }
+
+        let global_count: usize = self.r.read_len()?;
+        let mut global_names: Vec<String> = Vec::with_capacity(global_count);
+        for _ in 0..global_count {
+            global_names.push(self.r.read_str()?.to_string());
+        }
+        let init_proc: usize = self.r.read_usize()?;
+        let function_count: usize = self.r.read_len()?;
+        let mut functions: Vec<CompiledFunction> = Vec::with_capacity(function_count);
+        for _ in 0..function_count {
+            functions.push(self.deserialize_function()?);
+        }
+
+        let overload_table_count: usize = self.r.read_len()?;
+        let mut overload_tables: Vec<OverloadTable> = Vec::with_capacity(overload_table_count);
+        for _ in 0..overload_table_count {
+            let candidates: Vec<usize> = self.r.read_usizes()?;
+            overload_tables.push(OverloadTable::new(candidates.into_boxed_slice()));
+        }
+
+        let func_id_count: usize = self.r.read_len()?;
+        let mut func_ids: HashMap<String, usize> = HashMap::with_capacity(func_id_count);
+        for _ in 0..func_id_count {
+            let func_name: String = self.r.read_str()?.to_string();
+            func_ids.insert(func_name, self.r.read_usize()?);
+        }
+
+        let line_table_len: usize = self.r.read_len()?;
+        let mut line_table: Vec<LineTableEntry> = Vec::with_capacity(line_table_len);
+        for _ in 0..line_table_len {
+            line_table.push(LineTableEntry::new(
+                self.r.read_usize()?,
+                self.r.read_u32()?,
+                self.r.read_u32()?,
+                self.r.read_u32()?
+            ));
+        }
+        let func_name_count: usize = self.r.read_len()?;
+        let mut func_names: Vec<String> = Vec::with_capacity(func_name_count);
+        for _ in 0..func_name_count {
+            func_names.push(self.r.read_str()?.to_string());
+        }
+
+        let ffi_func_count: usize = self.r.read_len()?;
+        let mut ffi_funcs: Vec<&'static dyn FFIFunction<Combustor<A>>> =
+            Vec::with_capacity(ffi_func_count);
+        let mut ffi_func_names: Vec<String> = Vec::with_capacity(ffi_func_count);
+        for _ in 0..ffi_func_count {
+            let name: &str = self.r.read_str()?;
+            let ffi_func: &'static dyn FFIFunction<Combustor<A>> = ffi_registry.get_func(name)
+                .ok_or_else(|| DeserializeError::UnresolvedFFIFunc { name: name.to_string() })?;
+            ffi_funcs.push(ffi_func);
+            ffi_func_names.push(name.to_string());
+        }
+
+        let async_ffi_func_count: usize = self.r.read_len()?;
+        #[cfg(feature = "async")]
+        let mut async_ffi_funcs: Vec<&'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>> =
+            Vec::with_capacity(async_ffi_func_count);
+        #[cfg(feature = "async")]
+        let mut async_ffi_func_names: Vec<String> = Vec::with_capacity(async_ffi_func_count);
+        for _ in 0..async_ffi_func_count {
+            let name: &str = self.r.read_str()?;
+            #[cfg(feature = "async")]
+            {
+                let async_ffi_func: &'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>> =
+                    ffi_registry.get_async_func(name).ok_or_else(
+                        || DeserializeError::UnresolvedFFIFunc { name: name.to_string() }
+                    )?;
+                async_ffi_funcs.push(async_ffi_func);
+                async_ffi_func_names.push(name.to_string());
+            }
+            // async host functions cannot be registered without the `async` feature
+            #[cfg(not(feature = "async"))]
+            return Err(DeserializeError::UnresolvedFFIFunc { name: name.to_string() });
+        }
+
+        if self.r.pos != self.r.bytes.len() {
+            return Err(DeserializeError::Malformed { reason: "trailing bytes" });
+        }
+
+        program.slice_arena = self.slice_arena;
+        program.code = code.into_boxed_slice();
+        program.tyck_info_pool = self.tyck_info_pool;
+        program.global_names = global_names.into_boxed_slice();
+        program.init_proc = init_proc;
+        program.functions = functions.into_boxed_slice();
+        program.func_ids = func_ids;
+        program.generic_vts = generic_vts;
+        program.overload_tables = overload_tables.into_boxed_slice();
+        program.exception_parents = ffi_registry.exception_parents().clone();
+        program.exception_type_names = ffi_registry.exception_type_names().clone();
+        program.debug_info = DebugInfo {
+            line_table: line_table.into_boxed_slice(),
+            func_names: func_names.into_boxed_slice()
+        };
+        program.ffi_funcs = ffi_funcs.into_boxed_slice();
+        program.ffi_func_names = ffi_func_names.into_boxed_slice();
+        #[cfg(feature = "async")]
+        {
+            program.async_ffi_funcs = async_ffi_funcs.into_boxed_slice();
+            program.async_ffi_func_names = async_ffi_func_names.into_boxed_slice();
+        }
+        Ok(program)
+    }
+
+    /// Makes an empty program owning `const_pool`
+    fn make_program_shell<A: Alloc>(const_pool: Vec<Value>) -> CompiledProgram<A> {
+        CompiledProgram {
+            slice_arena: SliceArena::new(),
+            code: Box::new([]),
+            const_pool: const_pool.into_boxed_slice(),
+            tyck_info_pool: TyckInfoPool::new(),
+            global_names: Box::new([]),
+            init_proc: 0,
+            functions: Box::new([]),
+            func_ids: HashMap::new(),
+            generic_vts: Box::new([]),
+            overload_tables: Box::new([]),
+            exception_parents: HashMap::new(),
+            exception_type_names: HashMap::new(),
+            debug_info: DebugInfo::default(),
+            ffi_funcs: Box::new([]),
+            ffi_func_names: Box::new([]),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: Box::new([]),
+            #[cfg(feature = "async")]
+            async_ffi_func_names: Box::new([])
+        }
+    }
+
+    fn deserialize_tyck_info(&mut self) -> Result<NonNull<TyckInfo>, DeserializeError> {
+        let tyck_info: NonNull<TyckInfo> = match self.r.read_u8()? {
+            TYCK_ANY => self.tyck_info_pool.get_any_type(),
+            TYCK_PLAIN => {
+                let type_name: &str = self.r.read_str()?;
+                if let Some(tyck_info) = self.tyck_info_pool.get_builtin_type(type_name) {
+                    tyck_info
+                } else {
+                    let type_id: TypeId = self.type_id(type_name)?;
+                    self.tyck_info_pool.create_plain_type(type_id)
+                }
+            },
+            TYCK_NULLABLE => {
+                let base: NonNull<TyckInfo> = self.read_tyck_info()?;
+                self.tyck_info_pool.create_nullable_type(base)
+            },
+            TYCK_CONTAINER => {
+                let type_name: &str = self.r.read_str()?;
+                let type_id: TypeId = self.type_id(type_name)?;
+                let params: Vec<NonNull<TyckInfo>> = self.read_tyck_infos()?;
+                self.tyck_info_pool.create_container_type(type_id, &params)
+            },
+            TYCK_FUNCTION => {
+                let params: Vec<NonNull<TyckInfo>> = self.read_tyck_infos()?;
+                let rets: Vec<NonNull<TyckInfo>> = self.read_tyck_infos()?;
+                let exceptions: Vec<NonNull<TyckInfo>> = self.read_tyck_infos()?;
+                self.tyck_info_pool.create_function_type(&params, &rets, &exceptions)
+            },
+            _ => return Err(DeserializeError::Malformed { reason: "invalid type kind" })
+        };
+        Ok(tyck_info)
+    }
+
+    /// Resolves host exception types through the names they were registered by
+    fn type_id(&self, type_name: &str) -> Result<TypeId, DeserializeError> {
+        builtin_type_id(type_name)
+            .or_else(|| self.exception_types.get(type_name).copied())
+            .ok_or_else(|| DeserializeError::UnknownType { type_name: type_name.to_string() })
+    }
+
+    /// Reads a reference to a type already loaded
+    fn read_tyck_info(&mut self) -> Result<NonNull<TyckInfo>, DeserializeError> {
+        let tyck_id: usize = self.r.read_usize()?;
+        self.tyck_infos.get(tyck_id)
+            .copied()
+            .ok_or(DeserializeError::Malformed { reason: "type index out of range" })
+    }
+
+    fn read_tyck_infos(&mut self) -> Result<Vec<NonNull<TyckInfo>>, DeserializeError> {
+        let len: usize = self.r.read_len()?;
+        (0..len).map(|_| self.read_tyck_info()).collect::<_>()
+    }
+
+    fn deserialize_vts(&mut self) -> Result<Box<[GenericTypeVT]>, DeserializeError> {
+        let vt_count: usize = self.r.read_len()?;
+        let mut vts: Vec<GenericTypeVT> = Vec::with_capacity(vt_count);
+        for _ in 0..vt_count {
+            let type_name: &str = self.r.read_str()?;
+            let type_id: TypeId = self.type_id(type_name)?;
+            let params: Vec<NonNull<TyckInfo>> = self.read_tyck_infos()?;
+            if type_id == TypeId::of::<VMGenericVec>() && params.len() == 1 {
+                vts.push(create_vm_vec_vt(&mut self.tyck_info_pool, params[0]));
+                self.vt_ctors.push(Some(vec_ctor));
+            } else if type_id == TypeId::of::<Closure>() {
+                vts.push(create_closure_vt(&mut self.tyck_info_pool, &params));
+                self.vt_ctors.push(None);
+            } else {
+                return Err(DeserializeError::Malformed { reason: "invalid container type" });
+            }
+        }
+
+        // the boxed slice never moves its elements, so pointers taken here stay valid
+        let vts: Box<[GenericTypeVT]> = vts.into_boxed_slice();
+        self.vts = vts.iter().map(NonNull::from).collect::<_>();
+        Ok(vts)
+    }
+
+    unsafe fn deserialize_const(&mut self) -> Result<Value, DeserializeError> {
+        let value: Value = match self.r.read_u8()? {
+            CONST_NULL => Value::new_null(),
+            CONST_INT => Value::new_int(self.r.read_i64()?),
+            CONST_FLOAT => Value::new_float(self.r.read_f64()?),
+            CONST_BOOL => Value::new_bool(self.r.read_bool()?),
+            CONST_STRING => {
+                let value: Value = Value::new_owned(self.r.read_str()?.to_string());
+                value.set_ownership_info(OwnershipInfo::GlobalConst);
+                value
+            },
+            _ => return Err(DeserializeError::Malformed { reason: "invalid constant kind" })
+        };
+        Ok(value)
+    }
+
+    fn deserialize_function(&mut self) -> Result<CompiledFunction, DeserializeError> {
+        let start_addr: usize = self.r.read_usize()?;
+        let arg_count: usize = self.r.read_usize()?;
+        let ret_count: usize = self.r.read_usize()?;
+        let stack_size: usize = self.r.read_usize()?;
+
+        let param_count: usize = self.r.read_len()?;
+        let mut param_tyck_info: Vec<Option<NonNull<TyckInfo>>> = Vec::with_capacity(param_count);
+        for _ in 0..param_count {
+            if self.r.read_bool()? {
+                param_tyck_info.push(Some(self.read_tyck_info()?));
+            } else {
+                param_tyck_info.push(None);
+            }
+        }
+        let param_tyck_info: Box<[Option<NonNull<TyckInfo>>]> = param_tyck_info.into_boxed_slice();
+
+        if !self.r.read_bool()? {
+            return Ok(CompiledFunction::new(
+                start_addr,
+                arg_count,
+                ret_count,
+                stack_size,
+                param_tyck_info
+            ));
+        }
+
+        let exc_handler_count: usize = self.r.read_len()?;
+        let mut exc_handlers: Vec<ExceptionHandlingBlock> = Vec::with_capacity(exc_handler_count);
+        for _ in 0..exc_handler_count {
+            let insc_ptr_start: usize = self.r.read_usize()?;
+            let insc_ptr_end: usize = self.r.read_usize()?;
+            let kind: HandlerKind = match self.r.read_u8()? {
+                HANDLER_TYPED => {
+                    let type_name: &str = self.r.read_str()?;
+                    HandlerKind::Typed(self.type_id(type_name)?)
+                },
+                HANDLER_CATCH_ALL => HandlerKind::CatchAll,
+                HANDLER_FINALLY => HandlerKind::Finally,
+                _ => return Err(DeserializeError::Malformed { reason: "invalid handler kind" })
+            };
+            let handler_addr: usize = self.r.read_usize()?;
+            exc_handlers.push(ExceptionHandlingBlock::new(
+                insc_ptr_start,
+                insc_ptr_end,
+                kind,
+                handler_addr
+            ));
+        }
+        Ok(CompiledFunction::new_with_exc(
+            start_addr,
+            arg_count,
+            ret_count,
+            stack_size,
+            param_tyck_info,
+            exc_handlers.into_boxed_slice()
+        ))
+    }
+
+    fn read_slice(&mut self) -> Result<&'static [usize], DeserializeError> {
+        let values: Vec<usize> = self.r.read_usizes()?;
+        Ok(unsafe { self.slice_arena.unsafe_make(&values) })
+    }
+
+    fn read_vt(
+        &mut self
+    ) -> Result<(NonNull<GenericTypeVT>, Option<GenericTypeCtor>), DeserializeError> {
+        let vt_id: usize = self.r.read_usize()?;
+        if vt_id >= self.vts.len() {
+            return Err(DeserializeError::Malformed { reason: "vtable index out of range" });
+        }
+        Ok((self.vts[vt_id], self.vt_ctors[vt_id]))
+    }
+
+    #[cfg(feature = "al31f-builtin-ops")]
+    fn read_field_name(&mut self) -> Result<NonNull<str>, DeserializeError> {
+        let field_name: &str = self.r.read_str()?;
+        unsafe {
+            let bytes: &'static [u8] = self.slice_arena.unsafe_make(field_name.as_bytes());
+            Ok(NonNull::from(std::str::from_utf8_unchecked(bytes)))
+        }
+    }
+
+    fn deserialize_insc(&mut self) -> Result<Insc, DeserializeError> {
+        let opcode: u8 = self.r.read_u8()?;
+        let r: &mut Reader = &mut self.r;
+        let insc: Insc = match opcode {
+            0 => Insc::Move(r.read_usize()?, r.read_usize()?),
+            1 => Insc::AddInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            2 => Insc::AddFloat(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            3 => Insc::AddAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            4 => Insc::IncrInt(r.read_usize()?),
+            5 => Insc::DecrInt(r.read_usize()?),
+            6 => Insc::SubInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            7 => Insc::SubFloat(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            8 => Insc::SubAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            9 => Insc::MulInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            10 => Insc::MulFloat(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            11 => Insc::MulAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            12 => Insc::DivInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            13 => Insc::DivFloat(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            14 => Insc::DivAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            15 => Insc::ModInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            16 => Insc::ModAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            17 => Insc::EqValue(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            18 => Insc::EqRef(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            19 => Insc::EqAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            20 => Insc::NeValue(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            21 => Insc::NeRef(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            22 => Insc::NeAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            23 => Insc::LtInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            24 => Insc::LtFloat(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            25 => Insc::LtAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            26 => Insc::LeInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            27 => Insc::LeFloat(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            28 => Insc::LeAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            29 => Insc::BAndInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            30 => Insc::BAndAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            31 => Insc::BOrInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            32 => Insc::BOrAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            33 => Insc::BXorInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            34 => Insc::BXorAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            35 => Insc::BNotInt(r.read_usize()?, r.read_usize()?),
+            36 => Insc::BNotAny(r.read_usize()?, r.read_usize()?),
+            37 => Insc::NegInt(r.read_usize()?, r.read_usize()?),
+            38 => Insc::NegFloat(r.read_usize()?, r.read_usize()?),
+            39 => Insc::NegAny(r.read_usize()?, r.read_usize()?),
+            40 => Insc::AndBool(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            41 => Insc::AndAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            42 => Insc::OrBool(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            43 => Insc::OrAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            44 => Insc::NotBool(r.read_usize()?, r.read_usize()?),
+            45 => Insc::NotAny(r.read_usize()?, r.read_usize()?),
+            46 => Insc::ShlInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            47 => Insc::ShlAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            48 => Insc::ShrInt(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            49 => Insc::ShrAny(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            50 => Insc::MakeIntConst(r.read_i64()?, r.read_usize()?),
+            51 => Insc::MakeFloatConst(r.read_f64()?, r.read_usize()?),
+            52 => Insc::MakeBoolConst(r.read_bool()?, r.read_usize()?),
+            53 => Insc::MakeNull(r.read_usize()?),
+            54 => Insc::LoadConst(r.read_usize()?, r.read_usize()?),
+            55 => Insc::SaveConst(r.read_usize()?, r.read_usize()?),
+            56 => Insc::CastFloatInt(r.read_usize()?, r.read_usize()?),
+            57 => Insc::CastBoolInt(r.read_usize()?, r.read_usize()?),
+            58 => Insc::CastAnyInt(r.read_usize()?, r.read_usize()?),
+            59 => Insc::CastIntFloat(r.read_usize()?, r.read_usize()?),
+            60 => Insc::CastAnyFloat(r.read_usize()?, r.read_usize()?),
+            61 => Insc::CastIntBool(r.read_usize()?, r.read_usize()?),
+            62 => Insc::CastAnyBool(r.read_usize()?, r.read_usize()?),
+            63 => Insc::IsNull(r.read_usize()?, r.read_usize()?),
+            64 => Insc::NullCheck(r.read_usize()?),
+            65 => {
+                let src: usize = self.r.read_usize()?;
+                let tyck_info: NonNull<TyckInfo> = self.read_tyck_info()?;
+                Insc::IsType(src, tyck_info, self.r.read_usize()?)
+            },
+            66 => {
+                let src: usize = self.r.read_usize()?;
+                Insc::TypeCheck(src, self.read_tyck_info()?)
+            },
+            67 => Insc::OwnershipInfoCheck(r.read_usize()?, r.read_u8()?),
+            68 => Insc::Call(self.r.read_usize()?, self.read_slice()?, self.read_slice()?),
+            69 => Insc::CallPtr(self.r.read_usize()?, self.read_slice()?, self.read_slice()?),
+            70 => Insc::CallOverload(self.r.read_usize()?, self.read_slice()?, self.read_slice()?),
+            71 => Insc::ReturnNothing,
+            72 => Insc::ReturnOne(r.read_usize()?),
+            73 => Insc::Return(self.read_slice()?),
+            74 => Insc::FFICallRtlc(self.r.read_usize()?, self.read_slice()?, self.read_slice()?),
+            #[cfg(feature = "optimized-rtlc")]
+            75 => Insc::FFICall(self.r.read_usize()?, self.read_slice()?, self.read_slice()?),
+            #[cfg(feature = "async")]
+            76 => Insc::FFICallAsync(self.r.read_usize()?, self.read_slice()?, self.r.read_usize()?),
+            #[cfg(feature = "async")]
+            77 => Insc::Await(self.r.read_usize()?, self.read_slice()?),
+            #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+            78 => Insc::Spawn(self.r.read_usize()?, self.read_slice()?),
+            79 => Insc::Raise(r.read_usize()?),
+            80 => Insc::JumpIfTrue(r.read_usize()?, r.read_usize()?),
+            81 => Insc::JumpIfFalse(r.read_usize()?, r.read_usize()?),
+            82 => Insc::Jump(r.read_usize()?),
+            83 => {
+                let (vt, ctor): (NonNull<GenericTypeVT>, Option<GenericTypeCtor>) = self.read_vt()?;
+                let ctor: GenericTypeCtor = ctor.ok_or(DeserializeError::Malformed {
+                    reason: "container type cannot be constructed"
+                })?;
+                Insc::CreateContainer(ctor, vt, self.r.read_usize()?)
+            },
+            84 => {
+                let func_id: usize = self.r.read_usize()?;
+                let captures: &'static [usize] = self.read_slice()?;
+                let (vt, _): (NonNull<GenericTypeVT>, Option<GenericTypeCtor>) = self.read_vt()?;
+                Insc::CreateClosure(func_id, captures, vt, self.r.read_usize()?)
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            85 => Insc::CreateString(r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            86 => Insc::CreateObject(r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            87 => Insc::VecIndex(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            88 => Insc::VecIndexPut(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            89 => Insc::VecPush(r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            90 => Insc::VecLen(r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            91 => Insc::StrClone(r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            92 => Insc::StrConcat(self.read_slice()?, self.r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            93 => Insc::StrLen(r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            94 => Insc::StrEquals(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            95 => Insc::ObjectGet(
+                self.r.read_usize()?,
+                self.read_field_name()?,
+                self.r.read_usize()?
+            ),
+            #[cfg(feature = "al31f-builtin-ops")]
+            96 => Insc::ObjectGetDyn(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            #[cfg(feature = "al31f-builtin-ops")]
+            97 => Insc::ObjectPut(
+                self.r.read_usize()?,
+                self.read_field_name()?,
+                self.r.read_usize()?
+            ),
+            #[cfg(feature = "al31f-builtin-ops")]
+            98 => Insc::ObjectPutDyn(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            99 => Insc::Rethrow(r.read_usize()?),
+            100 => Insc::LoadGlobal(r.read_usize()?, r.read_usize()?),
+            101 => Insc::StoreGlobal(r.read_usize()?, r.read_usize()?),
+            102 => Insc::CallableCheck(r.read_usize()?, r.read_usize()?, r.read_usize()?),
+            _ => return Err(DeserializeError::UnsupportedInsc { opcode })
+        };
+        Ok(insc)
+    }
+}
+
+#[cfg(all(test, feature = "compiler", feature = "al31f-builtin-ops"))]
+mod test {
+    use std::any::TypeId;
+
+    use super::{DeserializeError, deserialize_program, serialize_program};
+
+    use crate::data::Value;
+    use crate::driver::compile;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::{CompiledProgram, ExceptionHandlingBlock, HandlerKind};
+    use crate::vm::al31f::executor::vm_run_function_sync;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+    #[cfg(feature = "std47")] use crate::std47::io::PrintBind;
+
+    fn run_int(program: &CompiledProgram<DefaultAlloc>, func_id: usize, args: &[Value]) -> i64 {
+        let result: Vec<Value> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), program, func_id, args)
+        }.unwrap_or_else(|_| panic!("should not throw"));
+        unsafe { result[0].vt_data.inner.int_value }
+    }
+
+    #[test]
+    fn test_bytecode_round_trip() {
+        let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+            var last_checked int = 0;
+
+            func check(x int): int {
+                last_checked = x;
+                if x > 100 {
+                    throw "too large";
+                }
+                return x;
+            }
+
+            func guarded(x int): int {
+                try {
+                    return check(x) * 2;
+                } catch (e string) {
+                    if e == "too large" {
+                        return -1;
+                    }
+                    return -2;
+                }
+            }
+
+            func identity(x any): any {
+                return x;
+            }
+
+            func scaled(x int, k any): int {
+                return ((x as float) * (identity(k) as float)) as int;
+            }
+        "#)], FFIRegistry::new()).unwrap_or_else(|_| panic!("should compile"));
+
+        let bytes: Vec<u8> = serialize_program(&program).unwrap();
+        let loaded: CompiledProgram<DefaultAlloc> =
+            deserialize_program(&bytes, &FFIRegistry::new()).unwrap();
+        assert_eq!(loaded.func_ids, program.func_ids);
+        assert_eq!(loaded.global_names, program.global_names);
+        assert_eq!(loaded.debug_info.line_table, program.debug_info.line_table);
+        assert_eq!(serialize_program(&loaded).unwrap(), bytes);
+
+        let guarded: usize = loaded.func_ids["guarded"];
+        assert_eq!(run_int(&loaded, guarded, &[Value::new_int(21)]), 42);
+        assert_eq!(run_int(&loaded, guarded, &[Value::new_int(101)]), -1);
+        let scaled: usize = loaded.func_ids["scaled"];
+        assert_eq!(run_int(&loaded, scaled, &[Value::new_int(3), Value::new_float(1.5)]), 4);
+    }
+
+    #[cfg(feature = "std47")]
+    #[test]
+    fn test_bytecode_ffi_by_name() {
+        let mut ffi_registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+        ffi_registry.register_func("print", &PrintBind());
+        let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+            func print(s string);
+
+            func main() {
+                print("hello");
+            }
+        "#)], ffi_registry).unwrap_or_else(|_| panic!("should compile"));
+        let bytes: Vec<u8> = serialize_program(&program).unwrap();
+
+        match deserialize_program::<DefaultAlloc>(&bytes, &FFIRegistry::new()) {
+            Err(DeserializeError::UnresolvedFFIFunc { name }) => assert_eq!(name, "print"),
+            _ => panic!("should fail to bind `print`")
+        }
+
+        let mut ffi_registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+        ffi_registry.register_func("print", &PrintBind());
+        let loaded: CompiledProgram<DefaultAlloc> =
+            deserialize_program(&bytes, &ffi_registry).unwrap();
+        assert_eq!(&*loaded.ffi_func_names, &["print".to_string()]);
+    }
+
+    struct HostError();
+
+    #[test]
+    fn test_bytecode_exception_types_by_name() {
+        let type_id: TypeId = TypeId::of::<HostError>();
+        let registry = || {
+            let mut ffi_registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+            ffi_registry.register_exception_type("HostError", type_id, None);
+            ffi_registry
+        };
+        let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+            func guarded(x int): int {
+                try {
+                    return x;
+                } catch (e HostError) {
+                    return -1;
+                }
+            }
+
+            func describe(e HostError): int {
+                return 1;
+            }
+        "#)], registry()).unwrap_or_else(|_| panic!("should compile"));
+        let bytes: Vec<u8> = serialize_program(&program).unwrap();
+
+        match deserialize_program::<DefaultAlloc>(&bytes, &FFIRegistry::new()) {
+            Err(DeserializeError::UnknownType { type_name }) => assert_eq!(type_name, "HostError"),
+            _ => panic!("should fail to resolve `HostError`")
+        }
+
+        let loaded: CompiledProgram<DefaultAlloc> =
+            deserialize_program(&bytes, &registry()).unwrap();
+        let guarded: usize = loaded.func_ids["guarded"];
+        assert!(matches!(
+            loaded.functions[guarded].exc_handlers.as_deref(),
+            Some([ExceptionHandlingBlock { kind: HandlerKind::Typed(handled), .. }])
+                if *handled == type_id
+        ));
+        assert_eq!(serialize_program(&loaded).unwrap(), bytes);
+        assert_eq!(run_int(&loaded, guarded, &[Value::new_int(21)]), 21);
+    }
+
+    #[test]
+    fn test_bytecode_malformed() {
+        let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+            func main(): string {
+                return "text";
+            }
+        "#)], FFIRegistry::new()).unwrap_or_else(|_| panic!("should compile"));
+        let bytes: Vec<u8> = serialize_program(&program).unwrap();
+
+        let registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+        assert!(matches!(
+            deserialize_program(b"P47X", &registry),
+            Err(DeserializeError::BadMagic)
+        ));
+        let mut bad_version: Vec<u8> = bytes.clone();
+        bad_version[4] = 0xff;
+        assert!(matches!(
+            deserialize_program(&bad_version, &registry),
+            Err(DeserializeError::UnsupportedVersion { .. })
+        ));
+        for len /*: usize*/ in 4..bytes.len() {
+            assert!(deserialize_program(&bytes[..len], &registry).is_err());
+        }
+    }
+}
@@ -1,20 +1,42 @@
 use std::any::TypeId;
+use std::collections::HashMap;
 use std::ptr::NonNull;
+use std::sync::Mutex;
+
 use xjbutil::slice_arena::SliceArena;
 
+use crate::builtins::vm_error::vm_error_parent;
 use crate::data::Value;
-use crate::data::tyck::TyckInfo;
+use crate::data::generic::GenericTypeVT;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
+use crate::data::wrapper::{DynBase, OwnershipInfo};
 use crate::ffi::sync_fn::Function as FFIFunction;
 use crate::vm::al31f::Combustor;
 use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::executor::overload::ArgTypeKey;
 use crate::vm::al31f::insc::Insc;
 
+#[cfg(feature = "compiler")] use crate::diag::location::SourceRange;
+
 #[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
 #[cfg(feature = "async")] use crate::vm::al31f::{AL31F, AsyncCombustor};
 
+/// Exceptions an `ExceptionHandlingBlock` handles
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HandlerKind {
+    /// Exceptions of the type, and of all types registered under it, see
+    /// `CompiledProgram::is_exception_of`
+    Typed(TypeId),
+    /// All exceptions, generated for `catch (e any)`
+    CatchAll,
+    /// All exceptions, like `CatchAll`. The handler runs a `finally` block and then throws the
+    /// exception again with `Rethrow`
+    Finally
+}
+
 pub struct ExceptionHandlingBlock {
     pub insc_ptr_range: (usize, usize),
-    pub exception_id: TypeId,
+    pub kind: HandlerKind,
     pub handler_addr: usize
 }
 
@@ -22,15 +44,29 @@ impl ExceptionHandlingBlock {
     pub fn new(
         insc_ptr_start: usize,
         insc_ptr_end: usize,
-        exception_id: TypeId,
+        kind: HandlerKind,
         handler_addr: usize
     ) -> Self {
         Self {
             insc_ptr_range: (insc_ptr_start, insc_ptr_end),
-            exception_id,
+            kind,
             handler_addr
         }
     }
+
+    /// Checks if this handler handles exceptions of `exception_type_id` thrown at `insc_ptr`
+    pub fn handles<A: Alloc>(
+        &self,
+        program: &CompiledProgram<A>,
+        insc_ptr: usize,
+        exception_type_id: TypeId
+    ) -> bool {
+        let (start_insc, end_insc): (usize, usize) = self.insc_ptr_range;
+        insc_ptr >= start_insc && insc_ptr <= end_insc && match self.kind {
+            HandlerKind::Typed(type_id) => program.is_exception_of(exception_type_id, type_id),
+            HandlerKind::CatchAll | HandlerKind::Finally => true
+        }
+    }
 }
 
 pub struct CompiledFunction {
@@ -80,15 +116,151 @@ impl CompiledFunction {
     }
 }
 
+/// Candidates of a `CallOverload` instruction, selected from by run-time argument types
+///
+/// Every call site gets its own table, so the target cached in a table is cached per call site.
+pub struct OverloadTable {
+    /// Function ids of candidates, in declaration order
+    pub candidates: Box<[usize]>,
+    /// Argument types of the last call through this table, and the candidate selected for them
+    pub cache: Mutex<Option<(Box<[ArgTypeKey]>, usize)>>
+}
+
+impl OverloadTable {
+    pub fn new(candidates: Box<[usize]>) -> Self {
+        Self {
+            candidates,
+            cache: Mutex::new(None)
+        }
+    }
+}
+
+/// Maps the instruction at `insc_ptr`, and all instructions after it until the next entry, to a
+/// range of source code
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LineTableEntry {
+    pub insc_ptr: usize,
+    /// `u32::MAX` if the instructions do not come from any source code
+    pub file_id: u32,
+    pub offset_begin: u32,
+    pub offset_end: u32
+}
+
+impl LineTableEntry {
+    pub fn new(insc_ptr: usize, file_id: u32, offset_begin: u32, offset_end: u32) -> Self {
+        Self { insc_ptr, file_id, offset_begin, offset_end }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.file_id == u32::MAX
+    }
+
+    #[cfg(feature = "compiler")]
+    pub fn source_range(&self) -> SourceRange {
+        SourceRange::new(self.file_id, self.offset_begin, self.offset_end)
+    }
+}
+
+/// Debug information of a program, only used for reporting
+///
+/// Programs not generated from source code (assembled ones, for example) carry no line table,
+/// and may carry no function names either.
+#[derive(Default)]
+pub struct DebugInfo {
+    /// Sorted by `insc_ptr`
+    pub line_table: Box<[LineTableEntry]>,
+    /// Names of functions, indexed by function id
+    pub func_names: Box<[String]>
+}
+
+impl DebugInfo {
+    /// Finds the line table entry covering the instruction at `insc_ptr`
+    pub fn line_table_entry(&self, insc_ptr: usize) -> Option<&LineTableEntry> {
+        let idx: usize = self.line_table
+            .partition_point(|entry: &LineTableEntry| entry.insc_ptr <= insc_ptr);
+        if idx == 0 {
+            return None;
+        }
+        let entry: &LineTableEntry = &self.line_table[idx - 1];
+        if entry.is_unknown() { None } else { Some(entry) }
+    }
+
+    pub fn func_name(&self, func_id: usize) -> Option<&str> {
+        self.func_names.get(func_id).map(String::as_str)
+    }
+}
+
 pub struct CompiledProgram<A: Alloc> {
     pub slice_arena: SliceArena<8192, 8>,
 
     pub code: Box<[Insc]>,
     pub const_pool: Box<[Value]>,
+    /// Owns all the type information referred by `code` and `functions`
+    pub tyck_info_pool: TyckInfoPool,
+    /// Names of global variables, indexed by global ids used by `LoadGlobal` and `StoreGlobal`
+    pub global_names: Box<[String]>,
+    /// Initializes global variables. Runs once on each VM before other functions.
     pub init_proc: usize,
     pub functions: Box<[CompiledFunction]>,
+    /// Maps names of script functions to their function ids
+    pub func_ids: HashMap<String, usize>,
+    /// Owns all the generic type vtables referred by `code`
+    pub generic_vts: Box<[GenericTypeVT]>,
+    /// Overload tables referred by `CallOverload` instructions
+    pub overload_tables: Box<[OverloadTable]>,
+    /// Maps exception types to their parent types, see `is_exception_of`
+    pub exception_parents: HashMap<TypeId, TypeId>,
+    /// Names host exception types were registered by, so that they can be resolved again after
+    /// deserialization
+    pub exception_type_names: HashMap<TypeId, String>,
+    pub debug_info: DebugInfo,
 
     pub ffi_funcs: Box<[&'static dyn FFIFunction<Combustor<A>>]>,
+    /// Names `ffi_funcs` were bound by, so that they can be bound again after deserialization
+    pub ffi_func_names: Box<[String]>,
+    #[cfg(feature = "async")]
+    pub async_ffi_funcs: Box<[&'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>]>,
     #[cfg(feature = "async")]
-    pub async_ffi_funcs: Box<[&'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>]>
+    pub async_ffi_func_names: Box<[String]>
+}
+
+impl<A: Alloc> CompiledProgram<A> {
+    /// Checks if exceptions of `type_id` belong to the family of `family_type_id`, that is,
+    /// `family_type_id` is `type_id` itself or one of its ancestors
+    ///
+    /// Error types in `builtins::vm_error` all belong to the `VMError` family, and host exception
+    /// types are registered under their parents with `FFIRegistry::register_exception_type`.
+    pub fn is_exception_of(&self, type_id: TypeId, family_type_id: TypeId) -> bool {
+        let mut type_id: TypeId = type_id;
+        loop {
+            if type_id == family_type_id {
+                return true;
+            }
+            match self.exception_parents.get(&type_id).copied()
+                .or_else(|| vm_error_parent(type_id))
+            {
+                Some(parent) => type_id = parent,
+                None => return false
+            }
+        }
+    }
+}
+
+impl<A: Alloc> Drop for CompiledProgram<A> {
+    fn drop(&mut self) {
+        // Constants are never managed by garbage collectors, so they should be freed here. Only
+        // `GlobalConst` objects are owned by the constant pool.
+        for value /*: &Value*/ in self.const_pool.iter() {
+            unsafe {
+                if value.is_ref()
+                    && !value.is_null()
+                    && !value.is_container()
+                    && value.ownership_info() == OwnershipInfo::GlobalConst
+                {
+                    let boxed: Box<dyn DynBase> = Box::from_raw(value.get_as_dyn_base());
+                    drop(boxed);
+                }
+            }
+        }
+    }
 }
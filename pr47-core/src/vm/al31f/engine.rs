@@ -0,0 +1,295 @@
+//! # Reusable engine for running many script functions against the same state
+//!
+//! `vm_run_function_sync` creates a new VM for each run and drops all managed objects after it.
+//! An `Engine` instead keeps one VM, together with its heap, for its whole lifetime:
+//!
+//! ```rust,ignore
+//! let mut engine: Engine<DefaultAlloc> = unsafe { Engine::new(AL31F::new(alloc), &program)? };
+//! let rets: Vec<Value> = unsafe { engine.call(func_id, &[Value::new_int(42)])? };
+//! let retained: Retained = engine.retain(&rets);
+//! // `rets` stay alive across further calls and collections, until released
+//! unsafe { engine.release(retained) };
+//! ```
+//!
+//! Global variables of the program get initialized once, when the engine is created.
+
+use std::marker::{PhantomData, PhantomPinned};
+use std::ptr::NonNull;
+
+use xjbutil::unchecked::UncheckedSendSync;
+
+use crate::data::Value;
+use crate::vm::al31f::AL31F;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::CompiledProgram;
+use crate::vm::al31f::exception::Exception;
+use crate::vm::al31f::executor::{VMThread, vm_thread_init_globals, vm_thread_run_function};
+use crate::vm::al31f::stack::Stack;
+
+#[cfg(feature = "async")]
+use crate::util::serializer::CoroutineContext;
+
+/// Owns an `AL31F` and keeps managed objects alive between calls
+///
+/// Managed objects returned from a call stay alive until the next call or collection on the
+/// same engine. Use `retain` to keep them longer.
+pub struct Engine<'p, A: Alloc> {
+    thread: Box<VMThread<A>>,
+    _program: PhantomData<&'p CompiledProgram<A>>
+}
+
+/// Objects kept alive by `Engine::retain`
+#[must_use = "retained objects are kept alive until released"]
+pub struct Retained {
+    pin: *mut bool
+}
+
+impl<'p, A: Alloc> Engine<'p, A> {
+    /// Creates an engine running `program` on `vm`, initializing global variables of `program`
+    pub unsafe fn new(vm: AL31F<A>, program: &'p CompiledProgram<A>) -> Result<Self, Exception> {
+        let stack: Stack = vm.create_stack();
+
+        #[cfg(feature = "async")]
+        let vm: CoroutineContext<AL31F<A>> =
+            pollster::block_on(CoroutineContext::main_context(vm));
+
+        let mut thread: Box<VMThread<A>> = Box::new(VMThread {
+            vm,
+            program: NonNull::from(program),
+            stack,
+            _phantom: PhantomPinned
+        });
+        let stack: *const Stack = &thread.stack;
+        thread.vm_mut().alloc.add_stack(stack);
+
+        pollster::block_on(vm_thread_init_globals::<_, true>(&mut thread))?;
+        Ok(Self { thread, _program: PhantomData })
+    }
+
+    pub fn program(&self) -> &'p CompiledProgram<A> {
+        unsafe { &*self.thread.program.as_ptr() }
+    }
+
+    /// Gets the VM of this engine, for example to set fuel or to create interrupt handles
+    pub fn vm_mut(&mut self) -> &mut AL31F<A> {
+        self.thread.vm_mut()
+    }
+
+    /// Runs function `func_id` to completion on the current thread
+    ///
+    /// Referenced `args` should have been added to the allocator of this engine.
+    pub unsafe fn call(
+        &mut self,
+        func_id: usize,
+        args: &[Value]
+    ) -> Result<Vec<Value>, Exception> {
+        let thread: &mut VMThread<A> = &mut self.thread;
+        let ret: Result<Vec<Value>, Exception> = pollster::block_on(async {
+            vm_thread_run_function::<_, true>(UncheckedSendSync::new((thread, func_id, args)))?
+                .await
+                .into_inner()
+        });
+        self.thread.stack.clear();
+        ret
+    }
+
+    /// Like `call`, but allows coroutines and asynchronous host functions to run
+    #[cfg(feature = "async")]
+    pub async unsafe fn call_async(
+        &mut self,
+        func_id: usize,
+        args: &[Value]
+    ) -> Result<Vec<Value>, Exception> {
+        let thread: &mut VMThread<A> = &mut self.thread;
+        let arg_pack = UncheckedSendSync::new((thread, func_id, args));
+        let ret: Result<Vec<Value>, Exception> =
+            match vm_thread_run_function::<_, false>(arg_pack) {
+                Ok(fut) => fut.await.into_inner(),
+                Err(e) => Err(e)
+            };
+        self.thread.stack.clear();
+        ret
+    }
+
+    /// Keeps managed objects referred by `values` alive across calls and collections, until
+    /// `release` gets called
+    pub fn retain(&mut self, values: &[Value]) -> Retained {
+        Retained { pin: unsafe { self.vm_mut().alloc.pin_objects(values) } }
+    }
+
+    /// Releases objects retained by `retain` on this engine, making them collectable again
+    pub unsafe fn release(&mut self, retained: Retained) {
+        if !retained.pin.is_null() {
+            *retained.pin = false;
+        }
+    }
+
+    /// Collects objects not reachable from retained ones
+    pub fn collect(&mut self) {
+        unsafe { self.vm_mut().alloc.collect(); }
+    }
+
+    /// Allows or disallows collections triggered by allocations. Each call allows them again.
+    pub fn set_gc_allowed(&mut self, allowed: bool) {
+        self.vm_mut().alloc.set_gc_allowed(allowed);
+    }
+
+    /// Bytes of memory currently occupied by managed objects
+    pub fn memory_usage(&mut self) -> usize {
+        self.vm_mut().memory_usage()
+    }
+}
+
+#[cfg(all(test, feature = "compiler", feature = "al31f-builtin-ops"))]
+mod test {
+    use crate::data::Value;
+    use crate::data::exception::{ExceptionInner, UncheckedException};
+    use crate::driver::compile;
+    use crate::vm::al31f::AL31F;
+    use crate::vm::al31f::alloc::Alloc;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+    use crate::vm::al31f::host::ScriptFunction;
+
+    use super::{Engine, Retained};
+
+    const SOURCE: &str = r#"
+        func concat(a string, b string): string {
+            return a + b;
+        }
+
+        func divide(a int, b int): int {
+            return a / b;
+        }
+    "#;
+
+    #[test]
+    fn test_engine_reuse() {
+        let program: CompiledProgram<DefaultAlloc> =
+            compile(&[("main.47", SOURCE)], FFIRegistry::new())
+                .unwrap_or_else(|_| panic!("should compile"));
+        let mut engine: Engine<DefaultAlloc> =
+            unsafe { Engine::new(AL31F::new(DefaultAlloc::new()), &program) }
+                .unwrap_or_else(|_| panic!("should initialize"));
+
+        let concat: ScriptFunction<DefaultAlloc, (String, String), (String,)> =
+            program.get_function("concat").unwrap();
+        for i /*: usize*/ in 0..2048 {
+            assert_eq!(
+                concat.call_in(&mut engine, (i.to_string(), "!".to_string())).unwrap(),
+                (format!("{}!", i),)
+            );
+        }
+
+        let divide: ScriptFunction<DefaultAlloc, (i64, i64), (i64,)> =
+            program.get_function("divide").unwrap();
+        assert!(divide.call_in(&mut engine, (1, 0)).is_err());
+        assert_eq!(divide.call_in(&mut engine, (6, 3)).unwrap(), (2,));
+        let e = unsafe { engine.call(program.func_ids["divide"], &[Value::new_int(1)]) }
+            .err()
+            .unwrap();
+        assert!(matches!(
+            e.inner,
+            ExceptionInner::Unchecked(UncheckedException::ArgCountMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_engine_retain() {
+        let program: CompiledProgram<DefaultAlloc> =
+            compile(&[("main.47", SOURCE)], FFIRegistry::new())
+                .unwrap_or_else(|_| panic!("should compile"));
+        let mut engine: Engine<DefaultAlloc> =
+            unsafe { Engine::new(AL31F::new(DefaultAlloc::new()), &program) }
+                .unwrap_or_else(|_| panic!("should initialize"));
+
+        let args: [Value; 2] = [
+            Value::new_owned("hello, ".to_string()),
+            Value::new_owned("world".to_string())
+        ];
+        engine.set_gc_allowed(false);
+        unsafe {
+            engine.vm_mut().alloc.add_managed(args[0]);
+            engine.vm_mut().alloc.add_managed(args[1]);
+        }
+        let rets: Vec<Value> = unsafe { engine.call(program.func_ids["concat"], &args) }
+            .unwrap_or_else(|_| panic!("should run"));
+        let retained: Retained = engine.retain(&rets);
+        engine.collect();
+        assert!(engine.vm_mut().alloc.contains_ptr(unsafe { rets[0].ptr_repr }));
+        assert_eq!(
+            unsafe { &*rets[0].get_as_mut_ptr::<String>() },
+            "hello, world"
+        );
+
+        unsafe { engine.release(retained) };
+        engine.collect();
+        assert!(!engine.vm_mut().alloc.contains_ptr(unsafe { rets[0].ptr_repr }));
+        assert_eq!(engine.memory_usage(), 0);
+    }
+
+    #[test]
+    fn test_engine_globals() {
+        let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+            var greeting string = `hello, ` + `world`;
+            var counter int = 0;
+
+            func bump(): int {
+                counter += 1;
+                return counter;
+            }
+
+            func greet(name string): string {
+                greeting = `hello, ` + name;
+                return greeting;
+            }
+
+            func get_greeting(): string {
+                return greeting;
+            }
+        "#)], FFIRegistry::new())
+            .unwrap_or_else(|_| panic!("should compile"));
+        let mut engine: Engine<DefaultAlloc> =
+            unsafe { Engine::new(AL31F::new(DefaultAlloc::new()), &program) }
+                .unwrap_or_else(|_| panic!("should initialize"));
+
+        let bump: ScriptFunction<DefaultAlloc, (), (i64,)> = program.get_function("bump").unwrap();
+        for i /*: i64*/ in 1..=16 {
+            assert_eq!(bump.call_in(&mut engine, ()).unwrap(), (i,));
+        }
+
+        let greet: ScriptFunction<DefaultAlloc, (String,), (String,)> =
+            program.get_function("greet").unwrap();
+        let get_greeting: ScriptFunction<DefaultAlloc, (), (String,)> =
+            program.get_function("get_greeting").unwrap();
+        assert_eq!(get_greeting.call_in(&mut engine, ()).unwrap(), ("hello, world".to_string(),));
+        assert_eq!(
+            greet.call_in(&mut engine, ("pr47".to_string(),)).unwrap(),
+            ("hello, pr47".to_string(),)
+        );
+        // the global string is only referred by the VM now
+        engine.collect();
+        assert_ne!(engine.memory_usage(), 0);
+        assert_eq!(get_greeting.call_in(&mut engine, ()).unwrap(), ("hello, pr47".to_string(),));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_engine_call_async() {
+        let program: CompiledProgram<DefaultAlloc> =
+            compile(&[("main.47", SOURCE)], FFIRegistry::new())
+                .unwrap_or_else(|_| panic!("should compile"));
+        let mut engine: Engine<DefaultAlloc> =
+            unsafe { Engine::new(AL31F::new(DefaultAlloc::new()), &program) }
+                .unwrap_or_else(|_| panic!("should initialize"));
+
+        let divide: usize = program.func_ids["divide"];
+        for i /*: i64*/ in 1..16 {
+            let rets: Vec<Value> = pollster::block_on(unsafe {
+                engine.call_async(divide, &[Value::new_int(i * 7), Value::new_int(i)])
+            }).unwrap_or_else(|_| panic!("should run"));
+            assert_eq!(unsafe { rets[0].vt_data.inner.int_value }, 7);
+        }
+    }
+}
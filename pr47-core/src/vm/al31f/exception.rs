@@ -1,9 +1,17 @@
+use std::fmt::{Display, Formatter};
 use std::marker::PhantomPinned;
 use xjbutil::void::Void;
 
 use crate::data::exception::{CheckedException, ExceptionInner, StackTrace, UncheckedException};
 use crate::data::traits::{ChildrenType, StaticBase};
 
+#[cfg(feature = "compiler")] use std::fmt::Write;
+
+#[cfg(feature = "compiler")] use crate::diag::location::{SourceCoord, SourceLoc};
+#[cfg(feature = "compiler")] use crate::diag::source::SourceManager;
+#[cfg(feature = "compiler")] use crate::vm::al31f::alloc::Alloc;
+#[cfg(feature = "compiler")] use crate::vm::al31f::compiled::CompiledProgram;
+
 pub struct Exception {
     pub inner: ExceptionInner,
     pub trace: Vec<StackTrace>,
@@ -31,6 +39,52 @@ impl Exception {
         self.trace.push(StackTrace::new(func_id, insc_ptr))
     }
 
+    /// Renders the stack trace, one `at fib (main.p47:12:5)` line per frame, innermost first
+    ///
+    /// `source_mgr` should be the one `program` was compiled with. Frames without source
+    /// locations, for example frames of assembled programs, are rendered with function ids and
+    /// instruction addresses instead.
+    #[cfg(feature = "compiler")]
+    pub fn render_backtrace<A: Alloc>(
+        &self,
+        program: &CompiledProgram<A>,
+        source_mgr: &SourceManager
+    ) -> String {
+        let mut backtrace: String = String::new();
+        for trace /*: &StackTrace*/ in self.trace.iter() {
+            let func_name: &str = program.debug_info.func_name(trace.func_id)
+                .or_else(|| program.func_ids.iter()
+                    .find(|(_, func_id): &(&String, &usize)| **func_id == trace.func_id)
+                    .map(|(func_name, _): (&String, &usize)| func_name.as_str()))
+                .unwrap_or("<unknown>");
+
+            match program.debug_info.line_table_entry(trace.insc_ptr) {
+                Some(entry) if (entry.file_id as usize) < source_mgr.file_count() => {
+                    let loc: SourceLoc = entry.source_range().left();
+                    let (_, coord): (&str, SourceCoord) = loc.compute_coord(source_mgr);
+                    let _ = writeln!(
+                        backtrace,
+                        "at {} ({}:{}:{})",
+                        func_name,
+                        source_mgr.file_name(entry.file_id),
+                        coord.line + 1,
+                        coord.col + 1
+                    );
+                },
+                _ => {
+                    let _ = writeln!(
+                        backtrace,
+                        "at {} (function #{}, instruction #{})",
+                        func_name,
+                        trace.func_id,
+                        trace.insc_ptr
+                    );
+                }
+            }
+        }
+        backtrace
+    }
+
     #[cfg(test)]
     pub fn assert_checked(&self) -> CheckedException {
         match &self.inner {
@@ -40,6 +94,12 @@ impl Exception {
     }
 }
 
+impl Display for Exception {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
 impl StaticBase<Exception> for Void {
     fn type_name() -> String { "Exception".into() }
 
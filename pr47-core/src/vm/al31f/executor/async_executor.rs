@@ -18,7 +18,7 @@ use crate::data::wrapper::Wrapper;
 use crate::data::value_typed::INT_TYPE_TAG;
 use crate::ffi::FFIException;
 use crate::ffi::sync_fn::Function as FFIFunction;
-use crate::vm::al31f::{AL31F, Combustor};
+use crate::vm::al31f::{AL31F, Combustor, InterruptHandle};
 use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
 use crate::vm::al31f::exception::Exception;
@@ -26,7 +26,7 @@ use crate::vm::al31f::executor::checked_bin_ops::*;
 use crate::vm::al31f::executor::checked_cast_ops::*;
 use crate::vm::al31f::executor::checked_unary_ops::*;
 use crate::vm::al31f::executor::overload::call_overload;
-use crate::vm::al31f::executor::rtti::check_type;
+use crate::vm::al31f::executor::rtti::{check_callable, check_type};
 use crate::vm::al31f::executor::unwinding::*;
 use crate::vm::al31f::insc::Insc;
 use crate::vm::al31f::stack::{Stack, StackSlice};
@@ -48,6 +48,11 @@ use crate::vm::al31f::executor::coroutine_spawn::coroutine_spawn;
 include!("get_vm_makro.rs");
 include!("impl_makro.rs");
 
+/// Maximum count of arguments passed to a host function in one call
+pub const FFI_MAX_ARGS: usize = 32;
+/// Maximum count of values returned from a host function in one call
+pub const FFI_MAX_RETS: usize = 8;
+
 #[must_use = "VM threads are effective iff a function gets run on it"]
 pub struct VMThread<A: Alloc> {
     #[cfg(feature = "async")]
@@ -69,34 +74,54 @@ impl<A: Alloc> Drop for VMThread<A> {
     }
 }
 
+impl<A: Alloc> VMThread<A> {
+    /// Gets the VM this thread runs on
+    pub fn vm_mut(&mut self) -> &mut AL31F<A> {
+        get_vm!(self)
+    }
+
+    /// Creates a handle for stopping runs on the VM of this thread from other threads
+    pub fn interrupt_handle(&mut self) -> InterruptHandle {
+        get_vm!(self).interrupt_handle()
+    }
+}
+
 unsafe impl<A: Alloc> Send for VMThread<A> {}
 unsafe impl<A: Alloc> Sync for VMThread<A> {}
 
+/// Creates the main thread of a new VM running `program`, and initializes global variables of
+/// `program` on it
 #[cfg(feature = "async")]
 pub async fn create_vm_main_thread<A: Alloc>(
     alloc: A,
     program: &CompiledProgram<A>
-) -> Box<VMThread<A>> {
+) -> Result<Box<VMThread<A>>, Exception> {
+    let vm: AL31F<A> = AL31F::new(alloc);
+    let stack: Stack = vm.create_stack();
     let mut ret = Box::new(VMThread {
-        vm: CoroutineContext::main_context(AL31F::new(alloc)).await,
+        vm: CoroutineContext::main_context(vm).await,
         program: NonNull::from(program),
-        stack: Stack::new(),
+        stack,
         _phantom: PhantomPinned
     });
-    unsafe { ret.vm.get_shared_data_mut().alloc.add_stack(&ret.stack) };
-    ret
+    unsafe {
+        ret.vm.get_shared_data_mut().alloc.add_stack(&ret.stack);
+        vm_thread_init_globals::<_, false>(&mut ret).await?;
+    }
+    Ok(ret)
 }
 
 #[must_use = "VM threads are effective iff a function gets run on it"]
 #[cfg(feature = "async")]
 pub fn create_vm_child_thread<A: Alloc>(
-    child_context: CoroutineContext<AL31F<A>>,
+    mut child_context: CoroutineContext<AL31F<A>>,
     program: NonNull<CompiledProgram<A>>
 ) -> Box<VMThread<A>> {
+    let stack: Stack = child_context.get_shared_data_mut().create_stack();
     let mut ret = Box::new(VMThread {
         vm: child_context,
         program,
-        stack: Stack::new(),
+        stack,
         _phantom: PhantomPinned
     });
     unsafe { ret.vm.get_shared_data_mut().alloc.add_stack(&ret.stack) };
@@ -107,6 +132,11 @@ pub struct VMThreadRunFunctionFut<'a, A: Alloc, const S: bool> {
     thread: &'a mut VMThread<A>,
     slice: StackSlice,
     insc_ptr: usize,
+    /// Instructions this run may still execute, `u64::MAX` for no limit
+    fuel: u64,
+    interrupt_handle: InterruptHandle,
+    #[cfg(feature = "async")]
+    quantum: u64,
 
     #[cfg(feature = "async")]
     awaiting_promise: Option<Pin<Box<dyn Future<Output=PromiseResult<AL31F<A>>>>>>,
@@ -138,7 +168,6 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     ExceptionInner::Checked(checked) => {
                         let (new_slice, insc_ptr_next): (StackSlice, usize) =
                             checked_exception_unwind_stack(
-                                get_vm!(this.thread),
                                 this.thread.program.as_ref(),
                                 checked,
                                 &mut this.thread.stack,
@@ -148,9 +177,16 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                         this.insc_ptr = insc_ptr_next;
                     },
                     ExceptionInner::Unchecked(unchecked) => {
-                        return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                            unchecked, &mut this.thread.stack, this.insc_ptr
-                        )));
+                        let (new_slice, insc_ptr_next): (StackSlice, usize) =
+                            unchecked_exception_unwind_stack(
+                                this.thread.program.as_ref(),
+                                &mut get_vm!(this.thread).alloc,
+                                unchecked,
+                                &mut this.thread.stack,
+                                this.insc_ptr
+                            )?;
+                        this.slice = new_slice;
+                        this.insc_ptr = insc_ptr_next;
                     }
                 }
             }
@@ -162,24 +198,15 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
     let slice: &mut StackSlice = &mut this.slice;
     let thread: &mut VMThread<A> = this.thread;
     let program: &CompiledProgram<A> = thread.program.as_ref();
-    let mut ffi_args: [Value; 32] = [Value::new_null(); 32];
-    let mut ffi_rets: [*mut Value; 8] = [std::ptr::null_mut(); 8];
+    let mut ffi_args: [Value; FFI_MAX_ARGS] = [Value::new_null(); FFI_MAX_ARGS];
+    let mut ffi_rets: [*mut Value; FFI_MAX_RETS] = [std::ptr::null_mut(); FFI_MAX_RETS];
 
-    #[cfg(feature = "async-avoid-block")]
-    #[allow(unused)]
-    let mut insc_counter: u64 = 0;
+    // instructions executed in this poll, charged against `this.fuel` at checkpoints
+    let mut executed: u64 = 0;
 
     let mut insc_ptr: usize = this.insc_ptr;
     loop {
-        #[cfg(feature = "async-avoid-block")]
-        if !S {
-            insc_counter += 1;
-            if insc_counter == 500_000 {
-                this.insc_ptr = insc_ptr;
-                cx.waker().wake_by_ref();
-                return Poll::Pending;
-            }
-        }
+        executed += 1;
 
         #[cfg(not(debug_assertions))]
         let insc: &Insc = program.code.get_unchecked(insc_ptr);
@@ -220,9 +247,12 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 if let Some(result) = i64::checked_div(src1, src2) {
                     slice.set_value(*dst, Value::new_int(result))
                 } else {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::DivideByZero, &mut thread.stack, insc_ptr
-                    )));
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
+                        UncheckedException::DivideByZero
+                    ];
                 }
             },
             Insc::DivFloat(src1, src2, dst) => impl_float_binop![slice, src1, src2, dst, /],
@@ -234,9 +264,12 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 if let Some(result) = i64::checked_rem(src1, src2) {
                     slice.set_value(*dst, Value::new_int(result))
                 } else {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::DivideByZero, &mut thread.stack, insc_ptr
-                    )));
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
+                        UncheckedException::DivideByZero
+                    ];
                 }
             },
             Insc::ModAny(src1, src2, dst) =>
@@ -346,6 +379,14 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let constant: Value = slice.get_value(*const_src);
                 *thread.program.as_mut().const_pool.get_unchecked_mut(*const_id) = constant;
             }
+            Insc::LoadGlobal(global_id, dst) => {
+                let value: Value = *get_vm!(thread).globals.0.get_unchecked(*global_id);
+                slice.set_value(*dst, value);
+            }
+            Insc::StoreGlobal(src, global_id) => {
+                let value: Value = slice.get_value(*src);
+                *get_vm!(thread).globals.0.get_unchecked_mut(*global_id) = value;
+            }
             Insc::CastFloatInt(src, dst) =>
                 impl_cast_op![slice, src, dst, f64, i64, float_value, new_int],
             Insc::CastBoolInt(src, dst) =>
@@ -370,11 +411,12 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             Insc::NullCheck(src) => {
                 let src: Value = slice.get_value(*src);
                 if src.is_null() {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::UnexpectedNull { value: src },
-                        &mut thread.stack,
-                        insc_ptr
-                    )));
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
+                        UncheckedException::UnexpectedNull { value: src }
+                    ];
                 }
             },
             Insc::IsType(src, tyck_info, dest) => {
@@ -384,36 +426,49 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             Insc::TypeCheck(src, tyck_info) => {
                 let src: Value = slice.get_value(*src);
                 if !check_type(src, *tyck_info) {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
                         UncheckedException::TypeCheckFailure {
                             object: src,
                             expected_type: *tyck_info
-                        },
-                        &mut thread.stack,
-                        insc_ptr
-                    )));
+                        }
+                    ];
                 }
             },
             Insc::OwnershipInfoCheck(src, mask) => {
                 let src: Value = slice.get_value(*src);
                 if src.is_value() || ((src.ownership_info() as u8) & mask) != *mask {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
                         UncheckedException::OwnershipCheckFailure {
                             object: src,
                             expected_mask: *mask
-                        },
-                        &mut thread.stack,
-                        insc_ptr
-                    )));
+                        }
+                    ];
+                }
+            },
+            Insc::CallableCheck(src, arg_count, ret_count) => {
+                let src: Value = slice.get_value(*src);
+                if let Err(exception) =
+                    check_callable(&program.functions, src, *arg_count, *ret_count)
+                {
+                    impl_unwind_unchecked![thread, slice, insc_ptr, exception];
                 }
             },
             Insc::Call(func_id, args, rets) => {
+                impl_checkpoint![this, thread, slice, insc_ptr, executed, cx];
+
                 #[cfg(not(debug_assertions))]
                 let compiled: &CompiledFunction = program.functions.get_unchecked(*func_id);
                 #[cfg(debug_assertions)]
                 let compiled: &CompiledFunction = &program.functions[*func_id];
 
                 debug_assert_eq!(compiled.arg_count, args.len());
+                impl_check_stack![thread, slice, insc_ptr, *func_id, compiled.stack_size];
                 *slice = thread.stack.func_call_grow_stack(
                     *func_id,
                     compiled.stack_size,
@@ -424,6 +479,8 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 insc_ptr = compiled.start_addr;
             },
             Insc::CallPtr(func, args, rets) => {
+                impl_checkpoint![this, thread, slice, insc_ptr, executed, cx];
+
                 let func: Value = slice.get_value(*func);
                 if func.is_value() {
                     let func_id: usize = func.vt_data.inner.int_value as usize;
@@ -434,6 +491,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     let compiled: &CompiledFunction = &program.functions[func_id];
 
                     debug_assert_eq!(compiled.arg_count, args.len());
+                    impl_check_stack![thread, slice, insc_ptr, func_id, compiled.stack_size];
                     *slice = thread.stack.func_call_grow_stack(
                         func_id,
                         compiled.stack_size,
@@ -451,6 +509,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     #[cfg(debug_assertions)]
                     let compiled: &CompiledFunction = &program.functions[func_id];
 
+                    impl_check_stack![thread, slice, insc_ptr, func_id, compiled.stack_size];
                     *slice = thread.stack.closure_call_grow_stack(
                         func_id,
                         compiled.stack_size,
@@ -463,6 +522,8 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 }
             },
             Insc::CallOverload(overload_table, args, rets) => {
+                impl_checkpoint![this, thread, slice, insc_ptr, executed, cx];
+
                 match call_overload(
                     thread,
                     *slice,
@@ -541,7 +602,6 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                         FFIException::Checked(checked) => {
                             let (new_slice, insc_ptr_next): (StackSlice, usize) =
                                 checked_exception_unwind_stack(
-                                    get_vm!(thread),
                                     program,
                                     checked,
                                     &mut thread.stack,
@@ -551,11 +611,11 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                             insc_ptr = insc_ptr_next;
                         },
                         FFIException::Unchecked(unchecked) => {
-                            return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                                unchecked, &mut thread.stack, insc_ptr
-                            )));
+                            impl_unwind_unchecked![thread, slice, insc_ptr, unchecked];
                         }
                     }
+                } else {
+                    impl_check_quota![thread, slice, insc_ptr];
                 }
             },
             #[cfg(feature = "optimized-rtlc")]
@@ -590,7 +650,6 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                         FFIException::Checked(checked) => {
                             let (new_slice, insc_ptr_next): (StackSlice, usize) =
                                 checked_exception_unwind_stack(
-                                    get_vm!(thread),
                                     program,
                                     checked,
                                     &mut thread.stack,
@@ -600,11 +659,11 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                             insc_ptr = insc_ptr_next;
                         },
                         FFIException::Unchecked(unchecked) => {
-                            return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                                unchecked, &mut thread.stack, insc_ptr
-                            )));
+                            impl_unwind_unchecked![thread, slice, insc_ptr, unchecked];
                         }
                     }
+                } else {
+                    impl_check_quota![thread, slice, insc_ptr];
                 }
             },
             #[cfg(feature = "async")]
@@ -632,13 +691,13 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                         let promise: Value = Value::new_owned(promise);
                         thread.vm.get_shared_data_mut().alloc.add_managed(promise);
                         slice.set_value(*ret, promise);
+                        impl_check_quota![thread, slice, insc_ptr];
                     },
                     Err(e /*: FFIException*/) => {
                         match e {
                             FFIException::Checked(checked) => {
                                 let (new_slice, insc_ptr_next): (StackSlice, usize) =
                                     checked_exception_unwind_stack(
-                                        get_vm!(thread),
                                         program,
                                         checked,
                                         &mut thread.stack,
@@ -648,9 +707,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                                 insc_ptr = insc_ptr_next;
                             },
                             FFIException::Unchecked(unchecked) => {
-                                return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                                    unchecked, &mut thread.stack, insc_ptr
-                                )));
+                                impl_unwind_unchecked![thread, slice, insc_ptr, unchecked];
                             }
                         }
                     }
@@ -661,17 +718,19 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let promise: Value = slice.get_value(*promise);
                 let wrapper: *mut Wrapper<()> = promise.ptr_repr.ptr as *mut Wrapper<()>;
                 if (*wrapper).ownership_info == OwnershipInfo::MovedToRust as u8 {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::AlreadyAwaited { promise },
-                        &mut thread.stack,
-                        insc_ptr
-                    )));
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
+                        UncheckedException::AlreadyAwaited { promise }
+                    ];
                 }
 
                 let Promise(fut) = promise.move_out::<Promise<AL31F<A>>>();
                 (*wrapper).ownership_info = OwnershipInfo::MovedToRust as u8;
 
                 this.insc_ptr = insc_ptr;
+                this.fuel = this.fuel.saturating_sub(executed);
 
                 let thread: &'static VMThread<A> = transmute::<_, _>(thread);
                 this.awaiting_promise = Some(Box::pin(thread.vm.co_await(fut)));
@@ -683,14 +742,14 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let Promise(fut) = coroutine_spawn(thread, slice, *func, args);
                 this.awaiting_promise = Some(fut);
                 this.insc_ptr = insc_ptr + 1;
+                this.fuel = this.fuel.saturating_sub(executed);
                 cx.waker().wake_by_ref();
                 return Poll::Pending;
             },
-            Insc::Raise(exception_ptr) => {
+            Insc::Raise(exception_ptr) | Insc::Rethrow(exception_ptr) => {
                 let exception: Value = slice.get_value(*exception_ptr);
                 let (new_slice, insc_ptr_next): (StackSlice, usize) =
                     checked_exception_unwind_stack(
-                        get_vm!(thread),
                         program,
                         exception,
                         &mut thread.stack,
@@ -703,22 +762,32 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             Insc::JumpIfTrue(condition, dest) => {
                 let condition: bool = slice.get_value(*condition).vt_data.inner.bool_value;
                 if condition {
+                    if *dest < insc_ptr {
+                        impl_checkpoint![this, thread, slice, insc_ptr, executed, cx];
+                    }
                     insc_ptr = *dest;
                 }
             },
             Insc::JumpIfFalse(condition, dest) => {
                 let condition: bool = slice.get_value(*condition).vt_data.inner.bool_value;
                 if !condition {
+                    if *dest < insc_ptr {
+                        impl_checkpoint![this, thread, slice, insc_ptr, executed, cx];
+                    }
                     insc_ptr = *dest;
                 }
             },
             Insc::Jump(dest) => {
+                if *dest < insc_ptr {
+                    impl_checkpoint![this, thread, slice, insc_ptr, executed, cx];
+                }
                 insc_ptr = *dest;
             },
             Insc::CreateContainer(ctor, vt, dest) => {
                 let container: Value = Value::new_container(ctor(), vt.as_ref());
                 get_vm!(thread).alloc.add_managed(container);
                 slice.set_value(*dest, container);
+                impl_check_quota![thread, slice, insc_ptr];
             },
             Insc::CreateClosure(func_id, capture_idx, vt, dest) => {
                 let mut captures: SmallVec<[Value; 4]> = SmallVec::new();
@@ -735,6 +804,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 );
                 get_vm!(thread).alloc.add_managed(container);
                 slice.set_value(*dest, container);
+                impl_check_quota![thread, slice, insc_ptr];
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::CreateString(dest) => {
@@ -742,6 +812,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let string: Value = Value::new_owned(string);
                 get_vm!(thread).alloc.add_managed(string);
                 slice.set_value(*dest, string);
+                impl_check_quota![thread, slice, insc_ptr];
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::CreateObject(dest) => {
@@ -749,6 +820,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let object: Value = Value::new_owned(object);
                 get_vm!(thread).alloc.add_managed(object);
                 slice.set_value(*dest, object);
+                impl_check_quota![thread, slice, insc_ptr];
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecIndex(src, index, dst) => {
@@ -758,13 +830,12 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 if let Some(data) = vec.inner.get(index as usize) {
                     slice.set_value(*dst, *data);
                 } else {
-                    return Poll::Ready(Err(
-                        unchecked_exception_unwind_stack(
-                            UncheckedException::IndexOutOfBounds { indexed: vec_value, index },
-                            &mut thread.stack,
-                            insc_ptr
-                        )
-                    ));
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
+                        UncheckedException::IndexOutOfBounds { indexed: vec_value, index }
+                    ];
                 }
             },
             #[cfg(feature = "al31f-builtin-ops")]
@@ -777,13 +848,12 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     get_vm!(thread).alloc.mark_object(value);
                     *data = value;
                 } else {
-                    return Poll::Ready(Err(
-                        unchecked_exception_unwind_stack(
-                            UncheckedException::IndexOutOfBounds { indexed: vec_value, index },
-                            &mut thread.stack,
-                            insc_ptr
-                        )
-                    ));
+                    impl_unwind_unchecked![
+                        thread,
+                        slice,
+                        insc_ptr,
+                        UncheckedException::IndexOutOfBounds { indexed: vec_value, index }
+                    ];
                 }
             },
             #[cfg(feature = "al31f-builtin-ops")]
@@ -792,7 +862,15 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let vec: &mut VMGenericVec = &mut *(vec_value.get_as_mut_ptr());
                 let data: Value = slice.get_value(*data);
                 get_vm!(thread).alloc.mark_object(data);
+                let capacity: usize = vec.inner.capacity();
                 vec.inner.push(data);
+                // growing the buffer allocates without `add_managed`, so account for it here
+                let grown: usize =
+                    (vec.inner.capacity() - capacity) * std::mem::size_of::<Value>();
+                if grown != 0 {
+                    get_vm!(thread).alloc.adjust_usage(grown as isize);
+                    impl_check_quota![thread, slice, insc_ptr];
+                }
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecLen(src, dst) => {
@@ -809,6 +887,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let dest_value: Value = Value::new_owned(buffer);
                 get_vm!(thread).alloc.add_managed(dest_value);
                 slice.set_value(*dest, dest_value);
+                impl_check_quota![thread, slice, insc_ptr];
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::StrConcat(sources, dest) => {
@@ -821,6 +900,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let dest_value: Value = Value::new_owned(buffer);
                 get_vm!(thread).alloc.add_managed(dest_value);
                 slice.set_value(*dest, dest_value);
+                impl_check_quota![thread, slice, insc_ptr];
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::StrLen(src, dest) => {
@@ -891,6 +971,7 @@ pub unsafe fn vm_thread_run_function<'a, A: Alloc, const S: bool>(
     get_vm!(thread).alloc.set_gc_allowed(true);
 
     let program: &CompiledProgram<A> = thread.program.as_ref();
+    get_vm!(thread).ensure_globals(program.global_names.len());
     let compiled_function: &CompiledFunction = &program.functions[func_id];
     if compiled_function.arg_count != args.len() {
         let exception: UncheckedException = UncheckedException::ArgCountMismatch {
@@ -902,12 +983,37 @@ pub unsafe fn vm_thread_run_function<'a, A: Alloc, const S: bool>(
     let slice: StackSlice =
         thread.stack.ext_func_call_grow_stack(func_id, compiled_function.stack_size, args);
     let insc_ptr: usize = compiled_function.start_addr;
+    let fuel: u64 = get_vm!(thread).fuel.unwrap_or(u64::MAX);
+    #[cfg(feature = "async")]
+    let quantum: u64 = get_vm!(thread).quantum.unwrap_or(u64::MAX);
+    let interrupt_handle: InterruptHandle = get_vm!(thread).interrupt_handle();
 
     Ok(VMThreadRunFunctionFut {
         thread,
         slice,
         insc_ptr,
+        fuel,
+        interrupt_handle,
+        #[cfg(feature = "async")] quantum,
 
         #[cfg(feature = "async")] awaiting_promise: None
     })
 }
+
+/// Runs `init_proc` of the program on `thread`, if the program has global variables to
+/// initialize. This should be done once on each VM, before running other functions.
+pub async unsafe fn vm_thread_init_globals<A: Alloc, const S: bool>(
+    thread: &mut VMThread<A>
+) -> Result<(), Exception> {
+    let program: &CompiledProgram<A> = thread.program.as_ref();
+    if program.global_names.is_empty() {
+        return Ok(());
+    }
+
+    let init_proc: usize = program.init_proc;
+    vm_thread_run_function::<_, S>(UncheckedSendSync::new((&mut *thread, init_proc, &[])))?
+        .await
+        .into_inner()?;
+    thread.stack.clear();
+    Ok(())
+}
@@ -78,6 +78,91 @@ macro_rules! impl_rel_op {
     }
 }
 
+macro_rules! impl_unwind_unchecked {
+    ($thread:expr, $slice:expr, $insc_ptr:expr, $unchecked:expr) => {
+        {
+            let (new_slice, insc_ptr_next): (StackSlice, usize) =
+                unchecked_exception_unwind_stack(
+                    $thread.program.as_ref(),
+                    &mut get_vm!($thread).alloc,
+                    $unchecked,
+                    &mut $thread.stack,
+                    $insc_ptr
+                )?;
+            *$slice = new_slice;
+            $insc_ptr = insc_ptr_next;
+            continue;
+        }
+    }
+}
+
+/// Charges instructions executed in this poll against the fuel of the run, checks for interrupts
+/// from the host, and yields to other coroutines once the quantum is used up. Placed before
+/// backward jumps and calls take effect, so that the instruction can simply be executed again
+/// after resuming.
+macro_rules! impl_checkpoint {
+    ($this:expr, $thread:expr, $slice:expr, $insc_ptr:expr, $executed:expr, $cx:expr) => {
+        {
+            if $this.interrupt_handle.is_interrupted() {
+                impl_unwind_unchecked![
+                    $thread, $slice, $insc_ptr, UncheckedException::Interrupted
+                ];
+            }
+
+            if $executed >= $this.fuel {
+                let fuel: u64 = get_vm!($thread).fuel.unwrap_or(u64::MAX);
+                impl_unwind_unchecked![
+                    $thread, $slice, $insc_ptr, UncheckedException::OutOfFuel { fuel }
+                ];
+            }
+
+            #[cfg(feature = "async")]
+            if !S && $executed >= $this.quantum {
+                $this.fuel -= $executed;
+                $this.insc_ptr = $insc_ptr - 1;
+                $cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Raises a stack overflow exception if calling `func_id` with a frame of `frame_size` would
+/// exceed the stack limits of the thread
+macro_rules! impl_check_stack {
+    ($thread:expr, $slice:expr, $insc_ptr:expr, $func_id:expr, $frame_size:expr) => {
+        {
+            if $thread.stack.would_overflow($frame_size) {
+                let depth: usize = $thread.stack.frames.len();
+                impl_unwind_unchecked![
+                    $thread,
+                    $slice,
+                    $insc_ptr,
+                    UncheckedException::StackOverflow { func_id: $func_id, depth }
+                ];
+            }
+        }
+    }
+}
+
+/// Raises an out-of-memory exception if the last allocation left usage beyond quota. `Alloc`
+/// has already tried collecting garbage by then.
+macro_rules! impl_check_quota {
+    ($thread:expr, $slice:expr, $insc_ptr:expr) => {
+        {
+            let alloc: &A = &get_vm!($thread).alloc;
+            if let Some(quota /*: usize*/) = alloc.quota() {
+                let usage: usize = alloc.usage();
+                if usage > quota {
+                    impl_unwind_unchecked![
+                        $thread, $slice, $insc_ptr, UncheckedException::OutOfMemory { usage, quota }
+                    ];
+                }
+            }
+        }
+    }
+}
+
 macro_rules! impl_checked_bin_op {
     (
         $slice:ident,
@@ -93,9 +178,7 @@ macro_rules! impl_checked_bin_op {
             let src2: Value = $slice.get_value(*$src2);
             let dst: &mut Value = &mut *$slice.get_value_mut_ref(*$dst);
             if let Err(e /*: UncheckedException*/) = $checked_op(src1, src2, dst) {
-                return Poll::Ready(
-                    Err(unchecked_exception_unwind_stack(e, &mut $thread.stack, $insc_ptr))
-                );
+                impl_unwind_unchecked![$thread, $slice, $insc_ptr, e];
             }
         }
     }
@@ -107,9 +190,7 @@ macro_rules! impl_checked_unary_op {
             let src: Value = $slice.get_value(*$src);
             let dst: &mut Value = &mut *$slice.get_value_mut_ref(*$dst);
             if let Err(e /*: UncheckedException*/) = $checked_op(src, dst) {
-                return Poll::Ready(
-                    Err(unchecked_exception_unwind_stack(e, &mut $thread.stack, $insc_ptr))
-                );
+                impl_unwind_unchecked![$thread, $slice, $insc_ptr, e];
             }
         }
     }
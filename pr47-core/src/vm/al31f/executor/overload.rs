@@ -1,22 +1,150 @@
+use std::any::TypeId;
+use std::ptr::NonNull;
+use std::sync::MutexGuard;
+
+use smallvec::SmallVec;
+use xjbutil::unchecked::UnsafeFrom;
+
+use crate::data::Value;
 use crate::data::exception::UncheckedException;
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
 use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram, OverloadTable};
 use crate::vm::al31f::exception::Exception;
+use crate::vm::al31f::executor::rtti::check_type;
 use crate::vm::al31f::executor::unwinding::unchecked_exception_unwind_stack;
 use crate::vm::al31f::executor::VMThread;
 use crate::vm::al31f::stack::StackSlice;
 
+include!("get_vm_makro.rs");
+
+/// Run-time type of an argument, as far as overload selection can tell
+///
+/// Two values with the same key always pass or fail the same `check_type` checks, so a selected
+/// candidate can be reused for arguments of the same keys.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArgTypeKey {
+    Null,
+    Int,
+    Float,
+    Bool,
+    Ref(TypeId),
+    /// Address of the `GenericTypeVT` of a container
+    Container(usize)
+}
+
+impl ArgTypeKey {
+    pub unsafe fn of(value: Value) -> Self {
+        if value.is_null() {
+            ArgTypeKey::Null
+        } else if value.is_value() {
+            match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+                ValueTypeTag::Int => ArgTypeKey::Int,
+                ValueTypeTag::Float => ArgTypeKey::Float,
+                ValueTypeTag::Bool => ArgTypeKey::Bool
+            }
+        } else if value.is_container() {
+            ArgTypeKey::Container(value.ptr_repr.trivia)
+        } else {
+            ArgTypeKey::Ref((*value.get_as_dyn_base()).dyn_type_id())
+        }
+    }
+}
+
 #[inline(never)]
 pub unsafe fn call_overload<A: Alloc>(
     thread: &mut VMThread<A>,
-    _stack_slice: StackSlice,
+    stack_slice: StackSlice,
     insc_ptr: usize,
     overload_table: usize,
-    _args: &'static [usize],
-    _rets: &'static [usize]
+    args: &'static [usize],
+    rets: &'static [usize]
 ) -> Result<(StackSlice, usize), Exception> {
-    Err(unchecked_exception_unwind_stack(
-        UncheckedException::OverloadCallFailure { overload_table },
-        &mut thread.stack,
+    let program: &CompiledProgram<A> = thread.program.as_ref();
+    let table: &OverloadTable = &program.overload_tables[overload_table];
+
+    let arg_values: SmallVec<[Value; 4]> =
+        args.iter().map(|arg: &usize| *stack_slice.get_value_mut_ref(*arg)).collect();
+    let arg_keys: SmallVec<[ArgTypeKey; 4]> =
+        arg_values.iter().map(|value: &Value| ArgTypeKey::of(*value)).collect();
+
+    let mut cache: MutexGuard<Option<(Box<[ArgTypeKey]>, usize)>> =
+        table.cache.lock().unwrap_or_else(|e| e.into_inner());
+    let func_id: usize = match cache.as_ref() {
+        Some((cached_keys, func_id)) if cached_keys[..] == arg_keys[..] => *func_id,
+        _ => if let Some(func_id) = select_candidate(program, table, &arg_values) {
+            *cache = Some((arg_keys.into_iter().collect(), func_id));
+            func_id
+        } else {
+            drop(cache);
+            return unchecked_exception_unwind_stack(
+                program,
+                &mut get_vm!(thread).alloc,
+                UncheckedException::OverloadCallFailure { overload_table },
+                &mut thread.stack,
+                insc_ptr
+            );
+        }
+    };
+    drop(cache);
+
+    let compiled: &CompiledFunction = &program.functions[func_id];
+    if thread.stack.would_overflow(compiled.stack_size) {
+        let depth: usize = thread.stack.frames.len();
+        return unchecked_exception_unwind_stack(
+            program,
+            &mut get_vm!(thread).alloc,
+            UncheckedException::StackOverflow { func_id, depth },
+            &mut thread.stack,
+            insc_ptr
+        );
+    }
+
+    let new_slice: StackSlice = thread.stack.func_call_grow_stack(
+        func_id,
+        compiled.stack_size,
+        args,
+        NonNull::from(rets),
         insc_ptr
-    ))
+    );
+    Ok((new_slice, compiled.start_addr))
+}
+
+/// Selects the candidate whose parameters accept `args`
+///
+/// When several candidates accept the arguments, the one with the most typed (non-`any`)
+/// parameters is selected, and declaration order breaks ties.
+unsafe fn select_candidate<A: Alloc>(
+    program: &CompiledProgram<A>,
+    table: &OverloadTable,
+    args: &[Value]
+) -> Option<usize> {
+    let mut selected: Option<(usize, usize)> = None;
+    for func_id /*: &usize*/ in table.candidates.iter() {
+        let compiled: &CompiledFunction = &program.functions[*func_id];
+        if compiled.arg_count != args.len() {
+            continue;
+        }
+
+        let mut typed_params: usize = 0;
+        let mut accepted: bool = true;
+        for (param_tyck_info, arg) /*: (&Option<NonNull<TyckInfo>>, &Value)*/ in
+            compiled.param_tyck_info.iter().zip(args.iter())
+        {
+            if let Some(tyck_info) = param_tyck_info {
+                if !check_type(*arg, *tyck_info) {
+                    accepted = false;
+                    break;
+                }
+                if !tyck_info.as_ref().is_any() {
+                    typed_params += 1;
+                }
+            }
+        }
+
+        if accepted && !matches!(selected, Some((_, best)) if best >= typed_params) {
+            selected = Some((*func_id, typed_params));
+        }
+    }
+    selected.map(|(func_id, _): (usize, usize)| func_id)
 }
@@ -3,10 +3,13 @@ use std::ptr::NonNull;
 
 use xjbutil::unchecked::UnsafeFrom;
 
+use crate::builtins::closure::Closure;
+use crate::data::exception::UncheckedException;
 use crate::data::generic::GenericTypeVT;
-use crate::data::tyck::TyckInfo;
+use crate::data::tyck::{ContainerTyckInfo, FunctionTyckInfo, TyckInfo};
 use crate::data::Value;
 use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::vm::al31f::compiled::CompiledFunction;
 
 #[inline(never)]
 pub unsafe fn check_type(value: Value, tyck_info: NonNull<TyckInfo>) -> bool {
@@ -32,28 +35,167 @@ pub unsafe fn check_type(value: Value, tyck_info: NonNull<TyckInfo>) -> bool {
         }
         TyckInfo::Container(inner) => {
             if value.is_container() {
-                let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const GenericTypeVT;
-                let vt: &GenericTypeVT = &*vt;
+                let vt: &GenericTypeVT = &*(value.ptr_repr.trivia as *const GenericTypeVT);
+                let vt_tyck_info: &ContainerTyckInfo = vt.tyck_info.as_ref();
 
-                if vt.tyck_info.as_ref().type_id != inner.type_id {
-                    return false;
-                }
-
-                // TODO this is temporary patch for LY testing
-                vt.tyck_info.as_ref().params.as_ref().len() == inner.params.as_ref().len()
+                // container types are invariant, type arguments should be exactly the same
+                vt_tyck_info.type_id == inner.type_id
+                    && same_types(vt_tyck_info.params.as_ref(), inner.params.as_ref())
             } else if value.is_ref() {
                 value.get_as_dyn_base().as_ref().unwrap_unchecked().dyn_tyck(tyck_info.as_ref())
             } else {
                 false
             }
         }
-        TyckInfo::Function(_) => {
+        TyckInfo::Function(function_tyck_info) => {
+            // plain function ids carry no type information, so only closures can be checked
             if value.is_container() {
-                let _vt: *const GenericTypeVT = value.ptr_repr.trivia as _;
-                todo!()
+                let vt: &GenericTypeVT = &*(value.ptr_repr.trivia as *const GenericTypeVT);
+                let vt_tyck_info: &ContainerTyckInfo = vt.tyck_info.as_ref();
+                if vt_tyck_info.type_id != TypeId::of::<Closure>() {
+                    return false;
+                }
+
+                // the only type argument of a closure type is its function type
+                match vt_tyck_info.params.as_ref() {
+                    [signature] => if let TyckInfo::Function(signature) = signature.as_ref() {
+                        same_function_type(signature, function_tyck_info)
+                    } else {
+                        false
+                    },
+                    _ => false
+                }
             } else {
                 false
             }
         }
     }
 }
+
+/// Checks whether `value` is a function or closure among `functions`, which can be called with
+/// `arg_count` arguments for `ret_count` return values
+///
+/// Plain functions are represented by their ids, so any `int` denoting an existing function
+/// passes the check.
+pub unsafe fn check_callable(
+    functions: &[CompiledFunction],
+    value: Value,
+    arg_count: usize,
+    ret_count: usize
+) -> Result<(), UncheckedException> {
+    let (func_id, capture_count): (usize, usize) = if value.is_value() {
+        let tag: ValueTypeTag =
+            ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK);
+        if !matches!(tag, ValueTypeTag::Int) {
+            return Err(UncheckedException::NotCallable { value });
+        }
+        let func_id: i64 = value.vt_data.inner.int_value;
+        if func_id < 0 {
+            return Err(UncheckedException::NotCallable { value });
+        }
+        (func_id as usize, 0)
+    } else if value.is_container() {
+        let vt: &GenericTypeVT = &*(value.ptr_repr.trivia as *const GenericTypeVT);
+        if vt.tyck_info.as_ref().type_id != TypeId::of::<Closure>() {
+            return Err(UncheckedException::NotCallable { value });
+        }
+        let closure: &Closure = &*(value.get_as_mut_ptr::<Closure>() as *const _);
+        (closure.func_id, closure.captures.len())
+    } else {
+        return Err(UncheckedException::NotCallable { value });
+    };
+
+    let compiled: &CompiledFunction = if let Some(compiled) = functions.get(func_id) {
+        compiled
+    } else {
+        return Err(UncheckedException::NotCallable { value });
+    };
+    // captured values are passed before arguments
+    let expected_arg_count: usize = compiled.arg_count.saturating_sub(capture_count);
+    if expected_arg_count != arg_count {
+        return Err(UncheckedException::ArgCountMismatch {
+            func_id,
+            expected: expected_arg_count,
+            got: arg_count
+        });
+    }
+    if compiled.ret_count != ret_count {
+        return Err(UncheckedException::RetCountMismatch {
+            func_id,
+            expected: compiled.ret_count,
+            got: ret_count
+        });
+    }
+    Ok(())
+}
+
+/// Checks whether two types are structurally the same, even if they are not from the same
+/// `TyckInfoPool`
+pub unsafe fn same_type(tyck_info1: NonNull<TyckInfo>, tyck_info2: NonNull<TyckInfo>) -> bool {
+    if tyck_info1 == tyck_info2 {
+        return true;
+    }
+
+    match (tyck_info1.as_ref(), tyck_info2.as_ref()) {
+        (TyckInfo::AnyType, TyckInfo::AnyType) => true,
+        (TyckInfo::Plain(type_id1), TyckInfo::Plain(type_id2)) => type_id1 == type_id2,
+        (TyckInfo::Nullable(inner1), TyckInfo::Nullable(inner2)) => same_type(*inner1, *inner2),
+        (TyckInfo::Container(container1), TyckInfo::Container(container2)) =>
+            container1.type_id == container2.type_id
+                && same_types(container1.params.as_ref(), container2.params.as_ref()),
+        (TyckInfo::Function(function1), TyckInfo::Function(function2)) =>
+            same_function_type(function1, function2),
+        _ => false
+    }
+}
+
+unsafe fn same_function_type(function1: &FunctionTyckInfo, function2: &FunctionTyckInfo) -> bool {
+    same_types(function1.params.as_ref(), function2.params.as_ref())
+        && same_types(function1.rets.as_ref(), function2.rets.as_ref())
+        && same_types(function1.exceptions.as_ref(), function2.exceptions.as_ref())
+}
+
+unsafe fn same_types(types1: &[NonNull<TyckInfo>], types2: &[NonNull<TyckInfo>]) -> bool {
+    types1.len() == types2.len()
+        && types1.iter().zip(types2.iter()).all(
+            |(tyck_info1, tyck_info2): (&NonNull<TyckInfo>, &NonNull<TyckInfo>)| {
+                same_type(*tyck_info1, *tyck_info2)
+            }
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::data::Value;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::asm::assemble;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::executor::vm_run_function_sync;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+    #[test]
+    fn test_check_type_structural() {
+        let program: CompiledProgram<DefaultAlloc> = assemble(r#"
+            .func main entry L.main args 0 rets 6 stack 8
+
+            L.main:
+                %0 = create-container <vector<int>>
+                %1 = is-type %0, <vector<int>>
+                %2 = is-type %0, <vector<string>>
+                %3 = make-closure F.main <closure<func(int) -> (int)>>
+                %4 = is-type %3, <func(int) -> (int)>
+                %5 = is-type %3, <func(int) -> (string)>
+                %6 = is-type %3, <closure<func(int) -> (int)>>
+                %7 = is-type %0, <func(int) -> (int)>
+                ret [%1, %2, %4, %5, %6, %7]
+        "#, &FFIRegistry::new()).unwrap();
+
+        let result: Vec<Value> = unsafe {
+            vm_run_function_sync(DefaultAlloc::new(), &program, 0, &[])
+        }.unwrap_or_else(|_| panic!("should not throw"));
+        let result: Vec<bool> = result.iter()
+            .map(|value: &Value| unsafe { value.vt_data.inner.bool_value })
+            .collect::<_>();
+        assert_eq!(result, vec![true, false, true, false, true, false]);
+    }
+}
@@ -8,7 +8,7 @@ use crate::vm::al31f::AL31F;
 use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::compiled::CompiledProgram;
 use crate::vm::al31f::exception::Exception;
-use crate::vm::al31f::executor::{VMThread, vm_thread_run_function};
+use crate::vm::al31f::executor::{VMThread, vm_thread_init_globals, vm_thread_run_function};
 use crate::vm::al31f::stack::Stack;
 
 #[cfg(feature = "async")]
@@ -20,7 +20,29 @@ pub unsafe fn vm_run_function_sync<A: Alloc>(
     func_id: usize,
     args: &[Value]
 ) -> Result<Vec<Value>, Exception> {
-    let vm: AL31F<A> = AL31F::new(alloc);
+    vm_run_function_sync_with_vm(AL31F::new(alloc), program, func_id, args)
+}
+
+/// Like `vm_run_function_sync`, but runs on a configured `vm`, for example one with limited fuel
+pub unsafe fn vm_run_function_sync_with_vm<A: Alloc>(
+    vm: AL31F<A>,
+    program: &CompiledProgram<A>,
+    func_id: usize,
+    args: &[Value]
+) -> Result<Vec<Value>, Exception> {
+    vm_run_function_sync_map(vm, program, func_id, args, |rets: &[Value]| rets.to_vec())
+}
+
+/// Like `vm_run_function_sync_with_vm`, but hands returned values to `map` before `vm` and the
+/// objects it manages get dropped, so that `map` can still read referenced data
+pub unsafe fn vm_run_function_sync_map<A: Alloc, R>(
+    vm: AL31F<A>,
+    program: &CompiledProgram<A>,
+    func_id: usize,
+    args: &[Value],
+    map: impl FnOnce(&[Value]) -> R
+) -> Result<R, Exception> {
+    let stack: Stack = vm.create_stack();
 
     #[cfg(feature = "async")]
     return pollster::block_on(async {
@@ -28,13 +50,15 @@ pub unsafe fn vm_run_function_sync<A: Alloc>(
         let mut thread: VMThread<A> = VMThread {
             vm,
             program: NonNull::new_unchecked(program as *const _ as *mut _),
-            stack: Stack::new(),
+            stack,
             _phantom: PhantomPinned::default()
         };
         thread.vm.get_shared_data_mut().alloc.add_stack(&thread.stack);
+        vm_thread_init_globals::<_, true>(&mut thread).await?;
         vm_thread_run_function::<_, true>(UncheckedSendSync::new((&mut thread, func_id, args)))?
             .await
             .into_inner()
+            .map(|rets: Vec<Value>| map(&rets))
     });
 
     #[cfg(not(feature = "async"))]
@@ -42,12 +66,14 @@ pub unsafe fn vm_run_function_sync<A: Alloc>(
         let mut thread: VMThread<A> = VMThread {
             vm,
             program: NonNull::new_unchecked(program as *const _ as *mut _),
-            stack: Stack::new(),
+            stack,
             _phantom: PhantomPinned
         };
         thread.vm.alloc.add_stack(&thread.stack);
+        vm_thread_init_globals::<_, true>(&mut thread).await?;
         vm_thread_run_function::<_, true>(UncheckedSendSync::new((&mut thread, func_id, args)))?
             .await
             .into_inner()
+            .map(|rets: Vec<Value>| map(&rets))
     });
 }
@@ -1,35 +1,44 @@
 use std::any::TypeId;
+use std::hint::unreachable_unchecked;
 
-use crate::data::exception::{CheckedException, UncheckedException};
+use crate::builtins::vm_error::{catchable_type_id, reify};
 use crate::data::Value;
-use crate::vm::al31f::AL31F;
+use crate::data::exception::{CheckedException, ExceptionInner, UncheckedException};
 use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
 use crate::vm::al31f::exception::Exception;
 use crate::vm::al31f::stack::{FrameInfo, Stack, StackSlice};
 
 #[inline(never)]
-pub unsafe fn unchecked_exception_unwind_stack(
+pub unsafe fn unchecked_exception_unwind_stack<A: Alloc>(
+    program: &CompiledProgram<A>,
+    alloc: &mut A,
     unchecked_exception: UncheckedException,
     stack: &mut Stack,
     insc_ptr: usize
-) -> Exception {
-    let mut exception: Exception = Exception::unchecked_exc(unchecked_exception);
-
-    let mut insc_ptr: usize = insc_ptr;
-    while !stack.frames.is_empty() {
-        let last_frame: &FrameInfo = stack.frames.last().unwrap_unchecked();
-        exception.push_stack_trace(last_frame.func_id, insc_ptr);
-        insc_ptr = last_frame.ret_addr.saturating_sub(1);
+) -> Result<(StackSlice, usize), Exception> {
+    // Fatal exceptions have no type id, so they just unwind the whole stack
+    let exception_type_id: Option<TypeId> = catchable_type_id(&unchecked_exception);
 
-        stack.unwind_shrink_slice();
+    let mut exception: Exception = Exception::unchecked_exc(unchecked_exception);
+    if let Some((mut stack_slice, exception_reg, handler_addr)) =
+        unwind_to_handler(program, &mut exception, exception_type_id, stack, insc_ptr)
+    {
+        let exception_value: Value = if let ExceptionInner::Unchecked(e) = &exception.inner {
+            reify(e)
+        } else {
+            unreachable_unchecked()
+        };
+        alloc.add_managed(exception_value);
+        stack_slice.set_value(exception_reg, exception_value);
+        Ok((stack_slice, handler_addr))
+    } else {
+        Err(exception)
     }
-    exception
 }
 
 #[inline(never)]
 pub unsafe fn checked_exception_unwind_stack<A: Alloc>(
-    vm: &mut AL31F<A>,
     program: &CompiledProgram<A>,
     checked_exception: CheckedException,
     stack: &mut Stack,
@@ -38,7 +47,32 @@ pub unsafe fn checked_exception_unwind_stack<A: Alloc>(
     let exception_type_id: TypeId = (*checked_exception.get_as_dyn_base()).dyn_type_id();
 
     let mut exception: Exception = Exception::checked_exc(checked_exception);
-    let mut insc_ptr: usize = insc_ptr;
+    if let Some((mut stack_slice, exception_reg, handler_addr)) =
+        unwind_to_handler(program, &mut exception, Some(exception_type_id), stack, insc_ptr)
+    {
+        stack_slice.set_value(exception_reg, checked_exception);
+        Ok((stack_slice, handler_addr))
+    } else {
+        Err(exception)
+    }
+}
+
+/// Unwinds `stack` until some frame handles exceptions of `exception_type_id`, recording stack
+/// traces into `exception` meanwhile
+///
+/// On success, returns the slice of the handling frame, the register the exception value should
+/// be put into, and the address of the handler. The handling frame is kept on the stack.
+unsafe fn unwind_to_handler<A: Alloc>(
+    program: &CompiledProgram<A>,
+    exception: &mut Exception,
+    exception_type_id: Option<TypeId>,
+    stack: &mut Stack,
+    insc_ptr: usize
+) -> Option<(StackSlice, usize, usize)> {
+    // `insc_ptr` points to the instruction next to the faulting one, while for caller frames
+    // `ret_addr - 1` is the address of the calling instruction. Always use the latter form so
+    // that exception handler ranges can be matched consistently.
+    let mut insc_ptr: usize = insc_ptr.saturating_sub(1);
 
     while !stack.frames.is_empty() {
         let frame: &FrameInfo = stack.frames.last().unwrap_unchecked();
@@ -47,22 +81,14 @@ pub unsafe fn checked_exception_unwind_stack<A: Alloc>(
 
         let compiled_function: &CompiledFunction = &program.functions[func_id];
 
-        if let Some(exc_handlers /*: &Box<[ExceptionHandlingBlock]>*/)
-        = &compiled_function.exc_handlers
+        if let (Some(exception_type_id), Some(exc_handlers /*: &Box<[ExceptionHandlingBlock]>*/))
+            = (exception_type_id, &compiled_function.exc_handlers)
         {
             for exc_handler /*: &ExceptionHandlingBlock*/ in exc_handlers.as_ref().iter() {
-                let (start_insc, end_insc): (usize, usize) = exc_handler.insc_ptr_range;
-                if insc_ptr >= start_insc &&
-                    insc_ptr <= end_insc &&
-                    exception_type_id == exc_handler.exception_id
-                {
+                if exc_handler.handles(program, insc_ptr, exception_type_id) {
                     let frame_size: usize = frame.frame_end - frame.frame_start;
-                    let exception_value: Value = Value::new_owned(exception);
-                    vm.alloc.add_managed(exception_value);
-                    let mut stack_slice: StackSlice = stack.last_frame_slice();
-                    stack_slice.set_value(frame_size - 1, exception_value);
-
-                    return Ok((stack_slice, exc_handler.handler_addr));
+                    let stack_slice: StackSlice = stack.last_frame_slice();
+                    return Some((stack_slice, frame_size - 1, exc_handler.handler_addr));
                 }
             }
         }
@@ -73,5 +99,5 @@ pub unsafe fn checked_exception_unwind_stack<A: Alloc>(
         stack.unwind_shrink_slice();
     }
 
-    Err(exception)
+    None
 }
@@ -0,0 +1,113 @@
+//! ## `ffi_registry.rs`: host functions available to scripts
+//!
+//! Scripts declare host functions with bodyless function declarations (`func print(s string);`),
+//! and the code generator binds these declarations to registered functions by name.
+//!
+//! Host exception types are registered here too, so that scripts can catch them by name. Each
+//! of them may be registered under a parent type, and handlers of the parent type catch it then.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use crate::builtins::vm_error::vm_error_parent;
+use crate::ffi::sync_fn::Function as FFIFunction;
+use crate::vm::al31f::Combustor;
+use crate::vm::al31f::alloc::Alloc;
+
+#[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
+#[cfg(feature = "async")] use crate::vm::al31f::{AL31F, AsyncCombustor};
+
+pub struct FFIRegistry<A: Alloc> {
+    funcs: HashMap<String, &'static dyn FFIFunction<Combustor<A>>>,
+    #[cfg(feature = "async")]
+    async_funcs: HashMap<String, &'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>>,
+    exception_types: HashMap<String, TypeId>,
+    exception_type_names: HashMap<TypeId, String>,
+    exception_parents: HashMap<TypeId, TypeId>
+}
+
+impl<A: Alloc> FFIRegistry<A> {
+    pub fn new() -> Self {
+        Self {
+            funcs: HashMap::new(),
+            #[cfg(feature = "async")]
+            async_funcs: HashMap::new(),
+            exception_types: HashMap::new(),
+            exception_type_names: HashMap::new(),
+            exception_parents: HashMap::new()
+        }
+    }
+
+    /// Registers a synchronous host function, replacing the previous one of the same name
+    pub fn register_func(
+        &mut self,
+        name: impl Into<String>,
+        func: &'static dyn FFIFunction<Combustor<A>>
+    ) {
+        self.funcs.insert(name.into(), func);
+    }
+
+    /// Registers an asynchronous host function, replacing the previous one of the same name
+    #[cfg(feature = "async")]
+    pub fn register_async_func(
+        &mut self,
+        name: impl Into<String>,
+        func: &'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>
+    ) {
+        self.async_funcs.insert(name.into(), func);
+    }
+
+    /// Registers a host exception type by `name`, optionally under a `parent` exception type
+    ///
+    /// `parent` may be a builtin error type like `VMError`, or another registered exception type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `type_id` would become an ancestor of itself.
+    pub fn register_exception_type(
+        &mut self,
+        name: impl Into<String>,
+        type_id: TypeId,
+        parent: Option<TypeId>
+    ) {
+        if let Some(parent) = parent {
+            let mut ancestor: Option<TypeId> = Some(parent);
+            while let Some(ancestor_type_id) = ancestor {
+                assert_ne!(ancestor_type_id, type_id, "cyclic exception type hierarchy");
+                ancestor = self.exception_parents.get(&ancestor_type_id).copied()
+                    .or_else(|| vm_error_parent(ancestor_type_id));
+            }
+            self.exception_parents.insert(type_id, parent);
+        }
+        let name: String = name.into();
+        self.exception_types.insert(name.clone(), type_id);
+        self.exception_type_names.insert(type_id, name);
+    }
+
+    pub fn get_func(&self, name: &str) -> Option<&'static dyn FFIFunction<Combustor<A>>> {
+        self.funcs.get(name).copied()
+    }
+
+    #[cfg(feature = "async")]
+    pub fn get_async_func(
+        &self,
+        name: &str
+    ) -> Option<&'static dyn FFIAsyncFunction<AL31F<A>, AsyncCombustor<A>>> {
+        self.async_funcs.get(name).copied()
+    }
+
+    /// Maps names of registered exception types to their type ids
+    pub fn exception_types(&self) -> &HashMap<String, TypeId> {
+        &self.exception_types
+    }
+
+    /// Maps registered exception types to their names, the last one if registered more than once
+    pub fn exception_type_names(&self) -> &HashMap<TypeId, String> {
+        &self.exception_type_names
+    }
+
+    /// Maps registered exception types to their parent types
+    pub fn exception_parents(&self) -> &HashMap<TypeId, TypeId> {
+        &self.exception_parents
+    }
+}
@@ -0,0 +1,379 @@
+//! # Typed host-side API for calling script functions
+//!
+//! Looks up script functions by name, checks their signatures against Rust types once, and then
+//! calls them with Rust tuples, getting Rust tuples back:
+//!
+//! ```rust,ignore
+//! let add: ScriptFunction<DefaultAlloc, (i64, i64), (i64,)> = program.get_function("add")?;
+//! let (sum,): (i64,) = add.call(DefaultAlloc::new(), (1, 2))?;
+//! ```
+//!
+//! `int`, `float` and `bool` are passed as `i64`, `f64` and `bool`. All other types are passed by
+//! their `StaticBase` types, like `String` for `string`, moving arguments into the VM and cloning
+//! returned values out of it.
+
+use std::any::TypeId;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use smallvec::{SmallVec, smallvec};
+use xjbutil::void::Void;
+
+use crate::data::Value;
+use crate::data::traits::StaticBase;
+use crate::data::tyck::TyckInfo;
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::ffi::sync_fn::value_copy;
+use crate::vm::al31f::AL31F;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
+use crate::vm::al31f::engine::Engine;
+use crate::vm::al31f::exception::Exception;
+use crate::vm::al31f::executor::vm_run_function_sync_map;
+
+/// Rust types which can be passed to and returned from script functions
+pub trait ScriptValue: 'static + Sized {
+    /// Checks if script values of `tyck_info` are of this type
+    fn tyck(tyck_info: &TyckInfo) -> bool;
+
+    /// Converts `self` into a script value. Objects created are not managed by any allocator yet.
+    fn into_value(self) -> Value;
+
+    /// Converts `value` back, or returns `None` if `value` is of some other type
+    unsafe fn from_value(value: Value) -> Option<Self>;
+}
+
+macro_rules! impl_value_typed_script_value {
+    ($type:ty, $tag:expr, $value:ident, $value_ctor:ident) => {
+        impl ScriptValue for $type {
+            fn tyck(tyck_info: &TyckInfo) -> bool {
+                matches!(tyck_info, TyckInfo::Plain(type_id) if *type_id == TypeId::of::<$type>())
+            }
+
+            fn into_value(self) -> Value {
+                Value::$value_ctor(self)
+            }
+
+            unsafe fn from_value(value: Value) -> Option<Self> {
+                if value.is_value()
+                    && (value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK == $tag as u8
+                {
+                    Some(value.vt_data.inner.$value)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl_value_typed_script_value!(i64, ValueTypeTag::Int, int_value, new_int);
+impl_value_typed_script_value!(f64, ValueTypeTag::Float, float_value, new_float);
+impl_value_typed_script_value!(bool, ValueTypeTag::Bool, bool_value, new_bool);
+
+impl<T> ScriptValue for T where T: 'static + Clone, Void: StaticBase<T> {
+    fn tyck(tyck_info: &TyckInfo) -> bool {
+        <Void as StaticBase<T>>::tyck(tyck_info)
+    }
+
+    fn into_value(self) -> Value {
+        Value::new_owned(self)
+    }
+
+    unsafe fn from_value(value: Value) -> Option<Self> {
+        if value.is_null() || value.is_value() || value.is_container()
+            || (*value.get_as_dyn_base()).dyn_type_id() != TypeId::of::<T>()
+        {
+            return None;
+        }
+        value_copy::<T>(value).ok()
+    }
+}
+
+/// Checks if a parameter of `param_tyck_info` accepts values of `T`
+fn param_accepts<T: ScriptValue>(param_tyck_info: Option<NonNull<TyckInfo>>) -> bool {
+    let param_tyck_info: &TyckInfo = match param_tyck_info {
+        // untyped parameters accept anything
+        None => return true,
+        Some(param_tyck_info) => unsafe { param_tyck_info.as_ref() }
+    };
+    match param_tyck_info {
+        TyckInfo::AnyType => true,
+        TyckInfo::Nullable(inner) => T::tyck(unsafe { inner.as_ref() }),
+        _ => T::tyck(param_tyck_info)
+    }
+}
+
+/// Tuples of `ScriptValue`s passed as arguments
+pub trait ScriptArgs {
+    const COUNT: usize;
+
+    /// Checks types of the tuple against parameters. Lengths should have been checked already.
+    fn check(param_tyck_info: &[Option<NonNull<TyckInfo>>]) -> Result<(), HostCallError>;
+
+    fn into_values(self) -> SmallVec<[Value; 4]>;
+}
+
+/// Tuples of `ScriptValue`s returned
+pub trait ScriptRets: Sized {
+    const COUNT: usize;
+
+    unsafe fn from_values(values: &[Value]) -> Result<Self, HostCallError>;
+}
+
+macro_rules! impl_script_tuple {
+    ($($type:ident: $idx:tt),*) => {
+        impl<$($type: ScriptValue),*> ScriptArgs for ($($type,)*) {
+            const COUNT: usize = <[usize]>::len(&[$($idx),*]);
+
+            #[allow(unused_variables)]
+            fn check(param_tyck_info: &[Option<NonNull<TyckInfo>>]) -> Result<(), HostCallError> {
+                $(
+                    if !param_accepts::<$type>(param_tyck_info[$idx]) {
+                        return Err(HostCallError::ArgTypeMismatch { index: $idx });
+                    }
+                )*
+                Ok(())
+            }
+
+            #[allow(unused_variables)]
+            fn into_values(self) -> SmallVec<[Value; 4]> {
+                smallvec![$(self.$idx.into_value()),*]
+            }
+        }
+
+        impl<$($type: ScriptValue),*> ScriptRets for ($($type,)*) {
+            const COUNT: usize = <[usize]>::len(&[$($idx),*]);
+
+            #[allow(unused_variables)]
+            unsafe fn from_values(values: &[Value]) -> Result<Self, HostCallError> {
+                Ok(($(
+                    $type::from_value(values[$idx]).ok_or_else(|| HostCallError::RetTypeMismatch {
+                        index: $idx,
+                        got: values[$idx].type_name()
+                    })?,
+                )*))
+            }
+        }
+    }
+}
+
+impl_script_tuple!();
+impl_script_tuple!(T0: 0);
+impl_script_tuple!(T0: 0, T1: 1);
+impl_script_tuple!(T0: 0, T1: 1, T2: 2);
+impl_script_tuple!(T0: 0, T1: 1, T2: 2, T3: 3);
+impl_script_tuple!(T0: 0, T1: 1, T2: 2, T3: 3, T4: 4);
+impl_script_tuple!(T0: 0, T1: 1, T2: 2, T3: 3, T4: 4, T5: 5);
+
+pub enum HostCallError {
+    UnknownFunction { name: String },
+    /// A function id beyond the functions of the program
+    InvalidFunctionId { func_id: usize },
+    ArgCountMismatch { expected: usize, got: usize },
+    ArgTypeMismatch { index: usize },
+    RetCountMismatch { expected: usize, got: usize },
+    /// The script returned a value of some other type, which is only possible through `any`
+    RetTypeMismatch { index: usize, got: String },
+    /// The script threw an exception not caught by itself
+    Exception(Exception)
+}
+
+impl Display for HostCallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HostCallError::UnknownFunction { name } => write!(f, "unknown function {}", name),
+            HostCallError::InvalidFunctionId { func_id } =>
+                write!(f, "invalid function id {}", func_id),
+            HostCallError::ArgCountMismatch { expected, got } =>
+                write!(f, "argument count mismatch: expected {}, got {}", expected, got),
+            HostCallError::ArgTypeMismatch { index } =>
+                write!(f, "argument #{} is not accepted by the parameter", index),
+            HostCallError::RetCountMismatch { expected, got } =>
+                write!(f, "return value count mismatch: expected {}, got {}", expected, got),
+            HostCallError::RetTypeMismatch { index, got } =>
+                write!(f, "return value #{} has unexpected type {}", index, got),
+            HostCallError::Exception(e) => write!(f, "uncaught exception: {}", e)
+        }
+    }
+}
+
+impl Debug for HostCallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HostCallError({})", self)
+    }
+}
+
+/// A script function with its signature checked against `Args` and `Rets`
+pub struct ScriptFunction<'a, A: Alloc, Args: ScriptArgs, Rets: ScriptRets> {
+    program: &'a CompiledProgram<A>,
+    func_id: usize,
+    _phantom: PhantomData<fn(Args) -> Rets>
+}
+
+impl<'a, A: Alloc, Args: ScriptArgs, Rets: ScriptRets> ScriptFunction<'a, A, Args, Rets> {
+    pub fn new(program: &'a CompiledProgram<A>, func_id: usize) -> Result<Self, HostCallError> {
+        let compiled: &CompiledFunction = program.functions.get(func_id)
+            .ok_or(HostCallError::InvalidFunctionId { func_id })?;
+        if compiled.arg_count != Args::COUNT {
+            return Err(HostCallError::ArgCountMismatch {
+                expected: compiled.arg_count,
+                got: Args::COUNT
+            });
+        }
+        if compiled.ret_count != Rets::COUNT {
+            return Err(HostCallError::RetCountMismatch {
+                expected: compiled.ret_count,
+                got: Rets::COUNT
+            });
+        }
+        Args::check(&compiled.param_tyck_info)?;
+
+        Ok(Self { program, func_id, _phantom: PhantomData })
+    }
+
+    pub fn func_id(&self) -> usize {
+        self.func_id
+    }
+
+    /// Calls the function on a new VM managing memory with `alloc`
+    pub fn call(&self, alloc: A, args: Args) -> Result<Rets, HostCallError> {
+        self.call_with_vm(AL31F::new(alloc), args)
+    }
+
+    /// Calls the function on a configured `vm`, for example one with limited fuel
+    pub fn call_with_vm(&self, mut vm: AL31F<A>, args: Args) -> Result<Rets, HostCallError> {
+        let args: SmallVec<[Value; 4]> = args.into_values();
+        for arg /*: &Value*/ in args.iter() {
+            if arg.is_ref() && !arg.is_null() {
+                unsafe { vm.alloc.add_managed(*arg); }
+            }
+        }
+
+        unsafe {
+            vm_run_function_sync_map(
+                vm,
+                self.program,
+                self.func_id,
+                &args,
+                |rets: &[Value]| Rets::from_values(rets)
+            )
+        }.map_err(HostCallError::Exception)?
+    }
+
+    /// Calls the function on `engine`, which should be running the program of this function
+    pub fn call_in(&self, engine: &mut Engine<'_, A>, args: Args) -> Result<Rets, HostCallError> {
+        assert!(std::ptr::eq(engine.program(), self.program));
+
+        // arguments are not reachable until the call starts, so keep them from being collected
+        engine.set_gc_allowed(false);
+        let args: SmallVec<[Value; 4]> = args.into_values();
+        for arg /*: &Value*/ in args.iter() {
+            if arg.is_ref() && !arg.is_null() {
+                unsafe { engine.vm_mut().alloc.add_managed(*arg); }
+            }
+        }
+
+        let rets: Vec<Value> = unsafe { engine.call(self.func_id, &args) }
+            .map_err(HostCallError::Exception)?;
+        unsafe { Rets::from_values(&rets) }
+    }
+}
+
+impl<A: Alloc> CompiledProgram<A> {
+    /// Looks up the script function named `name`, checking its signature against `Args` and
+    /// `Rets`
+    pub fn get_function<Args: ScriptArgs, Rets: ScriptRets>(
+        &self,
+        name: &str
+    ) -> Result<ScriptFunction<'_, A, Args, Rets>, HostCallError> {
+        let func_id: usize = *self.func_ids.get(name)
+            .ok_or_else(|| HostCallError::UnknownFunction { name: name.to_string() })?;
+        ScriptFunction::new(self, func_id)
+    }
+}
+
+#[cfg(all(test, feature = "compiler", feature = "al31f-builtin-ops"))]
+mod test {
+    use crate::data::exception::{ExceptionInner, UncheckedException};
+    use crate::driver::compile;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+    use super::{HostCallError, ScriptFunction};
+
+    #[test]
+    fn test_host_call() {
+        let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+            func add(a int, b int): int {
+                return a + b;
+            }
+
+            func greet(name string, times int): (string, int) {
+                var greeting string = `hello, ` + name;
+                return greeting, times * 2;
+            }
+
+            func identity(x any): any {
+                return x;
+            }
+
+            func divide(a int, b int): int {
+                return a / b;
+            }
+        "#)], FFIRegistry::new()).unwrap_or_else(|_| panic!("should compile"));
+
+        let add: ScriptFunction<DefaultAlloc, (i64, i64), (i64,)> =
+            program.get_function("add").unwrap();
+        assert_eq!(add.call(DefaultAlloc::new(), (1, 2)).unwrap(), (3,));
+
+        let greet: ScriptFunction<DefaultAlloc, (String, i64), (String, i64)> =
+            program.get_function("greet").unwrap();
+        assert_eq!(
+            greet.call(DefaultAlloc::new(), ("world".to_string(), 21)).unwrap(),
+            ("hello, world".to_string(), 42)
+        );
+
+        let identity: ScriptFunction<DefaultAlloc, (f64,), (f64,)> =
+            program.get_function("identity").unwrap();
+        assert_eq!(identity.call(DefaultAlloc::new(), (0.5,)).unwrap(), (0.5,));
+        let identity: ScriptFunction<DefaultAlloc, (bool,), (String,)> =
+            program.get_function("identity").unwrap();
+        assert!(matches!(
+            identity.call(DefaultAlloc::new(), (true,)),
+            Err(HostCallError::RetTypeMismatch { index: 0, got }) if got == "bool"
+        ));
+
+        assert!(matches!(
+            program.get_function::<(i64, i64), (i64,)>("subtract"),
+            Err(HostCallError::UnknownFunction { name }) if name == "subtract"
+        ));
+        let func_count: usize = program.functions.len();
+        assert!(matches!(
+            ScriptFunction::<DefaultAlloc, (i64, i64), (i64,)>::new(&program, func_count),
+            Err(HostCallError::InvalidFunctionId { func_id }) if func_id == func_count
+        ));
+        assert!(matches!(
+            program.get_function::<(i64,), (i64,)>("add"),
+            Err(HostCallError::ArgCountMismatch { expected: 2, got: 1 })
+        ));
+        assert!(matches!(
+            program.get_function::<(i64, String), (i64,)>("add"),
+            Err(HostCallError::ArgTypeMismatch { index: 1 })
+        ));
+        assert!(matches!(
+            program.get_function::<(i64, i64), ()>("add"),
+            Err(HostCallError::RetCountMismatch { expected: 1, got: 0 })
+        ));
+
+        let divide: ScriptFunction<DefaultAlloc, (i64, i64), (i64,)> =
+            program.get_function("divide").unwrap();
+        assert!(matches!(
+            divide.call(DefaultAlloc::new(), (1, 0)),
+            Err(HostCallError::Exception(e))
+                if matches!(e.inner, ExceptionInner::Unchecked(UncheckedException::DivideByZero))
+        ));
+    }
+}
@@ -343,6 +343,17 @@ pub enum Insc {
     /// not generate codes in such a way.
     SaveConst(usize, usize),
 
+    /// `LOAD-GLOBAL [GLOBAL-ID] [DEST]`
+    ///
+    /// Load global variable `GLOBAL-ID`, and put it to register `DEST`. Global variables are
+    /// `null` until initialized by the `init_proc` of the program.
+    LoadGlobal(usize, usize),
+
+    /// `STORE-GLOBAL [SRC] [GLOBAL-ID]`
+    ///
+    /// Store the value in register `SRC` to global variable `GLOBAL-ID`.
+    StoreGlobal(usize, usize),
+
     /// `CAST-FLOAT-INT [FLOAT@SRC] [DEST]`
     ///
     /// Convert the float in `SRC` to integer, put the result to register `DEST`.
@@ -389,6 +400,13 @@ pub enum Insc {
     /// Check if data stored in `SRC` satisfies given `MASK`, throws RTLC exception if not.
     OwnershipInfoCheck(usize, u8),
 
+    /// `CALLABLE-CHECK [SRC] [ARG-COUNT] [RET-COUNT]`
+    ///
+    /// Check if data stored in `SRC` is a function or closure taking `ARG-COUNT` arguments and
+    /// returning `RET-COUNT` values, throws exception if not. `CALL-PTR` trusts its callee, so
+    /// callees not statically known to be functions should be checked by this first.
+    CallableCheck(usize, usize, usize),
+
     /// `CALL-UNCHECKED [FUNC-ID] [ARGS..] [RETS..]`
     ///
     /// Call the function denoted by `FUNC-ID` with given `ARGS`, store the return values to `RETS`.
@@ -441,6 +459,12 @@ pub enum Insc {
     /// `RAISE [EXCEPTION]`
     Raise(usize),
 
+    /// `RETHROW [EXCEPTION]`
+    ///
+    /// Throw an exception caught earlier again. Unwinds just like `RAISE`, generated for
+    /// `rethrow` statements and at the end of `finally` handlers.
+    Rethrow(usize),
+
     JumpIfTrue(usize, usize),
     JumpIfFalse(usize, usize),
     Jump(usize),
@@ -470,130 +494,82 @@ pub enum Insc {
 }
 
 impl Insc {
+    /// Formats the instruction in the form accepted by the assembler (see `asm.rs`). Jump targets
+    /// are written as `L.<address>` labels.
     pub unsafe fn unsafe_to_string(&self) -> String {
         match self {
             Insc::Move(src, dst) => format!("%{} = %{}", dst, src),
             Insc::AddInt(src1, src2, dst) => format!("%{} = add int %{}, %{}", dst, src1, src2),
             Insc::AddFloat(src1, src2, dst) => format!("%{} = add float %{}, %{}", dst, src1, src2),
             Insc::AddAny(src1, src2, dst) => format!("%{} = add ? %{}, %{}", dst, src1, src2),
+            Insc::IncrInt(pos) => format!("incr %{}", pos),
+            Insc::DecrInt(pos) => format!("decr %{}", pos),
             Insc::SubInt(src1, src2, dst) => format!("%{} = sub int %{}, %{}", dst, src1, src2),
+            Insc::SubFloat(src1, src2, dst) => format!("%{} = sub float %{}, %{}", dst, src1, src2),
+            Insc::SubAny(src1, src2, dst) => format!("%{} = sub ? %{}, %{}", dst, src1, src2),
+            Insc::MulInt(src1, src2, dst) => format!("%{} = mul int %{}, %{}", dst, src1, src2),
+            Insc::MulFloat(src1, src2, dst) => format!("%{} = mul float %{}, %{}", dst, src1, src2),
+            Insc::MulAny(src1, src2, dst) => format!("%{} = mul ? %{}, %{}", dst, src1, src2),
+            Insc::DivInt(src1, src2, dst) => format!("%{} = div int %{}, %{}", dst, src1, src2),
+            Insc::DivFloat(src1, src2, dst) => format!("%{} = div float %{}, %{}", dst, src1, src2),
+            Insc::DivAny(src1, src2, dst) => format!("%{} = div ? %{}, %{}", dst, src1, src2),
+            Insc::ModInt(src1, src2, dst) => format!("%{} = mod int %{}, %{}", dst, src1, src2),
+            Insc::ModAny(src1, src2, dst) => format!("%{} = mod ? %{}, %{}", dst, src1, src2),
             Insc::EqValue(src1, src2, dst) => format!("%{} = eq value %{}, %{}", dst, src1, src2),
             Insc::EqRef(src1, src2, dst) => format!("%{} = eq ref %{}, %{}", dst, src1, src2),
             Insc::EqAny(src1, src2, dst) => format!("%{} = eq ? %{}, %{}", dst, src1, src2),
+            Insc::NeValue(src1, src2, dst) => format!("%{} = ne value %{}, %{}", dst, src1, src2),
+            Insc::NeRef(src1, src2, dst) => format!("%{} = ne ref %{}, %{}", dst, src1, src2),
+            Insc::NeAny(src1, src2, dst) => format!("%{} = ne ? %{}, %{}", dst, src1, src2),
+            Insc::LtInt(src1, src2, dst) => format!("%{} = lt int %{}, %{}", dst, src1, src2),
+            Insc::LtFloat(src1, src2, dst) => format!("%{} = lt float %{}, %{}", dst, src1, src2),
+            Insc::LtAny(src1, src2, dst) => format!("%{} = lt ? %{}, %{}", dst, src1, src2),
+            Insc::LeInt(src1, src2, dst) => format!("%{} = le int %{}, %{}", dst, src1, src2),
+            Insc::LeFloat(src1, src2, dst) => format!("%{} = le float %{}, %{}", dst, src1, src2),
+            Insc::LeAny(src1, src2, dst) => format!("%{} = le ? %{}, %{}", dst, src1, src2),
+            Insc::BAndInt(src1, src2, dst) => format!("%{} = band int %{}, %{}", dst, src1, src2),
+            Insc::BAndAny(src1, src2, dst) => format!("%{} = band ? %{}, %{}", dst, src1, src2),
+            Insc::BOrInt(src1, src2, dst) => format!("%{} = bor int %{}, %{}", dst, src1, src2),
+            Insc::BOrAny(src1, src2, dst) => format!("%{} = bor ? %{}, %{}", dst, src1, src2),
+            Insc::BXorInt(src1, src2, dst) => format!("%{} = bxor int %{}, %{}", dst, src1, src2),
+            Insc::BXorAny(src1, src2, dst) => format!("%{} = bxor ? %{}, %{}", dst, src1, src2),
+            Insc::BNotInt(src, dst) => format!("%{} = bnot int %{}", dst, src),
+            Insc::BNotAny(src, dst) => format!("%{} = bnot ? %{}", dst, src),
+            Insc::NegInt(src, dst) => format!("%{} = neg int %{}", dst, src),
+            Insc::NegFloat(src, dst) => format!("%{} = neg float %{}", dst, src),
+            Insc::NegAny(src, dst) => format!("%{} = neg ? %{}", dst, src),
+            Insc::AndBool(src1, src2, dst) => format!("%{} = and bool %{}, %{}", dst, src1, src2),
+            Insc::AndAny(src1, src2, dst) => format!("%{} = and ? %{}, %{}", dst, src1, src2),
+            Insc::OrBool(src1, src2, dst) => format!("%{} = or bool %{}, %{}", dst, src1, src2),
+            Insc::OrAny(src1, src2, dst) => format!("%{} = or ? %{}, %{}", dst, src1, src2),
+            Insc::NotBool(src, dst) => format!("%{} = not bool %{}", dst, src),
+            Insc::NotAny(src, dst) => format!("%{} = not ? %{}", dst, src),
+            Insc::ShlInt(src1, src2, dst) => format!("%{} = shl int %{}, %{}", dst, src1, src2),
+            Insc::ShlAny(src1, src2, dst) => format!("%{} = shl ? %{}, %{}", dst, src1, src2),
+            Insc::ShrInt(src1, src2, dst) => format!("%{} = shr int %{}, %{}", dst, src1, src2),
+            Insc::ShrAny(src1, src2, dst) => format!("%{} = shr ? %{}, %{}", dst, src1, src2),
             Insc::MakeIntConst(int_const, dst) => format!("%{} = int ${}", dst, int_const),
-            Insc::MakeBoolConst(bool_const, dst) => format!("%{} = bool ${}", dst, bool_const),
             Insc::MakeFloatConst(float_const, dst) => format!("%{} = float ${}", dst, float_const),
+            Insc::MakeBoolConst(bool_const, dst) => format!("%{} = bool ${}", dst, bool_const),
             Insc::MakeNull(dst) => format!("%{} = null", dst),
             Insc::LoadConst(const_id, dst) => format!("%{} = load {}", dst, const_id),
             Insc::SaveConst(src, const_id) => format!("store {}, %{}", const_id, src),
-            Insc::Call(func_id, args, rets) => {
-                let mut result: String = String::from("[");
-                for (i, ret) /*: (usize, &usize)*/ in rets.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&ret.to_string());
-                    if i != rets.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result.push_str("] = call F.");
-                result.push_str(&func_id.to_string());
-                result.push(' ');
-                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&arg.to_string());
-                    if i != args.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result
-            },
-            Insc::CallPtr(func, args, rets) => {
-                let mut result: String = String::from("[");
-                for (i, ret) /*: (usize, &usize)*/ in rets.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&ret.to_string());
-                    if i != rets.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result.push_str("] = call %");
-                result.push_str(&func.to_string());
-                result.push(' ');
-                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&arg.to_string());
-                    if i != args.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result
-            },
-            Insc::FFICallRtlc(ffi_func_id, args, rets) => {
-                let mut result: String = String::from("[");
-                for (i, ret) /*: (usize, &usize)*/ in rets.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&ret.to_string());
-                    if i != rets.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result.push_str("] = ffi-call F.");
-                result.push_str(&ffi_func_id.to_string());
-                result.push(' ');
-                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&arg.to_string());
-                    if i != args.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result
-            },
-            Insc::ReturnNothing => "ret".into(),
-            Insc::ReturnOne(ret_value_loc) => format!("ret %{}", ret_value_loc),
-            Insc::Return(ret_value_locs) => {
-                let mut result: String = String::from("ret ");
-                for (i, ret_value_loc) /*: (usize, &usize)*/ in ret_value_locs.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&ret_value_loc.to_string());
-                    if i != ret_value_locs.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result
-            },
-            Insc::Raise(exception_loc) => format!("raise %{}", exception_loc),
-            Insc::CreateContainer(container_ctor, _, dst) =>
-                format!("%{} = create-container <{:X}>", dst, container_ctor as *const _ as usize),
-            Insc::CreateObject(dest) => format!("%{} = new object", dest),
-            Insc::JumpIfTrue(condition, dest) => format!("if %{} goto L.{}", condition, dest),
-            Insc::JumpIfFalse(condition, dest) => format!("if not %{} goto L.{}", condition, dest),
-            Insc::Jump(dest) => format!("goto L.{}", dest),
-            Insc::CreateClosure(func_id, captures, _, dest) => {
-                let mut result: String = String::from("%");
-                result.push_str(&dest.to_string());
-                result.push_str(" = make-closure F.");
-                result.push_str(&func_id.to_string());
-                result.push(' ');
-                for (i, capture) /*: (usize, &usize)*/ in captures.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&capture.to_string());
-                    if i != captures.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result
-            },
+            Insc::LoadGlobal(global_id, dst) => format!("%{} = load-global {}", dst, global_id),
+            Insc::StoreGlobal(src, global_id) =>
+                format!("store-global {}, %{}", global_id, src),
+            Insc::CastFloatInt(src, dst) => format!("%{} = cast float int %{}", dst, src),
+            Insc::CastBoolInt(src, dst) => format!("%{} = cast bool int %{}", dst, src),
+            Insc::CastAnyInt(src, dst) => format!("%{} = cast ? int %{}", dst, src),
+            Insc::CastIntFloat(src, dst) => format!("%{} = cast int float %{}", dst, src),
+            Insc::CastAnyFloat(src, dst) => format!("%{} = cast ? float %{}", dst, src),
+            Insc::CastIntBool(src, dst) => format!("%{} = cast int bool %{}", dst, src),
+            Insc::CastAnyBool(src, dst) => format!("%{} = cast ? bool %{}", dst, src),
+            Insc::IsNull(src, dst) => format!("%{} = is-null %{}", dst, src),
+            Insc::NullCheck(src) => format!("null-check %{}", src),
+            Insc::IsType(src, tyck_info, dst) =>
+                format!("%{} = is-type %{}, <{}>", dst, src, tyck_info.as_ref().unsafe_to_string()),
             Insc::TypeCheck(value_loc, tyck_info) =>
-                format!("type-check %{}, <{:X}>", value_loc, tyck_info.as_ptr() as usize),
+                format!("type-check %{}, <{}>", value_loc, tyck_info.as_ref().unsafe_to_string()),
             Insc::OwnershipInfoCheck(value_loc, ownership_info) => {
                 let ownership_info = *ownership_info;
                 format!(
@@ -606,51 +582,109 @@ impl Insc {
                     if ownership_info & OWN_INFO_COLLECT_MASK != 0 { "C" } else { "-" },
                     if ownership_info & OWN_INFO_OWNED_MASK != 0 { "O" } else { "-" },
                 )
-            }
+            },
+            Insc::CallableCheck(src, arg_count, ret_count) =>
+                format!("callable-check %{}, {}, {}", src, arg_count, ret_count),
+            Insc::Call(func_id, args, rets) =>
+                format!("[{}] = call F.{} {}", regs_to_string(rets), func_id, regs_to_string(args)),
+            Insc::CallPtr(func, args, rets) =>
+                format!("[{}] = call %{} {}", regs_to_string(rets), func, regs_to_string(args)),
+            Insc::CallOverload(overload_table, args, rets) => format!(
+                "[{}] = call-overload O.{} {}",
+                regs_to_string(rets),
+                overload_table,
+                regs_to_string(args)
+            ),
+            Insc::ReturnNothing => "ret".into(),
+            Insc::ReturnOne(ret_value_loc) => format!("ret %{}", ret_value_loc),
+            Insc::Return(ret_value_locs) => format!("ret [{}]", regs_to_string(ret_value_locs)),
+            Insc::FFICallRtlc(ffi_func_id, args, rets) => format!(
+                "[{}] = ffi-call F.{} {}",
+                regs_to_string(rets),
+                ffi_func_id,
+                regs_to_string(args)
+            ),
+            #[cfg(feature = "optimized-rtlc")]
+            Insc::FFICall(ffi_func_id, args, rets) => format!(
+                "[{}] = ffi-call-unchecked F.{} {}",
+                regs_to_string(rets),
+                ffi_func_id,
+                regs_to_string(args)
+            ),
+            #[cfg(feature = "async")]
+            Insc::FFICallAsync(async_ffi_func_id, args, dst) => format!(
+                "%{} = ffi-call-async F.{} {}",
+                dst,
+                async_ffi_func_id,
+                regs_to_string(args)
+            ),
+            #[cfg(feature = "async")]
+            Insc::Await(task_loc, dests) =>
+                format!("[{}] = await %{}", regs_to_string(dests), task_loc),
+            #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+            Insc::Spawn(func_id, args) => format!("spawn F.{} {}", func_id, regs_to_string(args)),
+            Insc::Raise(exception_loc) => format!("raise %{}", exception_loc),
+            Insc::Rethrow(exception_loc) => format!("rethrow %{}", exception_loc),
+            Insc::JumpIfTrue(condition, dest) => format!("if %{} goto L.{}", condition, dest),
+            Insc::JumpIfFalse(condition, dest) => format!("if not %{} goto L.{}", condition, dest),
+            Insc::Jump(dest) => format!("goto L.{}", dest),
+            Insc::CreateContainer(_, vt, dst) => format!(
+                "%{} = create-container <{}>",
+                dst,
+                vt.as_ref().tyck_info.as_ref().unsafe_to_string()
+            ),
+            Insc::CreateClosure(func_id, captures, vt, dest) => format!(
+                "%{} = make-closure F.{} <{}> {}",
+                dest,
+                func_id,
+                vt.as_ref().tyck_info.as_ref().unsafe_to_string(),
+                regs_to_string(captures)
+            ),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CreateString(dest) => format!("%{} = new string", dest),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CreateObject(dest) => format!("%{} = new object", dest),
+            #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecIndex(vec_loc, idx, dest) =>
                 format!("%{} = vec-index %{}, %{}", dest, vec_loc, idx),
+            #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecIndexPut(vec_loc, idx, value_loc) =>
                 format!("vec-index-put %{}, %{}, %{}", vec_loc, idx, value_loc),
-            Insc::VecPush(vec_loc, value_loc) => format!("vec-push %{} %{}", vec_loc, value_loc),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecPush(vec_loc, value_loc) => format!("vec-push %{}, %{}", vec_loc, value_loc),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecLen(vec_loc, dest) => format!("%{} = vec-len %{}", dest, vec_loc),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrClone(str_loc, dest) => format!("%{} = str-clone %{}", dest, str_loc),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrConcat(str_locs, dest) =>
+                format!("%{} = str-concat {}", dest, regs_to_string(str_locs)),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrLen(str_loc, dest) => format!("%{} = str-len %{}", dest, str_loc),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrEquals(src1, src2, dest) =>
+                format!("%{} = str-equals %{}, %{}", dest, src1, src2),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectGet(obj_loc, field_name, dest) =>
+                format!("%{} = object-get %{}, {:?}", dest, obj_loc, field_name.as_ref()),
+            #[cfg(feature = "al31f-builtin-ops")]
             Insc::ObjectGetDyn(obj_loc, field_name, dest) =>
                 format!("%{} = object-get %{}, %{}", dest, obj_loc, field_name),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectPut(obj_loc, field_name, value_loc) =>
+                format!("object-put %{}, {:?}, %{}", obj_loc, field_name.as_ref(), value_loc),
+            #[cfg(feature = "al31f-builtin-ops")]
             Insc::ObjectPutDyn(obj_loc, field_name, value_loc) =>
-                format!("object-put %{}, %{}, %{}", obj_loc, field_name, value_loc),
-            #[cfg(feature = "async")]
-            Insc::Spawn(func_id, args) => {
-                let mut result: String = String::from("spawn F.");
-                result.push_str(&func_id.to_string());
-                result.push(' ');
-                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&arg.to_string());
-                    if i != args.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result
-            },
-            #[cfg(feature = "async")]
-            Insc::Await(task_loc, dests) => {
-                let mut result = String::from("[");
-                for (i, dest) /*: (usize, &usize)*/ in dests.iter().enumerate() {
-                    result.push('%');
-                    result.push_str(&dest.to_string());
-                    if i != dests.len() - 1 {
-                        result.push(',');
-                        result.push(' ');
-                    }
-                }
-                result.push_str("] = await %");
-                result.push_str(&task_loc.to_string());
-                result
-            }
-            _ => "unimplemented insc".to_string()
+                format!("object-put %{}, %{}, %{}", obj_loc, field_name, value_loc)
         }
     }
 }
 
+/// Formats registers like `%1, %2`
+fn regs_to_string(regs: &[usize]) -> String {
+    regs.iter().map(|reg| format!("%{}", reg)).collect::<Vec<_>>().join(", ")
+}
+
 #[cfg(test)]
 #[cfg_attr(miri, ignore)]
 #[test] fn count_instructions() {
@@ -1,19 +1,30 @@
 pub mod alloc;
+pub mod asm;
+pub mod bytecode;
 pub mod compiled;
+pub mod engine;
 pub mod exception;
 pub mod executor;
+pub mod ffi_registry;
+pub mod host;
 pub mod insc;
 pub mod stack;
+pub mod verifier;
 
 #[cfg(all(test, feature = "async"))]      pub mod test_async;
 #[cfg(all(test, not(feature = "async")))] pub mod test_sync;
 #[cfg(any(test, feature = "bench"))]      pub mod test_program;
+#[cfg(all(test, feature = "compiler"))]   pub mod test_limits;
 
+use std::mem::take;
 use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::data::Value;
 use crate::ffi::sync_fn::VMContext;
 use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::stack::{DEFAULT_MAX_FRAMES, DEFAULT_MAX_VALUES, Stack};
 
 #[cfg(feature = "async")] use crate::ffi::async_fn::AsyncVMContext;
 #[cfg(feature = "async")] use crate::ffi::async_fn::LockedCtx;
@@ -21,12 +32,131 @@ use crate::vm::al31f::alloc::Alloc;
 #[cfg(feature = "async")] use crate::vm::al31f::compiled::CompiledProgram;
 
 pub struct AL31F<A: Alloc> {
-    pub alloc: A
+    pub alloc: A,
+    /// Instructions each run may execute before raising `UncheckedException::OutOfFuel`, `None`
+    /// for no limit
+    pub fuel: Option<u64>,
+    /// Instructions a coroutine executes before yielding to others, `None` for never yielding
+    #[cfg(feature = "async")]
+    pub quantum: Option<u64>,
+    /// Maximum count of frames on stacks of threads created later
+    pub max_frames: usize,
+    /// Maximum count of value slots on stacks of threads created later
+    pub max_values: usize,
+    /// Set by `InterruptHandle`s to stop all runs on this VM
+    pub interrupted: Arc<AtomicBool>,
+    /// Global variables of the program running on this VM, registered to `alloc` as roots
+    globals: Globals
 }
 
+/// Storage of global variables of an `AL31F`
+///
+/// `Value`s are not `Send` since referred objects may not be. Global variables only refer to
+/// objects managed by the `Alloc` of the same VM, which is required to be `Send`, and they are
+/// only accessed by whoever runs the VM. So they may cross threads together with the allocator.
+struct Globals(Box<[Value]>);
+
+unsafe impl Send for Globals {}
+
 impl<A: Alloc> AL31F<A> {
     pub fn new(alloc: A) -> Self {
-        Self { alloc }
+        Self {
+            alloc,
+            fuel: None,
+            #[cfg(feature = "async")]
+            quantum: if cfg!(feature = "async-avoid-block") { Some(500_000) } else { None },
+            max_frames: DEFAULT_MAX_FRAMES,
+            max_values: DEFAULT_MAX_VALUES,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            globals: Globals(Box::new([]))
+        }
+    }
+
+    /// Limits instructions each run may execute. The budget is checked at backward jumps and
+    /// calls, so runs may execute a few more instructions than `fuel` before stopping.
+    #[must_use] pub fn with_fuel(mut self, fuel: Option<u64>) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Sets instructions a coroutine executes before yielding to others. Like fuel, this is
+    /// checked at backward jumps and calls. Defaults to 500000 with the `async-avoid-block`
+    /// feature, and never yielding otherwise.
+    #[cfg(feature = "async")]
+    #[must_use] pub fn with_quantum(mut self, quantum: Option<u64>) -> Self {
+        self.quantum = quantum;
+        self
+    }
+
+    /// Limits stacks of threads created later to `max_frames` frames and `max_values` value slots
+    /// in total. Calls beyond the limits raise `UncheckedException::StackOverflow`.
+    #[must_use] pub fn with_stack_limits(mut self, max_frames: usize, max_values: usize) -> Self {
+        self.max_frames = max_frames;
+        self.max_values = max_values;
+        self
+    }
+
+    /// Creates a stack for a new thread, with the limits configured
+    pub fn create_stack(&self) -> Stack {
+        Stack::with_limits(self.max_frames, self.max_values)
+    }
+
+    /// Limits bytes of memory managed objects may occupy, raising
+    /// `UncheckedException::OutOfMemory` when a collection cannot bring usage below `quota`
+    #[must_use] pub fn with_memory_quota(mut self, quota: Option<usize>) -> Self {
+        self.alloc.set_quota(quota);
+        self
+    }
+
+    /// Bytes of memory currently occupied by managed objects
+    pub fn memory_usage(&self) -> usize {
+        self.alloc.usage()
+    }
+
+    /// Creates a handle for stopping runs on this VM from other threads
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle { interrupted: self.interrupted.clone() }
+    }
+
+    /// Global variables, indexed by global ids of the program
+    pub fn globals(&self) -> &[Value] {
+        &self.globals.0
+    }
+
+    /// Makes room for at least `count` global variables, new ones being `null`
+    pub fn ensure_globals(&mut self, count: usize) {
+        if self.globals.0.len() >= count {
+            return;
+        }
+
+        let mut globals: Vec<Value> = take(&mut self.globals.0).into_vec();
+        globals.resize(count, Value::new_null());
+        self.globals.0 = globals.into_boxed_slice();
+        unsafe { self.alloc.set_globals(&*self.globals.0); }
+    }
+}
+
+/// Stops runs on an `AL31F` from other threads, for example on request timeout
+///
+/// Runs check for interruption at backward jumps and calls, and unwind with
+/// `UncheckedException::Interrupted` once interrupted. The VM stays interrupted, failing new runs
+/// the same way, until `reset` gets called.
+#[derive(Clone)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>
+}
+
+impl InterruptHandle {
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Release);
+    }
+
+    pub fn reset(&self) {
+        self.interrupted.store(false, Ordering::Release);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Acquire)
     }
 }
 
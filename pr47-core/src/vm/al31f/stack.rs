@@ -74,17 +74,43 @@ impl FrameInfo {
 #[cfg(debug_assertions)]
 pub struct Stack {
     pub values: Vec<Option<Value>>,
-    pub frames: Vec<FrameInfo>
+    pub frames: Vec<FrameInfo>,
+    /// Maximum count of frames, calls beyond this raise `UncheckedException::StackOverflow`
+    pub max_frames: usize,
+    /// Maximum count of value slots of all frames
+    pub max_values: usize
 }
 
 pub const EMPTY_RET_LOCS_SLICE: &[usize] = &[];
 
-#[cfg(debug_assertions)]
+pub const DEFAULT_MAX_FRAMES: usize = 65536;
+pub const DEFAULT_MAX_VALUES: usize = 1048576;
+
 impl Stack {
     pub fn new() -> Self {
+        Self::with_limits(DEFAULT_MAX_FRAMES, DEFAULT_MAX_VALUES)
+    }
+
+    /// Checks if pushing a frame of `frame_size` value slots would exceed the limits
+    #[inline(always)] pub fn would_overflow(&self, frame_size: usize) -> bool {
+        self.frames.len() >= self.max_frames || self.values.len() + frame_size > self.max_values
+    }
+
+    /// Drops all frames, including the bottom one left by a finished run
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.frames.clear();
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Stack {
+    pub fn with_limits(max_frames: usize, max_values: usize) -> Self {
         Self {
             values: Vec::with_capacity(64),
-            frames: Vec::with_capacity(4)
+            frames: Vec::with_capacity(4),
+            max_frames,
+            max_values
         }
     }
 
@@ -265,15 +291,19 @@ impl Stack {
 #[cfg(not(debug_assertions))]
 pub struct Stack {
     pub values: Vec<Value>,
-    pub frames: Vec<FrameInfo>
+    pub frames: Vec<FrameInfo>,
+    pub max_frames: usize,
+    pub max_values: usize
 }
 
 #[cfg(not(debug_assertions))]
 impl Stack {
-    pub fn new() -> Self {
+    pub fn with_limits(max_frames: usize, max_values: usize) -> Self {
         Self {
             values: Vec::with_capacity(64),
-            frames: Vec::with_capacity(4)
+            frames: Vec::with_capacity(4),
+            max_frames,
+            max_values
         }
     }
 
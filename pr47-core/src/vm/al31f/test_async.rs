@@ -6,12 +6,16 @@ use xjbutil::unchecked::UncheckedSendSync;
 
 use crate::builtins::object::Object;
 use crate::data::Value;
+use crate::data::exception::{ExceptionInner, UncheckedException};
 use crate::data::wrapper::DynBase;
 use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+#[cfg(feature = "compiler")] use crate::driver::compile;
+use crate::vm::al31f::InterruptHandle;
 use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
 use crate::vm::al31f::compiled::CompiledProgram;
 use crate::vm::al31f::exception::Exception;
 use crate::vm::al31f::executor::{VMThread, create_vm_main_thread, vm_thread_run_function};
+#[cfg(feature = "compiler")] use crate::vm::al31f::ffi_registry::FFIRegistry;
 use crate::vm::al31f::test_program::{
     async_ffi_call_program,
     async_spawn_program,
@@ -28,7 +32,8 @@ async fn basic_program_eval() {
     let program: CompiledProgram<DefaultAlloc> = basic_program::<>();
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
-    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value])
         = (&mut vm_thread, 0, &[Value::new_int(114), Value::new_int(514)]);
     let result: Result<Vec<Value>, Exception> = unsafe {
@@ -54,7 +59,8 @@ async fn basic_fn_call() {
     let program: CompiledProgram<DefaultAlloc> = basic_fn_call_program::<>();
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
-    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
     let result: Result<Vec<Value>, Exception> = unsafe {
         vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
@@ -80,7 +86,7 @@ async fn fibonacci_call() {
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &fib_program).await;
+        create_vm_main_thread(alloc, &fib_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) =
         (&mut vm_thread, 0, &[Value::new_int(7)]);
     let result: Result<Vec<Value>, Exception> = unsafe {
@@ -102,12 +108,98 @@ async fn fibonacci_call() {
     }
 }
 
+async fn fibonacci_call_small_quantum() {
+    let fib_program: CompiledProgram<DefaultAlloc> = fibonacci_program();
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &fib_program).await.expect_silent("damn it");
+    vm_thread.vm.get_shared_data_mut().quantum = Some(3);
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) =
+        (&mut vm_thread, 0, &[Value::new_int(7)]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 1);
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 13);
+    } else {
+        panic!()
+    }
+}
+
+async fn fibonacci_call_stack_overflow() {
+    let fib_program: CompiledProgram<DefaultAlloc> = fibonacci_program();
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &fib_program).await.expect_silent("damn it");
+    vm_thread.stack.max_frames = 4;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) =
+        (&mut vm_thread, 0, &[Value::new_int(7)]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Err(e /*: Exception*/) = result {
+        assert!(matches!(
+            e.inner,
+            ExceptionInner::Unchecked(UncheckedException::StackOverflow { func_id: 0, depth: 4 })
+        ));
+        assert_eq!(e.trace.len(), 4);
+    } else {
+        panic!()
+    }
+}
+
+async fn fibonacci_call_interrupted() {
+    let fib_program: CompiledProgram<DefaultAlloc> = fibonacci_program();
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &fib_program).await.expect_silent("damn it");
+    let interrupt_handle: InterruptHandle = vm_thread.interrupt_handle();
+    interrupt_handle.interrupt();
+    for interrupted /*: bool*/ in [true, false] {
+        let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) =
+            (&mut vm_thread, 0, &[Value::new_int(7)]);
+        let result: Result<Vec<Value>, Exception> = unsafe {
+            vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+                .expect_silent("damn it")
+                .await
+                .into_inner()
+        };
+        match result {
+            Err(e) => {
+                assert!(interrupted);
+                assert!(matches!(
+                    e.inner,
+                    ExceptionInner::Unchecked(UncheckedException::Interrupted)
+                ));
+                // the stack is unwound completely, so the thread can run again
+                assert!(vm_thread.stack.frames.is_empty());
+                assert!(vm_thread.stack.values.is_empty());
+            },
+            Ok(result) => {
+                assert!(!interrupted);
+                assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 13);
+            }
+        }
+        interrupt_handle.reset();
+    }
+}
+
 async fn exception_no_eh_call() {
     let exception_no_eh_program: CompiledProgram<DefaultAlloc> = exception_no_eh_program();
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &exception_no_eh_program).await;
+        create_vm_main_thread(alloc, &exception_no_eh_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
     let result: Result<Vec<Value>, Exception> = unsafe {
         vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
@@ -135,7 +227,7 @@ async fn exception_call() {
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &exception_program).await;
+        create_vm_main_thread(alloc, &exception_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
     let result: Result<Vec<Value>, Exception> = unsafe {
         vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
@@ -162,7 +254,7 @@ async fn ffi_call() {
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &ffi_call_program).await;
+        create_vm_main_thread(alloc, &ffi_call_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
     let result: Result<Vec<Value>, Exception> = unsafe {
         vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
@@ -182,7 +274,7 @@ async fn ffi_call2() {
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &ffi_call_program).await;
+        create_vm_main_thread(alloc, &ffi_call_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) =
         (&mut vm_thread, 0, &[Value::new_int(114), Value::new_int(514)]);
     let result: Result<Vec<Value>, Exception> = unsafe {
@@ -205,7 +297,7 @@ async fn async_ffi_call() {
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &async_ffi_call_program).await;
+        create_vm_main_thread(alloc, &async_ffi_call_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
     let result: Result<Vec<Value>, Exception> = unsafe {
         vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
@@ -227,7 +319,7 @@ async fn async_spawn() {
     let alloc: DefaultAlloc = DefaultAlloc::new();
 
     let mut vm_thread: Box<VMThread<DefaultAlloc>> =
-        create_vm_main_thread(alloc, &async_spawn_program).await;
+        create_vm_main_thread(alloc, &async_spawn_program).await.expect_silent("damn it");
     let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
     let result: Result<Vec<Value>, Exception> = unsafe {
         vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
@@ -242,6 +334,41 @@ async fn async_spawn() {
     }
 }
 
+#[cfg(feature = "compiler")]
+async fn globals_init() {
+    let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+        var counter int = base() + 2;
+        var greeting string = `hello`;
+
+        func base(): int {
+            return 40;
+        }
+
+        func get_counter(): int {
+            return counter;
+        }
+    "#)], FFIRegistry::new()).expect_silent("should compile");
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &program).await.expect_silent("damn it");
+    assert!(vm_thread.vm_mut().globals()[1].is_ref());
+    let func_id: usize = program.func_ids["get_counter"];
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, func_id, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 1);
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 42);
+    } else {
+        panic!()
+    }
+}
+
 #[test] fn test_basic_program_eval() {
     block_on_future(basic_program_eval());
 }
@@ -254,6 +381,18 @@ async fn async_spawn() {
     block_on_future(fibonacci_call());
 }
 
+#[test] fn test_fibonacci_call_small_quantum() {
+    block_on_future(fibonacci_call_small_quantum());
+}
+
+#[test] fn test_fibonacci_call_stack_overflow() {
+    block_on_future(fibonacci_call_stack_overflow());
+}
+
+#[test] fn test_fibonacci_call_interrupted() {
+    block_on_future(fibonacci_call_interrupted());
+}
+
 #[test] fn test_exception_no_eh() {
     block_on_future(exception_no_eh_call());
 }
@@ -276,3 +415,8 @@ async fn async_spawn() {
     let fut = async_spawn();
     block_on_future(fut)
 }
+
+#[cfg(feature = "compiler")]
+#[test] fn test_globals_init() {
+    block_on_future(globals_init());
+}
@@ -0,0 +1,257 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+use xjbutil::std_ext::ExpectSilentExt;
+
+use crate::data::Value;
+use crate::data::exception::{ExceptionInner, UncheckedException};
+use crate::data::tyck::TyckInfoPool;
+use crate::driver::compile;
+use crate::ffi::{FFIException, Signature};
+use crate::ffi::sync_fn::{FunctionBase, VMContext};
+use crate::vm::al31f::{AL31F, InterruptHandle};
+use crate::vm::al31f::asm::assemble;
+use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+use crate::vm::al31f::compiled::CompiledProgram;
+use crate::vm::al31f::exception::Exception;
+use crate::vm::al31f::executor::vm_run_function_sync_with_vm;
+use crate::vm::al31f::ffi_registry::FFIRegistry;
+
+fn compile_source(source: &str) -> CompiledProgram<DefaultAlloc> {
+    compile(&[("main.47", source)], FFIRegistry::new()).expect_silent("should compile")
+}
+
+/// Runs `func_id` of `program` with integer argument `n` on a new VM set up by `configure`,
+/// getting the integer it returns
+fn run_limited(
+    program: &CompiledProgram<DefaultAlloc>,
+    configure: impl FnOnce(AL31F<DefaultAlloc>) -> AL31F<DefaultAlloc>,
+    func_id: usize,
+    n: i64
+) -> Result<i64, ExceptionInner> {
+    let vm: AL31F<DefaultAlloc> = configure(AL31F::new(DefaultAlloc::new()));
+    unsafe {
+        vm_run_function_sync_with_vm(vm, program, func_id, &[Value::new_int(n)])
+            .map(|result: Vec<Value>| result[0].vt_data.inner.int_value)
+            .map_err(|e: Exception| e.inner)
+    }
+}
+
+#[test]
+fn test_fuel() {
+    let program: CompiledProgram<DefaultAlloc> = compile_source(r#"
+        func count(n int): int {
+            var i int = 0;
+            while i < n {
+                i = i + 1;
+            }
+            return i;
+        }
+
+        func recurse(n int): int {
+            return recurse(n + 1);
+        }
+
+        func guarded_count(n int): int {
+            try {
+                return count(n);
+            } catch (e any) {
+                return -1;
+            }
+        }
+    "#);
+    let run = |func_id: usize, n: i64| {
+        run_limited(&program, |vm: AL31F<DefaultAlloc>| vm.with_fuel(Some(10_000)), func_id, n)
+    };
+
+    assert!(matches!(run(0, 100), Ok(100)));
+    // every run gets a budget of its own
+    assert!(matches!(run(0, 100), Ok(100)));
+    for func_id /*: usize*/ in [0, 1, 2] {
+        assert!(matches!(
+            run(func_id, 1_000_000),
+            Err(ExceptionInner::Unchecked(UncheckedException::OutOfFuel { fuel: 10_000 }))
+        ));
+    }
+}
+
+#[test]
+fn test_stack_overflow() {
+    let program: CompiledProgram<DefaultAlloc> = compile_source(r#"
+        func depth(n int): int {
+            if n == 0 {
+                return 0;
+            }
+            return depth(n - 1) + 1;
+        }
+
+        func guarded_depth(n int): int {
+            try {
+                return depth(n);
+            } catch (e StackOverflow) {
+                return -1;
+            }
+        }
+    "#);
+    let run = |func_id: usize, n: i64| run_limited(
+        &program,
+        |vm: AL31F<DefaultAlloc>| vm.with_stack_limits(100, 100_000),
+        func_id,
+        n
+    );
+
+    assert!(matches!(run(0, 90), Ok(90)));
+    assert!(matches!(run(1, 90), Ok(90)));
+    assert!(matches!(
+        run(0, 1000),
+        Err(ExceptionInner::Unchecked(UncheckedException::StackOverflow {
+            func_id: 0,
+            depth: 100
+        }))
+    ));
+    assert!(matches!(run(1, 1000), Ok(-1)));
+    // recursion without limits set still stops at the default limits
+    assert!(matches!(
+        run_limited(&program, |vm: AL31F<DefaultAlloc>| vm, 1, 1_000_000),
+        Ok(-1)
+    ));
+}
+
+/// Set by scripts calling `started()`, so that the host knows the script is running
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+struct StartedBind();
+
+impl FunctionBase for StartedBind {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        unimplemented!("started is only bound by name")
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_unchecked(context, args, rets)
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        _context: &mut CTX,
+        _args: &[Value],
+        _rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        STARTED.store(true, Ordering::Release);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_interrupt() {
+    let mut ffi_registry: FFIRegistry<DefaultAlloc> = FFIRegistry::new();
+    ffi_registry.register_func("started", &StartedBind());
+    let program: CompiledProgram<DefaultAlloc> = compile(&[("main.47", r#"
+        func started();
+
+        func spin(): int {
+            started();
+            var i int = 0;
+            while true {
+                i = i + 1;
+            }
+            return i;
+        }
+    "#)], ffi_registry).expect_silent("should compile");
+    let vm: AL31F<DefaultAlloc> = AL31F::new(DefaultAlloc::new());
+    let interrupt_handle: InterruptHandle = vm.interrupt_handle();
+    let interrupter: JoinHandle<()> = std::thread::spawn(move || {
+        // interrupt only once the script runs, or it would stop before doing anything
+        while !STARTED.load(Ordering::Acquire) {
+            std::thread::yield_now();
+        }
+        interrupt_handle.interrupt();
+    });
+
+    let func_id: usize = program.func_ids["spin"];
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_run_function_sync_with_vm(vm, &program, func_id, &[])
+    };
+    interrupter.join().unwrap();
+    assert!(matches!(
+        result.map_err(|e: Exception| e.inner),
+        Err(ExceptionInner::Unchecked(UncheckedException::Interrupted))
+    ));
+}
+
+#[test]
+fn test_memory_quota() {
+    let program: CompiledProgram<DefaultAlloc> = compile_source(r#"
+        func churn(n int): int {
+            var s string = "0123456789abcdef";
+            var i int = 0;
+            while i < n {
+                var t string = s + s;
+                i = i + 1;
+            }
+            return i;
+        }
+
+        func grow(n int): int {
+            var s string = "0123456789abcdef";
+            var i int = 0;
+            while i < n {
+                s = s + s;
+                i = i + 1;
+            }
+            return i;
+        }
+    "#);
+    let run = |func_id: usize, n: i64| run_limited(
+        &program,
+        |vm: AL31F<DefaultAlloc>| vm.with_memory_quota(Some(64 * 1024)),
+        func_id,
+        n
+    );
+
+    // garbage gets collected instead of counting against the quota
+    assert!(matches!(run(0, 10_000), Ok(10_000)));
+    assert!(matches!(run(1, 8), Ok(8)));
+    assert!(matches!(
+        run(1, 16),
+        Err(ExceptionInner::Unchecked(UncheckedException::OutOfMemory { quota: 65536, .. }))
+    ));
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test]
+fn test_memory_quota_vec_push() {
+    // scripts cannot push into vectors yet, so this one is assembled
+    let program: CompiledProgram<DefaultAlloc> = assemble(r#"
+        ; fill(n) -> int, pushing n ints into a vector kept alive all along
+        .func fill entry L.fill args 1 rets 1 stack 5 params int
+
+        L.fill:
+            %1 = create-container <vector<int>>
+            %2 = int $0
+            %3 = int $1
+        L.loop:
+            %4 = lt int %2, %0
+            if %4 goto L.push
+            ret %2
+        L.push:
+            vec-push %1, %3
+            %2 = add int %2, %3
+            goto L.loop
+    "#, &FFIRegistry::new()).expect_silent("should assemble");
+    let run = |n: i64| run_limited(
+        &program,
+        |vm: AL31F<DefaultAlloc>| vm.with_memory_quota(Some(64 * 1024)),
+        0,
+        n
+    );
+
+    assert!(matches!(run(1000), Ok(1000)));
+    assert!(matches!(
+        run(1_000_000),
+        Err(ExceptionInner::Unchecked(UncheckedException::OutOfMemory { quota: 65536, .. }))
+    ));
+}
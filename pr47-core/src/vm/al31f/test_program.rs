@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use xjbutil::boxed_slice;
 use xjbutil::slice_arena::SliceArena;
 use xjbutil::void::Void;
@@ -9,7 +11,13 @@ use crate::data::tyck::TyckInfoPool;
 use crate::ffi::{FFIException, Signature};
 use crate::ffi::sync_fn::{FunctionBase, OwnershipGuard, VMContext, value_into_ref};
 use crate::vm::al31f::alloc::Alloc;
-use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram, ExceptionHandlingBlock};
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    DebugInfo,
+    ExceptionHandlingBlock,
+    HandlerKind
+};
 use crate::vm::al31f::insc::Insc;
 
 #[cfg(feature = "async")] use crate::data::exception::ExceptionInner;
@@ -37,10 +45,21 @@ pub fn basic_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 2, 1, 2, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![],
@@ -69,11 +88,22 @@ pub fn basic_fn_call_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 1, 2, boxed_slice![]), // application_start
             CompiledFunction::new(4, 2, 1, 2, boxed_slice![]), // sum
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![],
@@ -108,10 +138,21 @@ pub fn fibonacci_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 1, 1, 4, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![],
@@ -140,10 +181,21 @@ pub fn alloc_1m_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 4, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![],
@@ -181,14 +233,30 @@ pub fn exception_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new_with_exc(0, 0, 1, 1, boxed_slice![], boxed_slice![
-                ExceptionHandlingBlock::new(0, 2, <Void as StaticBase<Object>>::type_id(), 3)
+                ExceptionHandlingBlock::new(
+                    0,
+                    2,
+                    HandlerKind::Typed(<Void as StaticBase<Object>>::type_id()),
+                    3
+                )
             ]),
             CompiledFunction::new(5, 0, 0, 0, boxed_slice![]),
             CompiledFunction::new(7, 0, 0, 1, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![]
@@ -215,11 +283,22 @@ pub fn exception_no_eh_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 1, 1, boxed_slice![]),
             CompiledFunction::new(2, 0, 1, 1, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![]
@@ -289,10 +368,21 @@ pub fn ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 1, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![PR47BINDER_FFI_FUNCTION as _],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
@@ -318,10 +408,21 @@ pub fn bench_raw_iter_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 5, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
@@ -352,10 +453,21 @@ pub fn bench_ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 5, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![PR47BINDER_FFI_FUNCTION as _],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
@@ -418,10 +530,21 @@ pub fn ffi_call_program2<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 2, 1, 2, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![PR47_BINDER_FFI_FUNCTION2 as _],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
@@ -458,10 +581,21 @@ pub fn bench_ffi_call_program2<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 6, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![PR47_BINDER_FFI_FUNCTION2 as _],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
@@ -551,10 +685,21 @@ pub fn async_ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 1, 1, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![],
         async_ffi_funcs: boxed_slice![PR47BINDER_ASYNC_FFI_FUNCTION as _]
     }
@@ -620,11 +765,22 @@ pub fn async_spawn_program<A: Alloc>() -> CompiledProgram<A> {
         slice_arena,
         code,
         const_pool: boxed_slice![string1, string2, string3, string4],
+        tyck_info_pool: TyckInfoPool::new(),
+        global_names: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 2, boxed_slice![]),
             CompiledFunction::new(12, 0, 0, 1, boxed_slice![])
         ],
+        func_ids: HashMap::new(),
+        generic_vts: boxed_slice![],
+        overload_tables: boxed_slice![],
+        exception_parents: HashMap::new(),
+        exception_type_names: HashMap::new(),
+        debug_info: DebugInfo::default(),
+        ffi_func_names: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_func_names: boxed_slice![],
         ffi_funcs: boxed_slice![PRINT_BIND as _],
         async_ffi_funcs: boxed_slice![SLEEP_MS_BIND as _]
     }
@@ -0,0 +1,1018 @@
+//! ## `verifier.rs`: static verification of compiled programs
+//!
+//! The executor trusts the program it runs: register indices are not bounds-checked, jump
+//! targets and function ids are used as-is, and the `*Int`/`*Float`/`*Bool` instructions do no
+//! type checking. `verify_program` checks all of these ahead of time, so that programs from
+//! untrusted sources (bytecode files, assembly) can be rejected before anything runs.
+//!
+//! Types of registers are tracked with a simple data flow analysis over each function, seeded
+//! by `param_tyck_info` and constants. Registers of unknown types are never rejected, so the
+//! analysis only catches instructions which are certainly misused.
+
+use std::any::TypeId;
+use std::cell::Cell;
+
+use xjbutil::unchecked::UnsafeFrom;
+
+use crate::builtins::closure::Closure;
+use crate::builtins::object::Object;
+use crate::builtins::vec::VMGenericVec;
+use crate::data::Value;
+use crate::data::tyck::TyckInfo;
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
+use crate::vm::al31f::executor::{FFI_MAX_ARGS, FFI_MAX_RETS};
+use crate::vm::al31f::insc::Insc;
+
+#[derive(Debug)]
+pub enum VerifyError {
+    BadInitProc { init_proc: usize },
+    BadFunction { func_id: usize, reason: &'static str },
+    BadExceptionHandler { func_id: usize, handler_idx: usize, reason: &'static str },
+    RegisterOutOfBounds { func_id: usize, insc_ptr: usize, reg: usize, stack_size: usize },
+    BadJumpTarget { func_id: usize, insc_ptr: usize, dest: usize },
+    /// Control flow reaches the end of function without returning
+    FallsThrough { func_id: usize },
+    UnknownFunction { func_id: usize, insc_ptr: usize, callee: usize },
+    UnknownOverloadTable { func_id: usize, insc_ptr: usize, overload_table: usize },
+    UnknownFFIFunc { func_id: usize, insc_ptr: usize, ffi_func_id: usize },
+    #[cfg(feature = "async")]
+    UnknownAsyncFFIFunc { func_id: usize, insc_ptr: usize, async_ffi_func_id: usize },
+    UnknownConst { func_id: usize, insc_ptr: usize, const_id: usize },
+    UnknownGlobal { func_id: usize, insc_ptr: usize, global_id: usize },
+    /// More values than the executor can pass to or take from a host function
+    FFIValueOverflow { func_id: usize, insc_ptr: usize, count: usize, max: usize },
+    ArgCountMismatch { func_id: usize, insc_ptr: usize, expected: usize, got: usize },
+    RetCountMismatch { func_id: usize, insc_ptr: usize, expected: usize, got: usize },
+    TypeMismatch {
+        func_id: usize,
+        insc_ptr: usize,
+        reg: usize,
+        expected: &'static str,
+        found: &'static str
+    }
+}
+
+/// Statically known type of a register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RegType {
+    Int,
+    Float,
+    Bool,
+    /// Any non-null reference, like strings, objects and containers
+    Ref,
+    Any
+}
+
+impl RegType {
+    fn name(self) -> &'static str {
+        match self {
+            RegType::Int => "int",
+            RegType::Float => "float",
+            RegType::Bool => "bool",
+            RegType::Ref => "reference",
+            RegType::Any => "any"
+        }
+    }
+
+    fn join(self, other: RegType) -> RegType {
+        if self == other { self } else { RegType::Any }
+    }
+
+    unsafe fn from_tyck_info(tyck_info: Option<&TyckInfo>) -> RegType {
+        match tyck_info {
+            Some(TyckInfo::Plain(type_id)) => {
+                let type_id: TypeId = *type_id;
+                if type_id == TypeId::of::<i64>() {
+                    RegType::Int
+                } else if type_id == TypeId::of::<f64>() {
+                    RegType::Float
+                } else if type_id == TypeId::of::<bool>() {
+                    RegType::Bool
+                } else if type_id == TypeId::of::<String>() || type_id == TypeId::of::<Object>() {
+                    RegType::Ref
+                } else {
+                    RegType::Any
+                }
+            },
+            Some(TyckInfo::Container(container)) => {
+                let type_id: TypeId = container.type_id;
+                if type_id == TypeId::of::<VMGenericVec>() || type_id == TypeId::of::<Closure>() {
+                    RegType::Ref
+                } else {
+                    RegType::Any
+                }
+            },
+            _ => RegType::Any
+        }
+    }
+
+    unsafe fn from_const(value: Value) -> RegType {
+        if value.is_null() {
+            RegType::Any
+        } else if value.is_value() {
+            match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+                ValueTypeTag::Int => RegType::Int,
+                ValueTypeTag::Float => RegType::Float,
+                ValueTypeTag::Bool => RegType::Bool
+            }
+        } else {
+            RegType::Ref
+        }
+    }
+}
+
+/// How control flows after an instruction
+enum Flow {
+    Next,
+    Branch(usize),
+    Jump(usize),
+    Exit
+}
+
+/// Verifies the whole program, returns the first error found
+pub fn verify_program<A: Alloc>(program: &CompiledProgram<A>) -> Result<(), VerifyError> {
+    if program.init_proc >= program.functions.len() {
+        return Err(VerifyError::BadInitProc { init_proc: program.init_proc });
+    }
+
+    let mut start_addrs: Vec<usize> = program.functions.iter()
+        .map(|function| function.start_addr)
+        .collect::<_>();
+    start_addrs.sort_unstable();
+    start_addrs.dedup();
+
+    for (func_id, function) /*: (usize, &CompiledFunction)*/ in
+        program.functions.iter().enumerate()
+    {
+        let end_addr: usize = start_addrs.iter()
+            .copied()
+            .find(|start_addr| *start_addr > function.start_addr)
+            .unwrap_or(program.code.len());
+        let verifier: FunctionVerifier<A> = FunctionVerifier {
+            program,
+            func_id,
+            function,
+            range: (function.start_addr, end_addr),
+            check_types: Cell::new(false)
+        };
+        verifier.verify()?;
+    }
+    Ok(())
+}
+
+struct FunctionVerifier<'p, A: Alloc> {
+    program: &'p CompiledProgram<A>,
+    func_id: usize,
+    function: &'p CompiledFunction,
+    /// Instructions of this function, assumed to be laid out until the next function
+    range: (usize, usize),
+    /// Types are only checked after register types reach a fixed point
+    check_types: Cell<bool>
+}
+
+impl<'p, A: Alloc> FunctionVerifier<'p, A> {
+    fn verify(&self) -> Result<(), VerifyError> {
+        self.verify_signature()?;
+
+        let (start_addr, end_addr): (usize, usize) = self.range;
+        let mut states: Vec<Option<Box<[RegType]>>> = vec![None; end_addr - start_addr];
+        let mut worklist: Vec<usize> = vec![start_addr];
+        let mut entry_state: Box<[RegType]> =
+            vec![RegType::Any; self.function.stack_size].into_boxed_slice();
+        for (i, param) /*: (usize, &Option<NonNull<TyckInfo>>)*/ in
+            self.function.param_tyck_info.iter().enumerate()
+        {
+            entry_state[i] = unsafe { RegType::from_tyck_info(param.map(|p| p.as_ref())) };
+        }
+        states[0] = Some(entry_state);
+
+        // registers may hold anything when entering an exception handler
+        for exc_handler /*: &ExceptionHandlingBlock*/ in
+            self.function.exc_handlers.iter().flatten()
+        {
+            let handler_addr: usize = exc_handler.handler_addr;
+            if states[handler_addr - start_addr].is_none() {
+                states[handler_addr - start_addr] =
+                    Some(vec![RegType::Any; self.function.stack_size].into_boxed_slice());
+                worklist.push(handler_addr);
+            }
+        }
+
+        while let Some(insc_ptr) = worklist.pop() {
+            let mut state: Box<[RegType]> = states[insc_ptr - start_addr].clone().unwrap();
+            let successors: [Option<usize>; 2] = match self.transfer(insc_ptr, &mut state)? {
+                Flow::Next => [Some(insc_ptr + 1), None],
+                Flow::Branch(dest) => [Some(insc_ptr + 1), Some(dest)],
+                Flow::Jump(dest) => [Some(dest), None],
+                Flow::Exit => [None, None]
+            };
+
+            for successor /*: usize*/ in successors.iter().flatten().copied() {
+                if successor == end_addr {
+                    return Err(VerifyError::FallsThrough { func_id: self.func_id });
+                }
+
+                let changed: bool = match &mut states[successor - start_addr] {
+                    Some(successor_state) => {
+                        let mut changed: bool = false;
+                        for (reg_type, new_type) /*: (&mut RegType, &RegType)*/ in
+                            successor_state.iter_mut().zip(state.iter())
+                        {
+                            let joined: RegType = reg_type.join(*new_type);
+                            changed |= joined != *reg_type;
+                            *reg_type = joined;
+                        }
+                        changed
+                    },
+                    successor_state => {
+                        *successor_state = Some(state.clone());
+                        true
+                    }
+                };
+                if changed {
+                    worklist.push(successor);
+                }
+            }
+        }
+
+        // unreachable instructions are still checked for bounds and ids, as if registers could
+        // hold anything
+        self.check_types.set(true);
+        for (insc_ptr, state) /*: (usize, Option<Box<[RegType]>>)*/ in
+            (start_addr..end_addr).zip(states)
+        {
+            let mut state: Box<[RegType]> = state.unwrap_or_else(|| {
+                vec![RegType::Any; self.function.stack_size].into_boxed_slice()
+            });
+            self.transfer(insc_ptr, &mut state)?;
+        }
+        Ok(())
+    }
+
+    fn verify_signature(&self) -> Result<(), VerifyError> {
+        let bad_function = |reason: &'static str| VerifyError::BadFunction {
+            func_id: self.func_id,
+            reason
+        };
+
+        let function: &CompiledFunction = self.function;
+        if self.range.0 >= self.range.1 {
+            return Err(bad_function("function has no instructions"));
+        }
+        if function.stack_size < function.arg_count {
+            return Err(bad_function("stack size is smaller than argument count"));
+        }
+        if !function.param_tyck_info.is_empty()
+            && function.param_tyck_info.len() != function.arg_count
+        {
+            return Err(bad_function("parameter types do not match argument count"));
+        }
+
+        for (handler_idx, exc_handler) /*: (usize, &ExceptionHandlingBlock)*/ in
+            function.exc_handlers.iter().flatten().enumerate()
+        {
+            let bad_handler = |reason: &'static str| VerifyError::BadExceptionHandler {
+                func_id: self.func_id,
+                handler_idx,
+                reason
+            };
+
+            let (start_insc, end_insc): (usize, usize) = exc_handler.insc_ptr_range;
+            if function.stack_size == 0 {
+                return Err(bad_handler("no register for the caught exception"));
+            }
+            if start_insc > end_insc {
+                return Err(bad_handler("range is reversed"));
+            }
+            if !self.in_range(start_insc) || !self.in_range(end_insc) {
+                return Err(bad_handler("range is outside of function"));
+            }
+            if !self.in_range(exc_handler.handler_addr) {
+                return Err(bad_handler("handler is outside of function"));
+            }
+        }
+        Ok(())
+    }
+
+    fn in_range(&self, insc_ptr: usize) -> bool {
+        insc_ptr >= self.range.0 && insc_ptr < self.range.1
+    }
+
+    fn check_reg(&self, insc_ptr: usize, reg: usize) -> Result<(), VerifyError> {
+        if reg < self.function.stack_size {
+            Ok(())
+        } else {
+            Err(VerifyError::RegisterOutOfBounds {
+                func_id: self.func_id,
+                insc_ptr,
+                reg,
+                stack_size: self.function.stack_size
+            })
+        }
+    }
+
+    fn check_global(&self, insc_ptr: usize, global_id: usize) -> Result<(), VerifyError> {
+        if global_id < self.program.global_names.len() {
+            Ok(())
+        } else {
+            Err(VerifyError::UnknownGlobal { func_id: self.func_id, insc_ptr, global_id })
+        }
+    }
+
+    fn read(&self, state: &[RegType], insc_ptr: usize, reg: usize)
+        -> Result<RegType, VerifyError>
+    {
+        self.check_reg(insc_ptr, reg)?;
+        Ok(state[reg])
+    }
+
+    fn check_regs(&self, insc_ptr: usize, regs: &[usize]) -> Result<(), VerifyError> {
+        for reg /*: &usize*/ in regs {
+            self.check_reg(insc_ptr, *reg)?;
+        }
+        Ok(())
+    }
+
+    fn expect(&self, state: &[RegType], insc_ptr: usize, reg: usize, expected: RegType)
+        -> Result<(), VerifyError>
+    {
+        match self.read(state, insc_ptr, reg)? {
+            _ if !self.check_types.get() || expected == RegType::Any => Ok(()),
+            RegType::Any => Ok(()),
+            found if found == expected => Ok(()),
+            found => Err(self.type_mismatch(insc_ptr, reg, expected.name(), found))
+        }
+    }
+
+    fn expect_all(&self, state: &[RegType], insc_ptr: usize, regs: &[usize], expected: RegType)
+        -> Result<(), VerifyError>
+    {
+        for reg /*: &usize*/ in regs {
+            self.expect(state, insc_ptr, *reg, expected)?;
+        }
+        Ok(())
+    }
+
+    /// Expects two registers to hold plain values of the same type
+    fn expect_same_values(&self, state: &[RegType], insc_ptr: usize, src1: usize, src2: usize)
+        -> Result<(), VerifyError>
+    {
+        let type1: RegType = self.read(state, insc_ptr, src1)?;
+        let type2: RegType = self.read(state, insc_ptr, src2)?;
+        if !self.check_types.get() {
+            return Ok(());
+        }
+        if type1 == RegType::Ref {
+            return Err(self.type_mismatch(insc_ptr, src1, "value", type1));
+        }
+        if type2 == RegType::Ref {
+            return Err(self.type_mismatch(insc_ptr, src2, "value", type2));
+        }
+        if type1 != RegType::Any && type2 != RegType::Any && type1 != type2 {
+            return Err(self.type_mismatch(insc_ptr, src2, type1.name(), type2));
+        }
+        Ok(())
+    }
+
+    /// Expects a register to hold a reference, if its type is known
+    fn expect_ref(&self, state: &[RegType], insc_ptr: usize, reg: usize)
+        -> Result<(), VerifyError>
+    {
+        self.expect(state, insc_ptr, reg, RegType::Ref)
+    }
+
+    fn write(&self, state: &mut [RegType], insc_ptr: usize, reg: usize, reg_type: RegType)
+        -> Result<(), VerifyError>
+    {
+        self.check_reg(insc_ptr, reg)?;
+        state[reg] = reg_type;
+        Ok(())
+    }
+
+    fn write_all(&self, state: &mut [RegType], insc_ptr: usize, regs: &[usize])
+        -> Result<(), VerifyError>
+    {
+        for reg /*: &usize*/ in regs {
+            self.write(state, insc_ptr, *reg, RegType::Any)?;
+        }
+        Ok(())
+    }
+
+    fn type_mismatch(&self, insc_ptr: usize, reg: usize, expected: &'static str, found: RegType)
+        -> VerifyError
+    {
+        VerifyError::TypeMismatch {
+            func_id: self.func_id,
+            insc_ptr,
+            reg,
+            expected,
+            found: found.name()
+        }
+    }
+
+    fn jump(&self, insc_ptr: usize, dest: usize) -> Result<usize, VerifyError> {
+        if self.in_range(dest) {
+            Ok(dest)
+        } else {
+            Err(VerifyError::BadJumpTarget { func_id: self.func_id, insc_ptr, dest })
+        }
+    }
+
+    fn callee(&self, insc_ptr: usize, callee: usize)
+        -> Result<&'p CompiledFunction, VerifyError>
+    {
+        self.program.functions.get(callee).ok_or(VerifyError::UnknownFunction {
+            func_id: self.func_id,
+            insc_ptr,
+            callee
+        })
+    }
+
+    fn expect_count(&self, insc_ptr: usize, expected: usize, got: usize, rets: bool)
+        -> Result<(), VerifyError>
+    {
+        if expected == got {
+            Ok(())
+        } else if rets {
+            Err(VerifyError::RetCountMismatch { func_id: self.func_id, insc_ptr, expected, got })
+        } else {
+            Err(VerifyError::ArgCountMismatch { func_id: self.func_id, insc_ptr, expected, got })
+        }
+    }
+
+    /// Checks a call to a script function, including argument types when known
+    fn verify_call(
+        &self,
+        state: &[RegType],
+        insc_ptr: usize,
+        callee: usize,
+        args: &[usize]
+    ) -> Result<&'p CompiledFunction, VerifyError> {
+        let callee: &CompiledFunction = self.callee(insc_ptr, callee)?;
+        self.expect_count(insc_ptr, callee.arg_count, args.len(), false)?;
+        for (arg, param) /*: (&usize, &Option<NonNull<TyckInfo>>)*/ in
+            args.iter().zip(callee.param_tyck_info.iter())
+        {
+            let param_type: RegType =
+                unsafe { RegType::from_tyck_info(param.map(|p| p.as_ref())) };
+            self.expect(state, insc_ptr, *arg, param_type)?;
+        }
+        self.check_regs(insc_ptr, args)?;
+        Ok(callee)
+    }
+
+    fn verify_ffi_call(
+        &self,
+        insc_ptr: usize,
+        ffi_func_id: usize,
+        args: &[usize],
+        rets: &[usize]
+    ) -> Result<(), VerifyError> {
+        if ffi_func_id >= self.program.ffi_funcs.len() {
+            return Err(VerifyError::UnknownFFIFunc {
+                func_id: self.func_id,
+                insc_ptr,
+                ffi_func_id
+            });
+        }
+        self.check_ffi_count(insc_ptr, args.len(), FFI_MAX_ARGS)?;
+        self.check_ffi_count(insc_ptr, rets.len(), FFI_MAX_RETS)?;
+        self.check_regs(insc_ptr, args)
+    }
+
+    fn check_ffi_count(&self, insc_ptr: usize, count: usize, max: usize)
+        -> Result<(), VerifyError>
+    {
+        if count <= max {
+            Ok(())
+        } else {
+            Err(VerifyError::FFIValueOverflow { func_id: self.func_id, insc_ptr, count, max })
+        }
+    }
+
+    fn verify_return(&self, insc_ptr: usize, srcs: &[usize]) -> Result<Flow, VerifyError> {
+        self.expect_count(insc_ptr, self.function.ret_count, srcs.len(), true)?;
+        self.check_regs(insc_ptr, srcs)?;
+        Ok(Flow::Exit)
+    }
+
+    /// Checks one instruction against `state`, and updates `state` to the one after it
+    fn transfer(&self, insc_ptr: usize, state: &mut [RegType]) -> Result<Flow, VerifyError> {
+        use RegType::{Any, Bool, Float, Int, Ref};
+
+        let s: &mut [RegType] = state;
+        match &self.program.code[insc_ptr] {
+            Insc::Move(src, dst) => {
+                let src_type: RegType = self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, src_type)?;
+            },
+            Insc::AddInt(src1, src2, dst)
+            | Insc::SubInt(src1, src2, dst)
+            | Insc::MulInt(src1, src2, dst)
+            | Insc::DivInt(src1, src2, dst)
+            | Insc::ModInt(src1, src2, dst)
+            | Insc::BAndInt(src1, src2, dst)
+            | Insc::BOrInt(src1, src2, dst)
+            | Insc::BXorInt(src1, src2, dst)
+            | Insc::ShlInt(src1, src2, dst)
+            | Insc::ShrInt(src1, src2, dst) => {
+                self.expect_all(s, insc_ptr, &[*src1, *src2], Int)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::AddFloat(src1, src2, dst)
+            | Insc::SubFloat(src1, src2, dst)
+            | Insc::MulFloat(src1, src2, dst)
+            | Insc::DivFloat(src1, src2, dst) => {
+                self.expect_all(s, insc_ptr, &[*src1, *src2], Float)?;
+                self.write(s, insc_ptr, *dst, Float)?;
+            },
+            Insc::LtInt(src1, src2, dst) | Insc::LeInt(src1, src2, dst) => {
+                self.expect_all(s, insc_ptr, &[*src1, *src2], Int)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::LtFloat(src1, src2, dst) | Insc::LeFloat(src1, src2, dst) => {
+                self.expect_all(s, insc_ptr, &[*src1, *src2], Float)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::AndBool(src1, src2, dst) | Insc::OrBool(src1, src2, dst) => {
+                self.expect_all(s, insc_ptr, &[*src1, *src2], Bool)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::EqValue(src1, src2, dst) | Insc::NeValue(src1, src2, dst) => {
+                self.expect_same_values(s, insc_ptr, *src1, *src2)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::EqRef(src1, src2, dst) | Insc::NeRef(src1, src2, dst) => {
+                for src /*: usize*/ in [*src1, *src2] {
+                    let src_type: RegType = self.read(s, insc_ptr, src)?;
+                    if self.check_types.get() && src_type != Ref && src_type != Any {
+                        return Err(self.type_mismatch(insc_ptr, src, Ref.name(), src_type));
+                    }
+                }
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::AddAny(src1, src2, dst)
+            | Insc::SubAny(src1, src2, dst)
+            | Insc::MulAny(src1, src2, dst)
+            | Insc::DivAny(src1, src2, dst) => {
+                self.check_regs(insc_ptr, &[*src1, *src2])?;
+                self.write(s, insc_ptr, *dst, Any)?;
+            },
+            Insc::ModAny(src1, src2, dst)
+            | Insc::BAndAny(src1, src2, dst)
+            | Insc::BOrAny(src1, src2, dst)
+            | Insc::BXorAny(src1, src2, dst)
+            | Insc::ShlAny(src1, src2, dst)
+            | Insc::ShrAny(src1, src2, dst) => {
+                self.check_regs(insc_ptr, &[*src1, *src2])?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::EqAny(src1, src2, dst)
+            | Insc::NeAny(src1, src2, dst)
+            | Insc::LtAny(src1, src2, dst)
+            | Insc::LeAny(src1, src2, dst)
+            | Insc::AndAny(src1, src2, dst)
+            | Insc::OrAny(src1, src2, dst) => {
+                self.check_regs(insc_ptr, &[*src1, *src2])?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::IncrInt(pos) | Insc::DecrInt(pos) => self.expect(s, insc_ptr, *pos, Int)?,
+            Insc::BNotInt(src, dst) | Insc::NegInt(src, dst) => {
+                self.expect(s, insc_ptr, *src, Int)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::NegFloat(src, dst) => {
+                self.expect(s, insc_ptr, *src, Float)?;
+                self.write(s, insc_ptr, *dst, Float)?;
+            },
+            Insc::NotBool(src, dst) => {
+                self.expect(s, insc_ptr, *src, Bool)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::BNotAny(src, dst) => {
+                self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::NegAny(src, dst) => {
+                self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Any)?;
+            },
+            Insc::NotAny(src, dst) | Insc::IsNull(src, dst) | Insc::IsType(src, _, dst) => {
+                self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::MakeIntConst(_, dst) => self.write(s, insc_ptr, *dst, Int)?,
+            Insc::MakeFloatConst(_, dst) => self.write(s, insc_ptr, *dst, Float)?,
+            Insc::MakeBoolConst(_, dst) => self.write(s, insc_ptr, *dst, Bool)?,
+            Insc::MakeNull(dst) => self.write(s, insc_ptr, *dst, Any)?,
+            Insc::LoadConst(const_id, dst) => {
+                let value: Value = *self.program.const_pool.get(*const_id).ok_or(
+                    VerifyError::UnknownConst {
+                        func_id: self.func_id,
+                        insc_ptr,
+                        const_id: *const_id
+                    }
+                )?;
+                self.write(s, insc_ptr, *dst, unsafe { RegType::from_const(value) })?;
+            },
+            Insc::SaveConst(src, const_id) => {
+                if *const_id >= self.program.const_pool.len() {
+                    return Err(VerifyError::UnknownConst {
+                        func_id: self.func_id,
+                        insc_ptr,
+                        const_id: *const_id
+                    });
+                }
+                self.read(s, insc_ptr, *src)?;
+            },
+            Insc::LoadGlobal(global_id, dst) => {
+                self.check_global(insc_ptr, *global_id)?;
+                self.write(s, insc_ptr, *dst, Any)?;
+            },
+            Insc::StoreGlobal(src, global_id) => {
+                self.check_global(insc_ptr, *global_id)?;
+                self.read(s, insc_ptr, *src)?;
+            },
+            Insc::CastFloatInt(src, dst) => {
+                self.expect(s, insc_ptr, *src, Float)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::CastBoolInt(src, dst) => {
+                self.expect(s, insc_ptr, *src, Bool)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::CastIntFloat(src, dst) => {
+                self.expect(s, insc_ptr, *src, Int)?;
+                self.write(s, insc_ptr, *dst, Float)?;
+            },
+            Insc::CastIntBool(src, dst) => {
+                self.expect(s, insc_ptr, *src, Int)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::CastAnyInt(src, dst) => {
+                self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            Insc::CastAnyFloat(src, dst) => {
+                self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Float)?;
+            },
+            Insc::CastAnyBool(src, dst) => {
+                self.read(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            Insc::NullCheck(src)
+            | Insc::TypeCheck(src, _)
+            | Insc::OwnershipInfoCheck(src, _)
+            | Insc::CallableCheck(src, _, _) => {
+                self.read(s, insc_ptr, *src)?;
+            },
+            Insc::Call(func_id, args, rets) => {
+                let callee: &CompiledFunction = self.verify_call(s, insc_ptr, *func_id, args)?;
+                self.expect_count(insc_ptr, callee.ret_count, rets.len(), true)?;
+                self.write_all(s, insc_ptr, rets)?;
+            },
+            Insc::CallPtr(func, args, rets) => {
+                self.check_regs(insc_ptr, &[*func])?;
+                self.check_regs(insc_ptr, args)?;
+                self.write_all(s, insc_ptr, rets)?;
+            },
+            Insc::CallOverload(overload_table, args, rets) => {
+                let candidates: &[usize] = &self.program.overload_tables.get(*overload_table)
+                    .ok_or(VerifyError::UnknownOverloadTable {
+                        func_id: self.func_id,
+                        insc_ptr,
+                        overload_table: *overload_table
+                    })?
+                    .candidates;
+                // argument types are only checked when the candidate is selected at run time
+                for candidate /*: &usize*/ in candidates.iter() {
+                    let callee: &CompiledFunction = self.callee(insc_ptr, *candidate)?;
+                    self.expect_count(insc_ptr, callee.ret_count, rets.len(), true)?;
+                }
+                self.check_regs(insc_ptr, args)?;
+                self.write_all(s, insc_ptr, rets)?;
+            },
+            Insc::ReturnNothing => return self.verify_return(insc_ptr, &[]),
+            Insc::ReturnOne(src) => return self.verify_return(insc_ptr, &[*src]),
+            Insc::Return(srcs) => return self.verify_return(insc_ptr, srcs),
+            Insc::FFICallRtlc(ffi_func_id, args, rets) => {
+                self.verify_ffi_call(insc_ptr, *ffi_func_id, args, rets)?;
+                self.write_all(s, insc_ptr, rets)?;
+            },
+            #[cfg(feature = "optimized-rtlc")]
+            Insc::FFICall(ffi_func_id, args, rets) => {
+                self.verify_ffi_call(insc_ptr, *ffi_func_id, args, rets)?;
+                self.write_all(s, insc_ptr, rets)?;
+            },
+            #[cfg(feature = "async")]
+            Insc::FFICallAsync(async_ffi_func_id, args, dst) => {
+                if *async_ffi_func_id >= self.program.async_ffi_funcs.len() {
+                    return Err(VerifyError::UnknownAsyncFFIFunc {
+                        func_id: self.func_id,
+                        insc_ptr,
+                        async_ffi_func_id: *async_ffi_func_id
+                    });
+                }
+                self.check_ffi_count(insc_ptr, args.len(), FFI_MAX_ARGS)?;
+                self.check_regs(insc_ptr, args)?;
+                self.write(s, insc_ptr, *dst, Ref)?;
+            },
+            #[cfg(feature = "async")]
+            Insc::Await(promise, rets) => {
+                self.expect_ref(s, insc_ptr, *promise)?;
+                self.write_all(s, insc_ptr, rets)?;
+            },
+            #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+            Insc::Spawn(func_id, args) => {
+                self.verify_call(s, insc_ptr, *func_id, args)?;
+            },
+            Insc::Raise(src) | Insc::Rethrow(src) => {
+                self.read(s, insc_ptr, *src)?;
+                return Ok(Flow::Exit);
+            },
+            Insc::JumpIfTrue(cond, dest) | Insc::JumpIfFalse(cond, dest) => {
+                self.expect(s, insc_ptr, *cond, Bool)?;
+                return Ok(Flow::Branch(self.jump(insc_ptr, *dest)?));
+            },
+            Insc::Jump(dest) => return Ok(Flow::Jump(self.jump(insc_ptr, *dest)?)),
+            Insc::CreateContainer(_, _, dst) => self.write(s, insc_ptr, *dst, Ref)?,
+            Insc::CreateClosure(func_id, captures, _, dst) => {
+                let callee: &CompiledFunction = self.callee(insc_ptr, *func_id)?;
+                if captures.len() > callee.arg_count {
+                    return Err(VerifyError::ArgCountMismatch {
+                        func_id: self.func_id,
+                        insc_ptr,
+                        expected: callee.arg_count,
+                        got: captures.len()
+                    });
+                }
+                self.check_regs(insc_ptr, captures)?;
+                self.write(s, insc_ptr, *dst, Ref)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CreateString(dst) | Insc::CreateObject(dst) => {
+                self.write(s, insc_ptr, *dst, Ref)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecIndex(src, index, dst) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.expect(s, insc_ptr, *index, Int)?;
+                self.write(s, insc_ptr, *dst, Any)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecIndexPut(src, index, value) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.expect(s, insc_ptr, *index, Int)?;
+                self.read(s, insc_ptr, *value)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecPush(src, value) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.read(s, insc_ptr, *value)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::VecLen(src, dst) | Insc::StrLen(src, dst) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Int)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrClone(src, dst) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Ref)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrConcat(srcs, dst) => {
+                self.expect_all(s, insc_ptr, srcs, Ref)?;
+                self.write(s, insc_ptr, *dst, Ref)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::StrEquals(src1, src2, dst) => {
+                self.expect_all(s, insc_ptr, &[*src1, *src2], Ref)?;
+                self.write(s, insc_ptr, *dst, Bool)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectGet(src, _, dst) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.write(s, insc_ptr, *dst, Any)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectGetDyn(src, field, dst) => {
+                self.expect_all(s, insc_ptr, &[*src, *field], Ref)?;
+                self.write(s, insc_ptr, *dst, Any)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectPut(src, _, value) => {
+                self.expect_ref(s, insc_ptr, *src)?;
+                self.read(s, insc_ptr, *value)?;
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::ObjectPutDyn(src, field, value) => {
+                self.expect_all(s, insc_ptr, &[*src, *field], Ref)?;
+                self.read(s, insc_ptr, *value)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{VerifyError, verify_program};
+
+    use crate::driver::compile;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::asm::assemble;
+    use crate::vm::al31f::compiled::CompiledProgram;
+    use crate::vm::al31f::ffi_registry::FFIRegistry;
+    use crate::vm::al31f::test_program::{
+        alloc_1m_program,
+        basic_fn_call_program,
+        basic_program,
+        bench_ffi_call_program,
+        bench_raw_iter_program,
+        exception_no_eh_program,
+        exception_program,
+        fibonacci_program,
+        ffi_call_program,
+        ffi_call_program2
+    };
+
+    fn verify_asm(source: &str) -> Result<(), VerifyError> {
+        let program: CompiledProgram<DefaultAlloc> = assemble(source, &FFIRegistry::new())
+            .unwrap_or_else(|e| panic!("should assemble: {:?}", e));
+        verify_program(&program)
+    }
+
+    #[test]
+    fn test_verify_valid_programs() {
+        let programs: Vec<CompiledProgram<DefaultAlloc>> = vec![
+            basic_program(),
+            basic_fn_call_program(),
+            fibonacci_program(),
+            alloc_1m_program(),
+            exception_program(),
+            exception_no_eh_program(),
+            ffi_call_program(),
+            bench_raw_iter_program(),
+            bench_ffi_call_program(),
+            ffi_call_program2(),
+            compile(&[("main.47", r#"
+                func check(x int): int {
+                    if x > 100 {
+                        throw "too large";
+                    }
+                    return x;
+                }
+
+                func guarded(x int, y float): int {
+                    try {
+                        var i = 0;
+                        while i < x {
+                            i = i + 1;
+                        }
+                        if y > 1.0e0 {
+                            return check(i);
+                        }
+                        return check(x) * 2;
+                    } catch (e string) {
+                        return -1;
+                    }
+                }
+            "#)], FFIRegistry::new()).unwrap_or_else(|_| panic!("should compile"))
+        ];
+        for program /*: &CompiledProgram<DefaultAlloc>*/ in programs.iter() {
+            verify_program(program).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_verify_structure() {
+        match verify_asm(r#"
+            .func main entry L.main args 0 rets 0 stack 2
+            L.main:
+                %2 = int $1
+                ret
+        "#) {
+            Err(VerifyError::RegisterOutOfBounds { insc_ptr: 0, reg: 2, .. }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        match verify_asm(r#"
+            .func main entry L.main args 0 rets 0 stack 1
+            .func other entry L.other args 0 rets 0 stack 0
+            L.main:
+                goto L.other
+            L.other:
+                ret
+        "#) {
+            Err(VerifyError::BadJumpTarget { func_id: 0, insc_ptr: 0, dest: 1 }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        match verify_asm(r#"
+            .func main entry L.main args 0 rets 0 stack 1
+            L.main:
+                %0 = int $1
+        "#) {
+            Err(VerifyError::FallsThrough { func_id: 0 }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        match verify_asm(r#"
+            .func main entry L.main args 0 rets 1 stack 1
+            .func two entry L.two args 2 rets 1 stack 2
+            L.main:
+                %0 = int $1
+                [%0] = call F.two %0
+                ret %0
+            L.two:
+                ret %0
+        "#) {
+            Err(VerifyError::ArgCountMismatch { insc_ptr: 1, expected: 2, got: 1, .. }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        match verify_asm(r#"
+            .func main entry L.main args 0 rets 1 stack 1
+            L.main:
+                %0 = load 0
+                ret %0
+        "#) {
+            Err(VerifyError::UnknownConst { const_id: 0, .. }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        match verify_asm(r#"
+            .global counter
+            .func main entry L.main args 0 rets 0 stack 1
+            L.main:
+                %0 = load-global 0
+                store-global 1, %0
+                ret
+        "#) {
+            Err(VerifyError::UnknownGlobal { insc_ptr: 1, global_id: 1, .. }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+    }
+
+    #[test]
+    fn test_verify_types() {
+        match verify_asm(r#"
+            .func main entry L.main args 1 rets 1 stack 2 params float
+            L.main:
+                %1 = int $1
+                %1 = add int %0, %1
+                ret %1
+        "#) {
+            Err(VerifyError::TypeMismatch {
+                insc_ptr: 1, reg: 0, expected: "int", found: "float", ..
+            }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        // register types are merged at join points, so `%0` may be either an int or a float
+        verify_asm(r#"
+            .func main entry L.main args 1 rets 1 stack 2 params bool
+            L.main:
+                if %0 goto L.float
+                %1 = int $1
+                goto L.join
+            L.float:
+                %1 = float $1.0
+            L.join:
+                %1 = add int %1, %1
+                ret %1
+        "#).unwrap();
+
+        match verify_asm(r#"
+            .func main entry L.main args 0 rets 0 stack 1
+            .func callee entry L.callee args 1 rets 0 stack 1 params int
+            L.main:
+                %0 = bool $true
+                [] = call F.callee %0
+                ret
+            L.callee:
+                ret
+        "#) {
+            Err(VerifyError::TypeMismatch {
+                insc_ptr: 1, expected: "int", found: "bool", ..
+            }) => {},
+            result => panic!("unexpected result: {:?}", result)
+        }
+
+        // untyped parameters accept anything
+        verify_asm(r#"
+            .func main entry L.main args 0 rets 0 stack 1
+            .func callee entry L.callee args 1 rets 0 stack 1 params -
+            L.main:
+                %0 = bool $true
+                [] = call F.callee %0
+                ret
+            L.callee:
+                ret
+        "#).unwrap();
+    }
+}